@@ -0,0 +1,297 @@
+//! crates/core/src/alloys.rs
+//! Composition- and temperature-dependent III-V alloy materials.
+//!
+//! Refractive indices follow Adachi's modified single-oscillator model
+//! ([`Dispersion::Adachi`]) with the room-temperature parameters of
+//! S. Adachi, J. Appl. Phys. 58, R1 (1985) for AlGaAs and J. Appl. Phys. 53, 5863 (1982)
+//! for InGaAsP lattice-matched to InP. InGaAs uses the GaAs and InAs oscillator
+//! parameters interpolated linearly, with its bowed direct gap.
+//!
+//! Temperature enters through the Varshni shift of the direct gap relative to 300 K
+//! (parameters from Vurgaftman et al., J. Appl. Phys. 89, 5815 (2001)), applied to
+//! both `E0` and `E0 + Δ0`. The oscillator strengths are room-temperature fits, so
+//! the temperature range is restricted to [`TEMPERATURE_RANGE`].
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use super::dispersion::Dispersion;
+use super::material::Material;
+use super::thermal::{Thermal, ThermalModel};
+use super::units::Length;
+
+/// Photon energy times wavelength, in eV·m.
+const HC_EV_M: f64 = 1.239_841_984e-6;
+/// Reference temperature of the oscillator parameters, in K.
+const REFERENCE_TEMPERATURE: f64 = 300.0;
+/// Temperatures (K) accepted by the alloy models.
+pub const TEMPERATURE_RANGE: RangeInclusive<f64> = 250.0..=400.0;
+/// Mole fractions accepted by the alloy models.
+pub const COMPOSITION_RANGE: RangeInclusive<f64> = 0.0..=1.0;
+
+/// Varshni parameters `(α [eV/K], β [K])` of the direct gap of the binaries.
+const VARSHNI_GAAS: (f64, f64) = (5.405e-4, 204.0);
+const VARSHNI_ALAS: (f64, f64) = (8.85e-4, 530.0);
+const VARSHNI_INAS: (f64, f64) = (2.76e-4, 93.0);
+const VARSHNI_INP: (f64, f64) = (3.63e-4, 162.0);
+const VARSHNI_GAP: (f64, f64) = (5.771e-4, 372.0);
+
+/// Reasons why an alloy model cannot be evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlloyError {
+    /// A mole fraction lies outside [`COMPOSITION_RANGE`].
+    CompositionOutOfRange { name: &'static str, value: f64 },
+    /// The temperature lies outside [`TEMPERATURE_RANGE`].
+    TemperatureOutOfRange { value: f64 },
+    /// The photon energy (eV) is not below the direct gap (eV), where the model loses validity.
+    AboveBandgap { photon_energy: f64, bandgap: f64 },
+}
+
+impl fmt::Display for AlloyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlloyError::CompositionOutOfRange { name, value } => write!(
+                f,
+                "{name} = {value} is outside [{}, {}]",
+                COMPOSITION_RANGE.start(),
+                COMPOSITION_RANGE.end()
+            ),
+            AlloyError::TemperatureOutOfRange { value } => write!(
+                f,
+                "temperature {value} K is outside [{}, {}] K",
+                TEMPERATURE_RANGE.start(),
+                TEMPERATURE_RANGE.end()
+            ),
+            AlloyError::AboveBandgap { photon_energy, bandgap } => write!(
+                f,
+                "photon energy {photon_energy:.4} eV is not below the direct gap {bandgap:.4} eV"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AlloyError {}
+
+/// Composition of one of the supported alloys.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "alloy", rename_all = "snake_case")]
+pub enum AlloyComposition {
+    /// Al(x)Ga(1-x)As.
+    AlGaAs { x: f64 },
+    /// In(x)Ga(1-x)As.
+    InGaAs { x: f64 },
+    /// In(1-x)Ga(x)As(y)P(1-y) lattice-matched to InP.
+    InGaAsP { y: f64 },
+}
+
+impl AlloyComposition {
+    /// Oscillator parameters at `temperature` (K).
+    pub fn parameters(&self, temperature: f64) -> Result<AlloyParameters, AlloyError> {
+        match *self {
+            AlloyComposition::AlGaAs { x } => AlloyParameters::al_ga_as(x, temperature),
+            AlloyComposition::InGaAs { x } => AlloyParameters::in_ga_as(x, temperature),
+            AlloyComposition::InGaAsP { y } => AlloyParameters::in_ga_as_p(y, temperature),
+        }
+    }
+
+    /// Dispersive material at the vacuum `wavelength` and `temperature` (K), which keeps
+    /// its composition so that it can be re-evaluated with [`Material::at_temperature`].
+    pub fn material(&self, wavelength: Length, temperature: f64) -> Result<Material, AlloyError> {
+        let mut material = self.parameters(temperature)?.material(wavelength)?;
+        material.thermal = Some(Thermal {
+            model: ThermalModel::Alloy(*self),
+            temperature,
+        });
+        Ok(material)
+    }
+}
+
+/// Oscillator parameters of an alloy at a given composition and temperature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlloyParameters {
+    pub a: f64,
+    pub b: f64,
+    /// Direct gap, in eV.
+    pub e0: f64,
+    /// Spin-orbit splitting, in eV.
+    pub delta0: f64,
+}
+
+impl AlloyParameters {
+    /// Al(x)Ga(1-x)As.
+    pub fn al_ga_as(x: f64, temperature: f64) -> Result<Self, AlloyError> {
+        check_composition("x", x)?;
+        check_temperature(temperature)?;
+        let varshni = lerp_pair(VARSHNI_GAAS, VARSHNI_ALAS, x);
+        let e0 = 1.425 + 1.155 * x + 0.37 * x * x;
+        let e0_delta0 = 1.765 + 1.115 * x + 0.37 * x * x;
+        Ok(Self {
+            a: 6.3 + 19.0 * x,
+            b: 9.4 - 10.2 * x,
+            e0: e0 + varshni_shift(varshni, temperature),
+            delta0: e0_delta0 - e0,
+        })
+    }
+
+    /// In(x)Ga(1-x)As.
+    pub fn in_ga_as(x: f64, temperature: f64) -> Result<Self, AlloyError> {
+        check_composition("x", x)?;
+        check_temperature(temperature)?;
+        let varshni = lerp_pair(VARSHNI_GAAS, VARSHNI_INAS, x);
+        Ok(Self {
+            a: 6.3 - 1.16 * x,
+            b: 9.4 + 0.75 * x,
+            e0: 1.424 - 1.548 * x + 0.478 * x * x + varshni_shift(varshni, temperature),
+            delta0: 0.34 + 0.04 * x,
+        })
+    }
+
+    /// In(1-x)Ga(x)As(y)P(1-y) lattice-matched to InP, parameterized by the arsenic fraction `y`.
+    pub fn in_ga_as_p(y: f64, temperature: f64) -> Result<Self, AlloyError> {
+        check_composition("y", y)?;
+        check_temperature(temperature)?;
+        let x = in_p_matched_gallium_fraction(y);
+        let varshni = [
+            (VARSHNI_GAAS, x * y),
+            (VARSHNI_GAP, x * (1.0 - y)),
+            (VARSHNI_INAS, (1.0 - x) * y),
+            (VARSHNI_INP, (1.0 - x) * (1.0 - y)),
+        ]
+        .iter()
+        .fold((0.0, 0.0), |acc, ((alpha, beta), w)| (acc.0 + w * alpha, acc.1 + w * beta));
+        Ok(Self {
+            a: 8.616 - 3.886 * y,
+            b: 6.621 + 3.135 * y,
+            e0: 1.35 - 0.72 * y + 0.12 * y * y + varshni_shift(varshni, temperature),
+            delta0: 0.11 + 0.31 * y - 0.09 * y * y,
+        })
+    }
+
+    /// The corresponding dispersion model.
+    pub fn dispersion(&self) -> Dispersion {
+        Dispersion::Adachi {
+            a: self.a,
+            b: self.b,
+            e0: self.e0,
+            delta0: self.delta0,
+        }
+    }
+
+    /// Dispersive material evaluated at `wavelength`, which must lie below the gap.
+    pub fn material(&self, wavelength: Length) -> Result<Material, AlloyError> {
+        let photon_energy = HC_EV_M / wavelength.as_meters();
+        if photon_energy >= self.e0 {
+            return Err(AlloyError::AboveBandgap {
+                photon_energy,
+                bandgap: self.e0,
+            });
+        }
+        Ok(Material::new_dispersive(self.dispersion(), wavelength))
+    }
+}
+
+impl Material {
+    /// Al(x)Ga(1-x)As at the vacuum `wavelength` and `temperature` (K).
+    pub fn al_ga_as(x: f64, wavelength: Length, temperature: f64) -> Result<Self, AlloyError> {
+        AlloyComposition::AlGaAs { x }.material(wavelength, temperature)
+    }
+
+    /// In(x)Ga(1-x)As at the vacuum `wavelength` and `temperature` (K).
+    pub fn in_ga_as(x: f64, wavelength: Length, temperature: f64) -> Result<Self, AlloyError> {
+        AlloyComposition::InGaAs { x }.material(wavelength, temperature)
+    }
+
+    /// In(1-x)Ga(x)As(y)P(1-y) lattice-matched to InP at the vacuum `wavelength` and `temperature` (K).
+    pub fn in_ga_as_p(y: f64, wavelength: Length, temperature: f64) -> Result<Self, AlloyError> {
+        AlloyComposition::InGaAsP { y }.material(wavelength, temperature)
+    }
+}
+
+/// Gallium fraction of In(1-x)Ga(x)As(y)P(1-y) lattice-matched to InP.
+pub fn in_p_matched_gallium_fraction(y: f64) -> f64 {
+    0.1894 * y / (0.4184 - 0.013 * y)
+}
+
+fn check_composition(name: &'static str, value: f64) -> Result<(), AlloyError> {
+    if COMPOSITION_RANGE.contains(&value) {
+        Ok(())
+    } else {
+        Err(AlloyError::CompositionOutOfRange { name, value })
+    }
+}
+
+fn check_temperature(value: f64) -> Result<(), AlloyError> {
+    if TEMPERATURE_RANGE.contains(&value) {
+        Ok(())
+    } else {
+        Err(AlloyError::TemperatureOutOfRange { value })
+    }
+}
+
+fn lerp_pair(a: (f64, f64), b: (f64, f64), x: f64) -> (f64, f64) {
+    (a.0 + x * (b.0 - a.0), a.1 + x * (b.1 - a.1))
+}
+
+/// Gap shift (eV) at `temperature` relative to the 300 K reference.
+fn varshni_shift((alpha, beta): (f64, f64), temperature: f64) -> f64 {
+    let varshni = |t: f64| alpha * t * t / (t + beta);
+    varshni(REFERENCE_TEMPERATURE) - varshni(temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_al_ga_as_reproduces_table_i_cladding() {
+        // Table I uses eps = 11.0224 for the Al0.4Ga0.6As cladding at 940 nm.
+        let clad = Material::al_ga_as(0.4, Length::micrometers(0.94), 300.0).unwrap();
+        assert!((clad.epsilon_matrix[(0, 0)] - 11.0224).abs() < 0.01);
+        assert!(clad.is_lossless());
+
+        let gaas = Material::al_ga_as(0.0, Length::micrometers(0.94), 300.0).unwrap();
+        assert!(gaas.epsilon_matrix[(0, 0)] > clad.epsilon_matrix[(0, 0)]);
+    }
+
+    #[test]
+    fn test_temperature_raises_index() {
+        let cold = Material::al_ga_as(0.0, Length::micrometers(0.94), 300.0).unwrap();
+        let hot = Material::al_ga_as(0.0, Length::micrometers(0.94), 340.0).unwrap();
+        let dn_dt = (hot.epsilon_matrix[(0, 0)].sqrt() - cold.epsilon_matrix[(0, 0)].sqrt()) / 40.0;
+        assert!(dn_dt > 1e-4 && dn_dt < 5e-4);
+    }
+
+    #[test]
+    fn test_in_ga_as_p_end_points() {
+        let inp = Material::in_ga_as_p(0.0, Length::micrometers(1.55), 300.0).unwrap();
+        assert!((inp.epsilon_matrix[(0, 0)].sqrt() - 3.17).abs() < 0.02);
+        assert!((in_p_matched_gallium_fraction(1.0) - 0.47).abs() < 0.01);
+
+        let q13 = AlloyParameters::in_ga_as_p(0.6, 300.0).unwrap();
+        assert!((HC_EV_M / q13.e0 - 1.3e-6).abs() < 0.1e-6);
+    }
+
+    #[test]
+    fn test_in_ga_as_gap() {
+        let lattice_matched = AlloyParameters::in_ga_as(0.53, 300.0).unwrap();
+        assert!((lattice_matched.e0 - 0.738).abs() < 0.01);
+        assert!(Material::in_ga_as(0.2, Length::micrometers(1.3), 300.0).is_ok());
+    }
+
+    #[test]
+    fn test_out_of_range_inputs() {
+        assert_eq!(
+            Material::al_ga_as(1.2, Length::micrometers(0.94), 300.0),
+            Err(AlloyError::CompositionOutOfRange { name: "x", value: 1.2 })
+        );
+        assert_eq!(
+            Material::in_ga_as(0.2, Length::micrometers(1.3), 77.0),
+            Err(AlloyError::TemperatureOutOfRange { value: 77.0 })
+        );
+        assert!(matches!(
+            Material::al_ga_as(0.0, Length::micrometers(0.8), 300.0),
+            Err(AlloyError::AboveBandgap { .. })
+        ));
+    }
+}
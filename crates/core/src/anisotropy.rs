@@ -0,0 +1,337 @@
+//! crates/core/src/anisotropy.rs
+//! Principal axes, classification and rotation of dielectric tensors.
+//!
+//! Principal axes are those of the real part of the tensor. Tensor elements are compared
+//! with a relative tolerance [`TENSOR_TOLERANCE`], scaled by the largest element.
+use std::fmt;
+
+use nalgebra::{Rotation3, SymmetricEigen};
+
+use super::material::{ComplexDielectricTensor, DielectricTensor, Material, MaterialDispersion};
+use super::vectorial::{Complex, Matrix3, Vector3};
+
+/// Relative tolerance below which two tensor elements are considered equal.
+pub const TENSOR_TOLERANCE: f64 = 1e-9;
+
+/// Reasons why a dielectric tensor is not physically admissible.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TensorError {
+    /// The tensor contains NaN or infinite elements.
+    NotFinite,
+    /// `ε ≠ εᵀ`, i.e. the medium is not reciprocal. `deviation` is the largest `|ε_ij - ε_ji|`.
+    NotSymmetric { deviation: f64 },
+    /// `ε ≠ ε†`, i.e. the medium is not lossless. `deviation` is the largest `|ε_ij - ε_ji*|`.
+    NotHermitian { deviation: f64 },
+}
+
+impl fmt::Display for TensorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TensorError::NotFinite => write!(f, "dielectric tensor has non-finite elements"),
+            TensorError::NotSymmetric { deviation } => {
+                write!(f, "dielectric tensor is not symmetric (deviation {deviation:e})")
+            }
+            TensorError::NotHermitian { deviation } => {
+                write!(f, "dielectric tensor is not Hermitian (deviation {deviation:e})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TensorError {}
+
+/// Principal dielectric constants and the corresponding orthonormal axes.
+///
+/// Axes are the columns of `axes`, ordered to best match x, y and z, oriented along the
+/// positive lab axes and forming a right-handed frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrincipalAxes {
+    pub epsilon: Vector3,
+    pub axes: Matrix3,
+}
+
+impl PrincipalAxes {
+    /// Principal refractive indices.
+    pub fn refractive_indices(&self) -> Vector3 {
+        self.epsilon.map(f64::sqrt)
+    }
+}
+
+/// Optical classification of a medium by the multiplicity of its principal dielectric constants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpticalClass {
+    Isotropic,
+    /// Two equal principal constants. `optic_axis` is the unit axis of the distinct one.
+    Uniaxial {
+        optic_axis: Vector3,
+        n_ordinary: f64,
+        n_extraordinary: f64,
+    },
+    /// Three distinct principal constants, with the principal indices in axis order.
+    Biaxial { indices: Vector3 },
+}
+
+/// Rotation matrix for the Euler angles (rad) in the z-x-z convention, `Rz(α) Rx(β) Rz(γ)`.
+pub fn euler_rotation(alpha: f64, beta: f64, gamma: f64) -> Matrix3 {
+    let rz = |angle| Rotation3::from_axis_angle(&Vector3::z_axis(), angle);
+    (rz(alpha) * Rotation3::from_axis_angle(&Vector3::x_axis(), beta) * rz(gamma)).into_inner()
+}
+
+/// Tensor `R ε Rᵀ` expressed in the lab frame after rotating the medium by `rotation`.
+pub fn rotate_tensor(tensor: &DielectricTensor, rotation: &Matrix3) -> DielectricTensor {
+    rotation * tensor * rotation.transpose()
+}
+
+/// Complex counterpart of [`rotate_tensor`].
+pub fn rotate_complex_tensor(tensor: &ComplexDielectricTensor, rotation: &Matrix3) -> ComplexDielectricTensor {
+    let r = rotation.map(|x| Complex::new(x, 0.0));
+    r * tensor * r.transpose()
+}
+
+/// Principal axes of the symmetric part of `tensor`.
+pub fn principal_axes(tensor: &DielectricTensor) -> PrincipalAxes {
+    let eigen = SymmetricEigen::new((tensor + tensor.transpose()) * 0.5);
+    let permutations = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    let overlap = |p: &[usize; 3]| (0..3).map(|i| eigen.eigenvectors[(i, p[i])].abs()).product::<f64>();
+    let best = permutations
+        .iter()
+        .max_by(|p, q| overlap(p).total_cmp(&overlap(q)))
+        .expect("non-empty permutation list");
+
+    let mut axes = Matrix3::from_columns(&best.map(|j| eigen.eigenvectors.column(j).into_owned()));
+    for i in 0..3 {
+        if axes[(i, i)] < 0.0 {
+            axes.set_column(i, &-axes.column(i));
+        }
+    }
+    if axes.determinant() < 0.0 {
+        let weakest = (0..3)
+            .min_by(|&i, &j| axes[(i, i)].abs().total_cmp(&axes[(j, j)].abs()))
+            .expect("three axes");
+        axes.set_column(weakest, &-axes.column(weakest));
+    }
+    PrincipalAxes {
+        epsilon: Vector3::from(best.map(|j| eigen.eigenvalues[j])),
+        axes,
+    }
+}
+
+/// Diagonal of the complex `tensor` in the principal frame of its real part.
+pub(crate) fn principal_diagonal(tensor: &ComplexDielectricTensor) -> nalgebra::Vector3<Complex> {
+    let principal = principal_axes(&tensor.map(|eps| eps.re));
+    rotate_complex_tensor(tensor, &principal.axes.transpose()).diagonal()
+}
+
+/// Largest element magnitude, used to scale [`TENSOR_TOLERANCE`].
+fn scale(tensor: &ComplexDielectricTensor) -> f64 {
+    tensor.iter().map(|eps| eps.norm()).fold(0.0, f64::max).max(f64::MIN_POSITIVE)
+}
+
+fn max_deviation(tensor: &ComplexDielectricTensor, mirror: impl Fn(Complex) -> Complex) -> f64 {
+    let mut deviation = 0.0f64;
+    for i in 0..3 {
+        for j in i..3 {
+            deviation = deviation.max((tensor[(i, j)] - mirror(tensor[(j, i)])).norm());
+        }
+    }
+    deviation
+}
+
+impl Material {
+    /// Creates a uniaxial material with dielectric constants `eps_o` and `eps_e` and the
+    /// given optic axis, which does not need to be normalized.
+    pub fn new_uniaxial(eps_o: f64, eps_e: f64, optic_axis: Vector3) -> Self {
+        let c = optic_axis.normalize();
+        Self::new_from_tensor(Matrix3::identity() * eps_o + c * c.transpose() * (eps_e - eps_o))
+    }
+
+    /// Checks that the complex tensor is finite and symmetric, as required for reciprocal media.
+    pub fn check_symmetric(&self) -> Result<(), TensorError> {
+        let epsilon = self.checked_complex_epsilon()?;
+        let deviation = max_deviation(&epsilon, |eps| eps);
+        if deviation > TENSOR_TOLERANCE * scale(&epsilon) {
+            return Err(TensorError::NotSymmetric { deviation });
+        }
+        Ok(())
+    }
+
+    /// Checks that the complex tensor is finite and Hermitian, as required for lossless media.
+    pub fn check_hermitian(&self) -> Result<(), TensorError> {
+        let epsilon = self.checked_complex_epsilon()?;
+        let tolerance = TENSOR_TOLERANCE * scale(&epsilon);
+        let deviation = max_deviation(&epsilon, |eps| eps.conj());
+        if deviation > tolerance {
+            return Err(TensorError::NotHermitian { deviation });
+        }
+        Ok(())
+    }
+
+    fn checked_complex_epsilon(&self) -> Result<ComplexDielectricTensor, TensorError> {
+        let epsilon = self.complex_epsilon();
+        if epsilon.iter().all(|eps| eps.re.is_finite() && eps.im.is_finite()) {
+            Ok(epsilon)
+        } else {
+            Err(TensorError::NotFinite)
+        }
+    }
+
+    /// Principal dielectric constants and axes of the real part of the tensor.
+    pub fn principal_axes(&self) -> PrincipalAxes {
+        principal_axes(&self.epsilon_matrix)
+    }
+
+    /// Classifies the real part of the tensor as isotropic, uniaxial or biaxial.
+    pub fn optical_class(&self) -> OpticalClass {
+        let principal = self.principal_axes();
+        let tolerance = TENSOR_TOLERANCE * scale(&self.complex_epsilon());
+        let eps = principal.epsilon;
+        let equal = |i: usize, j: usize| (eps[i] - eps[j]).abs() <= tolerance;
+        let distinct = match (equal(0, 1), equal(1, 2), equal(0, 2)) {
+            (true, true, _) => return OpticalClass::Isotropic,
+            (true, false, _) => 2,
+            (false, true, _) => 0,
+            (false, false, true) => 1,
+            (false, false, false) => {
+                return OpticalClass::Biaxial {
+                    indices: principal.refractive_indices(),
+                }
+            }
+        };
+        let ordinary = (distinct + 1) % 3;
+        OpticalClass::Uniaxial {
+            optic_axis: principal.axes.column(distinct).into_owned(),
+            n_ordinary: eps[ordinary].sqrt(),
+            n_extraordinary: eps[distinct].sqrt(),
+        }
+    }
+
+    /// Returns the material rotated by `rotation`, i.e. with tensor `R ε Rᵀ`.
+    ///
+    /// Dispersive materials keep their model, evaluated in the rotated frame.
+    pub fn rotated(&self, rotation: &Matrix3) -> Self {
+        Self {
+            epsilon_matrix: rotate_tensor(&self.epsilon_matrix, rotation),
+            epsilon_imag: rotate_tensor(&self.epsilon_imag, rotation),
+            dispersion: self.dispersion.clone().map(|dispersion| match dispersion {
+                MaterialDispersion::Rotated { principal, rotation: inner } => MaterialDispersion::Rotated {
+                    principal,
+                    rotation: rotation * inner,
+                },
+                principal => MaterialDispersion::Rotated {
+                    principal: Box::new(principal),
+                    rotation: *rotation,
+                },
+            }),
+            thermal: self.thermal,
+        }
+    }
+
+    /// Returns the material rotated by the z-x-z Euler angles (rad), see [`euler_rotation`].
+    pub fn rotated_euler(&self, alpha: f64, beta: f64, gamma: f64) -> Self {
+        self.rotated(&euler_rotation(alpha, beta, gamma))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispersion::Dispersion;
+    use crate::material::RefractiveIndex;
+    use crate::units::Length;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_off_diagonal_tensor_is_anisotropic() {
+        let tensor = Material::new_from_tensor(Matrix3::new(4.0, 0.5, 0.0, 0.5, 4.0, 0.0, 0.0, 0.0, 4.0));
+        assert!(!tensor.is_isotropic());
+        assert!(tensor.check_symmetric().is_ok());
+        match tensor.refractive_index() {
+            RefractiveIndex::Anisotropic(n) => {
+                // The xy pair is rotated by 45°, so only the set of indices is well defined.
+                assert!((n[0] * n[1] - (4.5f64 * 3.5).sqrt()).abs() < 1e-12);
+                assert!((n[0] + n[1] - 4.5f64.sqrt() - 3.5f64.sqrt()).abs() < 1e-12);
+                assert!((n[2] - 2.0).abs() < 1e-12);
+            }
+            RefractiveIndex::Isotropic(_) => panic!("expected principal indices"),
+        }
+
+        let rotated_isotropic = Material::new_from_eps(12.0).rotated_euler(0.3, 1.1, -0.4);
+        assert!(rotated_isotropic.is_isotropic());
+        assert_eq!(rotated_isotropic.optical_class(), OpticalClass::Isotropic);
+    }
+
+    #[test]
+    fn test_symmetric_and_hermitian_checks() {
+        let asymmetric = Material::new_from_tensor(Matrix3::new(4.0, 0.5, 0.0, 0.0, 4.0, 0.0, 0.0, 0.0, 4.0));
+        assert_eq!(
+            asymmetric.check_symmetric(),
+            Err(TensorError::NotSymmetric { deviation: 0.5 })
+        );
+        assert!(Material::new_anisotropic(2.0, 3.0, 4.0).check_hermitian().is_ok());
+        assert!(matches!(
+            Material::new_from_complex_n(3.5, 0.01).check_hermitian(),
+            Err(TensorError::NotHermitian { .. })
+        ));
+
+        let mut gyrotropic = Material::new_from_eps(4.0);
+        gyrotropic.epsilon_imag[(0, 1)] = 0.1;
+        gyrotropic.epsilon_imag[(1, 0)] = -0.1;
+        assert!(gyrotropic.check_hermitian().is_ok());
+        assert!(gyrotropic.check_symmetric().is_err());
+
+        let mut broken = Material::new_from_eps(4.0);
+        broken.epsilon_matrix[(2, 2)] = f64::NAN;
+        assert_eq!(broken.check_symmetric(), Err(TensorError::NotFinite));
+    }
+
+    #[test]
+    fn test_rotation_and_principal_axes() {
+        let gan = Material::new_uniaxial(5.35, 5.54, Vector3::z());
+        assert!(matches!(
+            gan.optical_class(),
+            OpticalClass::Uniaxial { optic_axis, .. } if (optic_axis - Vector3::z()).norm() < 1e-12
+        ));
+
+        // Tilting the c axis by 90° about x brings it along -y.
+        let tilted = gan.rotated_euler(0.0, FRAC_PI_2, 0.0);
+        assert!((tilted.epsilon_matrix[(1, 1)] - 5.54).abs() < 1e-12);
+        assert!((tilted.epsilon_matrix[(2, 2)] - 5.35).abs() < 1e-12);
+        match tilted.optical_class() {
+            OpticalClass::Uniaxial { optic_axis, n_ordinary, n_extraordinary } => {
+                assert!((optic_axis.dot(&Vector3::y()).abs() - 1.0).abs() < 1e-12);
+                assert!((n_ordinary - 5.35f64.sqrt()).abs() < 1e-12);
+                assert!((n_extraordinary - 5.54f64.sqrt()).abs() < 1e-12);
+            }
+            class => panic!("unexpected class {class:?}"),
+        }
+
+        // Small rotations keep each principal axis closest to its original lab axis.
+        let rotation = euler_rotation(0.2, 0.3, -0.1);
+        let biaxial = Material::new_anisotropic(2.0, 3.0, 4.0).rotated(&rotation);
+        let principal = biaxial.principal_axes();
+        assert!((principal.epsilon - Vector3::new(2.0, 3.0, 4.0)).norm() < 1e-12);
+        assert!((principal.axes.determinant() - 1.0).abs() < 1e-12);
+        let back = rotate_tensor(&biaxial.epsilon_matrix, &principal.axes.transpose());
+        assert!((back - Matrix3::from_diagonal(&principal.epsilon)).norm() < 1e-12);
+        assert!(matches!(biaxial.optical_class(), OpticalClass::Biaxial { .. }));
+    }
+
+    #[test]
+    fn test_rotated_dispersive_and_lossy_material() {
+        let ordinary = Dispersion::Cauchy { coefficients: vec![2.3] };
+        let extraordinary = Dispersion::Cauchy { coefficients: vec![2.4] };
+        let gan = Material::new_from_dispersion(
+            MaterialDispersion::Diagonal(Box::new([ordinary.clone(), ordinary, extraordinary])),
+            Length::micrometers(1.0),
+        );
+        let rotation = euler_rotation(0.0, 0.4, 0.0);
+        let tilted = gan.rotated(&rotation);
+        assert!((tilted.at_wavelength(Length::micrometers(0.5)).epsilon_matrix - tilted.epsilon_matrix).norm() < 1e-12);
+        let twice = tilted.rotated(&rotation);
+        assert!((twice.epsilon_at(Length::micrometers(1.0)) - gan.rotated_euler(0.0, 0.8, 0.0).epsilon_matrix).norm() < 1e-12);
+
+        let lossy = Material::new_from_complex_n(3.5, 0.01).rotated_euler(0.5, 0.5, 0.5);
+        let n = lossy.complex_refractive_indices();
+        assert!((n[1] - Complex::new(3.5, 0.01)).norm() < 1e-12);
+    }
+}
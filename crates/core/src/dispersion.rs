@@ -0,0 +1,261 @@
+//! crates/core/src/dispersion.rs
+//! Wavelength-dependent permittivity models.
+//!
+//! All models are evaluated at a vacuum wavelength. Loss corresponds to a positive imaginary
+//! part of the permittivity.
+use std::fmt;
+
+use nalgebra::Complex;
+use serde::{Deserialize, Serialize};
+
+use super::units::Length;
+
+/// Photon energy times wavelength, in eV·µm.
+const HC_EV_UM: f64 = 1.239_841_984;
+
+/// One term `B λ² / (λ² - C)` of a Sellmeier equation, with `C` in µm².
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SellmeierTerm {
+    pub b: f64,
+    pub c: f64,
+}
+
+/// A Lorentz oscillator `f ωp² / (ω0² - ω² - iγω)`, with energies in eV.
+/// An oscillator with zero `energy` is a Drude (free-carrier) term.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LorentzOscillator {
+    pub strength: f64,
+    pub energy: f64,
+    pub damping: f64,
+}
+
+/// A dispersion model for a scalar permittivity.
+///
+/// Serialized with a `model` tag, e.g. `{ model = "cauchy", coefficients = [1.5, 0.01] }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Dispersion {
+    /// `n² = a + Σ B λ² / (λ² - C)`, with λ in µm.
+    Sellmeier { a: f64, terms: Vec<SellmeierTerm> },
+    /// `n = Σ c_k / λ^(2k)`, with λ in µm.
+    Cauchy { coefficients: Vec<f64> },
+    /// `ε = ε∞ + Σ f ωp² / (ω0² - ω² - iγω)`, with photon energies in eV.
+    LorentzDrude {
+        eps_inf: f64,
+        plasma_energy: f64,
+        oscillators: Vec<LorentzOscillator>,
+    },
+    /// Adachi's modified single-oscillator model for III-V semiconductors,
+    /// `ε = A [f(χ) + ½ (E0 / (E0 + Δ0))^1.5 f(χ_so)] + B`, with `χ = ħω / E0`,
+    /// `χ_so = ħω / (E0 + Δ0)` and energies in eV. Above `E0` the imaginary part
+    /// describes the band-edge absorption.
+    Adachi { a: f64, b: f64, e0: f64, delta0: f64 },
+    /// Linearly interpolated `n` and `k`, clamped outside the tabulated range.
+    /// Wavelengths are in meters and strictly increasing.
+    Tabulated {
+        wavelengths: Vec<f64>,
+        n: Vec<f64>,
+        k: Vec<f64>,
+    },
+}
+
+/// Reasons why tabulated data cannot form a dispersion model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DispersionError {
+    /// The table has no rows.
+    Empty,
+    /// The wavelength, `n` and `k` columns have different lengths.
+    MismatchedLengths,
+    /// The wavelengths are not strictly increasing.
+    NotIncreasing,
+}
+
+impl fmt::Display for DispersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispersionError::Empty => write!(f, "tabulated dispersion has no data"),
+            DispersionError::MismatchedLengths => {
+                write!(f, "wavelength, n and k columns must have the same length")
+            }
+            DispersionError::NotIncreasing => write!(f, "tabulated wavelengths must be strictly increasing"),
+        }
+    }
+}
+
+impl std::error::Error for DispersionError {}
+
+impl Dispersion {
+    /// Creates a tabulated model after checking the table.
+    pub fn tabulated(wavelengths: Vec<f64>, n: Vec<f64>, k: Vec<f64>) -> Result<Self, DispersionError> {
+        if wavelengths.is_empty() {
+            return Err(DispersionError::Empty);
+        }
+        if wavelengths.len() != n.len() || wavelengths.len() != k.len() {
+            return Err(DispersionError::MismatchedLengths);
+        }
+        if wavelengths.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(DispersionError::NotIncreasing);
+        }
+        Ok(Dispersion::Tabulated { wavelengths, n, k })
+    }
+
+    /// Checks the parameters that cannot be enforced by construction, i.e. the tabulated data.
+    pub fn validate(&self) -> Result<(), DispersionError> {
+        match self {
+            Dispersion::Tabulated { wavelengths, n, k } => {
+                Dispersion::tabulated(wavelengths.clone(), n.clone(), k.clone()).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Complex permittivity at the vacuum `wavelength`.
+    pub fn permittivity(&self, wavelength: Length) -> Complex<f64> {
+        let lambda_um = wavelength.as_micrometers();
+        match self {
+            Dispersion::Sellmeier { a, terms } => {
+                let l2 = lambda_um * lambda_um;
+                let n2 = a + terms.iter().map(|t| t.b * l2 / (l2 - t.c)).sum::<f64>();
+                Complex::new(n2, 0.0)
+            }
+            Dispersion::Cauchy { coefficients } => {
+                let inv_l2 = 1.0 / (lambda_um * lambda_um);
+                let n = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0.0, |acc, c| acc * inv_l2 + c);
+                Complex::new(n * n, 0.0)
+            }
+            Dispersion::LorentzDrude {
+                eps_inf,
+                plasma_energy,
+                oscillators,
+            } => {
+                let omega = HC_EV_UM / lambda_um;
+                let wp2 = plasma_energy * plasma_energy;
+                oscillators
+                    .iter()
+                    .fold(Complex::new(*eps_inf, 0.0), |eps, osc| {
+                        let denominator = Complex::new(osc.energy * osc.energy - omega * omega, -osc.damping * omega);
+                        eps + osc.strength * wp2 / denominator
+                    })
+            }
+            Dispersion::Adachi { a, b, e0, delta0 } => {
+                let energy = HC_EV_UM / lambda_um;
+                let f = |chi: f64| {
+                    // Principal branch below the gap, absorbing branch (Im ε > 0) above it.
+                    let below = 1.0 - chi;
+                    let root = if below >= 0.0 {
+                        Complex::new(below.sqrt(), 0.0)
+                    } else {
+                        Complex::new(0.0, -(-below).sqrt())
+                    };
+                    (2.0 - (1.0 + chi).sqrt() - root) / (chi * chi)
+                };
+                let split_off = 0.5 * (e0 / (e0 + delta0)).powf(1.5) * f(energy / (e0 + delta0));
+                (f(energy / e0) + split_off) * *a + b
+            }
+            Dispersion::Tabulated { .. } => {
+                let n = self.refractive_index(wavelength);
+                n * n
+            }
+        }
+    }
+
+    /// Complex refractive index `n + ik` at the vacuum `wavelength`.
+    pub fn refractive_index(&self, wavelength: Length) -> Complex<f64> {
+        match self {
+            Dispersion::Tabulated { wavelengths, n, k } => {
+                let wavelength = wavelength.as_meters();
+                let last = wavelengths.len() - 1;
+                let i = wavelengths.partition_point(|&w| w < wavelength);
+                if i == 0 {
+                    Complex::new(n[0], k[0])
+                } else if i > last {
+                    Complex::new(n[last], k[last])
+                } else {
+                    let x = (wavelength - wavelengths[i - 1]) / (wavelengths[i] - wavelengths[i - 1]);
+                    Complex::new(n[i - 1] + x * (n[i] - n[i - 1]), k[i - 1] + x * (k[i] - k[i - 1]))
+                }
+            }
+            _ => self.permittivity(wavelength).sqrt(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sellmeier_fused_silica() {
+        // Malitson (1965) coefficients for fused silica.
+        let silica = Dispersion::Sellmeier {
+            a: 1.0,
+            terms: vec![
+                SellmeierTerm { b: 0.696_166_3, c: 0.068_404_3f64.powi(2) },
+                SellmeierTerm { b: 0.407_942_6, c: 0.116_241_4f64.powi(2) },
+                SellmeierTerm { b: 0.897_479_4, c: 9.896_161f64.powi(2) },
+            ],
+        };
+        let n = silica.refractive_index(Length::micrometers(1.55));
+        assert!((n.re - 1.444).abs() < 1e-3);
+        assert_eq!(n.im, 0.0);
+    }
+
+    #[test]
+    fn test_cauchy() {
+        let model = Dispersion::Cauchy {
+            coefficients: vec![1.5, 0.01],
+        };
+        let n = model.refractive_index(Length::micrometers(0.5));
+        assert!((n.re - 1.54).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_drude_metal_is_lossy_and_negative() {
+        let metal = Dispersion::LorentzDrude {
+            eps_inf: 1.0,
+            plasma_energy: 9.0,
+            oscillators: vec![LorentzOscillator {
+                strength: 1.0,
+                energy: 0.0,
+                damping: 0.05,
+            }],
+        };
+        let eps = metal.permittivity(Length::micrometers(1.0));
+        let omega = HC_EV_UM;
+        let expected = 1.0 - 81.0 / Complex::new(omega * omega, 0.05 * omega);
+        assert!((eps - expected).norm() < 1e-12);
+        assert!(eps.re < 0.0 && eps.im > 0.0);
+    }
+
+    #[test]
+    fn test_adachi_gaas() {
+        // Adachi (1985) parameters of GaAs at room temperature.
+        let gaas = Dispersion::Adachi {
+            a: 6.3,
+            b: 9.4,
+            e0: 1.425,
+            delta0: 0.34,
+        };
+        let n = gaas.refractive_index(Length::micrometers(0.94));
+        assert!((n.re - 3.552).abs() < 1e-3);
+        assert_eq!(n.im, 0.0);
+        assert!(gaas.permittivity(Length::micrometers(0.8)).im > 0.0);
+    }
+
+    #[test]
+    fn test_tabulated_interpolation() {
+        assert_eq!(
+            Dispersion::tabulated(vec![1.0, 0.5], vec![1.0, 1.0], vec![0.0, 0.0]),
+            Err(DispersionError::NotIncreasing)
+        );
+        let model = Dispersion::tabulated(vec![0.9e-6, 1.0e-6], vec![3.6, 3.5], vec![0.0, 0.02]).unwrap();
+        let n = model.refractive_index(Length::micrometers(0.95));
+        assert!((n.re - 3.55).abs() < 1e-12 && (n.im - 0.01).abs() < 1e-12);
+        assert_eq!(model.refractive_index(Length::micrometers(2.0)), Complex::new(3.5, 0.02));
+        let eps = model.permittivity(Length::micrometers(0.9));
+        assert!((eps.re - 3.6 * 3.6).abs() < 1e-12);
+    }
+}
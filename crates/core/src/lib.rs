@@ -4,8 +4,14 @@
 
 
 pub mod material;
+pub mod dispersion;
+pub mod alloys;
+pub mod anisotropy;
+pub mod library;
 pub mod vectorial;
 pub mod shapes;
+pub mod units;
+pub mod thermal;
 
 // re-export nlaalgebra for convenience
 pub use nalgebra;
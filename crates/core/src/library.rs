@@ -0,0 +1,365 @@
+//! crates/core/src/library.rs
+//! File-based material libraries.
+//!
+//! A library is a TOML document with one `[[material]]` table per entry, see
+//! `data/materials.toml` for the built-in library and a description of the format.
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::dispersion::{Dispersion, DispersionError};
+use super::material::{Material, MaterialDispersion};
+use super::thermal::ThermoOptic;
+use super::units::Length;
+
+/// The built-in library shipped with the crate.
+const BUILTIN_LIBRARY: &str = include_str!("../data/materials.toml");
+
+/// Orientation dependence of a library material. The optic axis of uniaxial media is z.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Anisotropy {
+    /// The entry `dispersion` applies to all axes.
+    #[default]
+    Isotropic,
+    /// The entry `dispersion` applies to x and y, `extraordinary` to z.
+    Uniaxial { extraordinary: Dispersion },
+    /// The entry `dispersion` applies to x.
+    Biaxial { y: Dispersion, z: Dispersion },
+}
+
+/// Where the data of an entry come from and where it may be used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Citation or description of the data source.
+    pub source: String,
+    /// Wavelength interval (m) in which the model is trusted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_range: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+}
+
+/// One named material of a library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub dispersion: Dispersion,
+    #[serde(default)]
+    pub anisotropy: Anisotropy,
+    /// Optional thermo-optic coefficient; the dispersion model holds at its reference temperature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thermo_optic: Option<ThermoOptic>,
+    pub provenance: Provenance,
+}
+
+impl LibraryEntry {
+    /// Per-axis dispersion model of the entry.
+    pub fn material_dispersion(&self) -> MaterialDispersion {
+        match &self.anisotropy {
+            Anisotropy::Isotropic => MaterialDispersion::Isotropic(self.dispersion.clone()),
+            Anisotropy::Uniaxial { extraordinary } => MaterialDispersion::Diagonal(Box::new([
+                self.dispersion.clone(),
+                self.dispersion.clone(),
+                extraordinary.clone(),
+            ])),
+            Anisotropy::Biaxial { y, z } => {
+                MaterialDispersion::Diagonal(Box::new([self.dispersion.clone(), y.clone(), z.clone()]))
+            }
+        }
+    }
+
+    /// Whether `wavelength` lies in the validity range, if one is given.
+    pub fn is_valid_at(&self, wavelength: Length) -> bool {
+        self.provenance
+            .valid_range
+            .is_none_or(|[min, max]| (min..=max).contains(&wavelength.as_meters()))
+    }
+
+    fn check(&self) -> Result<(), LibraryError> {
+        let models: Vec<&Dispersion> = match &self.anisotropy {
+            Anisotropy::Isotropic => vec![&self.dispersion],
+            Anisotropy::Uniaxial { extraordinary } => vec![&self.dispersion, extraordinary],
+            Anisotropy::Biaxial { y, z } => vec![&self.dispersion, y, z],
+        };
+        models.iter().try_for_each(|model| {
+            model.validate().map_err(|error| LibraryError::InvalidDispersion {
+                name: self.name.clone(),
+                error,
+            })
+        })
+    }
+}
+
+/// Reasons why a library cannot be loaded or a material cannot be resolved.
+#[derive(Debug)]
+pub enum LibraryError {
+    /// The library file could not be read.
+    Io(std::io::Error),
+    /// The document is not a valid library.
+    Parse(toml::de::Error),
+    /// Two entries share the same name.
+    DuplicateName(String),
+    /// An entry carries an inconsistent dispersion model.
+    InvalidDispersion { name: String, error: DispersionError },
+    /// No entry has the requested name.
+    UnknownMaterial(String),
+    /// The wavelength lies outside the validity range of the entry.
+    OutOfRange { name: String, wavelength: Length, range: [f64; 2] },
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(error) => write!(f, "cannot read material library: {error}"),
+            LibraryError::Parse(error) => write!(f, "invalid material library: {error}"),
+            LibraryError::DuplicateName(name) => write!(f, "material '{name}' is defined more than once"),
+            LibraryError::InvalidDispersion { name, error } => write!(f, "material '{name}': {error}"),
+            LibraryError::UnknownMaterial(name) => write!(f, "unknown material '{name}'"),
+            LibraryError::OutOfRange { name, wavelength, range } => write!(
+                f,
+                "wavelength {wavelength} is outside the valid range [{:e}, {:e}] m of '{name}'",
+                range[0], range[1]
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LibraryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LibraryError::Io(error) => Some(error),
+            LibraryError::Parse(error) => Some(error),
+            LibraryError::InvalidDispersion { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A collection of named materials. Names are matched case-insensitively.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MaterialLibrary {
+    #[serde(rename = "material", default)]
+    pub entries: Vec<LibraryEntry>,
+}
+
+impl MaterialLibrary {
+    /// The built-in library: GaAs, AlGaAs, InP, GaN, Si, SiO2, SiN and ITO.
+    pub fn builtin() -> Self {
+        Self::from_toml_str(BUILTIN_LIBRARY).expect("the built-in material library is valid")
+    }
+
+    /// Parses and checks a library document.
+    pub fn from_toml_str(document: &str) -> Result<Self, LibraryError> {
+        let library: Self = toml::from_str(document).map_err(LibraryError::Parse)?;
+        for (i, entry) in library.entries.iter().enumerate() {
+            if library.entries[..i]
+                .iter()
+                .any(|other| other.name.eq_ignore_ascii_case(&entry.name))
+            {
+                return Err(LibraryError::DuplicateName(entry.name.clone()));
+            }
+            entry.check()?;
+        }
+        Ok(library)
+    }
+
+    /// Reads a library file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LibraryError> {
+        let document = std::fs::read_to_string(path).map_err(LibraryError::Io)?;
+        Self::from_toml_str(&document)
+    }
+
+    /// Serializes the library to a TOML document.
+    pub fn to_toml_string(&self) -> String {
+        toml::to_string_pretty(self).expect("material libraries are representable in TOML")
+    }
+
+    /// Adds the entries of `other`, replacing entries with the same name.
+    pub fn extend(&mut self, other: MaterialLibrary) {
+        for entry in other.entries {
+            match self.entries.iter_mut().find(|e| e.name.eq_ignore_ascii_case(&entry.name)) {
+                Some(existing) => *existing = entry,
+                None => self.entries.push(entry),
+            }
+        }
+    }
+
+    /// Names of the entries, in library order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Looks up an entry by name.
+    pub fn get(&self, name: &str) -> Option<&LibraryEntry> {
+        self.entries.iter().find(|entry| entry.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Resolves `name` into a dispersive material evaluated at `wavelength`.
+    pub fn material(&self, name: &str, wavelength: Length) -> Result<Material, LibraryError> {
+        let entry = self
+            .get(name)
+            .ok_or_else(|| LibraryError::UnknownMaterial(name.to_string()))?;
+        if let (false, Some(range)) = (entry.is_valid_at(wavelength), entry.provenance.valid_range) {
+            return Err(LibraryError::OutOfRange {
+                name: entry.name.clone(),
+                wavelength,
+                range,
+            });
+        }
+        let material = Material::new_from_dispersion(entry.material_dispersion(), wavelength);
+        Ok(match entry.thermo_optic {
+            Some(thermal) => material.with_thermo_optic(thermal.dn_dt, thermal.reference_temperature),
+            None => material,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_library() {
+        let library = MaterialLibrary::builtin();
+        let names: Vec<&str> = library.names().collect();
+        assert_eq!(names, ["GaAs", "AlGaAs", "InP", "GaN", "Si", "SiO2", "SiN", "ITO"]);
+
+        let n = |name: &str, wavelength: f64| {
+            library.material(name, Length::meters(wavelength)).unwrap().epsilon_matrix[(0, 0)].sqrt()
+        };
+        assert!((n("GaAs", 0.94e-6) - 3.552).abs() < 1e-3);
+        assert!((n("InP", 1.55e-6) - 3.17).abs() < 0.02);
+        assert!((n("Si", 1.55e-6) - 3.476).abs() < 5e-3);
+        assert!((n("SiO2", 1.55e-6) - 1.444).abs() < 1e-3);
+        assert!((n("SiN", 1.55e-6) - 1.996).abs() < 5e-3);
+        assert!((n("GaN", 1.0e-6) - 2.33).abs() < 0.01);
+
+        let clad = library.material("algaas", Length::micrometers(0.94)).unwrap();
+        let alloy = Material::al_ga_as(0.4, Length::micrometers(0.94), 300.0).unwrap();
+        assert!((clad.epsilon_matrix[(0, 0)] - alloy.epsilon_matrix[(0, 0)]).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_thermal_and_lossy_entries() {
+        let library = MaterialLibrary::builtin();
+        let gan = library.material("GaN", Length::micrometers(0.45)).unwrap();
+        assert!(gan.is_isotropic());
+
+        let silicon = library.material("Si", Length::micrometers(1.55)).unwrap();
+        let hot = silicon.at_temperature(Length::micrometers(1.55), 343.0).unwrap();
+        let dn = hot.epsilon_matrix[(0, 0)].sqrt() - silicon.epsilon_matrix[(0, 0)].sqrt();
+        assert!((dn - 50.0 * 1.8e-4).abs() < 1e-12);
+
+        let ito = library.material("ITO", Length::micrometers(1.55)).unwrap();
+        assert!(!ito.is_lossless());
+        assert!(ito.epsilon_matrix[(0, 0)] < 0.0);
+        // Epsilon-near-zero wavelength of the cited film.
+        let enz = library.material("ITO", Length::micrometers(1.24)).unwrap();
+        assert!(enz.epsilon_matrix[(0, 0)].abs() < 0.05);
+    }
+
+    #[test]
+    fn test_resolution_errors() {
+        let library = MaterialLibrary::builtin();
+        assert!(matches!(
+            library.material("unobtainium", Length::micrometers(1.0)),
+            Err(LibraryError::UnknownMaterial(_))
+        ));
+        assert!(matches!(
+            library.material("Si", Length::micrometers(0.94)),
+            Err(LibraryError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn test_user_library() {
+        let document = r#"
+            [[material]]
+            name = "Polymer"
+            dispersion = { model = "cauchy", coefficients = [1.5, 0.01] }
+            provenance = { source = "datasheet" }
+
+            [[material]]
+            name = "GaAs"
+            dispersion = { model = "tabulated", wavelengths = [0.9e-6, 1.0e-6], n = [3.6, 3.5], k = [0.0, 0.0] }
+            provenance = { source = "ellipsometry", notes = "wafer 17" }
+
+            [[material]]
+            name = "LiquidCrystal"
+            dispersion = { model = "cauchy", coefficients = [1.5, 0.01] }
+            anisotropy = { kind = "uniaxial", extraordinary = { model = "cauchy", coefficients = [1.7, 0.02] } }
+            provenance = { source = "datasheet" }
+
+            [[material]]
+            name = "KTP"
+            dispersion = { model = "cauchy", coefficients = [1.7] }
+            anisotropy = { kind = "biaxial", y = { model = "cauchy", coefficients = [1.75] }, z = { model = "cauchy", coefficients = [1.8, 0.04] } }
+            provenance = { source = "datasheet" }
+        "#;
+        let user = MaterialLibrary::from_toml_str(document).unwrap();
+        assert_eq!(MaterialLibrary::from_toml_str(&user.to_toml_string()).unwrap(), user);
+
+        let mut library = MaterialLibrary::builtin();
+        library.extend(user);
+        assert_eq!(library.entries.len(), 11);
+        assert_eq!(library.get("GaAs").unwrap().provenance.source, "ellipsometry");
+        let polymer = library.material("polymer", Length::micrometers(0.5)).unwrap();
+        assert!((polymer.epsilon_matrix[(0, 0)] - 1.54 * 1.54).abs() < 1e-12);
+
+        // Anisotropic entries resolve to one dispersion per axis, evaluated at 0.5 µm.
+        for (name, diagonal) in [("LiquidCrystal", [1.54, 1.54, 1.78]), ("KTP", [1.7, 1.75, 1.96])] {
+            assert!(matches!(
+                library.get(name).unwrap().material_dispersion(),
+                MaterialDispersion::Diagonal(_)
+            ));
+            let epsilon = library.material(name, Length::micrometers(0.5)).unwrap().epsilon_matrix;
+            for (axis, n) in diagonal.into_iter().enumerate() {
+                assert!((epsilon[(axis, axis)] - n * n).abs() < 1e-12, "{name}: {epsilon}");
+            }
+            assert_eq!(epsilon[(0, 2)], 0.0);
+        }
+        // A uniaxial entry needs its extraordinary dispersion.
+        let missing = document.replace(r#"kind = "uniaxial", extraordinary = { model = "cauchy", coefficients = [1.7, 0.02] }"#, r#"kind = "uniaxial""#);
+        assert_ne!(missing, document);
+        assert!(matches!(MaterialLibrary::from_toml_str(&missing), Err(LibraryError::Parse(_))));
+    }
+
+    #[test]
+    fn test_invalid_libraries() {
+        let duplicate = r#"
+            [[material]]
+            name = "A"
+            dispersion = { model = "cauchy", coefficients = [1.5] }
+            provenance = { source = "" }
+            [[material]]
+            name = "a"
+            dispersion = { model = "cauchy", coefficients = [1.6] }
+            provenance = { source = "" }
+        "#;
+        assert!(matches!(
+            MaterialLibrary::from_toml_str(duplicate),
+            Err(LibraryError::DuplicateName(name)) if name == "a"
+        ));
+
+        let unsorted = r#"
+            [[material]]
+            name = "B"
+            dispersion = { model = "tabulated", wavelengths = [1.0e-6, 0.9e-6], n = [3.5, 3.6], k = [0.0, 0.0] }
+            provenance = { source = "" }
+        "#;
+        assert!(matches!(
+            MaterialLibrary::from_toml_str(unsorted),
+            Err(LibraryError::InvalidDispersion { error: DispersionError::NotIncreasing, .. })
+        ));
+
+        let unknown_model = "[[material]]\nname = \"C\"\ndispersion = { model = \"magic\" }\nprovenance = { source = \"\" }\n";
+        assert!(matches!(
+            MaterialLibrary::from_toml_str(unknown_model),
+            Err(LibraryError::Parse(_))
+        ));
+    }
+}
@@ -1,7 +1,14 @@
 //! crates/core/src/material.rs
-use super::vectorial::{Matrix3, Vector3};
+use super::anisotropy::{principal_axes, principal_diagonal, rotate_complex_tensor, TENSOR_TOLERANCE};
+use super::dispersion::Dispersion;
+use super::thermal::{shift_index, Thermal};
+use super::units::Length;
+use super::vectorial::{Complex, ComplexMatrix3, Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
 /// Alias for the dielectric tensor represented as a 3x3 matrix.
 pub type DielectricTensor = Matrix3;
+/// Alias for a complex dielectric tensor, with loss as a positive imaginary part.
+pub type ComplexDielectricTensor = ComplexMatrix3;
 
 /// Represents either isotropic or anisotropic dielectric constants.
 pub enum DielectricConstant {
@@ -27,12 +34,63 @@ pub const EPSILON_VACUUM: f64 = 1.0;
 pub const EPSILON_AIR: f64 = 1.0;
 
 
+/// Wavelength dependence of the principal dielectric constants of a material.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialDispersion {
+    /// The same model along every axis.
+    Isotropic(Dispersion),
+    /// Separate models along the x, y and z axes.
+    Diagonal(Box<[Dispersion; 3]>),
+    /// A model in the principal frame, rotated into the lab frame as `R ε Rᵀ`.
+    Rotated {
+        principal: Box<MaterialDispersion>,
+        rotation: Matrix3,
+    },
+}
+
+impl MaterialDispersion {
+    /// Complex dielectric tensor at the vacuum `wavelength`.
+    pub fn complex_epsilon_tensor(&self, wavelength: Length) -> ComplexDielectricTensor {
+        match self {
+            MaterialDispersion::Isotropic(model) => {
+                ComplexDielectricTensor::from_diagonal_element(model.permittivity(wavelength))
+            }
+            MaterialDispersion::Diagonal(models) => ComplexDielectricTensor::from_diagonal(&nalgebra::Vector3::new(
+                models[0].permittivity(wavelength),
+                models[1].permittivity(wavelength),
+                models[2].permittivity(wavelength),
+            )),
+            MaterialDispersion::Rotated { principal, rotation } => {
+                rotate_complex_tensor(&principal.complex_epsilon_tensor(wavelength), rotation)
+            }
+        }
+    }
+
+    /// Real part of the dielectric tensor at the vacuum `wavelength`.
+    pub fn epsilon_tensor(&self, wavelength: Length) -> DielectricTensor {
+        self.complex_epsilon_tensor(wavelength).map(|eps| eps.re)
+    }
+}
+
 /// Represents the physical properties of a material.
-/// For now, assumes a diagonal, isotropic dielectric tensor.
-#[derive(Debug, Clone, PartialEq, Copy)]
+///
+/// `epsilon_matrix` holds the dielectric tensor at a single wavelength. Dispersive
+/// materials additionally carry a model and can be re-evaluated with [`Material::at_wavelength`].
+/// Absorption and gain are carried by `epsilon_imag`, positive for loss and negative for gain.
+///
+/// Serialized as a table with `epsilon` and optional `epsilon_imag`, `dispersion` (one model),
+/// `axis_dispersion` (models along x, y and z), `rotation` and `thermal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "MaterialSpec", into = "MaterialSpec")]
 pub struct Material {
-    /// Diagonal elements of the dielectric tensor $(\epsilon_x, \epsilon_y, \epsilon_z)$.
+    /// Real part of the dielectric tensor, diagonal $(\epsilon_x, \epsilon_y, \epsilon_z)$ in the principal frame.
     pub epsilon_matrix: DielectricTensor,
+    /// Imaginary part of the dielectric tensor.
+    pub epsilon_imag: DielectricTensor,
+    /// Optional wavelength dependence. `None` means a constant dielectric tensor.
+    pub dispersion: Option<MaterialDispersion>,
+    /// Optional temperature dependence and the temperature the tensor is evaluated at.
+    pub thermal: Option<Thermal>,
 }
 
 
@@ -42,6 +100,9 @@ impl Material {
         let eps = n * n;
         Self {
             epsilon_matrix: DielectricTensor::from_diagonal_element(eps),
+            epsilon_imag: DielectricTensor::zeros(),
+            dispersion: None,
+            thermal: None,
         }
     }
 
@@ -49,6 +110,9 @@ impl Material {
     pub fn new_from_eps(eps: f64) -> Self {
         Self {
             epsilon_matrix: DielectricTensor::from_diagonal_element(eps),
+            epsilon_imag: DielectricTensor::zeros(),
+            dispersion: None,
+            thermal: None,
         }
     }
     
@@ -57,32 +121,146 @@ impl Material {
     pub fn new_anisotropic(eps_x: f64, eps_y: f64, eps_z: f64) -> Self {
         Self {
             epsilon_matrix: DielectricTensor::from_diagonal(&Vector3::new(eps_x, eps_y, eps_z)),
+            epsilon_imag: DielectricTensor::zeros(),
+            dispersion: None,
+            thermal: None,
         }
     }
 
     /// Creates a new anisotropic material from a full dielectric tensor.
     pub fn new_from_tensor(epsilon_matrix: DielectricTensor) -> Self {
-        Self { epsilon_matrix }
+        Self {
+            epsilon_matrix,
+            epsilon_imag: DielectricTensor::zeros(),
+            dispersion: None,
+            thermal: None,
+        }
+    }
+
+    /// Creates a new isotropic material from a complex refractive index `n + ik`.
+    pub fn new_from_complex_n(n: f64, k: f64) -> Self {
+        let n = Complex::new(n, k);
+        Self::new_from_complex_eps(n * n)
+    }
+
+    /// Creates a new isotropic material from a complex dielectric constant `eps`.
+    pub fn new_from_complex_eps(eps: Complex) -> Self {
+        Self::new_from_complex_tensor(ComplexDielectricTensor::from_diagonal_element(eps))
+    }
+
+    /// Creates a new material from a full complex dielectric tensor.
+    pub fn new_from_complex_tensor(epsilon: ComplexDielectricTensor) -> Self {
+        Self {
+            epsilon_matrix: epsilon.map(|eps| eps.re),
+            epsilon_imag: epsilon.map(|eps| eps.im),
+            dispersion: None,
+            thermal: None,
+        }
+    }
+
+    /// Creates a new isotropic dispersive material, evaluated at `reference_wavelength`.
+    pub fn new_dispersive(model: Dispersion, reference_wavelength: Length) -> Self {
+        Self::new_from_dispersion(MaterialDispersion::Isotropic(model), reference_wavelength)
+    }
+
+    /// Creates a new dispersive material from a per-axis model, evaluated at `reference_wavelength`.
+    pub fn new_from_dispersion(dispersion: MaterialDispersion, reference_wavelength: Length) -> Self {
+        let epsilon = dispersion.complex_epsilon_tensor(reference_wavelength);
+        Self {
+            epsilon_matrix: epsilon.map(|eps| eps.re),
+            epsilon_imag: epsilon.map(|eps| eps.im),
+            dispersion: Some(dispersion),
+            thermal: None,
+        }
+    }
+
+    /// Check if the material depends on wavelength.
+    pub fn is_dispersive(&self) -> bool {
+        self.dispersion.is_some()
+    }
+
+    /// Dielectric tensor at the vacuum `wavelength`.
+    ///
+    /// Non-dispersive materials return their constant tensor.
+    pub fn epsilon_at(&self, wavelength: Length) -> DielectricTensor {
+        self.complex_epsilon_at(wavelength).map(|eps| eps.re)
+    }
+
+    /// Complex dielectric tensor at the vacuum `wavelength`.
+    pub fn complex_epsilon_at(&self, wavelength: Length) -> ComplexDielectricTensor {
+        match &self.dispersion {
+            Some(dispersion) => {
+                let epsilon = dispersion.complex_epsilon_tensor(wavelength);
+                match self.thermal.and_then(|thermal| thermal.index_shift()) {
+                    Some(dn) => shift_index(&epsilon, dn),
+                    None => epsilon,
+                }
+            }
+            None => self.complex_epsilon(),
+        }
+    }
+
+    /// Returns a copy of the material evaluated at the vacuum `wavelength`.
+    pub fn at_wavelength(&self, wavelength: Length) -> Self {
+        let epsilon = self.complex_epsilon_at(wavelength);
+        Self {
+            epsilon_matrix: epsilon.map(|eps| eps.re),
+            epsilon_imag: epsilon.map(|eps| eps.im),
+            dispersion: self.dispersion.clone(),
+            thermal: self.thermal,
+        }
+    }
+
+    /// The complex dielectric tensor `epsilon_matrix + i epsilon_imag`.
+    pub fn complex_epsilon(&self) -> ComplexDielectricTensor {
+        self.epsilon_matrix.zip_map(&self.epsilon_imag, Complex::new)
     }
 
-    /// Check if the material is isotropic.
+    /// Check if the material has neither loss nor gain.
+    pub fn is_lossless(&self) -> bool {
+        self.epsilon_imag.iter().all(|&eps| eps == 0.0)
+    }
+
+    /// Complex refractive indices `n + ik` along the principal axes of `epsilon_matrix`.
+    pub fn complex_refractive_indices(&self) -> nalgebra::Vector3<Complex> {
+        principal_diagonal(&self.complex_epsilon()).map(|eps| eps.sqrt())
+    }
+
+    /// Intensity absorption coefficients `4πk/λ` (1/m) along the principal axes at the vacuum
+    /// `wavelength`. Negative values correspond to gain.
+    pub fn absorption_coefficients(&self, wavelength: Length) -> Vector3 {
+        let n = principal_diagonal(&self.complex_epsilon_at(wavelength)).map(|eps| eps.sqrt());
+        n.map(|n| 4.0 * std::f64::consts::PI * n.im / wavelength.as_meters())
+    }
+
+    /// Check if the material is isotropic, i.e. both parts of the tensor are multiples of
+    /// the identity up to [`TENSOR_TOLERANCE`].
     pub fn is_isotropic(&self) -> bool {
-        let d = self.epsilon_matrix.diagonal();
-        (d[0] == d[1]) && (d[1] == d[2])
-    }   
+        let scale = self
+            .complex_epsilon()
+            .iter()
+            .map(|eps| eps.norm())
+            .fold(0.0, f64::max);
+        let is_scalar = |tensor: &DielectricTensor| {
+            let mean = tensor.trace() / 3.0;
+            (tensor - DielectricTensor::from_diagonal_element(mean))
+                .iter()
+                .all(|x| x.abs() <= TENSOR_TOLERANCE * scale)
+        };
+        is_scalar(&self.epsilon_matrix) && is_scalar(&self.epsilon_imag)
+    }
 
     /// Check if the material is anisotropic.
     pub fn is_anisotropic(&self) -> bool {
         !self.is_isotropic()
     }
 
-    /// Get the refractive index.
+    /// Get the refractive index, along the principal axes for anisotropic materials.
     pub fn refractive_index(&self) -> RefractiveIndex {
-        let d = self.epsilon_matrix.diagonal();
         if self.is_isotropic() {
-            RefractiveIndex::Isotropic(d[0].sqrt())
+            RefractiveIndex::Isotropic(self.epsilon_matrix[(0, 0)].sqrt())
         } else {
-            RefractiveIndex::Anisotropic(Vector3::new(d[0].sqrt(), d[1].sqrt(), d[2].sqrt()))
+            RefractiveIndex::Anisotropic(principal_axes(&self.epsilon_matrix).refractive_indices())
         }
     }
 
@@ -104,6 +282,140 @@ impl Default for Material {
     }
 }
 
+/// Non-dispersive approximations of a few materials. See [`crate::library::MaterialLibrary`]
+/// for wavelength-dependent models.
+pub enum CommonMaterials {
+    Vacuum,
+    Air,
+    Silicon,
+    SiliconDioxide,
+    IndiumPhosphide,
+}
+
+impl From<CommonMaterials> for Material {
+    fn from(mat: CommonMaterials) -> Self {
+        match mat {
+            CommonMaterials::Vacuum => Material::new_from_eps(EPSILON_VACUUM),
+            CommonMaterials::Air => Material::new_from_eps(EPSILON_AIR),
+            CommonMaterials::Silicon => Material::new_from_eps(EPSILON_SILICON),
+            CommonMaterials::IndiumPhosphide => Material::new_from_eps(EPSILON_INDIUM_PHOSPHIDE),
+            CommonMaterials::SiliconDioxide => Material::new_from_eps(EPSILON_SILICON_DIOXIDE),
+        }
+    }
+}
+
+/// A dielectric tensor as written in documents: a number for isotropic tensors, three
+/// values for diagonal ones, or all nine elements row by row.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, expecting = "a number, three diagonal values or a 3 x 3 matrix")]
+enum TensorSpec {
+    Isotropic(f64),
+    Diagonal([f64; 3]),
+    Full([[f64; 3]; 3]),
+}
+
+impl From<&Matrix3> for TensorSpec {
+    /// The shortest form that holds `tensor` exactly.
+    fn from(tensor: &Matrix3) -> Self {
+        let diagonal = [tensor[(0, 0)], tensor[(1, 1)], tensor[(2, 2)]];
+        if *tensor != Matrix3::from_diagonal(&Vector3::from(diagonal)) {
+            TensorSpec::Full([0, 1, 2].map(|r| [0, 1, 2].map(|c| tensor[(r, c)])))
+        } else if diagonal.iter().all(|&eps| eps == diagonal[0]) {
+            TensorSpec::Isotropic(diagonal[0])
+        } else {
+            TensorSpec::Diagonal(diagonal)
+        }
+    }
+}
+
+impl From<TensorSpec> for Matrix3 {
+    fn from(spec: TensorSpec) -> Self {
+        match spec {
+            TensorSpec::Isotropic(eps) => Matrix3::from_diagonal_element(eps),
+            TensorSpec::Diagonal(diagonal) => Matrix3::from_diagonal(&Vector3::from(diagonal)),
+            TensorSpec::Full(rows) => Matrix3::from_fn(|r, c| rows[r][c]),
+        }
+    }
+}
+
+/// Document form of a [`Material`].
+///
+/// A rotation applies to the dispersion model, which is then given in the principal frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialSpec {
+    epsilon: TensorSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    epsilon_imag: Option<TensorSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dispersion: Option<Dispersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    axis_dispersion: Option<[Dispersion; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotation: Option<[[f64; 3]; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thermal: Option<Thermal>,
+}
+
+impl TryFrom<MaterialSpec> for Material {
+    type Error = String;
+
+    fn try_from(spec: MaterialSpec) -> Result<Self, String> {
+        let principal = match (spec.dispersion, spec.axis_dispersion) {
+            (Some(_), Some(_)) => return Err("give either `dispersion` or `axis_dispersion`, not both".into()),
+            (Some(model), None) => Some(MaterialDispersion::Isotropic(model)),
+            (None, Some(models)) => Some(MaterialDispersion::Diagonal(Box::new(models))),
+            (None, None) => None,
+        };
+        let dispersion = match (principal, spec.rotation) {
+            (None, Some(_)) => return Err("`rotation` needs a `dispersion` or `axis_dispersion` model".into()),
+            (Some(principal), Some(rows)) => Some(MaterialDispersion::Rotated {
+                principal: Box::new(principal),
+                rotation: Matrix3::from_fn(|r, c| rows[r][c]),
+            }),
+            (principal, None) => principal,
+        };
+        Ok(Material {
+            epsilon_matrix: spec.epsilon.into(),
+            epsilon_imag: spec.epsilon_imag.map_or_else(Matrix3::zeros, Matrix3::from),
+            dispersion,
+            thermal: spec.thermal,
+        })
+    }
+}
+
+impl From<Material> for MaterialSpec {
+    fn from(material: Material) -> Self {
+        // Nested rotations compose into one.
+        fn flatten(dispersion: MaterialDispersion, rotation: Option<Matrix3>) -> (MaterialDispersion, Option<Matrix3>) {
+            match dispersion {
+                MaterialDispersion::Rotated { principal, rotation: inner } => {
+                    flatten(*principal, Some(rotation.map_or(inner, |outer| outer * inner)))
+                }
+                principal => (principal, rotation),
+            }
+        }
+        let (mut dispersion, mut axis_dispersion, mut rotation) = (None, None, None);
+        if let Some(model) = material.dispersion {
+            let (principal, matrix) = flatten(model, None);
+            match principal {
+                MaterialDispersion::Isotropic(model) => dispersion = Some(model),
+                MaterialDispersion::Diagonal(models) => axis_dispersion = Some(*models),
+                MaterialDispersion::Rotated { .. } => unreachable!(),
+            }
+            rotation = matrix.map(|matrix| [0, 1, 2].map(|r| [0, 1, 2].map(|c| matrix[(r, c)])));
+        }
+        MaterialSpec {
+            epsilon: TensorSpec::from(&material.epsilon_matrix),
+            epsilon_imag: (material.epsilon_imag != Matrix3::zeros()).then(|| TensorSpec::from(&material.epsilon_imag)),
+            dispersion,
+            axis_dispersion,
+            rotation,
+            thermal: material.thermal,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,24 +448,85 @@ mod tests {
         let mat_tensor = Material::new_from_tensor(tensor);
         assert_eq!(mat_tensor.epsilon_matrix, tensor);          
     }
-}
 
-pub enum CommonMaterials {
-    Vacuum,
-    Air,
-    Silicon,
-    SiliconDioxide,
-    IndiumPhosphide,
-}
+    #[test]
+    fn test_complex_material() {
+        let lossless = Material::new_from_eps(12.7449);
+        assert!(lossless.is_lossless());
+        assert_eq!(lossless.complex_epsilon()[(1, 1)], Complex::new(12.7449, 0.0));
 
-impl From<CommonMaterials> for Material {
-    fn from(mat: CommonMaterials) -> Self {
-        match mat {
-            CommonMaterials::Vacuum => Material::new_from_eps(EPSILON_VACUUM),
-            CommonMaterials::Air => Material::new_from_eps(EPSILON_AIR),
-            CommonMaterials::Silicon => Material::new_from_eps(EPSILON_SILICON),
-            CommonMaterials::IndiumPhosphide => Material::new_from_eps(EPSILON_INDIUM_PHOSPHIDE),
-            CommonMaterials::SiliconDioxide => Material::new_from_eps(EPSILON_SILICON_DIOXIDE),
+        let lossy = Material::new_from_complex_n(3.5, 0.01);
+        assert!(!lossy.is_lossless());
+        assert!((lossy.epsilon_matrix[(0, 0)] - (3.5 * 3.5 - 0.01 * 0.01)).abs() < 1e-12);
+        assert!((lossy.epsilon_imag[(0, 0)] - 2.0 * 3.5 * 0.01).abs() < 1e-12);
+        let n = lossy.complex_refractive_indices();
+        assert!((n[2] - Complex::new(3.5, 0.01)).norm() < 1e-12);
+
+        let alpha = lossy.absorption_coefficients(Length::micrometers(1.0));
+        assert!((alpha[0] - 4.0 * std::f64::consts::PI * 0.01 / 1.0e-6).abs() < 1e-6);
+
+        let gain = Material::new_from_complex_n(3.6, -0.001);
+        assert!(gain.absorption_coefficients(Length::micrometers(0.94))[0] < 0.0);
+    }
+
+    #[test]
+    fn test_dispersive_material() {
+        let constant = Material::new_from_eps(4.0);
+        assert!(!constant.is_dispersive());
+        assert_eq!(constant.at_wavelength(Length::micrometers(0.94)), constant);
+
+        let model = Dispersion::Cauchy {
+            coefficients: vec![3.0, 0.1],
+        };
+        let mat = Material::new_dispersive(model, Length::micrometers(1.0));
+        assert!(mat.is_dispersive());
+        assert!((mat.epsilon_matrix[(0, 0)] - 3.1 * 3.1).abs() < 1e-12);
+
+        let shifted = mat.at_wavelength(Length::micrometers(0.5));
+        assert!((shifted.epsilon_matrix[(2, 2)] - 3.4 * 3.4).abs() < 1e-12);
+        assert!(shifted.is_isotropic());
+        assert_eq!(shifted.epsilon_at(Length::micrometers(1.0)), mat.epsilon_matrix);
+
+        let absorbing = Material::new_dispersive(
+            Dispersion::tabulated(vec![0.9e-6, 1.0e-6], vec![3.6, 3.5], vec![0.0, 0.02]).unwrap(),
+            Length::micrometers(0.9),
+        );
+        assert!(absorbing.is_lossless());
+        let evaluated = absorbing.at_wavelength(Length::micrometers(1.0));
+        assert!((evaluated.epsilon_imag[(0, 0)] - 2.0 * 3.5 * 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_material_serialization() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            material: Material,
         }
+        let round_trip = |material: Material| {
+            let text = toml::to_string(&Holder { material: material.clone() }).unwrap();
+            assert_eq!(toml::from_str::<Holder>(&text).unwrap().material, material, "{text}");
+            text
+        };
+        assert_eq!(round_trip(Material::new_from_eps(12.7449)), "[material]\nepsilon = 12.7449\n");
+        assert!(round_trip(Material::new_anisotropic(2.0, 2.0, 2.5)).contains("epsilon = [2.0, 2.0, 2.5]"));
+        let mut tensor = Matrix3::from_diagonal_element(2.0);
+        tensor[(0, 1)] = 0.1;
+        tensor[(1, 0)] = 0.1;
+        round_trip(Material::new_from_tensor(tensor));
+        round_trip(Material::new_from_complex_n(3.5, 0.02));
+
+        let cauchy = |a: f64| Dispersion::Cauchy { coefficients: vec![a, 0.01] };
+        let isotropic = Material::new_dispersive(cauchy(1.5), Length::micrometers(1.0));
+        assert!(round_trip(isotropic).contains("model = \"cauchy\""));
+        let rotated = MaterialDispersion::Rotated {
+            principal: Box::new(MaterialDispersion::Diagonal(Box::new([cauchy(1.5), cauchy(1.5), cauchy(1.6)]))),
+            rotation: Matrix3::new(0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0),
+        };
+        round_trip(Material::new_from_dispersion(rotated, Length::micrometers(1.0)));
+
+        let both = "[material]\nepsilon = 2.0\nrotation = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]\n";
+        assert!(toml::from_str::<Holder>(both).unwrap_err().message().contains("rotation"));
+        assert!(toml::from_str::<Holder>("[material]\nepsilon = [1.0, 2.0]\n").is_err());
+        assert!(toml::from_str::<Holder>("[material]\nepsilon = 1.0\ncolor = 1\n").is_err());
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 /// Represents the physical geometry of a single hole.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Dimensions are expressed in units of the lattice constant.
+/// Serialized with a `kind` tag, e.g. `{ kind = "circle", radius = 0.2 }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
 pub enum HoleShape {
     Circle { radius: f64 },
     Square {
@@ -9,12 +15,209 @@ pub enum HoleShape {
         width: f64,
         height: f64,
     },
+    /// Ellipse with semi-axes `radius_x` and `radius_y`, rotated by `rotation` radians counter-clockwise.
+    Ellipse {
+        radius_x: f64,
+        radius_y: f64,
+        rotation: f64,
+    },
+}
+
+impl HoleShape {
+    /// Area of the shape.
+    pub fn area(&self) -> f64 {
+        match self {
+            HoleShape::Circle { radius } => std::f64::consts::PI * radius * radius,
+            HoleShape::Square { side } => side * side,
+            HoleShape::Rectangle { width, height } => width * height,
+            HoleShape::Ellipse { radius_x, radius_y, .. } => std::f64::consts::PI * radius_x * radius_y,
+        }
+    }
+
+    /// Half widths of the axis-aligned bounding box along x and y.
+    pub fn half_extents(&self) -> (f64, f64) {
+        match self {
+            HoleShape::Circle { radius } => (*radius, *radius),
+            HoleShape::Square { side } => (0.5 * side, 0.5 * side),
+            HoleShape::Rectangle { width, height } => (0.5 * width, 0.5 * height),
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => {
+                let (sin, cos) = rotation.sin_cos();
+                (
+                    (radius_x * radius_x * cos * cos + radius_y * radius_y * sin * sin).sqrt(),
+                    (radius_x * radius_x * sin * sin + radius_y * radius_y * cos * cos).sqrt(),
+                )
+            }
+        }
+    }
+
+    /// Returns `true` if every dimension of the shape is strictly positive.
+    pub fn has_positive_dimensions(&self) -> bool {
+        match self {
+            HoleShape::Circle { radius } => *radius > 0.0,
+            HoleShape::Square { side } => *side > 0.0,
+            HoleShape::Rectangle { width, height } => *width > 0.0 && *height > 0.0,
+            HoleShape::Ellipse { radius_x, radius_y, .. } => *radius_x > 0.0 && *radius_y > 0.0,
+        }
+    }
+
+    /// Returns `true` if the point `(dx, dy)`, relative to the shape center, lies inside the shape.
+    pub fn contains(&self, dx: f64, dy: f64) -> bool {
+        match self {
+            HoleShape::Circle { radius } => dx * dx + dy * dy <= radius * radius,
+            HoleShape::Square { .. } | HoleShape::Rectangle { .. } => {
+                let (hx, hy) = self.half_extents();
+                dx.abs() <= hx && dy.abs() <= hy
+            }
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => {
+                let (sin, cos) = rotation.sin_cos();
+                let u = (cos * dx + sin * dy) / radius_x;
+                let v = (-sin * dx + cos * dy) / radius_y;
+                u * u + v * v <= 1.0
+            }
+        }
+    }
+
+    /// Signed distance from the point `(dx, dy)`, relative to the shape center, to the boundary:
+    /// negative inside and positive outside. Exact except for ellipses, which use the
+    /// first-order estimate `f / |∇f|`.
+    pub fn signed_distance(&self, dx: f64, dy: f64) -> f64 {
+        match self {
+            HoleShape::Circle { radius } => dx.hypot(dy) - radius,
+            HoleShape::Square { .. } | HoleShape::Rectangle { .. } => {
+                let (hx, hy) = self.half_extents();
+                let (qx, qy) = (dx.abs() - hx, dy.abs() - hy);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0)
+            }
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => {
+                let (sin, cos) = rotation.sin_cos();
+                let u = cos * dx + sin * dy;
+                let v = -sin * dx + cos * dy;
+                let k0 = (u / radius_x).hypot(v / radius_y);
+                let k1 = (u / (radius_x * radius_x)).hypot(v / (radius_y * radius_y));
+                if k1 == 0.0 {
+                    -radius_x.min(*radius_y)
+                } else {
+                    k0 * (k0 - 1.0) / k1
+                }
+            }
+        }
+    }
+
+    /// Outward unit normal of the boundary near the point `(dx, dy)`, relative to the shape center.
+    ///
+    /// Curved shapes use the gradient of their implicit equation, rectangles the normal of the
+    /// side closest to the point relative to its half width. The center returns `(1, 0)`.
+    pub fn normal(&self, dx: f64, dy: f64) -> (f64, f64) {
+        let (nx, ny) = match self {
+            HoleShape::Circle { .. } => (dx, dy),
+            HoleShape::Square { .. } | HoleShape::Rectangle { .. } => {
+                let (hx, hy) = self.half_extents();
+                if dx.abs() / hx >= dy.abs() / hy {
+                    (dx.signum(), 0.0)
+                } else {
+                    (0.0, dy.signum())
+                }
+            }
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => {
+                let (sin, cos) = rotation.sin_cos();
+                let u = (cos * dx + sin * dy) / (radius_x * radius_x);
+                let v = (-sin * dx + cos * dy) / (radius_y * radius_y);
+                (cos * u - sin * v, sin * u + cos * v)
+            }
+        };
+        let norm = nx.hypot(ny);
+        if norm == 0.0 {
+            (1.0, 0.0)
+        } else {
+            (nx / norm, ny / norm)
+        }
+    }
+
+    /// Returns the shape with every length multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> HoleShape {
+        match *self {
+            HoleShape::Circle { radius } => HoleShape::Circle { radius: radius * factor },
+            HoleShape::Square { side } => HoleShape::Square { side: side * factor },
+            HoleShape::Rectangle { width, height } => HoleShape::Rectangle {
+                width: width * factor,
+                height: height * factor,
+            },
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => HoleShape::Ellipse {
+                radius_x: radius_x * factor,
+                radius_y: radius_y * factor,
+                rotation,
+            },
+        }
+    }
+
+    /// Returns the shape grown by `delta` in every direction (shrunk if `delta` is negative).
+    ///
+    /// Rectangles and squares keep sharp corners, i.e. each side changes by `2 * delta`.
+    pub fn dilated(&self, delta: f64) -> HoleShape {
+        match *self {
+            HoleShape::Circle { radius } => HoleShape::Circle { radius: radius + delta },
+            HoleShape::Square { side } => HoleShape::Square { side: side + 2.0 * delta },
+            HoleShape::Rectangle { width, height } => HoleShape::Rectangle {
+                width: width + 2.0 * delta,
+                height: height + 2.0 * delta,
+            },
+            HoleShape::Ellipse { radius_x, radius_y, rotation } => HoleShape::Ellipse {
+                radius_x: radius_x + delta,
+                radius_y: radius_y + delta,
+                rotation,
+            },
+        }
+    }
 }
 
 // Tests for the geometry module
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_shape_serialization() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            shape: HoleShape,
+        }
+        let shape = HoleShape::Ellipse { radius_x: 0.2, radius_y: 0.1, rotation: 0.5 };
+        let text = toml::to_string(&Holder { shape: shape.clone() }).unwrap();
+        assert_eq!(toml::from_str::<Holder>(&text).unwrap().shape, shape);
+        let circle: Holder = toml::from_str("shape = { kind = \"circle\", radius = 0.2 }").unwrap();
+        assert_eq!(circle.shape, HoleShape::Circle { radius: 0.2 });
+        assert!(toml::from_str::<Holder>("shape = { kind = \"hexagon\", side = 0.2 }").is_err());
+        assert!(toml::from_str::<Holder>("shape = { kind = \"square\", radius = 0.2 }").is_err());
+    }
+    #[test]
+    fn test_signed_distance() {
+        let circle = HoleShape::Circle { radius: 0.2 };
+        assert!((circle.signed_distance(0.3, 0.0) - 0.1).abs() < 1e-12);
+        assert!((circle.signed_distance(0.0, -0.05) + 0.15).abs() < 1e-12);
+        let rectangle = HoleShape::Rectangle { width: 0.4, height: 0.2 };
+        assert!((rectangle.signed_distance(0.0, 0.05) + 0.05).abs() < 1e-12);
+        assert!((rectangle.signed_distance(0.23, 0.14) - 0.05).abs() < 1e-12);
+        let ellipse = HoleShape::Ellipse { radius_x: 0.3, radius_y: 0.1, rotation: 0.0 };
+        assert!((ellipse.signed_distance(0.31, 0.0) - 0.01).abs() < 1e-3);
+        assert!((ellipse.signed_distance(0.0, 0.09) + 0.01).abs() < 1e-3);
+        assert!(ellipse.signed_distance(0.0, 0.0) < 0.0);
+    }
+
+    #[test]
+    fn test_normals() {
+        let close = |(x, y): (f64, f64), (ex, ey): (f64, f64)| (x - ex).abs() < 1e-12 && (y - ey).abs() < 1e-12;
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        assert!(close(HoleShape::Circle { radius: 0.2 }.normal(0.1, -0.1), (h, -h)));
+        assert!(close(HoleShape::Rectangle { width: 0.4, height: 0.2 }.normal(0.15, 0.08), (0.0, 1.0)));
+        assert!(close(HoleShape::Square { side: 0.2 }.normal(-0.1, 0.05), (-1.0, 0.0)));
+        // On the x axis of an ellipse rotated by 90 degrees, the normal is along x.
+        let ellipse = HoleShape::Ellipse { radius_x: 0.1, radius_y: 0.2, rotation: std::f64::consts::FRAC_PI_2 };
+        assert!(close(ellipse.normal(0.2, 0.0), (1.0, 0.0)));
+        let tilted = HoleShape::Ellipse { radius_x: 0.3, radius_y: 0.1, rotation: 0.0 }.normal(0.3 * h, 0.1 * h);
+        assert!(close(tilted, (1.0 / 10f64.sqrt(), 3.0 / 10f64.sqrt())));
+        assert_eq!(HoleShape::Circle { radius: 0.2 }.normal(0.0, 0.0), (1.0, 0.0));
+    }
+
     #[test]
     fn test_hole_shape_creation() {
         let circle = HoleShape::Circle { radius: 0.1 };
@@ -34,4 +237,38 @@ mod tests {
         let circle = HoleShape::Circle { radius: 0.3 };
         assert_ne!(rect, circle);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_hole_shape_area_and_containment() {
+        let circle = HoleShape::Circle { radius: 0.2 };
+        assert!((circle.area() - std::f64::consts::PI * 0.04).abs() < 1e-12);
+        assert!(circle.contains(0.1, 0.1));
+        assert!(!circle.contains(0.15, 0.15));
+
+        let rect = HoleShape::Rectangle { width: 0.4, height: 0.2 };
+        assert!((rect.area() - 0.08).abs() < 1e-12);
+        assert_eq!(rect.half_extents(), (0.2, 0.1));
+        assert!(rect.contains(0.19, -0.09));
+        assert!(!rect.contains(0.0, 0.11));
+
+        assert!(!HoleShape::Square { side: -0.1 }.has_positive_dimensions());
+    }
+
+    #[test]
+    fn test_rotated_ellipse() {
+        let ellipse = HoleShape::Ellipse {
+            radius_x: 0.2,
+            radius_y: 0.1,
+            rotation: std::f64::consts::FRAC_PI_2,
+        };
+        assert!((ellipse.area() - std::f64::consts::PI * 0.02).abs() < 1e-12);
+        let (hx, hy) = ellipse.half_extents();
+        assert!((hx - 0.1).abs() < 1e-12 && (hy - 0.2).abs() < 1e-12);
+        assert!(ellipse.contains(0.0, 0.19));
+        assert!(!ellipse.contains(0.19, 0.0));
+
+        let shrunk = HoleShape::Rectangle { width: 0.3, height: 0.2 }.dilated(-0.05);
+        let (hx, hy) = shrunk.half_extents();
+        assert!((hx - 0.1).abs() < 1e-12 && (hy - 0.05).abs() < 1e-12);
+    }
+}
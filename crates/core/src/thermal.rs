@@ -0,0 +1,162 @@
+//! crates/core/src/thermal.rs
+//! Temperature dependence of materials.
+//!
+//! A material either follows a linear thermo-optic coefficient `dn/dT` around a reference
+//! temperature, or a full `n(λ, T)` model as provided by the III-V alloys of
+//! [`crate::alloys`]. Temperatures are in kelvin.
+use serde::{Deserialize, Serialize};
+
+use super::alloys::{AlloyComposition, AlloyError};
+use super::anisotropy::{principal_axes, rotate_tensor};
+use super::material::{ComplexDielectricTensor, DielectricTensor, Material};
+use super::units::Length;
+use super::vectorial::Complex;
+
+/// Linear change `Δn = dn/dT (T - T_ref)` of all principal refractive indices.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThermoOptic {
+    /// Thermo-optic coefficient, in 1/K.
+    pub dn_dt: f64,
+    /// Temperature at which the dispersion model or constant tensor holds, in K.
+    pub reference_temperature: f64,
+}
+
+/// How the dielectric tensor of a material depends on temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum ThermalModel {
+    ThermoOptic(ThermoOptic),
+    /// Full `n(λ, T)` of an alloy, whose dispersion model is rebuilt at each temperature.
+    Alloy(AlloyComposition),
+}
+
+/// Temperature dependence of a material together with its current temperature (K).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Thermal {
+    pub model: ThermalModel,
+    pub temperature: f64,
+}
+
+impl Thermal {
+    /// Index change to apply on top of the dispersion model at the current temperature.
+    pub(crate) fn index_shift(&self) -> Option<f64> {
+        match self.model {
+            ThermalModel::ThermoOptic(ThermoOptic {
+                dn_dt,
+                reference_temperature,
+            }) if self.temperature != reference_temperature => {
+                Some(dn_dt * (self.temperature - reference_temperature))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Adds `dn` to the principal refractive indices of the real part of `epsilon`.
+///
+/// The imaginary part is kept, and principal axes with non-positive permittivity are left unchanged.
+pub(crate) fn shift_index(epsilon: &ComplexDielectricTensor, dn: f64) -> ComplexDielectricTensor {
+    let principal = principal_axes(&epsilon.map(|eps| eps.re));
+    let shifted = principal
+        .epsilon
+        .map(|eps| if eps > 0.0 { (eps.sqrt() + dn).powi(2) } else { eps });
+    let real = rotate_tensor(&DielectricTensor::from_diagonal(&shifted), &principal.axes);
+    real.zip_map(epsilon, |re, eps| Complex::new(re, eps.im))
+}
+
+impl Material {
+    /// Attaches a thermo-optic coefficient `dn_dt` (1/K). The current tensor and dispersion
+    /// model are taken to hold at `reference_temperature` (K).
+    pub fn with_thermo_optic(mut self, dn_dt: f64, reference_temperature: f64) -> Self {
+        self.thermal = Some(Thermal {
+            model: ThermalModel::ThermoOptic(ThermoOptic {
+                dn_dt,
+                reference_temperature,
+            }),
+            temperature: reference_temperature,
+        });
+        self
+    }
+
+    /// Temperature (K) the material is evaluated at, if it is temperature dependent.
+    pub fn temperature(&self) -> Option<f64> {
+        self.thermal.map(|thermal| thermal.temperature)
+    }
+
+    /// Returns the material evaluated at the vacuum `wavelength` and `temperature` (K).
+    ///
+    /// Materials without a thermal model are only re-evaluated at `wavelength`. Only alloy
+    /// models can fail, outside their temperature range or above their band gap.
+    pub fn at_temperature(&self, wavelength: Length, temperature: f64) -> Result<Self, AlloyError> {
+        let Some(thermal) = self.thermal else {
+            return Ok(self.at_wavelength(wavelength));
+        };
+        match thermal.model {
+            ThermalModel::Alloy(composition) => composition.material(wavelength, temperature),
+            ThermalModel::ThermoOptic(ThermoOptic { dn_dt, .. }) => {
+                let mut heated = self.clone();
+                heated.thermal = Some(Thermal { temperature, ..thermal });
+                if heated.is_dispersive() {
+                    Ok(heated.at_wavelength(wavelength))
+                } else {
+                    let epsilon = shift_index(&self.complex_epsilon(), dn_dt * (temperature - thermal.temperature));
+                    heated.epsilon_matrix = epsilon.map(|eps| eps.re);
+                    heated.epsilon_imag = epsilon.map(|eps| eps.im);
+                    Ok(heated)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispersion::Dispersion;
+    use crate::vectorial::Vector3;
+
+    #[test]
+    fn test_thermo_optic_constant_material() {
+        let silicon = Material::new_from_n(3.476).with_thermo_optic(1.8e-4, 300.0);
+        assert_eq!(silicon.temperature(), Some(300.0));
+        let hot = silicon.at_temperature(Length::micrometers(1.55), 350.0).unwrap();
+        assert_eq!(hot.temperature(), Some(350.0));
+        assert!((hot.epsilon_matrix[(0, 0)].sqrt() - (3.476 + 0.009)).abs() < 1e-12);
+        assert!(hot.is_isotropic());
+
+        let back = hot.at_temperature(Length::micrometers(1.55), 300.0).unwrap();
+        assert!((back.epsilon_matrix - silicon.epsilon_matrix).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_thermo_optic_dispersive_and_anisotropic_material() {
+        let model = Dispersion::Cauchy { coefficients: vec![3.0, 0.1] };
+        let mat = Material::new_dispersive(model, Length::micrometers(1.0)).with_thermo_optic(2e-4, 300.0);
+        let hot = mat.at_temperature(Length::micrometers(0.5), 400.0).unwrap();
+        assert!((hot.epsilon_matrix[(1, 1)].sqrt() - 3.42).abs() < 1e-12);
+        // The shift follows subsequent wavelength changes.
+        assert!((hot.epsilon_at(Length::micrometers(1.0))[(2, 2)].sqrt() - 3.12).abs() < 1e-12);
+
+        let tilted = Material::new_uniaxial(4.0, 9.0, Vector3::new(1.0, 1.0, 0.0)).with_thermo_optic(0.1, 300.0);
+        let principal = tilted.at_temperature(Length::micrometers(1.0), 310.0).unwrap().principal_axes();
+        let mut indices = principal.refractive_indices();
+        indices.as_mut_slice().sort_by(f64::total_cmp);
+        assert!((indices - Vector3::new(3.0, 3.0, 4.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_alloy_at_temperature() {
+        let clad = Material::al_ga_as(0.4, Length::micrometers(0.94), 300.0).unwrap();
+        assert_eq!(clad.temperature(), Some(300.0));
+        let hot = clad.at_temperature(Length::micrometers(0.94), 350.0).unwrap();
+        assert!(hot.epsilon_matrix[(0, 0)] > clad.epsilon_matrix[(0, 0)]);
+        assert_eq!(hot, Material::al_ga_as(0.4, Length::micrometers(0.94), 350.0).unwrap());
+        assert!(matches!(
+            clad.at_temperature(Length::micrometers(0.94), 500.0),
+            Err(AlloyError::TemperatureOutOfRange { .. })
+        ));
+
+        let plain = Material::new_from_eps(2.1);
+        assert_eq!(plain.at_temperature(Length::micrometers(1.0), 400.0).unwrap(), plain);
+    }
+}
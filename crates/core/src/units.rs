@@ -0,0 +1,434 @@
+//! crates/core/src/units.rs
+//! Unit-safe physical quantities.
+//!
+//! Every quantity stores its value in SI units and is built and read through explicitly
+//! named constructors and accessors, e.g. `Length::nanometers(295.0).micrometers()`.
+//! Normalized units refer to a lattice constant `a`: frequencies as `a/λ`, wavenumbers as
+//! `k a / 2π` and losses as `α a`.
+use std::f64::consts::PI;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Speed of light in vacuum, in m/s.
+pub const SPEED_OF_LIGHT: f64 = 299_792_458.0;
+
+/// Implements the arithmetic shared by all quantities: sums and differences of the same
+/// quantity, scaling by a number, and the dimensionless ratio of two quantities.
+macro_rules! quantity_ops {
+    ($quantity:ident) => {
+        impl Add for $quantity {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self {
+                Self(self.0 + rhs.0)
+            }
+        }
+
+        impl Sub for $quantity {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self {
+                Self(self.0 - rhs.0)
+            }
+        }
+
+        impl Neg for $quantity {
+            type Output = Self;
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl Mul<f64> for $quantity {
+            type Output = Self;
+            fn mul(self, rhs: f64) -> Self {
+                Self(self.0 * rhs)
+            }
+        }
+
+        impl Mul<$quantity> for f64 {
+            type Output = $quantity;
+            fn mul(self, rhs: $quantity) -> $quantity {
+                $quantity(self * rhs.0)
+            }
+        }
+
+        impl Div<f64> for $quantity {
+            type Output = Self;
+            fn div(self, rhs: f64) -> Self {
+                Self(self.0 / rhs)
+            }
+        }
+
+        impl Div for $quantity {
+            type Output = f64;
+            fn div(self, rhs: Self) -> f64 {
+                self.0 / rhs.0
+            }
+        }
+    };
+}
+
+/// A length, e.g. a lattice constant, a layer thickness or a vacuum wavelength.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Length(f64);
+
+impl Length {
+    pub const fn meters(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn micrometers(value: f64) -> Self {
+        Self(value * 1e-6)
+    }
+
+    pub fn nanometers(value: f64) -> Self {
+        Self(value * 1e-9)
+    }
+
+    /// Length from a value in units of the lattice constant `a`.
+    pub fn from_normalized(value: f64, a: Length) -> Self {
+        Self(value * a.0)
+    }
+
+    /// Vacuum wavelength corresponding to the normalized frequency `a/λ`.
+    pub fn from_normalized_frequency(a_over_lambda: f64, a: Length) -> Self {
+        Self(a.0 / a_over_lambda)
+    }
+
+    pub fn as_meters(self) -> f64 {
+        self.0
+    }
+
+    pub fn as_micrometers(self) -> f64 {
+        self.0 * 1e6
+    }
+
+    pub fn as_nanometers(self) -> f64 {
+        self.0 * 1e9
+    }
+
+    /// This length in units of the lattice constant `a`.
+    pub fn normalized(self, a: Length) -> f64 {
+        self.0 / a.0
+    }
+
+    /// Normalized frequency `a/λ` of this vacuum wavelength.
+    pub fn normalized_frequency(self, a: Length) -> f64 {
+        a.0 / self.0
+    }
+
+    /// Frequency of light with this vacuum wavelength.
+    pub fn to_frequency(self) -> Frequency {
+        Frequency(SPEED_OF_LIGHT / self.0)
+    }
+
+    /// Vacuum wavenumber `2π/λ` of light with this vacuum wavelength.
+    pub fn to_wavenumber(self) -> Wavenumber {
+        Wavenumber(2.0 * PI / self.0)
+    }
+}
+
+/// An angular wavenumber `k`, in rad/m.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Wavenumber(f64);
+
+impl Wavenumber {
+    pub const fn radians_per_meter(value: f64) -> Self {
+        Self(value)
+    }
+
+    /// Wavenumber from a spectroscopic wavenumber `1/λ` in cm⁻¹.
+    pub fn from_inverse_centimeters(value: f64) -> Self {
+        Self(2.0 * PI * value * 1e2)
+    }
+
+    /// Wavenumber from its normalized value `k a / 2π`.
+    pub fn from_normalized(value: f64, a: Length) -> Self {
+        Self(2.0 * PI * value / a.0)
+    }
+
+    pub fn as_radians_per_meter(self) -> f64 {
+        self.0
+    }
+
+    /// Spectroscopic wavenumber `1/λ`, in cm⁻¹.
+    pub fn as_inverse_centimeters(self) -> f64 {
+        self.0 / (2.0 * PI) * 1e-2
+    }
+
+    /// Normalized value `k a / 2π`.
+    pub fn normalized(self, a: Length) -> f64 {
+        self.0 * a.0 / (2.0 * PI)
+    }
+
+    /// Vacuum wavelength `2π/k`.
+    pub fn to_wavelength(self) -> Length {
+        Length(2.0 * PI / self.0)
+    }
+}
+
+/// A frequency, in Hz.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Frequency(f64);
+
+impl Frequency {
+    pub const fn hertz(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn terahertz(value: f64) -> Self {
+        Self(value * 1e12)
+    }
+
+    /// Frequency from the normalized frequency `a/λ`.
+    pub fn from_normalized(a_over_lambda: f64, a: Length) -> Self {
+        Self(a_over_lambda * SPEED_OF_LIGHT / a.0)
+    }
+
+    pub fn as_hertz(self) -> f64 {
+        self.0
+    }
+
+    pub fn as_terahertz(self) -> f64 {
+        self.0 * 1e-12
+    }
+
+    /// Angular frequency `2πf`, in rad/s.
+    pub fn angular(self) -> f64 {
+        2.0 * PI * self.0
+    }
+
+    /// Normalized frequency `a/λ`.
+    pub fn normalized(self, a: Length) -> f64 {
+        self.0 * a.0 / SPEED_OF_LIGHT
+    }
+
+    /// Vacuum wavelength `c/f`.
+    pub fn to_wavelength(self) -> Length {
+        Length(SPEED_OF_LIGHT / self.0)
+    }
+}
+
+/// A power attenuation (or, when negative, gain) coefficient `α`, in 1/m.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Loss(f64);
+
+impl Loss {
+    pub const fn per_meter(value: f64) -> Self {
+        Self(value)
+    }
+
+    pub fn per_centimeter(value: f64) -> Self {
+        Self(value * 1e2)
+    }
+
+    /// Loss from a decibel attenuation per centimeter.
+    pub fn from_db_per_centimeter(value: f64) -> Self {
+        Self(value * 1e2 * std::f64::consts::LN_10 / 10.0)
+    }
+
+    /// Loss from its normalized value `α a`.
+    pub fn from_normalized(value: f64, a: Length) -> Self {
+        Self(value / a.0)
+    }
+
+    pub fn as_per_meter(self) -> f64 {
+        self.0
+    }
+
+    pub fn as_per_centimeter(self) -> f64 {
+        self.0 * 1e-2
+    }
+
+    pub fn as_db_per_centimeter(self) -> f64 {
+        self.as_per_centimeter() * 10.0 / std::f64::consts::LN_10
+    }
+
+    /// Normalized value `α a`.
+    pub fn normalized(self, a: Length) -> f64 {
+        self.0 * a.0
+    }
+}
+
+quantity_ops!(Length);
+quantity_ops!(Wavenumber);
+quantity_ops!(Frequency);
+quantity_ops!(Loss);
+
+/// Lengths are shown in nanometers, with the shortest number that reads back exactly.
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Usually the number of nanometers the length was built from.
+        let nanometers = (0..17)
+            .filter_map(|digits| format!("{:.*e}", digits, self.as_nanometers()).parse::<f64>().ok())
+            .find(|&nanometers| Length::nanometers(nanometers) == *self);
+        match nanometers {
+            Some(nanometers) => write!(f, "{nanometers} nm"),
+            None => write!(f, "{:e} m", self.0),
+        }
+    }
+}
+
+/// A length that could not be parsed from text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLengthError(String);
+
+impl fmt::Display for ParseLengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid length '{}': expected a number followed by m, mm, um, µm or nm", self.0)
+    }
+}
+
+impl std::error::Error for ParseLengthError {}
+
+/// Units accepted when parsing lengths, longest suffix first.
+const LENGTH_UNITS: [(&str, f64); 6] = [("mm", 1e-3), ("um", 1e-6), ("µm", 1e-6), ("μm", 1e-6), ("nm", 1e-9), ("m", 1.0)];
+
+impl FromStr for Length {
+    type Err = ParseLengthError;
+
+    /// Parses a number followed by a unit, e.g. `"295 nm"`, `"1.5um"` or `"2e-7 m"`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        LENGTH_UNITS
+            .iter()
+            .find_map(|(unit, scale)| {
+                let value = text.strip_suffix(unit)?.trim_end().parse::<f64>().ok()?;
+                Some(match *unit {
+                    "nm" => Length::nanometers(value),
+                    "m" => Length::meters(value),
+                    _ => Length::meters(value * scale),
+                })
+            })
+            .ok_or_else(|| ParseLengthError(text.to_owned()))
+    }
+}
+
+/// Lengths are written as their [`Display`](fmt::Display) text, which parses back exactly.
+impl Serialize for Length {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LengthVisitor;
+
+        impl de::Visitor<'_> for LengthVisitor {
+            type Value = Length;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a length with a unit, e.g. \"295 nm\"")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Length, E> {
+                text.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(LengthVisitor)
+    }
+}
+
+impl fmt::Display for Wavenumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rad/m", self.0)
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} THz", self.as_terahertz())
+    }
+}
+
+impl fmt::Display for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cm^-1", self.as_per_centimeter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-12 * a.abs().max(b.abs())
+    }
+
+    #[test]
+    fn test_length_conversions() {
+        let a = Length::nanometers(295.0);
+        assert!(close(a.as_meters(), 295e-9));
+        assert!(close(a.as_micrometers(), 0.295));
+        assert!(close((a * 2.0 - a).as_nanometers(), 295.0));
+        assert!(close(Length::micrometers(0.118) / a, 0.4));
+        assert!(close(Length::from_normalized(0.4, a).as_nanometers(), 118.0));
+    }
+
+    #[test]
+    fn test_normalized_frequency_round_trip() {
+        let a = Length::nanometers(295.0);
+        let lambda = Length::nanometers(940.0);
+        let u = lambda.normalized_frequency(a);
+        assert!(close(u, 295.0 / 940.0));
+        assert!(close(Length::from_normalized_frequency(u, a).as_meters(), lambda.as_meters()));
+        assert!(close(lambda.to_frequency().normalized(a), u));
+        assert!(close(Frequency::from_normalized(u, a).to_wavelength().as_meters(), lambda.as_meters()));
+        assert!(close(lambda.to_frequency().as_terahertz(), 318.928_146_808_510_6));
+    }
+
+    #[test]
+    fn test_wavenumber_conversions() {
+        let a = Length::nanometers(295.0);
+        let k = Length::micrometers(1.0).to_wavenumber();
+        assert!(close(k.as_inverse_centimeters(), 1e4));
+        assert!(close(Wavenumber::from_inverse_centimeters(1e4).as_radians_per_meter(), k.as_radians_per_meter()));
+        assert!(close(k.to_wavelength().as_micrometers(), 1.0));
+        // The first Brillouin zone edge of a square lattice is at k a / 2π = 0.5.
+        assert!(close(Wavenumber::from_normalized(0.5, a).as_radians_per_meter(), PI / a.as_meters()));
+    }
+
+    #[test]
+    fn test_length_parsing() {
+        assert_eq!("295 nm".parse(), Ok(Length::nanometers(295.0)));
+        assert_eq!("295nm".parse(), Ok(Length::nanometers(295.0)));
+        assert_eq!(" 2e-7 m ".parse(), Ok(Length::meters(2e-7)));
+        assert!(close("1.5 µm".parse::<Length>().unwrap().as_nanometers(), 1500.0));
+        assert!(close("1.5 um".parse::<Length>().unwrap().as_nanometers(), 1500.0));
+        assert!(close("0.1 mm".parse::<Length>().unwrap().as_micrometers(), 100.0));
+        assert!("295".parse::<Length>().is_err());
+        assert!("nm".parse::<Length>().is_err());
+        assert!("295 km".parse::<Length>().is_err());
+    }
+
+    #[test]
+    fn test_length_serialization_round_trips() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            length: Length,
+        }
+        for length in [Length::nanometers(295.0), Length::micrometers(1.5), Length::meters(1.0 / 3.0), Length::default()] {
+            let text = toml::to_string(&Holder { length }).unwrap();
+            assert_eq!(toml::from_str::<Holder>(&text).unwrap().length, length, "{text}");
+        }
+        assert_eq!(toml::to_string(&Holder { length: Length::nanometers(295.0) }).unwrap(), "length = \"295 nm\"\n");
+        let error = toml::from_str::<Holder>("length = 295").unwrap_err();
+        assert!(error.message().contains("a length with a unit"), "{error}");
+    }
+
+    #[test]
+    fn test_loss_conversions() {
+        let a = Length::nanometers(295.0);
+        let alpha = Loss::per_centimeter(10.0);
+        assert!(close(alpha.as_per_meter(), 1000.0));
+        assert!(close(alpha.normalized(a), 2.95e-4));
+        assert!(close(Loss::from_normalized(2.95e-4, a).as_per_centimeter(), 10.0));
+        assert!(close(Loss::from_db_per_centimeter(alpha.as_db_per_centimeter()).as_per_meter(), 1000.0));
+        assert!(close(Loss::from_db_per_centimeter(10.0).as_per_centimeter(), std::f64::consts::LN_10));
+        assert!((-alpha).as_per_meter() < 0.0);
+    }
+}
@@ -106,6 +106,12 @@ mod vector2_tests {
 /// Type alias for 3D Matrices
 pub type Matrix3 = nalgebra::Matrix3<f64>;
 
+/// Type alias for complex numbers
+pub type Complex = nalgebra::Complex<f64>;
+
+/// Type alias for complex 3D Matrices
+pub type ComplexMatrix3 = nalgebra::Matrix3<Complex>;
+
 
 // Test module for Matrix3D
 #[cfg(test)]
@@ -0,0 +1,226 @@
+//! crates/cwt/src/coupling.rs
+//! Coupling matrix of the four basic waves of a square lattice at the Γ point.
+//!
+//! The basic waves `Rx`, `Sx`, `Ry` and `Sy` travel along `+x`, `-x`, `+y` and `-y` with the
+//! Bragg wavenumber `β0 = 2π/a` and TE polarization. At the frequency where the slab mode has
+//! `δ = (k0² n_eff² - β0²) / (2β0)`, their uniform amplitudes solve `C v = δ v`, with `C` the
+//! sum of three terms:
+//!
+//! - direct (1D) coupling between waves through `ξ_mn` of their difference, weighted by the
+//!   confinement factor `Γ` of the photonic crystal layer;
+//! - radiative coupling through the vertical wave excited by `ξ_{±1,0}` and `ξ_{0,±1}`, with
+//!   a field uniform across the layer and a uniform medium of its average permittivity;
+//! - 2D coupling through the higher-order waves `|m|, |n| < order`, `m² + n² > 1`, solved
+//!   in-plane with the average permittivity `n_eff²` and the same vertical profile.
+use core::units::Length;
+use core::vectorial::Complex;
+use fourier::FourierCoefficients;
+use nalgebra::Matrix4;
+
+use super::modes::CwtError;
+
+/// A basic wave: its reciprocal lattice order and the direction of its electric field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BasicWave {
+    pub name: &'static str,
+    /// The wave varies as `exp(-i β0 (m x + n y))`.
+    pub order: (i32, i32),
+    pub polarization: (f64, f64),
+}
+
+/// The basic waves, in the order of the rows of the coupling matrix.
+pub const BASIC_WAVES: [BasicWave; 4] = [
+    BasicWave { name: "Rx", order: (1, 0), polarization: (0.0, 1.0) },
+    BasicWave { name: "Sx", order: (-1, 0), polarization: (0.0, 1.0) },
+    BasicWave { name: "Ry", order: (0, 1), polarization: (1.0, 0.0) },
+    BasicWave { name: "Sy", order: (0, -1), polarization: (1.0, 0.0) },
+];
+
+fn dot(a: (f64, f64), b: (f64, f64)) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+/// Overlap `∫∫ exp(-i x |s - t|) ds dt` over the unit square, 1 for a thin layer.
+fn phase_overlap(x: f64) -> Complex {
+    if x.abs() < 1e-3 {
+        return Complex::new(1.0 - x * x / 12.0, -x / 3.0);
+    }
+    let i = Complex::i();
+    ((Complex::new(1.0, 0.0) - (-i * x).exp()) / (x * x) - i / x) * 2.0
+}
+
+/// The slab mode and photonic crystal layer seen by the basic waves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coupling {
+    /// Vacuum wavelength at which the coefficients and the slab mode were computed.
+    pub wavelength: Length,
+    pub lattice_constant: Length,
+    pub effective_index: f64,
+    /// Confinement factor of the photonic crystal layer.
+    pub confinement: f64,
+    /// Thickness of the photonic crystal layer.
+    pub thickness: Length,
+}
+
+impl Coupling {
+    /// Bragg wavenumber `β0 = 2π/a`, in 1/m.
+    pub fn bragg_wavenumber(&self) -> f64 {
+        2.0 * std::f64::consts::PI / self.lattice_constant.as_meters()
+    }
+
+    fn k0(&self) -> f64 {
+        self.wavelength.to_wavenumber().as_radians_per_meter()
+    }
+
+    fn check(&self, coefficients: &FourierCoefficients) -> Result<(), CwtError> {
+        if coefficients.order() < 2 {
+            return Err(CwtError::OrderTooLow(coefficients.order()));
+        }
+        if !(self.confinement > 0.0 && self.confinement <= 1.0) {
+            return Err(CwtError::InvalidConfinement(self.confinement));
+        }
+        Ok(())
+    }
+
+    /// Direct coupling between the basic waves, in 1/m.
+    pub fn one_dimensional(&self, coefficients: &FourierCoefficients) -> Result<Matrix4<Complex>, CwtError> {
+        self.check(coefficients)?;
+        let scale = -self.k0().powi(2) * self.confinement / (2.0 * self.bragg_wavenumber());
+        Ok(Matrix4::from_fn(|i, j| {
+            let (to, from) = (BASIC_WAVES[i], BASIC_WAVES[j]);
+            if i == j {
+                return Complex::default();
+            }
+            let xi = coefficients.get(from.order.0 - to.order.0, from.order.1 - to.order.1).expect("within the order");
+            xi * scale * dot(to.polarization, from.polarization)
+        }))
+    }
+
+    /// Coupling through the radiated vertical wave, in 1/m. Its imaginary part is the
+    /// radiation loss.
+    pub fn radiative(&self, coefficients: &FourierCoefficients) -> Result<Matrix4<Complex>, CwtError> {
+        self.check(coefficients)?;
+        let k0 = self.k0();
+        let average = coefficients.get(0, 0).expect("within the order").re;
+        let k = k0 * average.max(0.0).sqrt();
+        let d = self.thickness.as_meters();
+        // ∫∫ Θ*(z) G(z, z') Θ(z') over the layer, with G = i exp(-ik|z - z'|) / 2k.
+        let overlap = Complex::i() * self.confinement * d * phase_overlap(k * d) / (2.0 * k);
+        let zeta = overlap * k0.powi(4) / (2.0 * self.bragg_wavenumber());
+        Ok(Matrix4::from_fn(|i, j| {
+            let (to, from) = (BASIC_WAVES[i], BASIC_WAVES[j]);
+            let out = coefficients.get(-to.order.0, -to.order.1).expect("within the order");
+            let into = coefficients.get(from.order.0, from.order.1).expect("within the order");
+            zeta * out * into * dot(to.polarization, from.polarization)
+        }))
+    }
+
+    /// Coupling through the higher-order waves, in 1/m.
+    pub fn two_dimensional(&self, coefficients: &FourierCoefficients) -> Result<Matrix4<Complex>, CwtError> {
+        self.check(coefficients)?;
+        let (k0, beta0, n2) = (self.k0(), self.bragg_wavenumber(), self.effective_index.powi(2));
+        let scale = -k0.powi(2) * self.confinement.powi(2) / (2.0 * beta0);
+        let limit = coefficients.order() as i32 - 1;
+        let mut matrix = Matrix4::<Complex>::zeros();
+        for m in -limit..=limit {
+            for n in -limit..=limit {
+                let norm2 = f64::from(m * m + n * n);
+                if norm2 <= 1.0 {
+                    continue;
+                }
+                let g = (f64::from(m) / norm2.sqrt(), f64::from(n) / norm2.sqrt());
+                let t = (-g.1, g.0);
+                // Transverse part of the wave from the in-plane wave equation, longitudinal
+                // part from div D = 0.
+                let transverse = k0.powi(2) / (norm2 * beta0.powi(2) - k0.powi(2) * n2);
+                for (i, to) in BASIC_WAVES.iter().enumerate() {
+                    let back = coefficients.get(m - to.order.0, n - to.order.1).expect("within the order");
+                    for (j, from) in BASIC_WAVES.iter().enumerate() {
+                        let forth = coefficients.get(from.order.0 - m, from.order.1 - n).expect("within the order");
+                        let weight = transverse * dot(to.polarization, t) * dot(t, from.polarization)
+                            - dot(to.polarization, g) * dot(g, from.polarization) / n2;
+                        matrix[(i, j)] += back * forth * scale * weight;
+                    }
+                }
+            }
+        }
+        Ok(matrix)
+    }
+
+    /// Full coupling matrix `C`, in 1/m.
+    pub fn matrix(&self, coefficients: &FourierCoefficients) -> Result<Matrix4<Complex>, CwtError> {
+        Ok(self.one_dimensional(coefficients)? + self.radiative(coefficients)? + self.two_dimensional(coefficients)?)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use core::material::Material;
+    use core::shapes::HoleShape;
+    use fourier::permittivity_coefficients;
+    use phc::base::UnitCellBase;
+    use phc::crystal_structure::PhotonicCrystal;
+    use phc::lattice::LatticeType;
+
+    pub(crate) fn coupling() -> Coupling {
+        Coupling {
+            wavelength: Length::nanometers(980.0),
+            lattice_constant: Length::nanometers(295.0),
+            effective_index: 3.3,
+            confinement: 0.1,
+            thickness: Length::nanometers(118.0),
+        }
+    }
+
+    pub(crate) fn coefficients(shape: HoleShape, center: (f64, f64)) -> FourierCoefficients {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::new();
+        base.background_material = Material::new_from_eps(12.7449);
+        base.add_atom(shape, (center.0, center.1, 0.0), Material::new_from_eps(1.0));
+        let crystal = PhotonicCrystal::new(lattice, base);
+        permittivity_coefficients(&crystal, Length::nanometers(980.0), 6, 64).unwrap()
+    }
+
+    #[test]
+    fn test_phase_overlap() {
+        assert!((phase_overlap(0.0) - Complex::new(1.0, 0.0)).norm() < 1e-12);
+        // Continuous across the thin-layer branch, with the real part sinc²(x/2).
+        assert!((phase_overlap(0.999_999e-3) - phase_overlap(1.000_001e-3)).norm() < 1e-8);
+        let x: f64 = 2.5;
+        assert!((phase_overlap(x).re - (2.0 * (x / 2.0).sin() / x).powi(2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_circular_holes() {
+        let xi = coefficients(HoleShape::Circle { radius: 0.16 }, (0.5, 0.5));
+        let coupling = coupling();
+        let one = coupling.one_dimensional(&xi).unwrap();
+        let kappa = -(coupling.k0().powi(2)) * 0.1 / (2.0 * coupling.bragg_wavenumber()) * xi.get(2, 0).unwrap();
+        assert!((one[(0, 1)] - kappa).norm() < 1e-9 * kappa.norm());
+        // Perpendicular TE waves do not couple directly.
+        assert_eq!(one[(0, 2)], Complex::default());
+
+        // Radiation only removes power, and only between waves of the same polarization.
+        let radiative = coupling.radiative(&xi).unwrap();
+        assert!(radiative[(0, 0)].im > 0.0);
+        assert_eq!(radiative[(0, 3)], Complex::default());
+
+        // A C4v-symmetric cell couples both directions alike.
+        let two = coupling.two_dimensional(&xi).unwrap();
+        assert!(two[(0, 2)].norm() > 0.0);
+        assert!((two[(0, 2)] - two[(1, 3)]).norm() < 1e-9 * two[(0, 2)].norm());
+        assert!((two[(0, 0)] - two[(2, 2)]).norm() < 1e-9 * two[(0, 0)].norm());
+    }
+
+    #[test]
+    fn test_errors() {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let crystal = PhotonicCrystal::new(lattice, UnitCellBase::new());
+        let low = permittivity_coefficients(&crystal, Length::nanometers(980.0), 1, 8).unwrap();
+        assert_eq!(coupling().matrix(&low), Err(CwtError::OrderTooLow(1)));
+        let xi = coefficients(HoleShape::Circle { radius: 0.16 }, (0.5, 0.5));
+        let outside = Coupling { confinement: 0.0, ..coupling() };
+        assert_eq!(outside.matrix(&xi), Err(CwtError::InvalidConfinement(0.0)));
+    }
+}
@@ -0,0 +1,9 @@
+//! crates/cwt/src/lib.rs
+//!
+//! Coupled-wave theory of photonic crystal lasers: band-edge modes of an infinite square
+//! lattice from the Fourier coefficients of its permittivity and the slab mode of the stack.
+pub mod coupling;
+pub mod modes;
+
+pub use coupling::{BasicWave, Coupling, BASIC_WAVES};
+pub use modes::{solve, CwtError, CwtMode, CwtSolution};
@@ -0,0 +1,280 @@
+//! crates/cwt/src/modes.rs
+//! Band-edge modes of the coupling matrix.
+//!
+//! Each eigenvalue `δ + iα/2` of the coupling matrix gives a mode at the frequency where the
+//! slab mode has detuning `δ`, found with the effective index held at its value at the design
+//! wavelength, and radiating the power loss `α`. The lasing mode needs the least modal gain
+//! `α + α_i` to reach threshold.
+use std::fmt;
+
+use core::units::{Length, Loss};
+use core::vectorial::Complex;
+use fourier::FourierCoefficients;
+use nalgebra::{Matrix4, Vector4};
+use phc::lattice::LatticeType;
+use waveguide::layers::{LayerType, Waveguide};
+use waveguide::slab::SlabMode;
+
+use super::coupling::Coupling;
+
+/// Iterations allowed to the Schur decomposition of the coupling matrix.
+const MAX_ITERATIONS: usize = 1000;
+/// Radiation losses closer than this fraction of the largest eigenvalue are equal.
+const LOSS_TOLERANCE: f64 = 1e-9;
+
+/// Reasons why the coupled-wave modes cannot be computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CwtError {
+    /// The stack has no photonic crystal layer.
+    NoPhotonicCrystal,
+    /// The photonic crystal layer is not a square lattice.
+    UnsupportedLattice(String),
+    /// The basic waves couple through orders up to 2, the coefficients stop at this order.
+    OrderTooLow(usize),
+    /// The confinement factor of the photonic crystal layer is not in (0, 1].
+    InvalidConfinement(f64),
+    /// The eigenvalues of the coupling matrix did not converge.
+    NoConvergence,
+}
+
+impl fmt::Display for CwtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CwtError::NoPhotonicCrystal => write!(f, "the stack has no photonic crystal layer"),
+            CwtError::UnsupportedLattice(layer) => {
+                write!(f, "layer '{layer}': only square lattices are supported")
+            }
+            CwtError::OrderTooLow(order) => {
+                write!(f, "Fourier order {order} is too low, the basic waves couple up to order 2")
+            }
+            CwtError::InvalidConfinement(confinement) => {
+                write!(f, "confinement factor {confinement} of the photonic crystal layer is not in (0, 1]")
+            }
+            CwtError::NoConvergence => write!(f, "the eigenvalues of the coupling matrix did not converge"),
+        }
+    }
+}
+
+impl std::error::Error for CwtError {}
+
+/// One mode of the basic waves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CwtMode {
+    /// Eigenvalue `δ + iα/2`, in 1/m.
+    pub eigenvalue: Complex,
+    /// Amplitudes of the basic waves, in the order of `BASIC_WAVES`, with unit norm.
+    pub amplitudes: [Complex; 4],
+}
+
+impl CwtMode {
+    /// Radiation loss `α`.
+    pub fn radiation_loss(&self) -> Loss {
+        Loss::per_meter(2.0 * self.eigenvalue.im)
+    }
+}
+
+/// Modes of the basic waves of a photonic crystal layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CwtSolution {
+    pub coupling: Coupling,
+    /// Modal internal loss `α_i` of the slab mode.
+    pub internal_loss: Loss,
+    /// The four modes, by increasing frequency.
+    pub modes: Vec<CwtMode>,
+}
+
+impl CwtSolution {
+    /// Vacuum wavelength of `mode`, where `δ = (k0² n_eff² - β0²) / (2β0)` matches its detuning.
+    pub fn band_edge(&self, mode: &CwtMode) -> Length {
+        let beta0 = self.coupling.bragg_wavenumber();
+        let k = (beta0 * beta0 + 2.0 * beta0 * mode.eigenvalue.re).sqrt();
+        Length::meters(2.0 * std::f64::consts::PI * self.coupling.effective_index / k)
+    }
+
+    /// Modal gain `α + α_i` that `mode` needs to reach threshold.
+    pub fn threshold_gain(&self, mode: &CwtMode) -> Loss {
+        mode.radiation_loss() + self.internal_loss
+    }
+
+    /// Quality factor `k n_eff / (α + α_i)` of `mode` at its band edge, infinite without loss.
+    pub fn quality_factor(&self, mode: &CwtMode) -> f64 {
+        let k = self.band_edge(mode).to_wavenumber().as_radians_per_meter();
+        k * self.coupling.effective_index / self.threshold_gain(mode).as_per_meter()
+    }
+
+    /// The mode with the lowest threshold, the lowest in frequency among equal ones.
+    pub fn lasing_mode(&self) -> &CwtMode {
+        let scale = self.modes.iter().map(|mode| mode.eigenvalue.norm()).fold(0.0, f64::max);
+        let lowest = self.modes.iter().map(|mode| mode.eigenvalue.im).fold(f64::INFINITY, f64::min);
+        self.modes
+            .iter()
+            .find(|mode| mode.eigenvalue.im <= lowest + LOSS_TOLERANCE * scale)
+            .expect("a solution has four modes")
+    }
+}
+
+/// Eigenvalues and unit eigenvectors of `matrix`, from its complex Schur form `Q T Q*` with
+/// the eigenvectors of the triangular `T` found by back substitution.
+fn eigen(matrix: Matrix4<Complex>) -> Option<Vec<(Complex, Vector4<Complex>)>> {
+    let (q, t) = matrix.try_schur(f64::EPSILON, MAX_ITERATIONS)?.unpack();
+    let floor = f64::EPSILON * matrix.norm().max(f64::MIN_POSITIVE);
+    let modes = (0..4)
+        .map(|k| {
+            let lambda = t[(k, k)];
+            let mut y = Vector4::<Complex>::zeros();
+            y[k] = Complex::new(1.0, 0.0);
+            for i in (0..k).rev() {
+                let sum: Complex = (i + 1..=k).map(|j| t[(i, j)] * y[j]).sum();
+                let mut pivot = t[(i, i)] - lambda;
+                // Repeated eigenvalues: perturb as LAPACK does rather than divide by zero.
+                if pivot.norm() < floor {
+                    pivot = Complex::new(floor, 0.0);
+                }
+                y[i] = -sum / pivot;
+            }
+            let mut vector = q * y;
+            // Unit norm, with the largest amplitude real and positive.
+            let largest = vector.iter().copied().fold(Complex::default(), |a, b| if b.norm() > a.norm() { b } else { a });
+            vector *= largest.conj() / (largest.norm() * vector.norm());
+            (lambda, vector)
+        })
+        .collect();
+    Some(modes)
+}
+
+/// Modes of the coupling `matrix`, by increasing frequency.
+fn modes(matrix: Matrix4<Complex>) -> Result<Vec<CwtMode>, CwtError> {
+    let scale = matrix.norm();
+    let mut modes: Vec<CwtMode> = eigen(matrix)
+        .ok_or(CwtError::NoConvergence)?
+        .into_iter()
+        .map(|(mut eigenvalue, vector)| {
+            // Round-off leaves the dark modes of symmetric cells a loss of either sign.
+            if eigenvalue.im.abs() < LOSS_TOLERANCE * scale {
+                eigenvalue.im = 0.0;
+            }
+            CwtMode {
+                eigenvalue,
+                amplitudes: [vector[0], vector[1], vector[2], vector[3]],
+            }
+        })
+        .collect();
+    modes.sort_by(|a, b| a.eigenvalue.re.total_cmp(&b.eigenvalue.re).then(a.eigenvalue.im.total_cmp(&b.eigenvalue.im)));
+    Ok(modes)
+}
+
+/// Solves the basic waves of the first photonic crystal layer of `waveguide`, with its slab
+/// `mode` and the `coefficients` of that layer, all at `wavelength`.
+pub fn solve(
+    waveguide: &Waveguide,
+    mode: &SlabMode,
+    coefficients: &FourierCoefficients,
+    wavelength: Length,
+) -> Result<CwtSolution, CwtError> {
+    let index = waveguide.layer_index().ok_or(CwtError::NoPhotonicCrystal)?;
+    let LayerType::PhotonicCrystal { name, thickness, definition, .. } = &waveguide.layers[index] else {
+        unreachable!("layer_index finds photonic crystal layers");
+    };
+    let LatticeType::Square(lattice) = &definition.lattice else {
+        return Err(CwtError::UnsupportedLattice(name.clone()));
+    };
+    let coupling = Coupling {
+        wavelength,
+        lattice_constant: lattice.lattice_constant(),
+        effective_index: mode.effective_index,
+        confinement: mode.layers[index].confinement,
+        thickness: *thickness,
+    };
+    let modes = modes(coupling.matrix(coefficients)?)?;
+    Ok(CwtSolution {
+        coupling,
+        internal_loss: mode.internal_loss(),
+        modes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coupling::tests::{coefficients, coupling};
+    use core::material::Material;
+    use core::shapes::HoleShape;
+    use phc::base::UnitCellBase;
+    use phc::crystal_structure::PhotonicCrystal;
+
+    fn solution(shape: HoleShape, center: (f64, f64)) -> CwtSolution {
+        let coupling = coupling();
+        let matrix = coupling.matrix(&coefficients(shape, center)).unwrap();
+        // Each pair solves C v = λ v.
+        for (eigenvalue, vector) in eigen(matrix).unwrap() {
+            assert!((matrix * vector - vector * eigenvalue).norm() < 1e-9 * matrix.norm());
+        }
+        CwtSolution {
+            coupling,
+            internal_loss: Loss::per_centimeter(5.0),
+            modes: modes(matrix).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_circular_holes() {
+        let solution = solution(HoleShape::Circle { radius: 0.16 }, (0.5, 0.5));
+        let losses: Vec<f64> = solution.modes.iter().map(|mode| mode.radiation_loss().as_per_centimeter()).collect();
+        // Two modes do not radiate, the other two are degenerate and radiate alike.
+        let mut sorted = losses.clone();
+        sorted.sort_by(f64::total_cmp);
+        assert!(sorted[0] == 0.0 && sorted[1] == 0.0, "{losses:?}");
+        assert!(sorted[2] > 1.0 && (sorted[3] - sorted[2]).abs() < 1e-6 * sorted[2], "{losses:?}");
+
+        let lasing = solution.lasing_mode();
+        assert_eq!(lasing.radiation_loss(), Loss::default());
+        // Of the two dark modes, the lower in frequency lases.
+        assert!(std::ptr::eq(lasing, &solution.modes[0]));
+        assert!((solution.threshold_gain(lasing).as_per_centimeter() - 5.0).abs() < 1e-6);
+        let edge = solution.band_edge(lasing);
+        // Band edges sit near the second-order Bragg wavelength a n_eff = 973.5 nm.
+        assert!((edge.as_nanometers() - 973.5).abs() < 20.0, "{edge}");
+        let q = solution.quality_factor(lasing);
+        let expected = 2.0 * std::f64::consts::PI * 3.3 / edge.as_meters() / 500.0;
+        assert!((q - expected).abs() < 1e-9 * expected);
+    }
+
+    #[test]
+    fn test_asymmetric_holes_radiate() {
+        let solution = solution(HoleShape::Ellipse { radius_x: 0.25, radius_y: 0.12, rotation: 0.0 }, (0.5, 0.5));
+        let losses: Vec<f64> = solution.modes.iter().map(|mode| mode.radiation_loss().as_per_meter()).collect();
+        assert!(losses.iter().all(|&loss| loss > -1e-6), "{losses:?}");
+        assert!(losses.iter().filter(|&&loss| loss > 1.0).count() >= 2, "{losses:?}");
+    }
+
+    #[test]
+    fn test_table_i() {
+        // The device of Table I of Liang et al., Phys. Rev. B 84, 195119 (2011). The expected
+        // values below are those of this model, which keeps the field uniform across the
+        // photonic crystal layer, and guard it against regressions.
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+        base.background_material = Material::new_from_eps(12.7449);
+        let crystal = PhotonicCrystal::new(lattice, base);
+        let wavelength = Length::nanometers(980.0);
+        let waveguide = Waveguide::new_from_table_i(crystal.clone(), Material::new_from_eps(12.7449));
+        let slab = waveguide.slab_mode(wavelength).unwrap();
+        let xi = fourier::permittivity_coefficients(&crystal, wavelength, 10, 256).unwrap();
+        let solution = solve(&waveguide, &slab, &xi, wavelength).unwrap();
+        assert!((solution.coupling.effective_index - 3.4049).abs() < 1e-4);
+        assert!((solution.coupling.confinement - 0.3024).abs() < 1e-4);
+
+        // κ1D = k0² Γ |ξ20| / 2β0, in 1/cm, within 0.5 %.
+        let kappa = solution.coupling.one_dimensional(&xi).unwrap()[(0, 1)].norm() / 100.0;
+        assert!((kappa / 1579.2 - 1.0).abs() < 5e-3, "{kappa}");
+
+        // Modes A to D by increasing frequency: detuning δ and radiation loss α in 1/cm,
+        // within 1 % of the larger of |δ| and α. A and B are dark, C and D a degenerate pair.
+        let expected: [(f64, f64); 4] = [(-1646.4, 0.0), (-1445.3, 0.0), (2045.2, 486.4), (2045.2, 486.4)];
+        for (mode, (delta, alpha)) in solution.modes.iter().zip(expected) {
+            let (d, a) = (mode.eigenvalue.re / 100.0, mode.radiation_loss().as_per_centimeter());
+            let tolerance = 1e-2 * delta.abs().max(alpha);
+            assert!((d - delta).abs() < tolerance && (a - alpha).abs() < tolerance, "δ {d}, α {a}");
+        }
+    }
+}
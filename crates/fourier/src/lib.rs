@@ -0,0 +1,6 @@
+//! crates/fourier/src/lib.rs
+//!
+//! Fourier expansions of the periodic permittivity of photonic crystal layers.
+pub mod permittivity;
+
+pub use permittivity::{permittivity_coefficients, FourierCoefficients, FourierError};
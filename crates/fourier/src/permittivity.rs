@@ -0,0 +1,179 @@
+//! crates/fourier/src/permittivity.rs
+//! Fourier coefficients `ξ_mn` of the in-plane permittivity of a photonic crystal layer.
+//!
+//! The permittivity is expanded as `ε(r) = Σ ξ_mn exp(i (m b1 + n b2)·r)` over the reciprocal
+//! lattice. The unit cell is sampled at the centers of an `N x N` grid of fractional
+//! coordinates `(s, t)` and transformed with an FFT. Each material contributes its `ε_xx`,
+//! the component seen by TE modes, at the requested wavelength.
+use std::fmt;
+
+use ndarray::Array2;
+use rustfft::FftPlanner;
+
+use core::units::Length;
+use core::vectorial::Complex;
+use phc::crystal_structure::PhotonicCrystal;
+
+/// Reasons why the coefficients cannot be computed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FourierError {
+    /// The sampling grid cannot resolve the requested orders, it needs `2 order + 1` points.
+    ResolutionTooLow { order: usize, resolution: usize },
+}
+
+impl fmt::Display for FourierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FourierError::ResolutionTooLow { order, resolution } => write!(
+                f,
+                "a resolution of {resolution} cannot resolve order {order}, at least {} is needed",
+                2 * order + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FourierError {}
+
+/// Coefficients `ξ_mn` for `|m|, |n| <= order`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FourierCoefficients {
+    order: usize,
+    /// Coefficients indexed `[m + order, n + order]`.
+    values: Array2<Complex>,
+}
+
+impl FourierCoefficients {
+    /// Largest `|m|` and `|n|` held.
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// Coefficient `ξ_mn`, or `None` beyond the order.
+    pub fn get(&self, m: i32, n: i32) -> Option<Complex> {
+        let index = |k: i32| usize::try_from(k + self.order as i32).ok().filter(|&k| k <= 2 * self.order);
+        Some(self.values[(index(m)?, index(n)?)])
+    }
+
+    /// All coefficients as `((m, n), ξ_mn)`, by increasing `m` then `n`.
+    pub fn iter(&self) -> impl Iterator<Item = ((i32, i32), Complex)> + '_ {
+        let order = self.order as i32;
+        self.values
+            .indexed_iter()
+            .map(move |((i, j), &xi)| ((i as i32 - order, j as i32 - order), xi))
+    }
+}
+
+/// Computes `ξ_mn` of `crystal` at the vacuum `wavelength` for `|m|, |n| <= order`,
+/// sampling the unit cell on a `resolution x resolution` grid.
+pub fn permittivity_coefficients(
+    crystal: &PhotonicCrystal,
+    wavelength: Length,
+    order: usize,
+    resolution: usize,
+) -> Result<FourierCoefficients, FourierError> {
+    if resolution < 2 * order + 1 {
+        return Err(FourierError::ResolutionTooLow { order, resolution });
+    }
+    let base = &crystal.base;
+    let lattice = crystal.lattice.lattice();
+    let eps_xx = |material: &core::material::Material| material.complex_epsilon_at(wavelength)[(0, 0)];
+    let background = eps_xx(&base.background_material);
+    let atoms: Vec<Complex> = base.atoms.iter().map(|atom| eps_xx(&atom.material)).collect();
+
+    let n = resolution;
+    let mut samples: Vec<Complex> = (0..n * n)
+        .map(|index| {
+            let (s, t) = (((index / n) as f64 + 0.5) / n as f64, ((index % n) as f64 + 0.5) / n as f64);
+            base.atom_index_at(s, t, lattice).map_or(background, |atom| atoms[atom])
+        })
+        .collect();
+
+    // Row transforms along t, then along s on the transposed samples, leaving `[n, m]` order.
+    let fft = FftPlanner::new().plan_fft_forward(n);
+    fft.process(&mut samples);
+    let mut transposed: Vec<Complex> = (0..n * n).map(|index| samples[(index % n) * n + index / n]).collect();
+    fft.process(&mut transposed);
+
+    // Samples sit at cell centers, half a step off the origin of the DFT.
+    let values = Array2::from_shape_fn((2 * order + 1, 2 * order + 1), |(i, j)| {
+        let (m, k) = (i as i64 - order as i64, j as i64 - order as i64);
+        let (row, column) = (k.rem_euclid(n as i64) as usize, m.rem_euclid(n as i64) as usize);
+        let shift = Complex::from_polar(1.0, -std::f64::consts::PI * (m + k) as f64 / n as f64);
+        transposed[row * n + column] * shift / (n * n) as f64
+    });
+    Ok(FourierCoefficients { order, values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::material::Material;
+    use core::shapes::HoleShape;
+    use phc::base::UnitCellBase;
+    use phc::fill_factor::AreaMethod;
+    use phc::lattice::LatticeType;
+
+    fn crystal(shape: HoleShape) -> PhotonicCrystal {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::new();
+        base.background_material = Material::new_from_eps(12.7449);
+        base.add_atom(shape, (0.5, 0.5, 0.0), Material::new_from_eps(1.0));
+        PhotonicCrystal::new(lattice, base)
+    }
+
+    #[test]
+    fn test_uniform_cell() {
+        let mut uniform = crystal(HoleShape::Circle { radius: 0.2 });
+        uniform.base.atoms.clear();
+        let xi = permittivity_coefficients(&uniform, Length::nanometers(980.0), 2, 16).unwrap();
+        assert_eq!(xi.iter().count(), 25);
+        for ((m, n), value) in xi.iter() {
+            let expected = if (m, n) == (0, 0) { 12.7449 } else { 0.0 };
+            assert!((value - Complex::new(expected, 0.0)).norm() < 1e-12, "ξ_{m}{n} = {value}");
+        }
+        assert_eq!(xi.get(3, 0), None);
+        assert_eq!(xi.get(0, -3), None);
+    }
+
+    #[test]
+    fn test_rectangle_matches_analytic_coefficients() {
+        let (width, height) = (0.5, 0.25);
+        let rectangle = crystal(HoleShape::Rectangle { width, height });
+        let xi = permittivity_coefficients(&rectangle, Length::nanometers(980.0), 3, 128).unwrap();
+        let contrast = 1.0 - 12.7449;
+        let sinc = |x: f64| if x == 0.0 { 1.0 } else { x.sin() / x };
+        for ((m, n), value) in xi.iter() {
+            let (m, n) = (m as f64, n as f64);
+            // The hole is centered at (½, ½), hence the sign (-1)^(m + n).
+            let sign = if (m + n) as i64 % 2 == 0 { 1.0 } else { -1.0 };
+            let hole = contrast * width * height * sinc(std::f64::consts::PI * m * width)
+                * sinc(std::f64::consts::PI * n * height)
+                * sign;
+            let expected = hole + if (m, n) == (0.0, 0.0) { 12.7449 } else { 0.0 };
+            assert!((value - Complex::new(expected, 0.0)).norm() < 1e-2, "ξ_{m}{n} = {value}, expected {expected}");
+        }
+    }
+
+    #[test]
+    fn test_circle_symmetries_and_average() {
+        let circle = crystal(HoleShape::Circle { radius: 0.2 });
+        let xi = permittivity_coefficients(&circle, Length::nanometers(980.0), 2, 256).unwrap();
+        let average = circle.average_complex_epsilon(AreaMethod::Analytic).unwrap()[(0, 0)];
+        assert!((xi.get(0, 0).unwrap() - average).norm() < 1e-2);
+        for ((m, n), value) in xi.iter() {
+            assert!(value.im.abs() < 1e-12, "ξ_{m}{n} = {value}");
+            assert!((xi.get(n, m).unwrap() - value).norm() < 1e-12);
+            assert!((xi.get(-m, -n).unwrap() - value).norm() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_resolution_must_resolve_the_order() {
+        let circle = crystal(HoleShape::Circle { radius: 0.2 });
+        assert_eq!(
+            permittivity_coefficients(&circle, Length::nanometers(980.0), 10, 20),
+            Err(FourierError::ResolutionTooLow { order: 10, resolution: 20 })
+        );
+    }
+}
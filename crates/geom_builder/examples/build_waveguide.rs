@@ -0,0 +1,36 @@
+//! Builds the Table I waveguide on a triangular lattice and streams its permittivity grid
+//! to `waveguide.h5`, one z-slab at a time.
+//!
+//! Run with `cargo run --example build_waveguide -p geom_builder --features hdf5`.
+use core::material::Material;
+use core::units::Length;
+use geom_builder::{slab_depth_for_budget, stream_waveguide_3d, RectifiedCell};
+use phc::base::UnitCellBase;
+use phc::crystal_structure::PhotonicCrystal;
+use phc::lattice::LatticeType;
+use waveguide::layers::Waveguide;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt().with_max_level(tracing::Level::DEBUG).init();
+
+    let air = Material::new_from_eps(1.0);
+    let gaas = Material::new_from_eps(12.7449);
+    let lattice = LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0));
+    let mut base = UnitCellBase::from_simple_circle(0.16, air);
+    base.background_material = gaas.clone();
+    let geom = PhotonicCrystal::new(lattice, base);
+    geom.validate().into_result().map_err(|errors| format!("invalid base: {errors:?}"))?;
+
+    let cell = RectifiedCell::new(geom.lattice.lattice())?;
+    let wg = Waveguide::new_from_table_i(geom, gaas);
+
+    let resolution = (64, 110, 256);
+    let (width, depth) = cell.size();
+    tracing::info!(%width, %depth, cells = cell.cells, "rectified cell");
+
+    // 1 MiB slabs, a few dozen voxel layers each.
+    let slab = slab_depth_for_budget(1 << 20, (resolution.0, resolution.1));
+    stream_waveguide_3d(&wg, resolution, slab, "waveguide.h5", "epsilon")?;
+    println!("wrote {resolution:?} grid to waveguide.h5 in slabs of {slab} layers");
+    Ok(())
+}
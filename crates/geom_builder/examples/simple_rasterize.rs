@@ -0,0 +1,49 @@
+//! Rasterizes the Table I waveguide and prints statistics of the grid.
+use core::material::Material;
+use core::units::Length;
+use geom_builder::{rasterize_waveguide_3d, RectifiedCell};
+use phc::base::UnitCellBase;
+use phc::crystal_structure::PhotonicCrystal;
+use phc::lattice::LatticeType;
+use ndarray::Axis;
+use waveguide::layers::Waveguide;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::fmt().with_max_level(tracing::Level::DEBUG).init();
+
+    let gaas = Material::new_from_eps(12.7449);
+    let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+    let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+    base.background_material = gaas.clone();
+    let cell = RectifiedCell::new(lattice.lattice())?;
+    let wg = Waveguide::new_from_table_i(PhotonicCrystal::new(lattice, base), gaas);
+
+    let (nx, ny, nz) = (64, 64, 128);
+    let grid = rasterize_waveguide_3d(&wg, (nx, ny, nz))?;
+
+    let (width, depth) = cell.size();
+    println!(
+        "grid {nx} x {ny} x {nz} over {:.1} x {:.1} x {:.1} nm",
+        width.as_nanometers(),
+        depth.as_nanometers(),
+        wg.total_thickness().as_nanometers()
+    );
+    let min = grid.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = grid.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    println!("epsilon: min {min:.4}, max {max:.4}, mean {:.4}", grid.mean().unwrap_or(0.0));
+
+    // One line per run of identical z-slices.
+    let dz = wg.total_thickness() / nz as f64;
+    let mut previous = None;
+    for k in 0..nz {
+        let slice = grid.index_axis(Axis(2), k);
+        let mean = slice.mean().unwrap_or(0.0);
+        if previous != Some(mean) {
+            let air = slice.iter().filter(|&&e| e < 1.5).count() as f64 / slice.len() as f64;
+            let z = dz * (k as f64 + 0.5);
+            println!("from z = {:>7.1} nm: mean epsilon {mean:>8.4}, air fraction {air:.3}", z.as_nanometers());
+            previous = Some(mean);
+        }
+    }
+    Ok(())
+}
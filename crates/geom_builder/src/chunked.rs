@@ -0,0 +1,219 @@
+//! crates/geom_builder/src/chunked.rs
+//! Rasterization in bounded memory, one z-slab at a time.
+//!
+//! Finite supercells quickly reach billions of voxels. The chunked rasterizer fills a single
+//! `(nx, ny, depth)` slab buffer in parallel, hands it to a sink (a file writer, a reduction, …)
+//! and reuses it for the next slab, so memory stays at one slab whatever `nz` is.
+use std::time::Instant;
+
+use ndarray::{Array3, ArrayView3, Axis, Slice};
+use waveguide::layers::Waveguide;
+
+use super::raster::{voxels_per_second, SliceSampler};
+use super::RasterError;
+
+/// Number of voxel layers per slab such that one slab of `f64` takes at most `budget` bytes.
+///
+/// Never less than one layer, so the actual footprint may exceed a tiny budget.
+pub fn slab_depth_for_budget(budget: usize, (nx, ny): (usize, usize)) -> usize {
+    (budget / (nx * ny * std::mem::size_of::<f64>()).max(1)).max(1)
+}
+
+/// Rasterizes `wg` like [`rasterize_waveguide_3d`](crate::rasterize_waveguide_3d), `depth`
+/// voxel layers at a time.
+///
+/// `sink` receives each slab with the index of its first voxel layer, in increasing z; the last
+/// slab is shorter when `depth` does not divide `nz`. The voxels are identical to those of the
+/// dense grid. Errors returned by `sink` stop the rasterization and are passed through.
+pub fn rasterize_waveguide_chunked<E, F>(
+    wg: &Waveguide,
+    (nx, ny, nz): (usize, usize, usize),
+    depth: usize,
+    mut sink: F,
+) -> Result<(), E>
+where
+    E: From<RasterError>,
+    F: FnMut(usize, ArrayView3<f64>) -> Result<(), E>,
+{
+    if depth == 0 {
+        return Err(RasterError::ZeroChunkDepth.into());
+    }
+    let sampler = SliceSampler::new(wg, (nx, ny, nz))?;
+    let depth = depth.min(nz);
+    let span = tracing::info_span!(
+        "rasterize_chunked",
+        nx,
+        ny,
+        nz,
+        depth,
+        voxels_per_second = tracing::field::Empty
+    )
+    .entered();
+    let start = Instant::now();
+
+    let mut buffer = Array3::zeros((nx, ny, depth));
+    for z_start in (0..nz).step_by(depth) {
+        let layers = depth.min(nz - z_start);
+        let chunk_start = Instant::now();
+        let mut slab = buffer.slice_axis_mut(Axis(2), Slice::from(0..layers));
+        sampler.fill_slab(z_start, slab.view_mut());
+        let rate = voxels_per_second(slab.len(), chunk_start.elapsed());
+        tracing::debug!(z_start, layers, voxels_per_second = rate, "rasterized slab");
+        sink(z_start, slab.view())?;
+    }
+
+    let voxels = nx * ny * nz;
+    let rate = voxels_per_second(voxels, start.elapsed());
+    span.record("voxels_per_second", rate);
+    tracing::info!(voxels, voxels_per_second = rate, "rasterized waveguide in slabs");
+    Ok(())
+}
+
+#[cfg(feature = "hdf5")]
+pub use self::stream::{stream_waveguide_3d, StreamError};
+
+#[cfg(feature = "hdf5")]
+mod stream {
+    use std::fmt;
+    use std::path::Path;
+
+    use io::{EpsilonGridWriter, Hdf5Error};
+    use waveguide::layers::Waveguide;
+
+    use super::rasterize_waveguide_chunked;
+    use crate::RasterError;
+
+    /// Failure while streaming a grid to HDF5.
+    #[derive(Debug)]
+    pub enum StreamError {
+        Raster(RasterError),
+        Hdf5(Hdf5Error),
+    }
+
+    impl fmt::Display for StreamError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                StreamError::Raster(error) => write!(f, "rasterization failed: {error}"),
+                StreamError::Hdf5(error) => write!(f, "HDF5 output failed: {error}"),
+            }
+        }
+    }
+
+    impl std::error::Error for StreamError {}
+
+    impl From<RasterError> for StreamError {
+        fn from(error: RasterError) -> Self {
+            StreamError::Raster(error)
+        }
+    }
+
+    impl From<Hdf5Error> for StreamError {
+        fn from(error: Hdf5Error) -> Self {
+            StreamError::Hdf5(error)
+        }
+    }
+
+    /// Rasterizes `wg` straight into the dataset `name` of a new HDF5 file at `path`,
+    /// holding at most `depth` voxel layers in memory.
+    pub fn stream_waveguide_3d(
+        wg: &Waveguide,
+        resolution: (usize, usize, usize),
+        depth: usize,
+        path: impl AsRef<Path>,
+        name: &str,
+    ) -> Result<(), StreamError> {
+        let writer = EpsilonGridWriter::create(path, name, resolution, depth)?;
+        rasterize_waveguide_chunked(wg, resolution, depth, |z_start, slab| {
+            writer.write_slab(z_start, slab).map_err(StreamError::from)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rasterize_waveguide_3d;
+    use crate::test_support::{square, table_i, triangular};
+    use waveguide::layers::LayerType;
+
+    #[test]
+    fn test_chunks_match_dense_grid() {
+        let wg = table_i(triangular(), 0.16);
+        let resolution = (24, 40, 200);
+        let dense = rasterize_waveguide_3d(&wg, resolution).unwrap();
+        for depth in [1, 7, 64, 200, 1000] {
+            let mut next = 0;
+            rasterize_waveguide_chunked(&wg, resolution, depth, |z_start, slab| {
+                assert_eq!(z_start, next);
+                assert!(slab.dim().2 <= depth);
+                for (dk, plane) in slab.axis_iter(Axis(2)).enumerate() {
+                    assert_eq!(plane, dense.index_axis(Axis(2), z_start + dk), "depth {depth}");
+                }
+                next += slab.dim().2;
+                Ok::<(), RasterError>(())
+            })
+            .unwrap();
+            assert_eq!(next, resolution.2);
+        }
+    }
+
+    #[test]
+    fn test_supercells_tile_the_primitive_grid() {
+        let primitive = table_i(square(), 0.16);
+        let n = 12;
+        let mut supercell = primitive.clone();
+        if let LayerType::PhotonicCrystal { definition, .. } = &mut supercell.layers[2] {
+            *definition = definition.supercell(n, n);
+        }
+        let (m, nz) = (16, 64);
+        let dense = rasterize_waveguide_3d(&primitive, (m, m, nz)).unwrap();
+        let mut layers = 0;
+        rasterize_waveguide_chunked(&supercell, (n * m, n * m, nz), 16, |z_start, slab| {
+            for ((i, j, dk), &epsilon) in slab.indexed_iter() {
+                assert_eq!(epsilon, dense[[i % m, j % m, z_start + dk]], "i = {i}, j = {j}, k = {}", z_start + dk);
+            }
+            layers += slab.dim().2;
+            Ok::<(), RasterError>(())
+        })
+        .unwrap();
+        assert_eq!(layers, nz);
+    }
+
+    #[test]
+    fn test_errors() {
+        let wg = table_i(triangular(), 0.16);
+        let ignore = |_: usize, _: ArrayView3<f64>| Ok::<(), RasterError>(());
+        assert_eq!(rasterize_waveguide_chunked(&wg, (4, 4, 4), 0, ignore), Err(RasterError::ZeroChunkDepth));
+        assert_eq!(rasterize_waveguide_chunked(&wg, (4, 0, 4), 2, ignore), Err(RasterError::ZeroResolution));
+
+        // Sink errors stop the rasterization.
+        #[derive(Debug, PartialEq)]
+        enum SinkError {
+            Raster(RasterError),
+            Full,
+        }
+        impl From<RasterError> for SinkError {
+            fn from(error: RasterError) -> Self {
+                SinkError::Raster(error)
+            }
+        }
+        let mut calls = 0;
+        let result = rasterize_waveguide_chunked(&wg, (4, 4, 16), 4, |z_start, _| {
+            calls += 1;
+            if z_start >= 4 {
+                Err(SinkError::Full)
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err(SinkError::Full));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_slab_depth_for_budget() {
+        assert_eq!(slab_depth_for_budget(64 * 64 * 8 * 10, (64, 64)), 10);
+        assert_eq!(slab_depth_for_budget(1, (64, 64)), 1);
+        assert_eq!(slab_depth_for_budget(1 << 30, (1600, 1600)), 52);
+    }
+}
@@ -0,0 +1,95 @@
+//! crates/geom_builder/src/lib.rs
+//!
+//! Rasterization of photonic crystal waveguide stacks into orthogonal voxel grids.
+use std::fmt;
+
+pub mod chunked;
+pub mod raster;
+pub mod rectify;
+pub mod slice;
+pub mod smoothing;
+
+pub use chunked::{rasterize_waveguide_chunked, slab_depth_for_budget};
+#[cfg(feature = "hdf5")]
+pub use chunked::{stream_waveguide_3d, StreamError};
+pub use raster::rasterize_waveguide_3d;
+pub use rectify::RectifiedCell;
+pub use slice::{rasterize_cross_section, rasterize_slice_xy, CutLine};
+pub use smoothing::rasterize_waveguide_tensor_3d;
+
+/// Reasons why a waveguide cannot be rasterized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RasterError {
+    /// A grid dimension is zero.
+    ZeroResolution,
+    /// A chunked rasterization was asked for slabs of zero voxel layers.
+    ZeroChunkDepth,
+    /// A slice was requested at a height outside the stack.
+    OutsideStack,
+    /// A cross-section was requested along a line of zero length.
+    DegenerateCut,
+    /// The stack has no photonic crystal layer to define the in-plane cell.
+    NoPhotonicCrystal,
+    /// The lattice has no orthogonal supercell of at most [`rectify::MAX_SUPERCELL`] cells.
+    NotRectifiable,
+}
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RasterError::ZeroResolution => write!(f, "grid resolution must be positive along every axis"),
+            RasterError::ZeroChunkDepth => write!(f, "chunk depth must be at least one voxel layer"),
+            RasterError::OutsideStack => write!(f, "the slice height lies outside the layer stack"),
+            RasterError::DegenerateCut => write!(f, "the cut line has zero length"),
+            RasterError::NoPhotonicCrystal => write!(f, "the waveguide has no photonic crystal layer"),
+            RasterError::NotRectifiable => write!(
+                f,
+                "the lattice has no orthogonal supercell of at most {} unit cells",
+                rectify::MAX_SUPERCELL
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RasterError {}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use core::material::Material;
+    use core::units::Length;
+    use phc::base::UnitCellBase;
+    use phc::crystal_structure::PhotonicCrystal;
+    use phc::lattice::LatticeType;
+    use waveguide::layers::Waveguide;
+
+    /// The Table I stack with air holes of `radius` (units of a) in GaAs on `lattice`.
+    pub(crate) fn table_i(lattice: LatticeType, radius: f64) -> Waveguide {
+        let gaas = Material::new_from_eps(12.7449);
+        let mut base = UnitCellBase::from_simple_circle(radius, Material::new_from_eps(1.0));
+        base.background_material = gaas.clone();
+        Waveguide::new_from_table_i(PhotonicCrystal::new(lattice, base), gaas)
+    }
+
+    /// The square lattice of Table I.
+    pub(crate) fn square() -> LatticeType {
+        LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0))
+    }
+
+    /// The triangular lattice with the period and thickness of Table I.
+    pub(crate) fn triangular() -> LatticeType {
+        LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0))
+    }
+
+    /// The voxel layer out of `nz` containing the middle of the named layer, with its center height.
+    pub(crate) fn middle_of(wg: &Waveguide, name: &str, nz: usize) -> (Length, usize) {
+        let mut bottom = Length::default();
+        for layer in &wg.layers {
+            if layer.name() == name {
+                let k = ((bottom + layer.thickness() * 0.5) / wg.total_thickness() * nz as f64) as usize;
+                return (wg.total_thickness() * ((k as f64 + 0.5) / nz as f64), k);
+            }
+            bottom = bottom + layer.thickness();
+        }
+        unreachable!()
+    }
+}
@@ -0,0 +1,284 @@
+//! crates/geom_builder/src/raster.rs
+//! Voxel permittivity grids of waveguide stacks.
+use std::time::{Duration, Instant};
+
+use core::material::{DielectricTensor, Material};
+use ndarray::{Array3, ArrayViewMut2, ArrayViewMut3, Axis};
+use phc::base::UnitCellBase;
+use phc::crystal_structure::PhotonicCrystal;
+use phc::lattice::Lattice;
+use rayon::prelude::*;
+use waveguide::layers::{LayerType, Waveguide};
+
+use super::rectify::RectifiedCell;
+use super::RasterError;
+
+/// Layer containing the height `z` (meters above the bottom of the stack) and the fractional
+/// height inside it, or `None` outside the stack. Interfaces belong to the layer below.
+pub(crate) fn layer_at(wg: &Waveguide, z: f64) -> Option<(&LayerType, f64)> {
+    if !(0.0..=wg.total_thickness().as_meters()).contains(&z) {
+        return None;
+    }
+    let mut bottom = 0.0;
+    for (index, layer) in wg.layers.iter().enumerate() {
+        let thickness = layer.thickness().as_meters();
+        if index + 1 == wg.layers.len() || z <= bottom + thickness {
+            return Some((layer, (z - bottom) / thickness));
+        }
+        bottom += thickness;
+    }
+    None
+}
+
+/// Layer and fractional height inside it at the center of each of `nz` voxel layers.
+pub(crate) fn z_samples(wg: &Waveguide, nz: usize) -> Vec<(&LayerType, f64)> {
+    let total = wg.total_thickness().as_meters();
+    (0..nz)
+        .filter_map(|k| layer_at(wg, (k as f64 + 0.5) / nz as f64 * total))
+        .collect()
+}
+
+/// Cross-section of a photonic crystal layer at the fractional height `u`, measured
+/// from the mid-plane of the layer in units of `a3`.
+pub(crate) fn pc_slice(definition: &PhotonicCrystal, layer: &LayerType, u: f64) -> UnitCellBase {
+    let lattice = definition.lattice.lattice();
+    let z = (u - 0.5) * layer.thickness().as_meters() / lattice.a3.norm();
+    definition.base.slice_at(z, lattice)
+}
+
+/// Scalar permittivity of a material, the mean of the diagonal of its tensor.
+pub(crate) fn scalar_epsilon(material: &Material) -> f64 {
+    material.epsilon_matrix.trace() / 3.0
+}
+
+/// What a voxel layer cuts through: a uniform layer, with its scalar permittivity and its full
+/// tensor, or a cross-section of a photonic crystal.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Plane<'a> {
+    Uniform { epsilon: f64, tensor: DielectricTensor },
+    Crystal { base: UnitCellBase, lattice: &'a Lattice },
+}
+
+/// Samples the voxel layers of a waveguide grid one z-slice at a time.
+///
+/// Shared by [`rasterize_waveguide_3d`], the chunked rasterizers and the smoothed tensors,
+/// so that every path samples the same heights and positions and produces bit-identical voxels.
+pub(crate) struct SliceSampler<'a> {
+    samples: Vec<(&'a LayerType, f64)>,
+    fractional: Vec<(f64, f64)>,
+    ny: usize,
+}
+
+impl<'a> SliceSampler<'a> {
+    pub(crate) fn new(wg: &'a Waveguide, (nx, ny, nz): (usize, usize, usize)) -> Result<Self, RasterError> {
+        if nz == 0 {
+            return Err(RasterError::ZeroResolution);
+        }
+        Self::with_samples(wg, (nx, ny), z_samples(wg, nz))
+    }
+
+    /// Sampler of the given `(layer, fractional height)` planes, indexed in that order.
+    pub(crate) fn with_samples(
+        wg: &'a Waveguide,
+        (nx, ny): (usize, usize),
+        samples: Vec<(&'a LayerType, f64)>,
+    ) -> Result<Self, RasterError> {
+        if nx == 0 || ny == 0 {
+            return Err(RasterError::ZeroResolution);
+        }
+        let crystal = match wg.get_layer() {
+            Some(LayerType::PhotonicCrystal { definition, .. }) => definition,
+            _ => return Err(RasterError::NoPhotonicCrystal),
+        };
+        let cell = RectifiedCell::new(crystal.lattice.lattice())?;
+        tracing::debug!(nx, ny, planes = samples.len(), cells = cell.cells, "rasterizing waveguide");
+
+        let fractional = (0..nx * ny)
+            .map(|index| {
+                let (i, j) = (index / ny, index % ny);
+                cell.fractional(cell.position((i as f64 + 0.5) / nx as f64, (j as f64 + 0.5) / ny as f64))
+            })
+            .collect();
+        Ok(Self::with_positions(samples, fractional, ny))
+    }
+
+    /// Sampler of arbitrary fractional in-plane positions, filled as rows of `ny` pixels.
+    pub(crate) fn with_positions(samples: Vec<(&'a LayerType, f64)>, fractional: Vec<(f64, f64)>, ny: usize) -> Self {
+        SliceSampler {
+            samples,
+            fractional,
+            ny,
+        }
+    }
+
+    /// Fractional in-plane position of every pixel, row by row.
+    pub(crate) fn fractional(&self) -> &[(f64, f64)] {
+        &self.fractional
+    }
+
+    /// Contents of voxel layer `k`.
+    pub(crate) fn plane(&self, k: usize) -> Plane<'a> {
+        let (layer, u) = self.samples[k];
+        match layer {
+            LayerType::Simple { material, .. } => Plane::Uniform {
+                epsilon: scalar_epsilon(material),
+                tensor: material.epsilon_matrix,
+            },
+            LayerType::Graded { profile, .. } => {
+                let epsilon = profile.epsilon_at(u);
+                Plane::Uniform {
+                    epsilon,
+                    tensor: DielectricTensor::identity() * epsilon,
+                }
+            }
+            LayerType::PhotonicCrystal { definition, .. } => Plane::Crystal {
+                base: pc_slice(definition, layer, u),
+                lattice: definition.lattice.lattice(),
+            },
+        }
+    }
+
+    /// Fills the `(nx, ny)` plane with the permittivities of voxel layer `k`, in parallel over rows.
+    pub(crate) fn fill_slice(&self, k: usize, mut plane: ArrayViewMut2<f64>) {
+        match self.plane(k) {
+            Plane::Uniform { epsilon, .. } => plane.fill(epsilon),
+            Plane::Crystal { base, lattice } => {
+                let lookup = base.lookup(lattice, 0.0);
+                plane.axis_iter_mut(Axis(0)).into_par_iter().enumerate().for_each(|(i, mut row)| {
+                    let fractional = &self.fractional[i * self.ny..(i + 1) * self.ny];
+                    for (epsilon, &(s, t)) in row.iter_mut().zip(fractional) {
+                        *epsilon = scalar_epsilon(lookup.material_at(s, t));
+                    }
+                });
+            }
+        }
+    }
+
+    /// Fills a `(nx, ny, depth)` slab starting at voxel layer `z_start`, in parallel over z.
+    pub(crate) fn fill_slab(&self, z_start: usize, mut slab: ArrayViewMut3<f64>) {
+        slab.axis_iter_mut(Axis(2))
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(dk, plane)| self.fill_slice(z_start + dk, plane));
+    }
+}
+
+/// Rasterizes `wg` into an `(nx, ny, nz)` grid of scalar permittivities over one
+/// rectified unit cell of its photonic crystal layer.
+///
+/// Voxel `[i, j, k]` is sampled at its center, `((i + ½)/nx) b1 + ((j + ½)/ny) b2` in plane
+/// (see [`RectifiedCell`]) and `(k + ½)/nz` of the total thickness above the bottom of the stack.
+/// The grid is filled in parallel; see [`crate::chunked`] for grids that do not fit in memory.
+pub fn rasterize_waveguide_3d(wg: &Waveguide, (nx, ny, nz): (usize, usize, usize)) -> Result<Array3<f64>, RasterError> {
+    let sampler = SliceSampler::new(wg, (nx, ny, nz))?;
+    let span = tracing::info_span!("rasterize", nx, ny, nz, voxels_per_second = tracing::field::Empty).entered();
+    let start = Instant::now();
+
+    let mut grid = Array3::zeros((nx, ny, nz));
+    sampler.fill_slab(0, grid.view_mut());
+
+    let rate = voxels_per_second(grid.len(), start.elapsed());
+    span.record("voxels_per_second", rate);
+    tracing::info!(voxels = grid.len(), voxels_per_second = rate, "rasterized waveguide");
+    Ok(grid)
+}
+
+/// Throughput of a rasterization pass.
+pub(crate) fn voxels_per_second(voxels: usize, elapsed: Duration) -> f64 {
+    voxels as f64 / elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{middle_of, square, table_i, triangular};
+    use phc::fill_factor::AreaMethod;
+
+    #[test]
+    fn test_basic_rasterization() {
+        let wg = table_i(square(), 0.16);
+        let nz = 512;
+        let grid = rasterize_waveguide_3d(&wg, (32, 32, nz)).unwrap();
+        assert_eq!(grid.dim(), (32, 32, nz));
+        assert_eq!(grid[[0, 0, 0]], 11.0224);
+        assert_eq!(grid[[5, 7, nz - 1]], 11.0224);
+        assert!(grid.index_axis(Axis(2), middle_of(&wg, "Active", nz).1).iter().all(|&e| e == 12.8603));
+
+        let pc = grid.index_axis(Axis(2), middle_of(&wg, "PC", nz).1);
+        assert_eq!(pc[[16, 16]], 1.0);
+        assert_eq!(pc[[0, 0]], 12.7449);
+        let air = pc.iter().filter(|&&e| e == 1.0).count() as f64 / pc.len() as f64;
+        assert!((air - std::f64::consts::PI * 0.16 * 0.16).abs() < 0.01);
+
+        assert_eq!(rasterize_waveguide_3d(&wg, (0, 4, 4)), Err(RasterError::ZeroResolution));
+        let no_pc = Waveguide {
+            layers: vec![wg.layers[0].clone()],
+        };
+        assert_eq!(rasterize_waveguide_3d(&no_pc, (4, 4, 4)), Err(RasterError::NoPhotonicCrystal));
+    }
+
+    #[test]
+    fn test_multiple_resolutions() {
+        let wg = table_i(square(), 0.16);
+        let expected = match wg.get_layer().unwrap() {
+            LayerType::PhotonicCrystal { definition, .. } => definition.average_epsilon(AreaMethod::Analytic).unwrap()[(0, 0)],
+            _ => unreachable!(),
+        };
+        for (n, tolerance) in [(16, 0.3), (64, 0.05), (128, 0.02)] {
+            let grid = rasterize_waveguide_3d(&wg, (n, n, 256)).unwrap();
+            let pc = grid.index_axis(Axis(2), middle_of(&wg, "PC", 256).1);
+            assert_eq!(pc.dim(), (n, n));
+            assert!((pc.mean().unwrap() - expected).abs() < tolerance, "n = {n}");
+        }
+    }
+
+    #[test]
+    fn test_grid_symmetry() {
+        let n = 40;
+        let wg = table_i(square(), 0.16);
+        let grid = rasterize_waveguide_3d(&wg, (n, n, 128)).unwrap();
+        let (_, k) = middle_of(&wg, "PC", 128);
+        for i in 0..n {
+            for j in 0..n {
+                let e = grid[[i, j, k]];
+                assert_eq!(e, grid[[n - 1 - i, j, k]]);
+                assert_eq!(e, grid[[i, n - 1 - j, k]]);
+                assert_eq!(e, grid[[j, i, k]]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_triangular_lattice_is_rectified() {
+        let wg = table_i(triangular(), 0.16);
+        let (nx, ny) = (40, 70);
+        let grid = rasterize_waveguide_3d(&wg, (nx, ny, 128)).unwrap();
+        let pc = grid.index_axis(Axis(2), middle_of(&wg, "PC", 128).1);
+        // The rectangle a x √3a holds two holes, at (0.75, 0.25) and (0.25, 0.75) of its sides.
+        assert_eq!(pc[[3 * nx / 4, ny / 4]], 1.0);
+        assert_eq!(pc[[nx / 4, 3 * ny / 4]], 1.0);
+        assert_eq!(pc[[nx / 4, ny / 4]], 12.7449);
+        let air = pc.iter().filter(|&&e| e == 1.0).count() as f64 / pc.len() as f64;
+        let expected = std::f64::consts::PI * 0.16 * 0.16 / (3f64.sqrt() / 2.0);
+        assert!((air - expected).abs() < 0.01);
+        // Inversion symmetry about the center of the rectangle.
+        for i in 0..nx {
+            for j in 0..ny {
+                assert_eq!(pc[[i, j]], pc[[nx - 1 - i, ny - 1 - j]]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sidewall_tilt_shrinks_holes_upwards() {
+        let mut wg = table_i(square(), 0.16);
+        if let LayerType::PhotonicCrystal { definition, .. } = &mut wg.layers[2] {
+            definition.base.atoms[0].sidewall_angle = 0.3;
+        }
+        let nz = 1024;
+        let grid = rasterize_waveguide_3d(&wg, (64, 64, nz)).unwrap();
+        let (_, k) = middle_of(&wg, "PC", nz);
+        let air = |k: usize| grid.index_axis(Axis(2), k).iter().filter(|&&e| e == 1.0).count();
+        assert!(air(k - 10) > air(k) && air(k) > air(k + 10));
+    }
+}
@@ -0,0 +1,95 @@
+//! crates/geom_builder/src/rectify.rs
+//! Orthogonal supercells of non-orthogonal lattices.
+use core::units::Length;
+use core::vectorial::Vector2;
+use core::nalgebra::Matrix2;
+use phc::lattice::Lattice;
+
+use super::RasterError;
+
+/// Largest number of unit cells searched for an orthogonal supercell.
+pub const MAX_SUPERCELL: usize = 8;
+/// Tolerance on the integer coefficients of the supercell vectors.
+const INTEGER_TOLERANCE: f64 = 1e-6;
+
+/// Rectangular cell spanned by `b1 = a1` and `b2 = m a1 + n a2 ⟂ a1`, in meters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RectifiedCell {
+    pub b1: Vector2,
+    pub b2: Vector2,
+    /// Number `n` of lattice unit cells in the rectangle.
+    pub cells: usize,
+    /// Maps lab coordinates to fractional coordinates of the lattice.
+    to_fractional: Matrix2<f64>,
+}
+
+impl RectifiedCell {
+    /// Finds the smallest orthogonal supercell of `lattice`.
+    pub fn new(lattice: &Lattice) -> Result<Self, RasterError> {
+        let (a1, a2) = lattice.in_plane_vectors();
+        let to_fractional = Matrix2::from_columns(&[a1, a2])
+            .try_inverse()
+            .ok_or(RasterError::NotRectifiable)?;
+        let ratio = a1.dot(&a2) / a1.norm_squared();
+        (1..=MAX_SUPERCELL)
+            .find_map(|n| {
+                let m = -(n as f64) * ratio;
+                ((m - m.round()).abs() < INTEGER_TOLERANCE).then(|| Self {
+                    b1: a1,
+                    b2: a1 * m.round() + a2 * n as f64,
+                    cells: n,
+                    to_fractional,
+                })
+            })
+            .ok_or(RasterError::NotRectifiable)
+    }
+
+    /// Side lengths `|b1|` and `|b2|`.
+    pub fn size(&self) -> (Length, Length) {
+        (Length::meters(self.b1.norm()), Length::meters(self.b2.norm()))
+    }
+
+    /// Lab position of the point at fractions `(u, v)` of `b1` and `b2`.
+    pub fn position(&self, u: f64, v: f64) -> Vector2 {
+        self.b1 * u + self.b2 * v
+    }
+
+    /// Fractional lattice coordinates `(s, t)` of the lab position `p`.
+    pub fn fractional(&self, p: Vector2) -> (f64, f64) {
+        let st = self.to_fractional * p;
+        (st.x, st.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phc::lattice::LatticeType;
+
+    #[test]
+    fn test_square_and_triangular_cells() {
+        let a = Length::nanometers(295.0);
+        let square = RectifiedCell::new(LatticeType::new_square(a, a).lattice()).unwrap();
+        assert_eq!(square.cells, 1);
+        assert_eq!(square.size(), (a, a));
+
+        let triangular = RectifiedCell::new(LatticeType::new_triangular(a, a).lattice()).unwrap();
+        assert_eq!(triangular.cells, 2);
+        assert!(triangular.b1.dot(&triangular.b2).abs() < 1e-24);
+        assert!((triangular.size().1 / a - 3f64.sqrt()).abs() < 1e-9);
+        // The corner of the rectangle opposite to the origin is a lattice point.
+        let (s, t) = triangular.fractional(triangular.position(1.0, 1.0));
+        assert!((s - s.round()).abs() < 1e-9 && (t - t.round()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_generic_oblique_lattice_is_rejected() {
+        use core::vectorial::Vector3;
+        let lattice = Lattice {
+            a1: Vector3::new(1.0, 0.0, 0.0),
+            a2: Vector3::new(0.3 * std::f64::consts::SQRT_2, 1.0, 0.0),
+            a3: Vector3::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(RectifiedCell::new(&lattice), Err(RasterError::NotRectifiable));
+    }
+}
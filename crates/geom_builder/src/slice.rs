@@ -0,0 +1,176 @@
+//! crates/geom_builder/src/slice.rs
+//! Two-dimensional cuts evaluated directly from the geometry.
+//!
+//! Neither cut builds the 3D grid: the in-plane slice samples a single height and the side
+//! cut a single line, at any resolution and along any in-plane direction.
+use core::nalgebra::Matrix2;
+use core::units::Length;
+use core::vectorial::Vector2;
+use ndarray::{Array2, Axis};
+use phc::lattice::Lattice;
+use rayon::prelude::*;
+use waveguide::layers::{LayerType, Waveguide};
+
+use super::raster::{layer_at, z_samples, SliceSampler};
+use super::RasterError;
+
+/// Straight segment in the plane of the layers, from `origin` to `origin + span`, in meters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CutLine {
+    pub origin: Vector2,
+    pub span: Vector2,
+}
+
+impl CutLine {
+    pub fn new(origin: Vector2, span: Vector2) -> Self {
+        CutLine { origin, span }
+    }
+
+    /// Cut starting at the fractional position `(s, t)` and running `periods` times along the
+    /// lattice vector `m a1 + n a2`, e.g. `(1, 0)` for Γ–X and `(1, 1)` for Γ–M of a square lattice.
+    pub fn along_lattice(lattice: &Lattice, (s, t): (f64, f64), (m, n): (i32, i32), periods: f64) -> Self {
+        let (a1, a2) = lattice.in_plane_vectors();
+        CutLine {
+            origin: a1 * s + a2 * t,
+            span: (a1 * m as f64 + a2 * n as f64) * periods,
+        }
+    }
+
+    pub fn length(&self) -> Length {
+        Length::meters(self.span.norm())
+    }
+
+    /// Position at the fraction `f` of the way along the cut.
+    pub fn point(&self, f: f64) -> Vector2 {
+        self.origin + self.span * f
+    }
+}
+
+/// Permittivity `ε(x, y)` over one rectified cell at the height `z` above the bottom of the stack.
+///
+/// Pixel `[i, j]` is sampled like the voxels of [`rasterize_waveguide_3d`](crate::rasterize_waveguide_3d),
+/// so at the center height of a voxel layer the slice equals that layer of the 3D grid.
+pub fn rasterize_slice_xy(wg: &Waveguide, z: Length, (nx, ny): (usize, usize)) -> Result<Array2<f64>, RasterError> {
+    let sample = layer_at(wg, z.as_meters()).ok_or(RasterError::OutsideStack)?;
+    let sampler = SliceSampler::with_samples(wg, (nx, ny), vec![sample])?;
+    let mut plane = Array2::zeros((nx, ny));
+    sampler.fill_slice(0, plane.view_mut());
+    Ok(plane)
+}
+
+/// Permittivity `ε(l, z)` on the vertical plane through `line`, over the whole stack.
+///
+/// Pixel `[l, k]` is sampled at `line.point((l + ½)/nl)` and `(k + ½)/nz` of the total
+/// thickness, the same heights as the voxel layers of the 3D grid. The line may run in any
+/// direction and over any number of cells; the lattice need not be rectifiable.
+pub fn rasterize_cross_section(wg: &Waveguide, line: &CutLine, (nl, nz): (usize, usize)) -> Result<Array2<f64>, RasterError> {
+    if nl == 0 || nz == 0 {
+        return Err(RasterError::ZeroResolution);
+    }
+    if line.span.norm() == 0.0 {
+        return Err(RasterError::DegenerateCut);
+    }
+    let crystal = match wg.get_layer() {
+        Some(LayerType::PhotonicCrystal { definition, .. }) => definition,
+        _ => return Err(RasterError::NoPhotonicCrystal),
+    };
+    let (a1, a2) = crystal.lattice.lattice().in_plane_vectors();
+    let to_fractional = Matrix2::from_columns(&[a1, a2])
+        .try_inverse()
+        .ok_or(RasterError::NotRectifiable)?;
+    tracing::debug!(nl, nz, length = %line.length(), "rasterizing cross-section");
+
+    let fractional: Vec<(f64, f64)> = (0..nl)
+        .map(|l| {
+            let st = to_fractional * line.point((l as f64 + 0.5) / nl as f64);
+            (st.x, st.y)
+        })
+        .collect();
+    let sampler = SliceSampler::with_positions(z_samples(wg, nz), fractional, nl);
+    let mut section = Array2::zeros((nl, nz));
+    section
+        .axis_iter_mut(Axis(1))
+        .into_par_iter()
+        .enumerate()
+        .for_each(|(k, column)| sampler.fill_slice(k, column.insert_axis(Axis(0))));
+    Ok(section)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{middle_of, square, table_i, triangular};
+    use crate::{rasterize_waveguide_3d, RectifiedCell};
+
+    fn air_fraction(values: impl Iterator<Item = f64>) -> f64 {
+        let (mut air, mut total) = (0, 0);
+        for e in values {
+            air += (e == 1.0) as usize;
+            total += 1;
+        }
+        air as f64 / total as f64
+    }
+
+    #[test]
+    fn test_xy_slice_matches_grid() {
+        for lattice in [square(), triangular()] {
+            let wg = table_i(lattice, 0.16);
+            let (nx, ny, nz) = (24, 40, 128);
+            let grid = rasterize_waveguide_3d(&wg, (nx, ny, nz)).unwrap();
+            for name in ["PC", "Active"] {
+                let (z, k) = middle_of(&wg, name, nz);
+                let slice = rasterize_slice_xy(&wg, z, (nx, ny)).unwrap();
+                assert_eq!(slice, grid.index_axis(Axis(2), k));
+            }
+        }
+        let wg = table_i(square(), 0.16);
+        assert_eq!(
+            rasterize_slice_xy(&wg, wg.total_thickness() * 1.01, (8, 8)),
+            Err(RasterError::OutsideStack)
+        );
+        assert_eq!(rasterize_slice_xy(&wg, Length::default(), (0, 8)), Err(RasterError::ZeroResolution));
+    }
+
+    #[test]
+    fn test_cross_section_matches_grid_row() {
+        let lattice = square();
+        let wg = table_i(lattice.clone(), 0.16);
+        let (n, nz) = (32, 128);
+        let grid = rasterize_waveguide_3d(&wg, (n, n, nz)).unwrap();
+        let cell = RectifiedCell::new(lattice.lattice()).unwrap();
+        for j in [0, 10, 16] {
+            let line = CutLine::new(cell.position(0.0, (j as f64 + 0.5) / n as f64), cell.b1);
+            let section = rasterize_cross_section(&wg, &line, (n, nz)).unwrap();
+            for k in 0..nz {
+                for i in 0..n {
+                    assert_eq!(section[[i, k]], grid[[i, j, k]], "i = {i}, j = {j}, k = {k}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cuts_through_and_between_rows_of_holes() {
+        let lattice = square();
+        let wg = table_i(lattice.clone(), 0.16);
+        let (nl, nz) = (2000, 128);
+        let (_, k) = middle_of(&wg, "PC", nz);
+        let air = |line: CutLine| {
+            let section = rasterize_cross_section(&wg, &line, (nl, nz)).unwrap();
+            air_fraction(section.index_axis(Axis(1), k).iter().cloned())
+        };
+        // Γ–X through the hole centers crosses a diameter per period, and misses them half a period away.
+        assert!((air(CutLine::along_lattice(lattice.lattice(), (0.0, 0.5), (1, 0), 3.0)) - 0.32).abs() < 0.002);
+        assert_eq!(air(CutLine::along_lattice(lattice.lattice(), (0.0, 0.0), (1, 0), 3.0)), 0.0);
+        // Γ–M through the hole centers crosses a diameter every √2 a.
+        let diagonal = CutLine::along_lattice(lattice.lattice(), (0.0, 0.0), (1, 1), 3.0);
+        assert!((diagonal.length() / (lattice.lattice().lattice_constant() * 2f64.sqrt()) - 3.0).abs() < 1e-12);
+        assert!((air(diagonal) - 0.32 / 2f64.sqrt()).abs() < 0.002);
+
+        assert_eq!(
+            rasterize_cross_section(&wg, &CutLine::new(Vector2::zeros(), Vector2::zeros()), (4, 4)),
+            Err(RasterError::DegenerateCut)
+        );
+        assert_eq!(rasterize_cross_section(&wg, &diagonal, (4, 0)), Err(RasterError::ZeroResolution));
+    }
+}
@@ -0,0 +1,360 @@
+//! crates/geom_builder/src/smoothing.rs
+//! Sub-pixel smoothed permittivity tensors (Kottke, Farjadpour and Johnson, PRE 77, 036611).
+//!
+//! A voxel cut by the boundary of a hole mixes the tensors of both sides in the frame of the
+//! boundary normal `n`: each tensor is mapped to `τ(ε)`, with `τ_nn = -1/ε_nn`,
+//! `τ_nt = ε_nt/ε_nn` and `τ_tt = ε_tt - ε_tn ε_nt/ε_nn`, the fill-weighted mean of `τ` is
+//! taken and mapped back. For tensors diagonal in that frame this is the harmonic mean of
+//! `ε_nn` across the interface and the arithmetic mean of the tangential block along it. The
+//! normal and the fill fraction come from the shape of the hole, approximating its boundary
+//! by a plane across the voxel, so the tensors vary continuously with the hole dimensions.
+//! Voxels away from an interface hold the tensor of their material unchanged, and voxels are
+//! sampled at their centers along z like the scalar grid.
+//!
+//! No solver reads the tensor grid yet: the Fourier coefficients are computed from the crystal
+//! geometry and the HDF5 layout of the `io` crate stores scalar grids only.
+use core::material::DielectricTensor;
+use core::nalgebra::Matrix2;
+use core::vectorial::{Vector2, Vector3};
+use ndarray::{Array5, ArrayViewMut4, Axis};
+use phc::base::AtomLookup;
+use rayon::prelude::*;
+use waveguide::layers::{LayerType, Waveguide};
+
+use super::raster::{Plane, SliceSampler};
+use super::rectify::RectifiedCell;
+use super::RasterError;
+
+/// Strips used to integrate the fill fraction of a voxel cut by a plane.
+const FILL_STRIPS: usize = 32;
+
+/// Kottke's map `τ(ε)` of a tensor expressed with the normal as its first axis. It is its own
+/// inverse up to the sign of the first row and column, which [`from_tau`] restores.
+fn tau(epsilon: &DielectricTensor) -> DielectricTensor {
+    let nn = epsilon[(0, 0)];
+    DielectricTensor::from_fn(|r, c| match (r, c) {
+        (0, 0) => -1.0 / nn,
+        (0, _) | (_, 0) => epsilon[(r, c)] / nn,
+        _ => epsilon[(r, c)] - epsilon[(r, 0)] * epsilon[(0, c)] / nn,
+    })
+}
+
+/// Inverse of [`tau`].
+fn from_tau(tau: &DielectricTensor) -> DielectricTensor {
+    let nn = tau[(0, 0)];
+    DielectricTensor::from_fn(|r, c| match (r, c) {
+        (0, 0) => -1.0 / nn,
+        (0, _) | (_, 0) => -tau[(r, c)] / nn,
+        _ => tau[(r, c)] - tau[(r, 0)] * tau[(0, c)] / nn,
+    })
+}
+
+/// Effective tensor of a mixture of `epsilon_in` (fraction `fill`) and `epsilon_out`
+/// separated by a plane with the in-plane unit normal `normal`.
+pub fn kottke_tensor(epsilon_in: &DielectricTensor, epsilon_out: &DielectricTensor, fill: f64, normal: Vector3) -> DielectricTensor {
+    // Rows: the normal, the in-plane tangent and z.
+    let frame = DielectricTensor::from_rows(&[
+        normal.transpose(),
+        Vector3::new(-normal.y, normal.x, 0.0).transpose(),
+        Vector3::z().transpose(),
+    ]);
+    let local = |epsilon: &DielectricTensor| tau(&(frame * epsilon * frame.transpose()));
+    let mixed = local(epsilon_in) * fill + local(epsilon_out) * (1.0 - fill);
+    frame.transpose() * from_tau(&mixed) * frame
+}
+
+/// Fraction of the rectangle with orthogonal edges `e1`, `e2`, centered at the origin, lying
+/// on the inner side `n·q < -distance` of a plane with unit normal `n`.
+pub fn plane_fill_fraction(normal: Vector2, distance: f64, e1: Vector2, e2: Vector2) -> f64 {
+    let (w, h) = (e1.norm(), e2.norm());
+    let (n1, n2) = (normal.dot(&e1) / w, normal.dot(&e2) / h);
+    // Integrate strips across the edge most aligned with the normal, exactly along it.
+    let (n_strip, n_exact, strip_width, exact_length) = if n2.abs() >= n1.abs() {
+        (n1, n2, w, h)
+    } else {
+        (n2, n1, h, w)
+    };
+    (0..FILL_STRIPS)
+        .map(|i| {
+            let x = ((i as f64 + 0.5) / FILL_STRIPS as f64 - 0.5) * strip_width;
+            let threshold = (-distance - n_strip * x) / n_exact;
+            let inside = if n_exact > 0.0 {
+                threshold + 0.5 * exact_length
+            } else {
+                0.5 * exact_length - threshold
+            };
+            inside.clamp(0.0, exact_length) / exact_length
+        })
+        .sum::<f64>()
+        / FILL_STRIPS as f64
+}
+
+/// In-plane geometry of a voxel, in units of the lattice constant.
+struct Pixel {
+    e1: Vector2,
+    e2: Vector2,
+    /// Maps normalized lab offsets to fractional offsets.
+    to_fractional: Matrix2<f64>,
+}
+
+impl Pixel {
+    /// Half the diagonal of the voxel, in units of the lattice constant.
+    fn half_diagonal(&self) -> f64 {
+        0.5 * (self.e1.norm_squared() + self.e2.norm_squared()).sqrt()
+    }
+}
+
+/// Smoothed tensor of the voxel centered at the fractional position `(s, t)`, with atoms
+/// looked up within half a voxel diagonal.
+fn smoothed_pixel(lookup: &AtomLookup, (s, t): (f64, f64), pixel: &Pixel) -> DielectricTensor {
+    let half_diagonal = pixel.half_diagonal();
+    let base = lookup.base();
+    // The topmost atom reaching into the voxel defines the interface.
+    for &index in lookup.candidates(s, t) {
+        let atom = &base.atoms[index];
+        let d = lookup.displacement_from_atom(index, s, t);
+        let distance = atom.shape.signed_distance(d.x, d.y);
+        if distance >= half_diagonal {
+            continue;
+        }
+        if distance <= -half_diagonal {
+            return atom.material.epsilon_matrix;
+        }
+        let (nx, ny) = atom.shape.normal(d.x, d.y);
+        let normal = Vector2::new(nx, ny);
+        let beyond = pixel.to_fractional * (normal * (half_diagonal - distance));
+        let outside = lookup.material_at(s + beyond.x, t + beyond.y);
+        return kottke_tensor(
+            &atom.material.epsilon_matrix,
+            &outside.epsilon_matrix,
+            plane_fill_fraction(normal, distance, pixel.e1, pixel.e2),
+            Vector3::new(nx, ny, 0.0),
+        );
+    }
+    base.background_material.epsilon_matrix
+}
+
+/// Rasterizes `wg` like [`crate::rasterize_waveguide_3d`], returning the smoothed 3x3
+/// permittivity tensor of every voxel as an `(nx, ny, nz, 3, 3)` array.
+pub fn rasterize_waveguide_tensor_3d(wg: &Waveguide, (nx, ny, nz): (usize, usize, usize)) -> Result<Array5<f64>, RasterError> {
+    let sampler = SliceSampler::new(wg, (nx, ny, nz))?;
+    let lattice = match wg.get_layer() {
+        Some(LayerType::PhotonicCrystal { definition, .. }) => definition.lattice.lattice(),
+        _ => return Err(RasterError::NoPhotonicCrystal),
+    };
+    let cell = RectifiedCell::new(lattice)?;
+    tracing::debug!(nx, ny, nz, "rasterizing smoothed tensors");
+
+    let a = lattice.lattice_constant().as_meters();
+    let (a1, a2) = lattice.normalized_in_plane_vectors();
+    let pixel = Pixel {
+        e1: cell.b1 / (nx as f64 * a),
+        e2: cell.b2 / (ny as f64 * a),
+        to_fractional: Matrix2::from_columns(&[a1, a2])
+            .try_inverse()
+            .ok_or(RasterError::NotRectifiable)?,
+    };
+
+    let mut grid = Array5::zeros((nx, ny, nz, 3, 3));
+    let set = |plane: &mut ArrayViewMut4<f64>, index: usize, tensor: &DielectricTensor| {
+        for r in 0..3 {
+            for c in 0..3 {
+                plane[[index / ny, index % ny, r, c]] = tensor[(r, c)];
+            }
+        }
+    };
+    grid.axis_iter_mut(Axis(2)).into_par_iter().enumerate().for_each(|(k, mut plane)| match sampler.plane(k) {
+        Plane::Uniform { tensor, .. } => (0..nx * ny).for_each(|index| set(&mut plane, index, &tensor)),
+        Plane::Crystal { base, lattice } => {
+            let lookup = base.lookup(lattice, pixel.half_diagonal());
+            for (index, &center) in sampler.fractional().iter().enumerate() {
+                set(&mut plane, index, &smoothed_pixel(&lookup, center, &pixel));
+            }
+        }
+    });
+    Ok(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rasterize_waveguide_3d;
+    use crate::test_support::{square, table_i};
+    use core::material::Material;
+    use phc::fill_factor::AreaMethod;
+
+    /// Voxel layer at the middle of the PC layer for `nz = 256`.
+    const PC_SLICE: usize = 130;
+
+    fn voxel(grid: &Array5<f64>, i: usize, j: usize, k: usize) -> DielectricTensor {
+        DielectricTensor::from_fn(|r, c| grid[[i, j, k, r, c]])
+    }
+
+    /// Mean of the tensor component `(c, c)` over the voxel layer `k`.
+    fn component_mean(grid: &Array5<f64>, k: usize, c: usize) -> f64 {
+        grid.index_axis(Axis(2), k).index_axis(Axis(2), c).index_axis(Axis(2), c).mean().unwrap()
+    }
+
+    #[test]
+    fn test_kottke_tensor() {
+        let (air, gaas) = (DielectricTensor::identity(), DielectricTensor::identity() * 12.0);
+        let normal = Vector3::new(1.0, 0.0, 0.0);
+        let tensor = kottke_tensor(&air, &gaas, 0.5, normal);
+        assert!((tensor[(0, 0)] - 2.0 / (1.0 + 1.0 / 12.0)).abs() < 1e-12);
+        assert!((tensor[(1, 1)] - 6.5).abs() < 1e-12 && (tensor[(2, 2)] - 6.5).abs() < 1e-12);
+
+        // A rotated normal gives the same tensor in the rotated frame.
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let rotated = kottke_tensor(&air, &gaas, 0.5, Vector3::new(h, h, 0.0));
+        assert!((rotated - rotated.transpose()).norm() < 1e-12);
+        assert!((rotated.trace() - tensor.trace()).abs() < 1e-12);
+        assert!((rotated * Vector3::new(h, h, 0.0) - Vector3::new(h, h, 0.0) * tensor[(0, 0)]).norm() < 1e-12);
+
+        // Diagonal tensors: harmonic mean of the normal components, mean of the others.
+        let uniaxial = DielectricTensor::from_diagonal(&Vector3::new(2.0, 2.0, 2.6));
+        let tensor = kottke_tensor(&uniaxial, &gaas, 0.25, Vector3::new(0.0, 1.0, 0.0));
+        let expected = Vector3::new(0.25 * 2.0 + 0.75 * 12.0, 1.0 / (0.25 / 2.0 + 0.75 / 12.0), 0.25 * 2.6 + 0.75 * 12.0);
+        assert!((tensor.diagonal() - expected).norm() < 1e-12, "{tensor}");
+        assert!((tensor - DielectricTensor::from_diagonal(&expected)).norm() < 1e-12);
+
+        // Off-diagonal terms survive the round trip through τ, and equal sides give themselves.
+        let tilted = DielectricTensor::new(4.0, 0.5, 0.2, 0.5, 3.0, 0.1, 0.2, 0.1, 5.0);
+        assert!((from_tau(&tau(&tilted)) - tilted).norm() < 1e-12);
+        assert!((kottke_tensor(&tilted, &tilted, 0.3, Vector3::new(0.6, 0.8, 0.0)) - tilted).norm() < 1e-12);
+    }
+
+    #[test]
+    fn test_plane_fill_fraction() {
+        let (e1, e2) = (Vector2::new(0.1, 0.0), Vector2::new(0.0, 0.2));
+        let x = Vector2::new(1.0, 0.0);
+        assert!((plane_fill_fraction(x, 0.0, e1, e2) - 0.5).abs() < 1e-12);
+        assert!((plane_fill_fraction(x, -0.025, e1, e2) - 0.75).abs() < 1e-12);
+        assert_eq!(plane_fill_fraction(-x, 0.06, e1, e2), 0.0);
+        // A diagonal plane through a corner of a square leaves half of it on each side.
+        let h = std::f64::consts::FRAC_1_SQRT_2;
+        let square = Vector2::new(0.0, 0.1);
+        assert!((plane_fill_fraction(Vector2::new(h, h), 0.0, e1, square) - 0.5).abs() < 1e-12);
+        // The plane x + y = -0.05 cuts off a corner triangle of legs 0.05.
+        let corner = plane_fill_fraction(Vector2::new(h, h), 0.05 * h, e1, square);
+        assert!((corner - 0.125).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_uniform_voxels_match_the_staircase() {
+        let wg = table_i(square(), 0.16);
+        let grid = rasterize_waveguide_tensor_3d(&wg, (24, 24, 256)).unwrap();
+        assert_eq!(grid.dim(), (24, 24, 256, 3, 3));
+        assert_eq!(voxel(&grid, 3, 5, 0), DielectricTensor::identity() * 11.0224);
+        assert_eq!(voxel(&grid, 12, 12, PC_SLICE), DielectricTensor::identity());
+
+        // Voxels away from the hole boundary keep their staircase value; the others are mixed.
+        let staircase = rasterize_waveguide_3d(&wg, (24, 24, 256)).unwrap();
+        let mut mixed = 0;
+        for i in 0..24 {
+            for j in 0..24 {
+                let tensor = voxel(&grid, i, j, PC_SLICE);
+                if tensor == DielectricTensor::identity() * tensor[(0, 0)] {
+                    assert_eq!(tensor[(0, 0)], staircase[[i, j, PC_SLICE]]);
+                } else {
+                    mixed += 1;
+                    let eigenvalues = tensor.symmetric_eigenvalues();
+                    // Up to the round-off of the rotation into the frame of the boundary.
+                    assert!(eigenvalues.iter().all(|&e| e > 1.0 && e < 12.7449 + 1e-12), "{eigenvalues}");
+                }
+            }
+        }
+        assert!(mixed > 8 && mixed < 100);
+        assert_eq!(rasterize_waveguide_tensor_3d(&wg, (0, 4, 4)), Err(RasterError::ZeroResolution));
+    }
+
+    #[test]
+    fn test_anisotropic_materials_keep_their_tensor() {
+        let mut wg = table_i(square(), 0.16);
+        let clad = Material::new_uniaxial(11.0, 11.6, Vector3::z());
+        let hole = Material::new_uniaxial(2.0, 2.6, Vector3::z());
+        if let LayerType::Simple { material, .. } = &mut wg.layers[0] {
+            *material = clad.clone();
+        }
+        if let LayerType::PhotonicCrystal { definition, .. } = &mut wg.layers[2] {
+            definition.base.atoms[0].material = hole.clone();
+        }
+        let n = 24;
+        let grid = rasterize_waveguide_tensor_3d(&wg, (n, n, 256)).unwrap();
+        assert_eq!(voxel(&grid, 3, 5, 0), clad.epsilon_matrix);
+        let center = voxel(&grid, 12, 12, PC_SLICE);
+        assert_eq!(center, hole.epsilon_matrix);
+        assert_eq!(center.diagonal(), Vector3::new(2.0, 2.0, 2.6));
+
+        // Mixed voxels keep the anisotropy: ε_zz is the mean of 2.6 and 12.7449 along the wall.
+        let mut mixed = 0;
+        for i in 0..n {
+            for j in 0..n {
+                let tensor = voxel(&grid, i, j, PC_SLICE);
+                let in_plane = (tensor[(0, 0)] + tensor[(1, 1)]) / 2.0;
+                if tensor != hole.epsilon_matrix && tensor[(2, 2)] < 12.7449 {
+                    mixed += 1;
+                    assert!(tensor[(2, 2)] > in_plane && tensor[(2, 2)] > 2.6, "{tensor}");
+                }
+            }
+        }
+        assert!(mixed > 8, "{mixed}");
+    }
+
+    #[test]
+    fn test_boundary_voxels_follow_the_normal() {
+        let wg = table_i(square(), 0.16);
+        let n = 33;
+        let grid = rasterize_waveguide_tensor_3d(&wg, (n, n, 256)).unwrap();
+        let tensor = |i: usize, j: usize| voxel(&grid, i, j, PC_SLICE);
+
+        // On the x axis through the hole center, the normal is along x.
+        let edge = (0..n)
+            .map(|i| tensor(i, n / 2))
+            .find(|t| t[(0, 0)] > 1.0 && t[(0, 0)] < 12.7449)
+            .expect("a mixed voxel on the x axis");
+        assert!(edge[(0, 0)] < edge[(1, 1)]);
+        assert!(edge[(0, 1)].abs() < 1e-9);
+        assert_eq!(edge[(1, 1)], edge[(2, 2)]);
+
+        // Symmetric tensors with off-diagonal terms on the diagonals of the cell.
+        for i in 0..n {
+            for j in 0..n {
+                let t = tensor(i, j);
+                assert!((t[(0, 1)] - t[(1, 0)]).abs() < 1e-12);
+                assert_eq!(t[(0, 2)], 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_smoothing_tracks_radius_sweeps() {
+        // The mean permittivity varies continuously with the radius, unlike the staircase.
+        let radii: Vec<f64> = (0..8).map(|i| 0.16 + 0.001 * i as f64).collect();
+        let staircase: Vec<f64> = radii
+            .iter()
+            .map(|&r| {
+                let grid = rasterize_waveguide_3d(&table_i(square(), r), (16, 16, 256)).unwrap();
+                grid.index_axis(Axis(2), PC_SLICE).mean().unwrap()
+            })
+            .collect();
+        let smoothed: Vec<f64> = radii
+            .iter()
+            .map(|&r| {
+                let grid = rasterize_waveguide_tensor_3d(&table_i(square(), r), (16, 16, 256)).unwrap();
+                component_mean(&grid, PC_SLICE, 0)
+            })
+            .collect();
+        assert!(staircase.windows(2).any(|w| w[0] == w[1]));
+        assert!(smoothed.windows(2).all(|w| w[1] < w[0]));
+
+        // And stays close to the exact average permittivity.
+        let wg = table_i(square(), 0.163);
+        let exact = match wg.get_layer().unwrap() {
+            LayerType::PhotonicCrystal { definition, .. } => definition.average_epsilon(AreaMethod::Analytic).unwrap()[(0, 0)],
+            _ => unreachable!(),
+        };
+        let grid = rasterize_waveguide_tensor_3d(&wg, (16, 16, 256)).unwrap();
+        let trace = (component_mean(&grid, PC_SLICE, 0) + component_mean(&grid, PC_SLICE, 1)) / 2.0;
+        assert!(trace < exact && trace > exact - 0.5);
+    }
+}
@@ -0,0 +1,75 @@
+//! crates/io/src/cwt.rs
+//! Results of coupled-wave calculations.
+//!
+//! ```text
+//! <name>                 @wavelength (m)
+//!   eigenvalues          (modes, 2) complex δ + iα/2, in 1/m
+//!   eigenvectors         (basis, modes, 2) complex, one column per mode
+//!   envelopes            (modes, waves, nx, ny, 2) complex amplitudes of the basic waves
+//!   x, y                 envelope sample positions, @units = "m"
+//! ```
+use hdf5::Group;
+use ndarray::{Array1, Array2, Array4, Ix1, Ix2, Ix4};
+
+use core::units::Length;
+use core::vectorial::Complex;
+
+use super::schema::{invalid, read_complex, read_f64, write_complex, write_f64, write_str, SchemaError};
+
+/// Modes of a coupled-wave calculation at one wavelength.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CwtResult {
+    pub wavelength: Length,
+    /// Complex eigenvalue `δ + iα/2` of each mode, in 1/m.
+    pub eigenvalues: Array1<Complex>,
+    /// Eigenvectors as columns, one per mode.
+    pub eigenvectors: Array2<Complex>,
+    /// Envelopes of the basic waves of each mode, indexed `[mode, wave, i, j]` over `x` and `y`.
+    pub envelopes: Array4<Complex>,
+    /// Envelope sample positions, in meters.
+    pub x: Array1<f64>,
+    pub y: Array1<f64>,
+}
+
+impl CwtResult {
+    fn check(&self, group: &Group) -> Result<(), SchemaError> {
+        let modes = self.eigenvalues.len();
+        let (_, columns) = self.eigenvectors.dim();
+        let (envelope_modes, _, nx, ny) = self.envelopes.dim();
+        if columns != modes || envelope_modes != modes {
+            return Err(invalid(group, "eigenvectors and envelopes must hold one entry per eigenvalue"));
+        }
+        if (self.x.len(), self.y.len()) != (nx, ny) {
+            return Err(invalid(group, "the axes do not match the shape of the envelopes"));
+        }
+        Ok(())
+    }
+}
+
+/// Writes `result` into the empty `group`.
+pub fn write_cwt(group: &Group, result: &CwtResult) -> Result<(), SchemaError> {
+    result.check(group)?;
+    write_f64(group, "wavelength", result.wavelength.as_meters())?;
+    write_complex(group, "eigenvalues", &result.eigenvalues)?;
+    write_complex(group, "eigenvectors", &result.eigenvectors)?;
+    write_complex(group, "envelopes", &result.envelopes)?;
+    for (name, axis) in [("x", &result.x), ("y", &result.y)] {
+        let axis = group.new_dataset_builder().with_data(axis).create(name)?;
+        write_str(&axis, "units", "m")?;
+    }
+    Ok(())
+}
+
+/// Reads back a result written by [`write_cwt`].
+pub fn read_cwt(group: &Group) -> Result<CwtResult, SchemaError> {
+    let result = CwtResult {
+        wavelength: Length::meters(read_f64(group, "wavelength")?),
+        eigenvalues: read_complex::<Ix1>(group, "eigenvalues")?,
+        eigenvectors: read_complex::<Ix2>(group, "eigenvectors")?,
+        envelopes: read_complex::<Ix4>(group, "envelopes")?,
+        x: group.dataset("x")?.read_1d::<f64>()?,
+        y: group.dataset("y")?.read_1d::<f64>()?,
+    };
+    result.check(group)?;
+    Ok(result)
+}
@@ -0,0 +1,55 @@
+//! crates/io/src/fourier.rs
+//! Tables of Fourier coefficients of the in-plane permittivity.
+//!
+//! ```text
+//! <name>                 @layer
+//!   orders               (n, 2) integers (m, n) of the reciprocal lattice vectors m b1 + n b2
+//!   coefficients         (n, 2) real and imaginary parts of ξ_mn
+//! ```
+use hdf5::Group;
+use ndarray::{Array1, Array2};
+
+use core::vectorial::Complex;
+
+use super::schema::{invalid, read_complex, read_str, write_complex, write_str, SchemaError};
+
+/// Fourier coefficients `ξ_mn` of the permittivity of one photonic crystal layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FourierTable {
+    /// Name of the layer the coefficients belong to.
+    pub layer: String,
+    pub orders: Vec<(i32, i32)>,
+    pub coefficients: Vec<Complex>,
+}
+
+/// Writes `table` into the empty `group`.
+pub fn write_fourier(group: &Group, table: &FourierTable) -> Result<(), SchemaError> {
+    if table.orders.len() != table.coefficients.len() {
+        return Err(invalid(group, "there must be one coefficient per order"));
+    }
+    write_str(group, "layer", &table.layer)?;
+    let orders = Array2::from_shape_fn((table.orders.len(), 2), |(row, column)| match column {
+        0 => table.orders[row].0,
+        _ => table.orders[row].1,
+    });
+    group.new_dataset_builder().with_data(&orders).create("orders")?;
+    write_complex(group, "coefficients", &Array1::from(table.coefficients.clone()))
+}
+
+/// Reads back a table written by [`write_fourier`].
+pub fn read_fourier(group: &Group) -> Result<FourierTable, SchemaError> {
+    let dataset = group.dataset("orders")?;
+    let orders = dataset.read_2d::<i32>()?;
+    if orders.ncols() != 2 {
+        return Err(invalid(&dataset, "orders must be pairs (m, n)"));
+    }
+    let coefficients = read_complex::<ndarray::Ix1>(group, "coefficients")?.to_vec();
+    if coefficients.len() != orders.nrows() {
+        return Err(invalid(group, "there must be one coefficient per order"));
+    }
+    Ok(FourierTable {
+        layer: read_str(group, "layer")?,
+        orders: orders.rows().into_iter().map(|row| (row[0], row[1])).collect(),
+        coefficients,
+    })
+}
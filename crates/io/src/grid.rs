@@ -0,0 +1,73 @@
+//! crates/io/src/grid.rs
+//! Rasterized permittivity grids together with their coordinate axes.
+//!
+//! ```text
+//! <name>                 @description
+//!   epsilon              (nx, ny, nz), @units = "1", @axes = "x y z"
+//!   x, y, z              voxel centers, @units = "m"
+//! ```
+use hdf5::Group;
+use ndarray::{Array1, Array3};
+
+use core::units::Length;
+
+use super::schema::{invalid, read_str, write_str, SchemaError};
+
+/// A scalar permittivity grid, indexed `[i, j, k]`, with the coordinates of its voxel centers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpsilonGrid {
+    pub epsilon: Array3<f64>,
+    /// Voxel centers along each axis, in meters.
+    pub x: Array1<f64>,
+    pub y: Array1<f64>,
+    pub z: Array1<f64>,
+    /// Free-form description, e.g. the cell or resolution the grid was rasterized with.
+    pub description: String,
+}
+
+impl EpsilonGrid {
+    /// Grid of equal voxels filling the box `width x depth x height` from the origin.
+    pub fn uniform(epsilon: Array3<f64>, (width, depth, height): (Length, Length, Length)) -> Self {
+        let (nx, ny, nz) = epsilon.dim();
+        let centers = |n: usize, size: Length| Array1::from_shape_fn(n, |i| (i as f64 + 0.5) / n as f64 * size.as_meters());
+        EpsilonGrid {
+            x: centers(nx, width),
+            y: centers(ny, depth),
+            z: centers(nz, height),
+            epsilon,
+            description: String::new(),
+        }
+    }
+}
+
+/// Writes `grid` into the empty `group`.
+pub fn write_grid(group: &Group, grid: &EpsilonGrid) -> Result<(), SchemaError> {
+    let (nx, ny, nz) = grid.epsilon.dim();
+    if (grid.x.len(), grid.y.len(), grid.z.len()) != (nx, ny, nz) {
+        return Err(invalid(group, "the axes do not match the shape of the grid"));
+    }
+    write_str(group, "description", &grid.description)?;
+    let epsilon = group.new_dataset_builder().with_data(&grid.epsilon).create("epsilon")?;
+    write_str(&epsilon, "units", "1")?;
+    write_str(&epsilon, "axes", "x y z")?;
+    for (name, axis) in [("x", &grid.x), ("y", &grid.y), ("z", &grid.z)] {
+        let axis = group.new_dataset_builder().with_data(axis).create(name)?;
+        write_str(&axis, "units", "m")?;
+    }
+    Ok(())
+}
+
+/// Reads back a grid written by [`write_grid`].
+pub fn read_grid(group: &Group) -> Result<EpsilonGrid, SchemaError> {
+    let grid = EpsilonGrid {
+        epsilon: group.dataset("epsilon")?.read::<f64, ndarray::Ix3>()?,
+        x: group.dataset("x")?.read_1d::<f64>()?,
+        y: group.dataset("y")?.read_1d::<f64>()?,
+        z: group.dataset("z")?.read_1d::<f64>()?,
+        description: read_str(group, "description")?,
+    };
+    if (grid.x.len(), grid.y.len(), grid.z.len()) != grid.epsilon.dim() {
+        return Err(invalid(group, "the axes do not match the shape of the grid"));
+    }
+    Ok(grid)
+}
@@ -0,0 +1,300 @@
+//! crates/io/src/lib.rs
+//!
+//! HDF5 input and output.
+//!
+//! [`SimulationFile`] stores a waveguide together with the slab modes, grids, Fourier tables
+//! and coupled-wave results computed from it, in the versioned layout described in [`schema`].
+use std::path::Path;
+
+use ndarray::{Array3, ArrayView3};
+
+pub mod cwt;
+pub mod fourier;
+pub mod grid;
+pub mod schema;
+pub mod simulation;
+pub mod slab;
+pub mod structure;
+pub mod sweep;
+
+pub use cwt::CwtResult;
+pub use fourier::FourierTable;
+pub use grid::EpsilonGrid;
+pub use hdf5::Error as Hdf5Error;
+pub use schema::{SchemaError, SCHEMA_VERSION};
+pub use simulation::SimulationFile;
+pub use slab::SlabResult;
+pub use sweep::SweepDataset;
+
+/// Writes a permittivity grid to the dataset `name` of a new HDF5 file at `path`.
+pub fn write_epsilon_grid(path: impl AsRef<Path>, name: &str, epsilon: &Array3<f64>) -> hdf5::Result<()> {
+    let file = hdf5::File::create(path)?;
+    file.new_dataset_builder().with_data(epsilon).create(name)?;
+    Ok(())
+}
+
+/// Permittivity grid written one z-slab at a time, for grids too large to hold in memory.
+///
+/// The dataset is chunked by `(nx, ny, chunk_depth)` so that each slab maps onto whole chunks.
+pub struct EpsilonGridWriter {
+    // Kept open for as long as the dataset is written.
+    _file: hdf5::File,
+    dataset: hdf5::Dataset,
+    shape: (usize, usize, usize),
+}
+
+impl EpsilonGridWriter {
+    /// Creates the dataset `name` of shape `(nx, ny, nz)` in a new HDF5 file at `path`.
+    pub fn create(
+        path: impl AsRef<Path>,
+        name: &str,
+        (nx, ny, nz): (usize, usize, usize),
+        chunk_depth: usize,
+    ) -> hdf5::Result<Self> {
+        let file = hdf5::File::create(path)?;
+        let dataset = file
+            .new_dataset::<f64>()
+            .chunk((nx, ny, chunk_depth.clamp(1, nz.max(1))))
+            .shape((nx, ny, nz))
+            .create(name)?;
+        Ok(EpsilonGridWriter {
+            _file: file,
+            dataset,
+            shape: (nx, ny, nz),
+        })
+    }
+
+    /// Shape of the whole dataset.
+    pub fn shape(&self) -> (usize, usize, usize) {
+        self.shape
+    }
+
+    /// Writes `slab` to the voxel layers `z_start..z_start + depth` of the dataset.
+    pub fn write_slab(&self, z_start: usize, slab: ArrayView3<f64>) -> hdf5::Result<()> {
+        let (nx, ny, depth) = slab.dim();
+        if (nx, ny) != (self.shape.0, self.shape.1) || z_start + depth > self.shape.2 {
+            return Err(format!(
+                "slab {:?} at z = {z_start} does not fit in a grid of shape {:?}",
+                slab.dim(),
+                self.shape
+            )
+            .into());
+        }
+        self.dataset.write_slice(slab, (.., .., z_start..z_start + depth))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use core::dispersion::Dispersion;
+    use core::material::{Material, MaterialDispersion};
+    use core::shapes::HoleShape;
+    use core::thermal::{Thermal, ThermalModel, ThermoOptic};
+    use core::units::Length;
+    use core::vectorial::Complex;
+    use ndarray::{Array1, Array2, Array4};
+    use phc::base::UnitCellBase;
+    use phc::crystal_structure::PhotonicCrystal;
+    use phc::lattice::LatticeType;
+    use waveguide::doping::Doping;
+    use waveguide::graded::GradedProfile;
+    use waveguide::layers::{LayerType, Waveguide};
+    use waveguide::slab::{LayerLoss, SlabMode};
+
+    /// Path of a scratch file unique to this test process.
+    pub(crate) fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("io-{name}-{}.h5", std::process::id()))
+    }
+
+    /// A stack exercising every kind of layer, material and hole.
+    pub(crate) fn full_stack() -> Waveguide {
+        let mut gaas = Material::new_from_eps(12.7449);
+        gaas.dispersion = Some(MaterialDispersion::Isotropic(Dispersion::Cauchy {
+            coefficients: vec![3.3, 0.1],
+        }));
+        gaas.thermal = Some(Thermal {
+            model: ThermalModel::ThermoOptic(ThermoOptic {
+                dn_dt: 2.5e-4,
+                reference_temperature: 300.0,
+            }),
+            temperature: 330.0,
+        });
+        let mut rotated = Material::new_anisotropic(2.0, 2.1, 2.4);
+        rotated.epsilon_imag[(2, 2)] = 1e-3;
+        rotated.dispersion = Some(MaterialDispersion::Rotated {
+            principal: Box::new(MaterialDispersion::Diagonal(Box::new([
+                Dispersion::Cauchy { coefficients: vec![1.4] },
+                Dispersion::Cauchy { coefficients: vec![1.45] },
+                Dispersion::Cauchy { coefficients: vec![1.5, 0.003] },
+            ]))),
+            rotation: core::vectorial::Matrix3::new(0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0),
+        });
+
+        let lattice = LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+        base.background_material = gaas.clone();
+        base.atoms[0].sidewall_angle = 0.05;
+        base.add_atom(HoleShape::Square { side: 0.1 }, (0.1, 0.2, 0.0), rotated.clone());
+        base.add_atom(HoleShape::Rectangle { width: 0.1, height: 0.05 }, (0.3, 0.7, 0.1), Material::new_from_eps(1.0));
+        base.add_atom(
+            HoleShape::Ellipse {
+                radius_x: 0.1,
+                radius_y: 0.05,
+                rotation: 0.3,
+            },
+            (0.8, 0.1, -0.1),
+            Material::new_from_eps(2.0),
+        );
+        Waveguide {
+            layers: vec![
+                LayerType::Simple {
+                    name: "n-clad".into(),
+                    thickness: Length::nanometers(1500.0),
+                    material: rotated,
+                    doping: Some(Doping::n_per_cm3(1e18)),
+                },
+                LayerType::Graded {
+                    name: "SCH".into(),
+                    thickness: Length::nanometers(100.0),
+                    profile: GradedProfile::tabulated(vec![0.0, 0.4, 1.0], vec![11.0, 11.8, 12.7]).unwrap(),
+                    doping: Some(Doping::p_per_cm3(5e17)),
+                    thermo_optic: Some(core::thermal::ThermoOptic {
+                        dn_dt: 2.5e-4,
+                        reference_temperature: 300.0,
+                    }),
+                },
+                LayerType::Graded {
+                    name: "SCH top".into(),
+                    thickness: Length::nanometers(50.0),
+                    profile: GradedProfile::ParabolicTop { bottom: 12.7, top: 11.0, dn: 0.01 },
+                    doping: None,
+                    thermo_optic: None,
+                },
+                LayerType::PhotonicCrystal {
+                    name: "PC".into(),
+                    thickness: Length::nanometers(118.0),
+                    definition: PhotonicCrystal::new(lattice, base),
+                    background_material: gaas,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_simulation_file_round_trip() {
+        let wg = full_stack();
+        let path = temp_path("simulation");
+        let grid = EpsilonGrid::uniform(
+            Array3::from_shape_fn((4, 3, 5), |(i, j, k)| (i + 10 * j + 100 * k) as f64),
+            (Length::nanometers(295.0), Length::nanometers(511.0), Length::nanometers(1768.0)),
+        );
+        let table = FourierTable {
+            layer: "PC".into(),
+            orders: vec![(0, 0), (1, 0), (-1, 2)],
+            coefficients: vec![Complex::new(9.0, 0.0), Complex::new(-0.5, 0.1), Complex::new(0.02, -0.03)],
+        };
+        let modes = CwtResult {
+            wavelength: Length::nanometers(940.0),
+            eigenvalues: Array1::from(vec![Complex::new(1.0, 2.0), Complex::new(-3.0, 4.0)]),
+            eigenvectors: Array2::from_shape_fn((4, 2), |(b, m)| Complex::new(b as f64, m as f64)),
+            envelopes: Array4::from_shape_fn((2, 4, 3, 2), |(m, w, i, j)| Complex::new((m + w) as f64, (i * j) as f64)),
+            x: Array1::from(vec![0.0, 1e-4, 2e-4]),
+            y: Array1::from(vec![0.0, 1e-4]),
+        };
+        let slab = SlabResult {
+            wavelength: Length::nanometers(940.0),
+            mode: SlabMode {
+                effective_index: 3.3,
+                layers: wg
+                    .layers
+                    .iter()
+                    .map(|layer| LayerLoss {
+                        name: layer.name().to_string(),
+                        confinement: 0.25,
+                        loss: core::units::Loss::per_centimeter(1.5),
+                    })
+                    .collect(),
+            },
+        };
+        let sweep = SweepDataset {
+            design: "grid".into(),
+            dimensions: vec!["layers.PC.thickness".into(), "layers.PC.background_material".into()],
+            coordinates: vec![
+                sweep::Coordinate {
+                    name: "layers.PC.thickness".into(),
+                    dimension: 0,
+                    values: sweep::CoordinateValues::Numbers {
+                        values: vec![1e-7, 1.2e-7, 1.4e-7],
+                        units: "m".into(),
+                    },
+                },
+                sweep::Coordinate {
+                    name: "layers.PC.background_material".into(),
+                    dimension: 1,
+                    values: sweep::CoordinateValues::Labels(vec!["GaAs".into(), "InP".into()]),
+                },
+            ],
+            // Read back in alphabetical order.
+            quantities: vec![
+                sweep::Quantity {
+                    name: "effective_index".into(),
+                    units: "1".into(),
+                    values: ndarray::ArrayD::from_shape_fn(ndarray::IxDyn(&[3, 2]), |i| 3.0 + (i[0] + i[1]) as f64 / 10.0),
+                },
+                sweep::Quantity {
+                    name: "internal_loss_per_cm".into(),
+                    units: "1/cm".into(),
+                    values: ndarray::ArrayD::from_elem(ndarray::IxDyn(&[3, 2]), f64::NAN),
+                },
+            ],
+            errors: ndarray::ArrayD::from_shape_fn(ndarray::IxDyn(&[3, 2]), |i| {
+                if i[0] == 2 { "too thick".into() } else { String::new() }
+            }),
+        };
+        {
+            let file = SimulationFile::create(&path, &wg).unwrap();
+            file.write_slab("fundamental", &slab).unwrap();
+            file.write_sweep("thickness", &sweep).unwrap();
+            let mut bad = sweep.clone();
+            bad.coordinates[1].dimension = 0;
+            assert!(matches!(file.write_sweep("bad", &bad), Err(SchemaError::Invalid { .. })));
+            file.write_grid("epsilon", &grid).unwrap();
+            file.write_fourier("pc", &table).unwrap();
+        }
+        SimulationFile::append(&path).unwrap().write_cwt("gamma", &modes).unwrap();
+
+        let file = SimulationFile::open(&path).unwrap();
+        assert_eq!(format!("{:?}", file.waveguide().unwrap()), format!("{wg:?}"));
+        assert_eq!(file.slab("fundamental").unwrap(), slab);
+        assert_eq!(file.grid("epsilon").unwrap(), grid);
+        assert_eq!(file.fourier("pc").unwrap(), table);
+        assert_eq!(file.cwt("gamma").unwrap(), modes);
+        let read = file.sweep("thickness").unwrap();
+        assert_eq!((read.shape(), read.failures()), (&[3, 2][..], 2));
+        assert_eq!((&read.coordinates, &read.errors), (&sweep.coordinates, &sweep.errors));
+        assert_eq!(read.quantities[0], sweep.quantities[0]);
+        assert!(read.quantities[1].values.iter().all(|loss| loss.is_nan()));
+        assert_eq!(file.entries("grids").unwrap(), vec!["epsilon".to_string()]);
+        assert!(file.entries("missing").unwrap().is_empty());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_foreign_and_newer_files_are_rejected() {
+        let path = temp_path("foreign");
+        write_epsilon_grid(&path, "epsilon", &Array3::zeros((2, 2, 2))).unwrap();
+        assert!(matches!(SimulationFile::open(&path), Err(SchemaError::NotASimulationFile)));
+
+        SimulationFile::create(&path, &full_stack()).unwrap();
+        {
+            let file = hdf5::File::open_rw(&path).unwrap();
+            file.attr("schema_version").unwrap().write_scalar(&(SCHEMA_VERSION + 1)).unwrap();
+        }
+        assert!(matches!(
+            SimulationFile::open(&path),
+            Err(SchemaError::UnsupportedVersion(version)) if version == SCHEMA_VERSION + 1
+        ));
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -0,0 +1,169 @@
+//! crates/io/src/schema.rs
+//! Versioning, errors and attribute helpers shared by the HDF5 layout.
+//!
+//! Layout of a simulation file, version 1. Lengths are in meters, permittivities relative,
+//! complex arrays carry a trailing axis of length 2 holding the real and imaginary parts.
+//!
+//! ```text
+//! /                      @format = "pc_sel_cwt", @schema_version = 1
+//! /structure             the simulated Waveguide, see `structure`
+//! /slab/<name>           fundamental slab modes with confinement and loss per layer, see `slab`
+//! /grids/<name>          rasterized ε grids with their x, y, z axes, see `grid`
+//! /fourier/<name>        Fourier coefficient tables, see `fourier`
+//! /cwt/<name>            coupled-wave modes, eigenvectors and envelopes, see `cwt`
+//! /sweeps/<name>         results over ranges of parameters, see `sweep`; /structure is the base device
+//! ```
+use std::fmt;
+
+use hdf5::types::VarLenUnicode;
+use hdf5::{Group, Location};
+use ndarray::{ArrayD, Dimension, IxDyn};
+
+use core::vectorial::{Complex, Matrix3};
+
+/// Value of the `format` attribute of the root group.
+pub const FORMAT: &str = "pc_sel_cwt";
+/// Version of the layout written by this crate.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Reasons why a simulation file cannot be written or read back.
+#[derive(Debug)]
+pub enum SchemaError {
+    Hdf5(hdf5::Error),
+    /// The root group has no `format` attribute naming this layout.
+    NotASimulationFile,
+    /// The file was written with a newer, unknown layout.
+    UnsupportedVersion(u32),
+    /// An object holds a value the layout does not allow.
+    Invalid { path: String, reason: String },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Hdf5(error) => write!(f, "HDF5 error: {error}"),
+            SchemaError::NotASimulationFile => write!(f, "not a {FORMAT} simulation file"),
+            SchemaError::UnsupportedVersion(version) => {
+                write!(f, "schema version {version} is newer than the supported version {SCHEMA_VERSION}")
+            }
+            SchemaError::Invalid { path, reason } => write!(f, "invalid {path}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<hdf5::Error> for SchemaError {
+    fn from(error: hdf5::Error) -> Self {
+        SchemaError::Hdf5(error)
+    }
+}
+
+/// Error for the object at `location`.
+pub(crate) fn invalid(location: &Location, reason: impl Into<String>) -> SchemaError {
+    SchemaError::Invalid {
+        path: location.name(),
+        reason: reason.into(),
+    }
+}
+
+pub(crate) fn write_str(location: &Location, name: &str, value: &str) -> Result<(), SchemaError> {
+    let value: VarLenUnicode = value
+        .parse()
+        .map_err(|_| invalid(location, format!("attribute '{name}' contains a null byte")))?;
+    location.new_attr::<VarLenUnicode>().create(name)?.write_scalar(&value)?;
+    Ok(())
+}
+
+pub(crate) fn read_str(location: &Location, name: &str) -> Result<String, SchemaError> {
+    Ok(location.attr(name)?.read_scalar::<VarLenUnicode>()?.as_str().to_owned())
+}
+
+pub(crate) fn write_f64(location: &Location, name: &str, value: f64) -> Result<(), SchemaError> {
+    location.new_attr::<f64>().create(name)?.write_scalar(&value)?;
+    Ok(())
+}
+
+pub(crate) fn read_f64(location: &Location, name: &str) -> Result<f64, SchemaError> {
+    Ok(location.attr(name)?.read_scalar::<f64>()?)
+}
+
+pub(crate) fn write_u32(location: &Location, name: &str, value: u32) -> Result<(), SchemaError> {
+    location.new_attr::<u32>().create(name)?.write_scalar(&value)?;
+    Ok(())
+}
+
+pub(crate) fn read_u32(location: &Location, name: &str) -> Result<u32, SchemaError> {
+    Ok(location.attr(name)?.read_scalar::<u32>()?)
+}
+
+pub(crate) fn has_attr(location: &Location, name: &str) -> Result<bool, SchemaError> {
+    Ok(location.attr_names()?.iter().any(|attr| attr == name))
+}
+
+/// Writes a 3 x 3 matrix as a row-major dataset.
+pub(crate) fn write_matrix(group: &Group, name: &str, matrix: &Matrix3) -> Result<(), SchemaError> {
+    let rows = ndarray::Array2::from_shape_fn((3, 3), |(r, c)| matrix[(r, c)]);
+    group.new_dataset_builder().with_data(&rows).create(name)?;
+    Ok(())
+}
+
+pub(crate) fn read_matrix(group: &Group, name: &str) -> Result<Matrix3, SchemaError> {
+    let dataset = group.dataset(name)?;
+    let rows = dataset.read_2d::<f64>()?;
+    if rows.dim() != (3, 3) {
+        return Err(invalid(&dataset, format!("expected a 3 x 3 matrix, found {:?}", rows.dim())));
+    }
+    Ok(Matrix3::from_fn(|r, c| rows[[r, c]]))
+}
+
+/// Writes a complex array as a real dataset with a trailing axis `[re, im]`.
+pub(crate) fn write_complex<D: Dimension>(
+    group: &Group,
+    name: &str,
+    values: &ndarray::Array<Complex, D>,
+) -> Result<(), SchemaError> {
+    let mut shape = values.shape().to_vec();
+    shape.push(2);
+    let parts = values.iter().flat_map(|z| [z.re, z.im]).collect();
+    let parts = ArrayD::from_shape_vec(IxDyn(&shape), parts).map_err(hdf5::Error::from)?;
+    group.new_dataset_builder().with_data(&parts).create(name)?;
+    Ok(())
+}
+
+pub(crate) fn read_complex<D: Dimension>(group: &Group, name: &str) -> Result<ndarray::Array<Complex, D>, SchemaError> {
+    let dataset = group.dataset(name)?;
+    let parts = dataset.read_dyn::<f64>()?;
+    let (&last, shape) = parts
+        .shape()
+        .split_last()
+        .ok_or_else(|| invalid(&dataset, "complex data must have a trailing axis of length 2"))?;
+    if last != 2 || shape.len() != D::NDIM.unwrap_or(shape.len()) {
+        return Err(invalid(&dataset, format!("unexpected shape {:?} for complex data", parts.shape())));
+    }
+    let values = parts
+        .as_slice()
+        .ok_or_else(|| invalid(&dataset, "complex data is not contiguous"))?
+        .chunks_exact(2)
+        .map(|pair| Complex::new(pair[0], pair[1]))
+        .collect();
+    ArrayD::from_shape_vec(IxDyn(shape), values)
+        .and_then(|values| values.into_dimensionality::<D>())
+        .map_err(|error| invalid(&dataset, error.to_string()))
+}
+
+/// Checks the root attributes of a file written by [`write_header`].
+pub(crate) fn check_header(root: &Location) -> Result<(), SchemaError> {
+    if !has_attr(root, "format")? || read_str(root, "format")? != FORMAT {
+        return Err(SchemaError::NotASimulationFile);
+    }
+    match read_u32(root, "schema_version")? {
+        version if version > SCHEMA_VERSION => Err(SchemaError::UnsupportedVersion(version)),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn write_header(root: &Location) -> Result<(), SchemaError> {
+    write_str(root, "format", FORMAT)?;
+    write_u32(root, "schema_version", SCHEMA_VERSION)
+}
@@ -0,0 +1,114 @@
+//! crates/io/src/simulation.rs
+//! Self-describing simulation files.
+use std::path::Path;
+
+use hdf5::{File, Group};
+use waveguide::layers::Waveguide;
+
+use super::cwt::{read_cwt, write_cwt, CwtResult};
+use super::fourier::{read_fourier, write_fourier, FourierTable};
+use super::grid::{read_grid, write_grid, EpsilonGrid};
+use super::schema::{check_header, write_header, SchemaError};
+use super::slab::{read_slab, write_slab, SlabResult};
+use super::structure::{read_waveguide, write_waveguide};
+use super::sweep::{read_sweep, write_sweep, SweepDataset};
+
+/// An HDF5 file holding a waveguide and the results computed from it.
+///
+/// The structure is written when the file is created, so every file carries what was simulated.
+pub struct SimulationFile {
+    file: File,
+}
+
+impl SimulationFile {
+    /// Creates a new file at `path` describing `wg`, replacing any existing file.
+    pub fn create(path: impl AsRef<Path>, wg: &Waveguide) -> Result<Self, SchemaError> {
+        let file = File::create(path)?;
+        write_header(&file)?;
+        write_waveguide(&file.create_group("structure")?, wg)?;
+        Ok(SimulationFile { file })
+    }
+
+    /// Opens an existing file for reading.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SchemaError> {
+        Self::checked(File::open(path)?)
+    }
+
+    /// Opens an existing file to add results to it.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self, SchemaError> {
+        Self::checked(File::open_rw(path)?)
+    }
+
+    fn checked(file: File) -> Result<Self, SchemaError> {
+        check_header(&file)?;
+        Ok(SimulationFile { file })
+    }
+
+    /// The underlying HDF5 file, for data outside the layout.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn waveguide(&self) -> Result<Waveguide, SchemaError> {
+        read_waveguide(&self.file.group("structure")?)
+    }
+
+    pub fn write_slab(&self, name: &str, result: &SlabResult) -> Result<(), SchemaError> {
+        write_slab(&self.new_entry("slab", name)?, result)
+    }
+
+    pub fn slab(&self, name: &str) -> Result<SlabResult, SchemaError> {
+        read_slab(&self.file.group(&format!("slab/{name}"))?)
+    }
+
+    pub fn write_grid(&self, name: &str, grid: &EpsilonGrid) -> Result<(), SchemaError> {
+        write_grid(&self.new_entry("grids", name)?, grid)
+    }
+
+    pub fn grid(&self, name: &str) -> Result<EpsilonGrid, SchemaError> {
+        read_grid(&self.file.group(&format!("grids/{name}"))?)
+    }
+
+    pub fn write_fourier(&self, name: &str, table: &FourierTable) -> Result<(), SchemaError> {
+        write_fourier(&self.new_entry("fourier", name)?, table)
+    }
+
+    pub fn fourier(&self, name: &str) -> Result<FourierTable, SchemaError> {
+        read_fourier(&self.file.group(&format!("fourier/{name}"))?)
+    }
+
+    pub fn write_cwt(&self, name: &str, result: &CwtResult) -> Result<(), SchemaError> {
+        write_cwt(&self.new_entry("cwt", name)?, result)
+    }
+
+    pub fn cwt(&self, name: &str) -> Result<CwtResult, SchemaError> {
+        read_cwt(&self.file.group(&format!("cwt/{name}"))?)
+    }
+
+    pub fn write_sweep(&self, name: &str, sweep: &SweepDataset) -> Result<(), SchemaError> {
+        write_sweep(&self.new_entry("sweeps", name)?, sweep)
+    }
+
+    pub fn sweep(&self, name: &str) -> Result<SweepDataset, SchemaError> {
+        read_sweep(&self.file.group(&format!("sweeps/{name}"))?)
+    }
+
+    /// Names of the entries of `section` (`"slab"`, `"grids"`, `"fourier"`, `"cwt"` or `"sweeps"`),
+    /// empty if it has none.
+    pub fn entries(&self, section: &str) -> Result<Vec<String>, SchemaError> {
+        if !self.file.link_exists(section) {
+            return Ok(Vec::new());
+        }
+        Ok(self.file.group(section)?.member_names()?)
+    }
+
+    /// New group `section/name`, creating the section on first use.
+    fn new_entry(&self, section: &str, name: &str) -> Result<Group, SchemaError> {
+        let section = if self.file.link_exists(section) {
+            self.file.group(section)?
+        } else {
+            self.file.create_group(section)?
+        };
+        Ok(section.create_group(name)?)
+    }
+}
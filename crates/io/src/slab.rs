@@ -0,0 +1,69 @@
+//! crates/io/src/slab.rs
+//! Fundamental slab modes of the layer stack.
+//!
+//! ```text
+//! <name>                 @wavelength (m), @effective_index
+//!   layers               names of the layers, bottom to top
+//!   confinement          fraction of the modal power in each layer
+//!   loss                 internal loss contributed by each layer, @units = "1/m"
+//! ```
+use hdf5::types::VarLenUnicode;
+use hdf5::Group;
+
+use core::units::{Length, Loss};
+use waveguide::slab::{LayerLoss, SlabMode};
+
+use super::schema::{invalid, read_f64, write_f64, write_str, SchemaError};
+
+/// A slab mode together with the wavelength it was solved at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlabResult {
+    pub wavelength: Length,
+    pub mode: SlabMode,
+}
+
+/// Writes `result` into the empty `group`.
+pub fn write_slab(group: &Group, result: &SlabResult) -> Result<(), SchemaError> {
+    write_f64(group, "wavelength", result.wavelength.as_meters())?;
+    write_f64(group, "effective_index", result.mode.effective_index)?;
+    let names = result
+        .mode
+        .layers
+        .iter()
+        .map(|layer| layer.name.parse::<VarLenUnicode>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid(group, "layer names must not contain null bytes"))?;
+    group.new_dataset_builder().with_data(names.as_slice()).create("layers")?;
+    let confinement: Vec<f64> = result.mode.layers.iter().map(|layer| layer.confinement).collect();
+    group.new_dataset_builder().with_data(confinement.as_slice()).create("confinement")?;
+    let loss: Vec<f64> = result.mode.layers.iter().map(|layer| layer.loss.as_per_meter()).collect();
+    let loss = group.new_dataset_builder().with_data(loss.as_slice()).create("loss")?;
+    write_str(&loss, "units", "1/m")
+}
+
+/// Reads back a result written by [`write_slab`].
+pub fn read_slab(group: &Group) -> Result<SlabResult, SchemaError> {
+    let names = group.dataset("layers")?.read_raw::<VarLenUnicode>()?;
+    let confinement = group.dataset("confinement")?.read_raw::<f64>()?;
+    let loss = group.dataset("loss")?.read_raw::<f64>()?;
+    if confinement.len() != names.len() || loss.len() != names.len() {
+        return Err(invalid(group, "there must be one confinement factor and loss per layer"));
+    }
+    let layers = names
+        .iter()
+        .zip(confinement)
+        .zip(loss)
+        .map(|((name, confinement), loss)| LayerLoss {
+            name: name.as_str().to_owned(),
+            confinement,
+            loss: Loss::per_meter(loss),
+        })
+        .collect();
+    Ok(SlabResult {
+        wavelength: Length::meters(read_f64(group, "wavelength")?),
+        mode: SlabMode {
+            effective_index: read_f64(group, "effective_index")?,
+            layers,
+        },
+    })
+}
@@ -0,0 +1,425 @@
+//! crates/io/src/structure.rs
+//! The layer stack, materials, lattice and atoms of a waveguide.
+//!
+//! ```text
+//! structure              @layers = N
+//!   layer_000 …          @kind = "simple" | "photonic_crystal" | "graded", @name, @thickness
+//!     material           simple layers, see below
+//!     crystal            photonic crystal layers
+//!       lattice          @kind = "square" | "triangular" | "oblique", vectors (3 x 3, rows a1 a2 a3)
+//!       base             background_material, @atoms = M, atom_000 …
+//!         atom_000       @shape and its dimensions (|a1| units), @center = [s, t, z], @sidewall_angle, material
+//!     background_material
+//!     @profile           graded layers: "linear" | "parabolic" | "parabolic_top" (@bottom, @top, optional @dn)
+//!                        or "tabulated" (positions, epsilon)
+//!     @thermo_optic      optional for graded layers, TOML
+//!     @doping            optional, "n" | "p", with @concentration (1/m³)
+//!
+//! material               epsilon, epsilon_imag (3 x 3), optional @thermal (TOML) and dispersion
+//! dispersion             @kind = "isotropic" (@model) | "diagonal" (@x, @y, @z) | "rotated" (rotation, principal)
+//! ```
+//!
+//! Dispersion and thermal models are stored as the TOML tables of the material library.
+use hdf5::{Group, Location};
+
+use core::material::{Material, MaterialDispersion};
+use core::shapes::HoleShape;
+use phc::base::{AtomInCell, UnitCellBase};
+use phc::crystal_structure::PhotonicCrystal;
+use phc::lattice::{Lattice, LatticeType};
+use waveguide::doping::{Doping, DopingType};
+use waveguide::graded::GradedProfile;
+use waveguide::layers::{LayerType, Waveguide};
+
+use super::schema::{
+    has_attr, invalid, read_f64, read_matrix, read_str, read_u32, write_f64, write_matrix, write_str, write_u32,
+    SchemaError,
+};
+
+/// Writes `wg` into the empty `group`.
+pub fn write_waveguide(group: &Group, wg: &Waveguide) -> Result<(), SchemaError> {
+    write_u32(group, "layers", wg.layers.len() as u32)?;
+    for (index, layer) in wg.layers.iter().enumerate() {
+        write_layer(&group.create_group(&format!("layer_{index:03}"))?, layer)?;
+    }
+    Ok(())
+}
+
+/// Reads back a waveguide written by [`write_waveguide`].
+pub fn read_waveguide(group: &Group) -> Result<Waveguide, SchemaError> {
+    let layers = (0..read_u32(group, "layers")?)
+        .map(|index| read_layer(&group.group(&format!("layer_{index:03}"))?))
+        .collect::<Result<_, _>>()?;
+    Ok(Waveguide { layers })
+}
+
+fn write_layer(group: &Group, layer: &LayerType) -> Result<(), SchemaError> {
+    write_str(group, "name", layer.name())?;
+    write_f64(group, "thickness", layer.thickness().as_meters())?;
+    match layer {
+        LayerType::Simple { material, doping, .. } => {
+            write_str(group, "kind", "simple")?;
+            write_material(group, "material", material)?;
+            write_doping(group, doping)
+        }
+        LayerType::PhotonicCrystal {
+            definition,
+            background_material,
+            ..
+        } => {
+            write_str(group, "kind", "photonic_crystal")?;
+            write_crystal(&group.create_group("crystal")?, definition)?;
+            write_material(group, "background_material", background_material)
+        }
+        LayerType::Graded {
+            profile,
+            doping,
+            thermo_optic,
+            ..
+        } => {
+            write_str(group, "kind", "graded")?;
+            write_profile(group, profile)?;
+            if let Some(thermo_optic) = thermo_optic {
+                write_toml(group, "thermo_optic", thermo_optic)?;
+            }
+            write_doping(group, doping)
+        }
+    }
+}
+
+fn read_layer(group: &Group) -> Result<LayerType, SchemaError> {
+    let name = read_str(group, "name")?;
+    let thickness = core::units::Length::meters(read_f64(group, "thickness")?);
+    match read_str(group, "kind")?.as_str() {
+        "simple" => Ok(LayerType::Simple {
+            name,
+            thickness,
+            material: read_material(&group.group("material")?)?,
+            doping: read_doping(group)?,
+        }),
+        "photonic_crystal" => Ok(LayerType::PhotonicCrystal {
+            name,
+            thickness,
+            definition: read_crystal(&group.group("crystal")?)?,
+            background_material: read_material(&group.group("background_material")?)?,
+        }),
+        "graded" => Ok(LayerType::Graded {
+            name,
+            thickness,
+            profile: read_profile(group)?,
+            doping: read_doping(group)?,
+            thermo_optic: if has_attr(group, "thermo_optic")? {
+                Some(read_toml(group, "thermo_optic")?)
+            } else {
+                None
+            },
+        }),
+        kind => Err(invalid(group, format!("unknown layer kind '{kind}'"))),
+    }
+}
+
+fn write_doping(group: &Group, doping: &Option<Doping>) -> Result<(), SchemaError> {
+    if let Some(doping) = doping {
+        let kind = match doping.kind {
+            DopingType::N => "n",
+            DopingType::P => "p",
+        };
+        write_str(group, "doping", kind)?;
+        write_f64(group, "concentration", doping.concentration)?;
+    }
+    Ok(())
+}
+
+fn read_doping(group: &Group) -> Result<Option<Doping>, SchemaError> {
+    if !has_attr(group, "doping")? {
+        return Ok(None);
+    }
+    let kind = match read_str(group, "doping")?.as_str() {
+        "n" => DopingType::N,
+        "p" => DopingType::P,
+        kind => return Err(invalid(group, format!("unknown doping type '{kind}'"))),
+    };
+    Ok(Some(Doping {
+        kind,
+        concentration: read_f64(group, "concentration")?,
+    }))
+}
+
+fn write_profile(group: &Group, profile: &GradedProfile) -> Result<(), SchemaError> {
+    match profile {
+        GradedProfile::Linear { bottom, top, dn }
+        | GradedProfile::Parabolic { bottom, top, dn }
+        | GradedProfile::ParabolicTop { bottom, top, dn } => {
+            let kind = match profile {
+                GradedProfile::Linear { .. } => "linear",
+                GradedProfile::Parabolic { .. } => "parabolic",
+                _ => "parabolic_top",
+            };
+            write_str(group, "profile", kind)?;
+            write_f64(group, "bottom", *bottom)?;
+            write_f64(group, "top", *top)?;
+            if *dn != 0.0 {
+                write_f64(group, "dn", *dn)?;
+            }
+            Ok(())
+        }
+        GradedProfile::Tabulated { positions, epsilon } => {
+            write_str(group, "profile", "tabulated")?;
+            group.new_dataset_builder().with_data(positions.as_slice()).create("positions")?;
+            group.new_dataset_builder().with_data(epsilon.as_slice()).create("epsilon")?;
+            Ok(())
+        }
+    }
+}
+
+fn read_profile(group: &Group) -> Result<GradedProfile, SchemaError> {
+    let analytic: fn(f64, f64, f64) -> GradedProfile = match read_str(group, "profile")?.as_str() {
+        "linear" => |bottom, top, dn| GradedProfile::Linear { bottom, top, dn },
+        "parabolic" => |bottom, top, dn| GradedProfile::Parabolic { bottom, top, dn },
+        "parabolic_top" => |bottom, top, dn| GradedProfile::ParabolicTop { bottom, top, dn },
+        "tabulated" => {
+            return GradedProfile::tabulated(
+                group.dataset("positions")?.read_raw::<f64>()?,
+                group.dataset("epsilon")?.read_raw::<f64>()?,
+            )
+            .map_err(|error| invalid(group, error.to_string()))
+        }
+        kind => return Err(invalid(group, format!("unknown profile '{kind}'"))),
+    };
+    let dn = if has_attr(group, "dn")? { read_f64(group, "dn")? } else { 0.0 };
+    Ok(analytic(read_f64(group, "bottom")?, read_f64(group, "top")?, dn))
+}
+
+fn write_crystal(group: &Group, crystal: &PhotonicCrystal) -> Result<(), SchemaError> {
+    let lattice = group.create_group("lattice")?;
+    let kind = match crystal.lattice {
+        LatticeType::Square(_) => "square",
+        LatticeType::Triangular(_) => "triangular",
+        LatticeType::Oblique(_) => "oblique",
+    };
+    write_str(&lattice, "kind", kind)?;
+    let vectors = crystal.lattice.lattice();
+    write_matrix(&lattice, "vectors", &core::vectorial::Matrix3::from_rows(&[
+        vectors.a1.transpose(),
+        vectors.a2.transpose(),
+        vectors.a3.transpose(),
+    ]))?;
+
+    let base = group.create_group("base")?;
+    write_material(&base, "background_material", &crystal.base.background_material)?;
+    write_u32(&base, "atoms", crystal.base.atoms.len() as u32)?;
+    for (index, atom) in crystal.base.atoms.iter().enumerate() {
+        let group = base.create_group(&format!("atom_{index:03}"))?;
+        write_shape(&group, &atom.shape)?;
+        let (s, t, z) = atom.center;
+        group.new_attr_builder().with_data([s, t, z].as_slice()).create("center")?;
+        write_f64(&group, "sidewall_angle", atom.sidewall_angle)?;
+        write_material(&group, "material", &atom.material)?;
+    }
+    Ok(())
+}
+
+fn read_crystal(group: &Group) -> Result<PhotonicCrystal, SchemaError> {
+    let lattice = group.group("lattice")?;
+    let vectors = read_matrix(&lattice, "vectors")?;
+    let vectors = Lattice {
+        a1: vectors.row(0).transpose(),
+        a2: vectors.row(1).transpose(),
+        a3: vectors.row(2).transpose(),
+    };
+    let lattice = match read_str(&lattice, "kind")?.as_str() {
+        "square" => LatticeType::Square(vectors),
+        "triangular" => LatticeType::Triangular(vectors),
+        "oblique" => LatticeType::Oblique(vectors),
+        kind => return Err(invalid(&lattice, format!("unknown lattice kind '{kind}'"))),
+    };
+
+    let base = group.group("base")?;
+    let atoms = (0..read_u32(&base, "atoms")?)
+        .map(|index| {
+            let group = base.group(&format!("atom_{index:03}"))?;
+            let center = group.attr("center")?.read_raw::<f64>()?;
+            let &[s, t, z] = center.as_slice() else {
+                return Err(invalid(&group, "the center must have three coordinates"));
+            };
+            Ok(AtomInCell {
+                shape: read_shape(&group)?,
+                center: (s, t, z),
+                material: read_material(&group.group("material")?)?,
+                sidewall_angle: read_f64(&group, "sidewall_angle")?,
+            })
+        })
+        .collect::<Result<_, SchemaError>>()?;
+    let base = UnitCellBase {
+        atoms,
+        background_material: read_material(&base.group("background_material")?)?,
+    };
+    Ok(PhotonicCrystal::new(lattice, base))
+}
+
+fn write_shape(location: &Location, shape: &HoleShape) -> Result<(), SchemaError> {
+    match *shape {
+        HoleShape::Circle { radius } => {
+            write_str(location, "shape", "circle")?;
+            write_f64(location, "radius", radius)
+        }
+        HoleShape::Square { side } => {
+            write_str(location, "shape", "square")?;
+            write_f64(location, "side", side)
+        }
+        HoleShape::Rectangle { width, height } => {
+            write_str(location, "shape", "rectangle")?;
+            write_f64(location, "width", width)?;
+            write_f64(location, "height", height)
+        }
+        HoleShape::Ellipse {
+            radius_x,
+            radius_y,
+            rotation,
+        } => {
+            write_str(location, "shape", "ellipse")?;
+            write_f64(location, "radius_x", radius_x)?;
+            write_f64(location, "radius_y", radius_y)?;
+            write_f64(location, "rotation", rotation)
+        }
+    }
+}
+
+fn read_shape(location: &Location) -> Result<HoleShape, SchemaError> {
+    match read_str(location, "shape")?.as_str() {
+        "circle" => Ok(HoleShape::Circle {
+            radius: read_f64(location, "radius")?,
+        }),
+        "square" => Ok(HoleShape::Square {
+            side: read_f64(location, "side")?,
+        }),
+        "rectangle" => Ok(HoleShape::Rectangle {
+            width: read_f64(location, "width")?,
+            height: read_f64(location, "height")?,
+        }),
+        "ellipse" => Ok(HoleShape::Ellipse {
+            radius_x: read_f64(location, "radius_x")?,
+            radius_y: read_f64(location, "radius_y")?,
+            rotation: read_f64(location, "rotation")?,
+        }),
+        shape => Err(invalid(location, format!("unknown hole shape '{shape}'"))),
+    }
+}
+
+/// Writes `material` to the new subgroup `name` of `parent`.
+pub(crate) fn write_material(parent: &Group, name: &str, material: &Material) -> Result<(), SchemaError> {
+    let group = parent.create_group(name)?;
+    write_matrix(&group, "epsilon", &material.epsilon_matrix)?;
+    write_matrix(&group, "epsilon_imag", &material.epsilon_imag)?;
+    if let Some(dispersion) = &material.dispersion {
+        write_dispersion(&group.create_group("dispersion")?, dispersion)?;
+    }
+    if let Some(thermal) = &material.thermal {
+        write_toml(&group, "thermal", thermal)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_material(group: &Group) -> Result<Material, SchemaError> {
+    Ok(Material {
+        epsilon_matrix: read_matrix(group, "epsilon")?,
+        epsilon_imag: read_matrix(group, "epsilon_imag")?,
+        dispersion: if group.link_exists("dispersion") {
+            Some(read_dispersion(&group.group("dispersion")?)?)
+        } else {
+            None
+        },
+        thermal: if has_attr(group, "thermal")? {
+            Some(read_toml(group, "thermal")?)
+        } else {
+            None
+        },
+    })
+}
+
+fn write_dispersion(group: &Group, dispersion: &MaterialDispersion) -> Result<(), SchemaError> {
+    match dispersion {
+        MaterialDispersion::Isotropic(model) => {
+            write_str(group, "kind", "isotropic")?;
+            write_toml(group, "model", model)
+        }
+        MaterialDispersion::Diagonal(models) => {
+            write_str(group, "kind", "diagonal")?;
+            for (axis, model) in ["x", "y", "z"].into_iter().zip(models.iter()) {
+                write_toml(group, axis, model)?;
+            }
+            Ok(())
+        }
+        MaterialDispersion::Rotated { principal, rotation } => {
+            write_str(group, "kind", "rotated")?;
+            write_matrix(group, "rotation", rotation)?;
+            write_dispersion(&group.create_group("principal")?, principal)
+        }
+    }
+}
+
+fn read_dispersion(group: &Group) -> Result<MaterialDispersion, SchemaError> {
+    match read_str(group, "kind")?.as_str() {
+        "isotropic" => Ok(MaterialDispersion::Isotropic(read_toml(group, "model")?)),
+        "diagonal" => Ok(MaterialDispersion::Diagonal(Box::new([
+            read_toml(group, "x")?,
+            read_toml(group, "y")?,
+            read_toml(group, "z")?,
+        ]))),
+        "rotated" => Ok(MaterialDispersion::Rotated {
+            principal: Box::new(read_dispersion(&group.group("principal")?)?),
+            rotation: read_matrix(group, "rotation")?,
+        }),
+        kind => Err(invalid(group, format!("unknown dispersion kind '{kind}'"))),
+    }
+}
+
+fn write_toml<T: serde::Serialize>(location: &Location, name: &str, value: &T) -> Result<(), SchemaError> {
+    let text = toml::to_string(value).map_err(|error| invalid(location, format!("attribute '{name}': {error}")))?;
+    write_str(location, name, &text)
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(location: &Location, name: &str) -> Result<T, SchemaError> {
+    toml::from_str(&read_str(location, name)?).map_err(|error| invalid(location, format!("attribute '{name}': {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{full_stack, temp_path};
+
+    #[test]
+    fn test_waveguide_round_trip() {
+        let wg = full_stack();
+        let path = temp_path("structure");
+        {
+            let file = hdf5::File::create(&path).unwrap();
+            write_waveguide(&file.create_group("structure").unwrap(), &wg).unwrap();
+        }
+        let file = hdf5::File::open(&path).unwrap();
+        let read = read_waveguide(&file.group("structure").unwrap()).unwrap();
+        // Debug output prints every float exactly, so equal strings mean identical structures.
+        assert_eq!(format!("{read:?}"), format!("{wg:?}"));
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_unknown_kind_is_reported() {
+        let path = temp_path("unknown-kind");
+        let file = hdf5::File::create(&path).unwrap();
+        let group = file.create_group("structure").unwrap();
+        write_u32(&group, "layers", 1).unwrap();
+        let layer = group.create_group("layer_000").unwrap();
+        write_str(&layer, "name", "x").unwrap();
+        write_f64(&layer, "thickness", 1e-7).unwrap();
+        write_str(&layer, "kind", "metal").unwrap();
+        match read_waveguide(&group) {
+            Err(SchemaError::Invalid { path, reason }) => {
+                assert_eq!(path, "/structure/layer_000");
+                assert!(reason.contains("metal"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        std::fs::remove_file(path).ok();
+    }
+}
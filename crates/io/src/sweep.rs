@@ -0,0 +1,193 @@
+//! crates/io/src/sweep.rs
+//! Results of parameter sweeps as labeled N-dimensional arrays.
+//!
+//! ```text
+//! <name>                 @design, e.g. "grid" or "latin_hypercube"
+//!   dimensions           names of the dimensions, in axis order
+//!   coordinates/<i>      values of the i-th parameter along one dimension,
+//!                        @name = its path, @dimension = index of the dimension, @units if numeric
+//!   values/<quantity>    one result per point, NaN where the point failed, @units
+//!   errors               why each point failed, empty where it succeeded
+//! ```
+use hdf5::types::VarLenUnicode;
+use hdf5::Group;
+use ndarray::{ArrayD, IxDyn};
+
+use super::schema::{has_attr, invalid, read_str, read_u32, write_str, write_u32, SchemaError};
+
+/// Values of one swept parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoordinateValues {
+    /// Numbers, in `units` (`"m"` for lengths, `"1"` for plain numbers).
+    Numbers { values: Vec<f64>, units: String },
+    /// Anything else, such as material names, as text.
+    Labels(Vec<String>),
+}
+
+impl CoordinateValues {
+    pub fn len(&self) -> usize {
+        match self {
+            CoordinateValues::Numbers { values, .. } => values.len(),
+            CoordinateValues::Labels(labels) => labels.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A swept parameter and the dimension its values run along.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coordinate {
+    /// Path of the parameter in the device file, e.g. `layers.PC.thickness`.
+    pub name: String,
+    pub dimension: usize,
+    pub values: CoordinateValues,
+}
+
+/// One scalar result at every point of a sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub name: String,
+    pub units: String,
+    pub values: ArrayD<f64>,
+}
+
+/// Results of a sweep, indexed by the dimensions of its design.
+///
+/// A grid sweep has one dimension per parameter; a Latin hypercube has a single `sample`
+/// dimension along which every parameter varies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepDataset {
+    pub design: String,
+    pub dimensions: Vec<String>,
+    pub coordinates: Vec<Coordinate>,
+    pub quantities: Vec<Quantity>,
+    /// Why each point failed, empty where it succeeded.
+    pub errors: ArrayD<String>,
+}
+
+impl SweepDataset {
+    /// Number of points along each dimension.
+    pub fn shape(&self) -> &[usize] {
+        self.errors.shape()
+    }
+
+    /// Number of points that failed.
+    pub fn failures(&self) -> usize {
+        self.errors.iter().filter(|error| !error.is_empty()).count()
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let shape = self.shape();
+        if shape.len() != self.dimensions.len() {
+            return Err(format!("{} dimensions named for an array of shape {shape:?}", self.dimensions.len()));
+        }
+        for coordinate in &self.coordinates {
+            match shape.get(coordinate.dimension) {
+                Some(&length) if length == coordinate.values.len() => {}
+                _ => return Err(format!("coordinate '{}' does not match the shape {shape:?}", coordinate.name)),
+            }
+        }
+        for quantity in &self.quantities {
+            if quantity.values.shape() != shape {
+                return Err(format!("quantity '{}' does not match the shape {shape:?}", quantity.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unicode(group: &Group, values: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Vec<VarLenUnicode>, SchemaError> {
+    values
+        .into_iter()
+        .map(|value| value.as_ref().parse::<VarLenUnicode>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid(group, "text must not contain null bytes"))
+}
+
+/// Writes `sweep` into the empty `group`.
+pub fn write_sweep(group: &Group, sweep: &SweepDataset) -> Result<(), SchemaError> {
+    sweep.check().map_err(|reason| invalid(group, reason))?;
+    write_str(group, "design", &sweep.design)?;
+    let dimensions = unicode(group, &sweep.dimensions)?;
+    group.new_dataset_builder().with_data(dimensions.as_slice()).create("dimensions")?;
+
+    let coordinates = group.create_group("coordinates")?;
+    for (index, coordinate) in sweep.coordinates.iter().enumerate() {
+        let name = index.to_string();
+        let dataset = match &coordinate.values {
+            CoordinateValues::Numbers { values, units } => {
+                let dataset = coordinates.new_dataset_builder().with_data(values.as_slice()).create(name.as_str())?;
+                write_str(&dataset, "units", units)?;
+                dataset
+            }
+            CoordinateValues::Labels(labels) => {
+                let labels = unicode(group, labels)?;
+                coordinates.new_dataset_builder().with_data(labels.as_slice()).create(name.as_str())?
+            }
+        };
+        write_str(&dataset, "name", &coordinate.name)?;
+        write_u32(&dataset, "dimension", coordinate.dimension as u32)?;
+    }
+
+    let values = group.create_group("values")?;
+    for quantity in &sweep.quantities {
+        let dataset = values.new_dataset_builder().with_data(&quantity.values).create(quantity.name.as_str())?;
+        write_str(&dataset, "units", &quantity.units)?;
+    }
+    let errors = ArrayD::from_shape_vec(IxDyn(sweep.shape()), unicode(group, &sweep.errors)?).map_err(hdf5::Error::from)?;
+    group.new_dataset_builder().with_data(&errors).create("errors")?;
+    Ok(())
+}
+
+/// Reads back a sweep written by [`write_sweep`], with its quantities in alphabetical order.
+pub fn read_sweep(group: &Group) -> Result<SweepDataset, SchemaError> {
+    let dimensions = group.dataset("dimensions")?.read_raw::<VarLenUnicode>()?;
+
+    let coordinates_group = group.group("coordinates")?;
+    let mut coordinates = Vec::new();
+    // Members are listed alphabetically, so look them up by index instead.
+    for index in 0..coordinates_group.member_names()?.len() {
+        let dataset = coordinates_group.dataset(&index.to_string())?;
+        let values = if has_attr(&dataset, "units")? {
+            CoordinateValues::Numbers {
+                values: dataset.read_raw::<f64>()?,
+                units: read_str(&dataset, "units")?,
+            }
+        } else {
+            let labels = dataset.read_raw::<VarLenUnicode>()?;
+            CoordinateValues::Labels(labels.iter().map(|label| label.as_str().to_owned()).collect())
+        };
+        coordinates.push(Coordinate {
+            name: read_str(&dataset, "name")?,
+            dimension: read_u32(&dataset, "dimension")? as usize,
+            values,
+        });
+    }
+
+    let values = group.group("values")?;
+    let quantities = values
+        .member_names()?
+        .into_iter()
+        .map(|name| {
+            let dataset = values.dataset(&name)?;
+            Ok(Quantity {
+                units: read_str(&dataset, "units")?,
+                values: dataset.read_dyn::<f64>()?,
+                name,
+            })
+        })
+        .collect::<Result<_, SchemaError>>()?;
+
+    let sweep = SweepDataset {
+        design: read_str(group, "design")?,
+        dimensions: dimensions.iter().map(|name| name.as_str().to_owned()).collect(),
+        coordinates,
+        quantities,
+        errors: group.dataset("errors")?.read_dyn::<VarLenUnicode>()?.map(|error| error.as_str().to_owned()),
+    };
+    sweep.check().map_err(|reason| invalid(group, reason))?;
+    Ok(sweep)
+}
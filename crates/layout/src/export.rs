@@ -0,0 +1,414 @@
+//! crates/layout/src/export.rs
+//! Finite layouts of photonic crystals.
+//!
+//! Lattice point (0, 0) of every crystal lies at the origin of the layout, so that a layout
+//! can be read back onto the same lattice by [`crate::import`]. Holes are drawn as their
+//! cross-section at the top surface of their layer, the opening defined by lithography.
+use core::shapes::HoleShape;
+use core::units::Length;
+use core::vectorial::Vector2;
+use phc::crystal_structure::PhotonicCrystal;
+use phc::disorder::{DisorderModel, SplitMix64};
+use waveguide::layers::{LayerType, Waveguide};
+
+use super::gdsii::{Cell, GdsLayer, Library, Polygon, MAX_VERTICES};
+use super::polygon::shape_vertices;
+use super::LayoutError;
+
+/// Region of the wafer covered by the photonic crystal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeviceArea {
+    /// `nx` x `ny` unit cells from the origin along `a1` and `a2`.
+    Cells { nx: usize, ny: usize },
+    /// Rectangle centered on the origin. Holes whose centers lie inside are kept.
+    Rectangle { width: Length, height: Length },
+    /// Disk centered on the origin. Holes whose centers lie inside are kept.
+    Circle { diameter: Length },
+}
+
+/// How crystals are laid out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// Name of the library and of its single cell.
+    pub cell_name: String,
+    pub area: DeviceArea,
+    pub database_unit: Length,
+    /// Vertices of each circular or elliptic hole.
+    pub circle_vertices: usize,
+    /// Vertices of a circular device outline.
+    pub outline_vertices: usize,
+    /// Whether curved holes keep their area when discretized, see [`shape_vertices`].
+    pub preserve_area: bool,
+    /// Layer of the outline of the device area, if it is drawn.
+    pub outline: Option<GdsLayer>,
+    /// Fabrication disorder and the seed of its realization.
+    pub disorder: Option<(DisorderModel, u64)>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            cell_name: "PCSEL".into(),
+            area: DeviceArea::Cells { nx: 1, ny: 1 },
+            database_unit: Length::nanometers(1.0),
+            circle_vertices: 64,
+            outline_vertices: 512,
+            preserve_area: true,
+            outline: None,
+            disorder: None,
+        }
+    }
+}
+
+/// A photonic crystal layer and the GDSII layer of its holes.
+#[derive(Debug, Clone, Copy)]
+pub struct HoleLayer<'a> {
+    pub crystal: &'a PhotonicCrystal,
+    /// Thickness of the etched layer, whose top surface is drawn.
+    pub thickness: Length,
+    pub layer: GdsLayer,
+}
+
+/// Lays out the holes of every photonic crystal layer of `wg`, the first on `first` and the
+/// next ones on the following layer numbers.
+pub fn export_waveguide(wg: &Waveguide, first: GdsLayer, options: &ExportOptions) -> Result<Library, LayoutError> {
+    let layers: Vec<HoleLayer> = wg
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerType::PhotonicCrystal { definition, thickness, .. } => Some((definition, *thickness)),
+            _ => None,
+        })
+        .enumerate()
+        .map(|(index, (crystal, thickness))| HoleLayer {
+            crystal,
+            thickness,
+            layer: GdsLayer::new(first.layer + index as i16, first.datatype),
+        })
+        .collect();
+    if layers.is_empty() {
+        return Err(LayoutError::Options("the waveguide has no photonic crystal layer".into()));
+    }
+    export_layout(&layers, options)
+}
+
+/// Lays out the holes of `layers` over the device area of `options`.
+pub fn export_layout(layers: &[HoleLayer], options: &ExportOptions) -> Result<Library, LayoutError> {
+    check(options)?;
+    let mut cell = Cell {
+        name: options.cell_name.clone(),
+        polygons: Vec::new(),
+    };
+    let mut seeds = options.disorder.map(|(_, seed)| SplitMix64::new(seed));
+    for layer in layers {
+        let disorder = options.disorder.zip(seeds.as_mut()).map(|((model, _), seeds)| (model, seeds.next_u64()));
+        for (center, shape) in holes(layer, options.area, disorder) {
+            let points = shape_vertices(&shape, options.circle_vertices, options.preserve_area)
+                .into_iter()
+                .map(|(x, y)| (center.x + x, center.y + y))
+                .collect::<Vec<_>>();
+            cell.polygons.push(polygon(layer.layer, &points, options.database_unit)?);
+        }
+    }
+    if let Some(layer) = options.outline {
+        let crystal = layers
+            .first()
+            .ok_or_else(|| LayoutError::Options("an outline needs a photonic crystal layer".into()))?
+            .crystal;
+        cell.polygons.push(polygon(layer, &outline(crystal, options), options.database_unit)?);
+    }
+    let mut library = Library::new(options.cell_name.clone(), options.database_unit);
+    library.cells.push(cell);
+    Ok(library)
+}
+
+fn check(options: &ExportOptions) -> Result<(), LayoutError> {
+    let invalid = |reason: String| Err(LayoutError::Options(reason));
+    for (name, count) in [("circle", options.circle_vertices), ("outline", options.outline_vertices)] {
+        if !(3..=MAX_VERTICES).contains(&count) {
+            return invalid(format!("{name} polygons need 3 to {MAX_VERTICES} vertices, found {count}"));
+        }
+    }
+    if options.database_unit.as_meters() <= 0.0 {
+        return invalid(format!("the database unit must be positive, found {}", options.database_unit));
+    }
+    let positive = match options.area {
+        DeviceArea::Cells { nx, ny } => nx > 0 && ny > 0,
+        DeviceArea::Rectangle { width, height } => width.as_meters() > 0.0 && height.as_meters() > 0.0,
+        DeviceArea::Circle { diameter } => diameter.as_meters() > 0.0,
+    };
+    if !positive {
+        return invalid(format!("the device area must not be empty, found {:?}", options.area));
+    }
+    Ok(())
+}
+
+/// `points`, in meters, rounded to the database grid.
+fn polygon(layer: GdsLayer, points: &[(f64, f64)], unit: Length) -> Result<Polygon, LayoutError> {
+    let snap = |value: f64| {
+        let value = (value / unit.as_meters()).round();
+        if value.abs() <= i32::MAX as f64 {
+            Ok(value as i32)
+        } else {
+            Err(LayoutError::Options(format!(
+                "the layout does not fit in 32-bit coordinates with a database unit of {unit}"
+            )))
+        }
+    };
+    let points = points
+        .iter()
+        .map(|&(x, y)| Ok((snap(x)?, snap(y)?)))
+        .collect::<Result<Vec<_>, LayoutError>>()?;
+    Ok(Polygon { layer, points })
+}
+
+/// Range of cell indices along `a1` and `a2` whose cells may hold holes of `area`.
+fn cell_range(crystal: &PhotonicCrystal, area: DeviceArea) -> ((i64, i64), (usize, usize)) {
+    let (half_width, half_height) = match area {
+        DeviceArea::Cells { nx, ny } => return ((0, 0), (nx, ny)),
+        DeviceArea::Rectangle { width, height } => (width.as_meters() / 2.0, height.as_meters() / 2.0),
+        DeviceArea::Circle { diameter } => (diameter.as_meters() / 2.0, diameter.as_meters() / 2.0),
+    };
+    let (a1, a2) = crystal.lattice.lattice().in_plane_vectors();
+    let to_fractional = core::nalgebra::Matrix2::from_columns(&[a1, a2])
+        .try_inverse()
+        .expect("lattice vectors must be linearly independent");
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+        .map(|(sx, sy)| to_fractional * Vector2::new(sx * half_width, sy * half_height));
+    let low = |f: fn(&Vector2) -> f64| corners.iter().map(f).fold(f64::INFINITY, f64::min).floor() as i64 - 1;
+    let high = |f: fn(&Vector2) -> f64| corners.iter().map(f).fold(f64::NEG_INFINITY, f64::max).ceil() as i64 + 1;
+    let (i0, j0) = (low(|p| p.x), low(|p| p.y));
+    let (i1, j1) = (high(|p| p.x), high(|p| p.y));
+    ((i0, j0), ((i1 - i0) as usize, (j1 - j0) as usize))
+}
+
+/// Centers and top cross-sections of the holes of `layer` in `area`, in meters.
+fn holes(layer: &HoleLayer, area: DeviceArea, disorder: Option<(DisorderModel, u64)>) -> Vec<(Vector2, HoleShape)> {
+    let crystal = layer.crystal;
+    let lattice = crystal.lattice.lattice();
+    let (a1, a2) = lattice.in_plane_vectors();
+    let ((i0, j0), (ni, nj)) = cell_range(crystal, area);
+    let origin = a1 * i0 as f64 + a2 * j0 as f64;
+    let top = 0.5 * layer.thickness.as_meters() / lattice.a3.norm();
+
+    // Fractional positions in units of `cells` lattice cells, and the atoms drawn there.
+    let realized = disorder.map(|(model, seed)| model.realize(crystal, (ni, nj), seed).crystal);
+    let (cells, placed) = match &realized {
+        None => {
+            let placed: Vec<_> = (0..nj)
+                .flat_map(|j| (0..ni).map(move |i| (i, j)))
+                .flat_map(|(i, j)| {
+                    crystal
+                        .base
+                        .atoms
+                        .iter()
+                        .map(move |atom| ((i as f64 + atom.center.0, j as f64 + atom.center.1), atom))
+                })
+                .collect();
+            ((1, 1), placed)
+        }
+        Some(realized) => {
+            // `realize` lists the atoms cell by cell, rows along a1 first, and wraps the
+            // perturbed centers into the supercell: undo the wrapping around each original center.
+            let count = crystal.base.atoms.len();
+            let placed = realized
+                .base
+                .atoms
+                .iter()
+                .enumerate()
+                .map(|(index, atom)| {
+                    let (cell, original) = (index / count, &crystal.base.atoms[index % count]);
+                    let s = ((cell % ni) as f64 + original.center.0) / ni as f64;
+                    let t = ((cell / ni) as f64 + original.center.1) / nj as f64;
+                    let (ds, dt) = (atom.center.0 - s, atom.center.1 - t);
+                    ((s + ds - ds.round(), t + dt - dt.round()), atom)
+                })
+                .collect();
+            ((ni, nj), placed)
+        }
+    };
+
+    let scale = a1.norm() * cells.0 as f64;
+    let height = lattice.a3.norm() / scale;
+    placed
+        .into_iter()
+        .filter_map(|((s, t), atom)| {
+            let center = origin + a1 * (s * cells.0 as f64) + a2 * (t * cells.1 as f64);
+            let shape = atom.shape_at_height((top - atom.center.2) * height);
+            let inside = match area {
+                DeviceArea::Cells { .. } => true,
+                DeviceArea::Rectangle { width, height } => {
+                    center.x.abs() <= width.as_meters() / 2.0 && center.y.abs() <= height.as_meters() / 2.0
+                }
+                DeviceArea::Circle { diameter } => center.norm() <= diameter.as_meters() / 2.0,
+            };
+            (inside && shape.has_positive_dimensions()).then(|| (center, shape.scaled(scale)))
+        })
+        .collect()
+}
+
+/// Outline of the device area, in meters.
+fn outline(crystal: &PhotonicCrystal, options: &ExportOptions) -> Vec<(f64, f64)> {
+    match options.area {
+        DeviceArea::Cells { nx, ny } => {
+            let (a1, a2) = crystal.lattice.lattice().in_plane_vectors();
+            let (a1, a2) = (a1 * nx as f64, a2 * ny as f64);
+            [Vector2::zeros(), a1, a1 + a2, a2].iter().map(|p| (p.x, p.y)).collect()
+        }
+        DeviceArea::Rectangle { width, height } => shape_vertices(
+            &HoleShape::Rectangle {
+                width: width.as_meters(),
+                height: height.as_meters(),
+            },
+            4,
+            false,
+        ),
+        DeviceArea::Circle { diameter } => shape_vertices(
+            &HoleShape::Circle {
+                radius: diameter.as_meters() / 2.0,
+            },
+            options.outline_vertices,
+            options.preserve_area,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::material::Material;
+    use phc::base::UnitCellBase;
+    use phc::disorder::Jitter;
+    use phc::lattice::LatticeType;
+
+    use crate::polygon::fit_shape;
+
+    fn square_crystal() -> PhotonicCrystal {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        PhotonicCrystal::new(lattice, UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0)))
+    }
+
+    fn layer(crystal: &PhotonicCrystal) -> HoleLayer<'_> {
+        HoleLayer {
+            crystal,
+            thickness: Length::nanometers(118.0),
+            layer: GdsLayer::new(1, 0),
+        }
+    }
+
+    #[test]
+    fn test_tiled_holes() {
+        let crystal = square_crystal();
+        let options = ExportOptions {
+            area: DeviceArea::Cells { nx: 4, ny: 3 },
+            circle_vertices: 32,
+            outline: Some(GdsLayer::new(10, 0)),
+            ..ExportOptions::default()
+        };
+        let library = export_layout(&[layer(&crystal)], &options).unwrap();
+        let cell = library.cell("PCSEL").unwrap();
+        assert_eq!(cell.polygons.len(), 13);
+        let holes: Vec<&Polygon> = cell.polygons.iter().filter(|p| p.layer == GdsLayer::new(1, 0)).collect();
+        assert_eq!(holes.len(), 12);
+        assert!(holes.iter().all(|hole| hole.points.len() == 32));
+
+        // The last hole is in the cell (3, 2), centered at (3.5, 2.5) a.
+        let points: Vec<(f64, f64)> = holes[11].points.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+        let (shape, center) = fit_shape(&points);
+        assert!((center.0 - 1032.5).abs() < 0.5 && (center.1 - 737.5).abs() < 0.5, "{center:?}");
+        assert!(matches!(shape, HoleShape::Circle { radius } if (radius - 0.16 * 295.0).abs() < 0.5), "{shape:?}");
+        assert_eq!(cell.polygons[12].points, [(0, 0), (1180, 0), (1180, 885), (0, 885)]);
+    }
+
+    #[test]
+    fn test_areas_and_sidewalls() {
+        let mut crystal = square_crystal();
+        crystal.base.atoms[0].sidewall_angle = 0.1;
+        let circle = ExportOptions {
+            area: DeviceArea::Circle {
+                diameter: Length::micrometers(3.0),
+            },
+            outline: Some(GdsLayer::new(10, 0)),
+            ..ExportOptions::default()
+        };
+        let library = export_layout(&[layer(&crystal)], &circle).unwrap();
+        let polygons = &library.cells[0].polygons;
+        let outline = polygons.last().unwrap();
+        assert_eq!(outline.points.len(), 512);
+        // Every hole center lies within 1.5 µm of the origin, and there are about π (1.5/0.295)² of them.
+        let holes = &polygons[..polygons.len() - 1];
+        assert!((75..=88).contains(&holes.len()), "{}", holes.len());
+        for hole in holes {
+            let points: Vec<(f64, f64)> = hole.points.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+            let (shape, (x, y)) = fit_shape(&points);
+            assert!(x.hypot(y) <= 1500.5);
+            // The top of the hole is narrower by tan(0.1) times half the thickness.
+            let expected = 0.16 * 295.0 - 59.0 * 0.1_f64.tan();
+            assert!(matches!(shape, HoleShape::Circle { radius } if (radius - expected).abs() < 0.5), "{shape:?}");
+        }
+
+        let rectangle = ExportOptions {
+            area: DeviceArea::Rectangle {
+                width: Length::nanometers(1000.0),
+                height: Length::nanometers(600.0),
+            },
+            ..ExportOptions::default()
+        };
+        // Centers at odd multiples of a/2 = 147.5 nm: four within ±500 nm and two within ±300 nm.
+        assert_eq!(export_layout(&[layer(&crystal)], &rectangle).unwrap().cells[0].polygons.len(), 4 * 2);
+    }
+
+    #[test]
+    fn test_disorder_and_layers() {
+        let crystal = square_crystal();
+        let model = DisorderModel {
+            position: Jitter::Normal { sigma: 0.01 },
+            radius: Jitter::Uniform { half_width: 0.005 },
+            ..DisorderModel::default()
+        };
+        let options = ExportOptions {
+            area: DeviceArea::Cells { nx: 5, ny: 5 },
+            disorder: Some((model, 42)),
+            ..ExportOptions::default()
+        };
+        let ordered = export_layout(&[layer(&crystal)], &ExportOptions { disorder: None, ..options.clone() }).unwrap();
+        let disordered = export_layout(&[layer(&crystal)], &options).unwrap();
+        assert_eq!(disordered, export_layout(&[layer(&crystal)], &options).unwrap());
+        let (ordered, disordered) = (&ordered.cells[0].polygons, &disordered.cells[0].polygons);
+        assert_eq!(ordered.len(), disordered.len());
+        for (a, b) in ordered.iter().zip(disordered) {
+            let fit = |p: &Polygon| fit_shape(&p.points.iter().map(|&(x, y)| (x as f64, y as f64)).collect::<Vec<_>>());
+            let ((_, ca), (_, cb)) = (fit(a), fit(b));
+            // Displacements stay near their own hole, even across the edges of the supercell.
+            let shift = (ca.0 - cb.0).hypot(ca.1 - cb.1);
+            assert!(shift > 0.0 && shift < 0.1 * 295.0, "{shift}");
+        }
+
+        let wg = Waveguide::new_from_table_i(crystal, Material::new_from_eps(12.7449));
+        let library = export_waveguide(&wg, GdsLayer::new(5, 2), &ExportOptions::default()).unwrap();
+        assert_eq!(library.cells[0].polygons[0].layer, GdsLayer::new(5, 2));
+        assert!(export_waveguide(&Waveguide { layers: Vec::new() }, GdsLayer::new(5, 2), &options).is_err());
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let crystal = square_crystal();
+        for options in [
+            ExportOptions {
+                circle_vertices: 2,
+                ..ExportOptions::default()
+            },
+            ExportOptions {
+                area: DeviceArea::Cells { nx: 0, ny: 3 },
+                ..ExportOptions::default()
+            },
+            ExportOptions {
+                database_unit: Length::nanometers(1e-12),
+                area: DeviceArea::Cells { nx: 10, ny: 10 },
+                ..ExportOptions::default()
+            },
+        ] {
+            assert!(matches!(export_layout(&[layer(&crystal)], &options), Err(LayoutError::Options(_))));
+        }
+    }
+}
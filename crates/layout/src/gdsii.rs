@@ -0,0 +1,469 @@
+//! crates/layout/src/gdsii.rs
+//! GDSII stream files, limited to what mask layouts of photonic crystals need.
+//!
+//! Libraries are flat: cells hold polygons (`BOUNDARY` elements) only. Paths, boxes, nodes
+//! and texts are skipped when reading; cell references are rejected, as layouts must be
+//! flattened before their holes can be read back.
+use std::io::{Read, Write};
+use std::path::Path;
+
+use core::units::Length;
+
+use super::LayoutError;
+
+const HEADER: u8 = 0x00;
+const BGNLIB: u8 = 0x01;
+const LIBNAME: u8 = 0x02;
+const UNITS: u8 = 0x03;
+const ENDLIB: u8 = 0x04;
+const BGNSTR: u8 = 0x05;
+const STRNAME: u8 = 0x06;
+const ENDSTR: u8 = 0x07;
+const BOUNDARY: u8 = 0x08;
+const PATH: u8 = 0x09;
+const SREF: u8 = 0x0A;
+const AREF: u8 = 0x0B;
+const TEXT: u8 = 0x0C;
+const LAYER: u8 = 0x0D;
+const DATATYPE: u8 = 0x0E;
+const XY: u8 = 0x10;
+const ENDEL: u8 = 0x11;
+const NODE: u8 = 0x15;
+const BOX: u8 = 0x2D;
+
+const NO_DATA: u8 = 0x00;
+const INT2: u8 = 0x02;
+const INT4: u8 = 0x03;
+const REAL8: u8 = 0x05;
+const ASCII: u8 = 0x06;
+
+/// Version written in the `HEADER` record.
+const STREAM_VERSION: i16 = 600;
+/// Most vertices of one polygon, bounded by the length of an `XY` record.
+pub const MAX_VERTICES: usize = 8190;
+
+/// Layer and datatype numbers of a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GdsLayer {
+    pub layer: i16,
+    pub datatype: i16,
+}
+
+impl GdsLayer {
+    pub const fn new(layer: i16, datatype: i16) -> Self {
+        GdsLayer { layer, datatype }
+    }
+}
+
+/// A closed polygon in database units. The closing vertex is not repeated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polygon {
+    pub layer: GdsLayer,
+    pub points: Vec<(i32, i32)>,
+}
+
+/// A named cell of polygons.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Cell {
+    pub name: String,
+    pub polygons: Vec<Polygon>,
+}
+
+/// A GDSII library.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Library {
+    pub name: String,
+    /// Size of one database unit; coordinates of polygons are integer multiples of it.
+    pub database_unit: Length,
+    pub cells: Vec<Cell>,
+}
+
+impl Library {
+    /// Empty library with coordinates in multiples of `database_unit`.
+    pub fn new(name: impl Into<String>, database_unit: Length) -> Self {
+        Library {
+            name: name.into(),
+            database_unit,
+            cells: Vec::new(),
+        }
+    }
+
+    /// The cell called `name`.
+    pub fn cell(&self, name: &str) -> Option<&Cell> {
+        self.cells.iter().find(|cell| cell.name == name)
+    }
+
+    /// Writes the library as a GDSII stream. User units are micrometers.
+    pub fn write(&self, out: &mut impl Write) -> Result<(), LayoutError> {
+        let unit = self.database_unit.as_meters();
+        if unit.is_nan() || unit <= 0.0 {
+            return Err(LayoutError::Options(format!("the database unit must be positive, found {}", self.database_unit)));
+        }
+        let mut stream = Stream { out };
+        // Dates are left at zero so that the same layout always gives the same file.
+        let dates = [0; 12];
+        stream.int2(HEADER, &[STREAM_VERSION])?;
+        stream.int2(BGNLIB, &dates)?;
+        stream.ascii(LIBNAME, &self.name)?;
+        stream.real8(UNITS, &[unit / 1e-6, unit])?;
+        for cell in &self.cells {
+            stream.int2(BGNSTR, &dates)?;
+            stream.ascii(STRNAME, &cell.name)?;
+            for polygon in &cell.polygons {
+                if polygon.points.len() < 3 || polygon.points.len() > MAX_VERTICES {
+                    return Err(LayoutError::Options(format!(
+                        "polygons need 3 to {MAX_VERTICES} vertices, found {} in cell '{}'",
+                        polygon.points.len(),
+                        cell.name
+                    )));
+                }
+                stream.record(BOUNDARY, NO_DATA, &[])?;
+                stream.int2(LAYER, &[polygon.layer.layer])?;
+                stream.int2(DATATYPE, &[polygon.layer.datatype])?;
+                let closed = polygon.points.iter().chain(&polygon.points[..1]);
+                let xy: Vec<i32> = closed.flat_map(|&(x, y)| [x, y]).collect();
+                stream.int4(XY, &xy)?;
+                stream.record(ENDEL, NO_DATA, &[])?;
+            }
+            stream.record(ENDSTR, NO_DATA, &[])?;
+        }
+        stream.record(ENDLIB, NO_DATA, &[])
+    }
+
+    /// Reads a GDSII stream.
+    pub fn read(input: &mut impl Read) -> Result<Self, LayoutError> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        Parser { bytes: &bytes, offset: 0 }.library()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), LayoutError> {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LayoutError> {
+        Self::read(&mut std::fs::File::open(path)?)
+    }
+}
+
+/// Encodes `value` as a GDSII eight-byte real: sign, excess-64 base-16 exponent, 56-bit mantissa.
+fn encode_real8(value: f64) -> u64 {
+    if value == 0.0 {
+        return 0;
+    }
+    let sign = if value < 0.0 { 1u64 << 63 } else { 0 };
+    let mut mantissa = value.abs();
+    let mut exponent = 64i32;
+    while mantissa >= 1.0 {
+        mantissa /= 16.0;
+        exponent += 1;
+    }
+    while mantissa < 1.0 / 16.0 {
+        mantissa *= 16.0;
+        exponent -= 1;
+    }
+    let mut bits = (mantissa * (1u64 << 56) as f64).round() as u64;
+    if bits == 1 << 56 {
+        bits >>= 4;
+        exponent += 1;
+    }
+    sign | ((exponent.clamp(0, 127) as u64) << 56) | bits
+}
+
+fn decode_real8(bits: u64) -> f64 {
+    let mantissa = (bits & ((1 << 56) - 1)) as f64 / (1u64 << 56) as f64;
+    let exponent = ((bits >> 56) & 0x7F) as i32 - 64;
+    let value = mantissa * 16f64.powi(exponent);
+    if bits >> 63 == 1 {
+        -value
+    } else {
+        value
+    }
+}
+
+struct Stream<'a, W: Write> {
+    out: &'a mut W,
+}
+
+impl<W: Write> Stream<'_, W> {
+    fn record(&mut self, kind: u8, data_type: u8, data: &[u8]) -> Result<(), LayoutError> {
+        let length = u16::try_from(data.len() + 4)
+            .map_err(|_| LayoutError::Options(format!("record of {} bytes is too long for GDSII", data.len())))?;
+        self.out.write_all(&length.to_be_bytes())?;
+        self.out.write_all(&[kind, data_type])?;
+        self.out.write_all(data)?;
+        Ok(())
+    }
+
+    fn int2(&mut self, kind: u8, values: &[i16]) -> Result<(), LayoutError> {
+        let data: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        self.record(kind, INT2, &data)
+    }
+
+    fn int4(&mut self, kind: u8, values: &[i32]) -> Result<(), LayoutError> {
+        let data: Vec<u8> = values.iter().flat_map(|value| value.to_be_bytes()).collect();
+        self.record(kind, INT4, &data)
+    }
+
+    fn real8(&mut self, kind: u8, values: &[f64]) -> Result<(), LayoutError> {
+        let data: Vec<u8> = values.iter().flat_map(|&value| encode_real8(value).to_be_bytes()).collect();
+        self.record(kind, REAL8, &data)
+    }
+
+    /// Writes `text`, padded with a null byte to an even length.
+    fn ascii(&mut self, kind: u8, text: &str) -> Result<(), LayoutError> {
+        if !text.is_ascii() {
+            return Err(LayoutError::Options(format!("GDSII names must be ASCII, found '{text}'")));
+        }
+        let mut data = text.as_bytes().to_vec();
+        if data.len() % 2 == 1 {
+            data.push(0);
+        }
+        self.record(kind, ASCII, &data)
+    }
+}
+
+/// One record of a stream.
+struct Record<'a> {
+    offset: usize,
+    kind: u8,
+    data: &'a [u8],
+}
+
+impl Record<'_> {
+    fn malformed(&self, reason: impl Into<String>) -> LayoutError {
+        LayoutError::Malformed {
+            offset: self.offset,
+            reason: reason.into(),
+        }
+    }
+
+    fn int2(&self) -> Vec<i16> {
+        self.data.chunks_exact(2).map(|b| i16::from_be_bytes([b[0], b[1]])).collect()
+    }
+
+    fn int4(&self) -> Vec<i32> {
+        self.data.chunks_exact(4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]])).collect()
+    }
+
+    fn real8(&self) -> Vec<f64> {
+        self.data
+            .chunks_exact(8)
+            .map(|b| decode_real8(u64::from_be_bytes(b.try_into().expect("chunks of eight bytes"))))
+            .collect()
+    }
+
+    fn ascii(&self) -> String {
+        String::from_utf8_lossy(self.data).trim_end_matches('\0').to_string()
+    }
+
+    fn single_int2(&self) -> Result<i16, LayoutError> {
+        self.int2().first().copied().ok_or_else(|| self.malformed("expected a number"))
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<Record<'a>, LayoutError> {
+        let offset = self.offset;
+        let malformed = |reason: &str| LayoutError::Malformed {
+            offset,
+            reason: reason.to_string(),
+        };
+        let header = self.bytes.get(offset..offset + 4).ok_or_else(|| malformed("unexpected end of file"))?;
+        let length = u16::from_be_bytes([header[0], header[1]]) as usize;
+        if length < 4 {
+            return Err(malformed("record shorter than its header"));
+        }
+        let data = self
+            .bytes
+            .get(offset + 4..offset + length)
+            .ok_or_else(|| malformed("record runs past the end of the file"))?;
+        self.offset += length;
+        Ok(Record {
+            offset,
+            kind: header[2],
+            data,
+        })
+    }
+
+    fn library(mut self) -> Result<Library, LayoutError> {
+        let header = self.next()?;
+        if header.kind != HEADER {
+            return Err(header.malformed("not a GDSII stream"));
+        }
+        let mut library = Library::new("", Length::nanometers(1.0));
+        loop {
+            let record = self.next()?;
+            match record.kind {
+                LIBNAME => library.name = record.ascii(),
+                UNITS => match record.real8()[..] {
+                    [_, unit] if unit > 0.0 => library.database_unit = Length::meters(unit),
+                    _ => return Err(record.malformed("expected the user and database units")),
+                },
+                BGNSTR => library.cells.push(self.cell()?),
+                ENDLIB => return Ok(library),
+                _ => {}
+            }
+        }
+    }
+
+    fn cell(&mut self) -> Result<Cell, LayoutError> {
+        let mut cell = Cell::default();
+        loop {
+            let record = self.next()?;
+            match record.kind {
+                STRNAME => cell.name = record.ascii(),
+                BOUNDARY => cell.polygons.push(self.boundary()?),
+                PATH | TEXT | BOX | NODE => self.skip_element()?,
+                SREF | AREF => {
+                    return Err(LayoutError::Unsupported(format!(
+                        "cell '{}' references other cells, flatten the layout first",
+                        cell.name
+                    )))
+                }
+                ENDSTR => return Ok(cell),
+                ENDLIB => return Err(record.malformed("library ends inside a cell")),
+                _ => {}
+            }
+        }
+    }
+
+    fn boundary(&mut self) -> Result<Polygon, LayoutError> {
+        let mut layer = GdsLayer::new(0, 0);
+        let mut points = Vec::new();
+        loop {
+            let record = self.next()?;
+            match record.kind {
+                LAYER => layer.layer = record.single_int2()?,
+                DATATYPE => layer.datatype = record.single_int2()?,
+                XY => points = record.int4().chunks_exact(2).map(|xy| (xy[0], xy[1])).collect(),
+                ENDEL => break,
+                ENDSTR | ENDLIB => return Err(record.malformed("element is not terminated")),
+                _ => {}
+            }
+        }
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        Ok(Polygon { layer, points })
+    }
+
+    fn skip_element(&mut self) -> Result<(), LayoutError> {
+        loop {
+            let record = self.next()?;
+            match record.kind {
+                ENDEL => return Ok(()),
+                ENDSTR | ENDLIB => return Err(record.malformed("element is not terminated")),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_real8_encoding() {
+        // Reference values from the GDSII specification.
+        assert_eq!(encode_real8(1.0), 0x4110_0000_0000_0000);
+        assert_eq!(encode_real8(-2.0), 0xC120_0000_0000_0000);
+        assert_eq!(encode_real8(0.0), 0);
+        for value in [1e-9, 1e-3, 0.001, 1e-6, 123.456, -7.25e-12] {
+            let decoded = decode_real8(encode_real8(value));
+            assert!((decoded - value).abs() <= value.abs() * 1e-15, "{value} read back as {decoded}");
+        }
+    }
+
+    #[test]
+    fn test_library_round_trip() {
+        let square = Polygon {
+            layer: GdsLayer::new(1, 0),
+            points: vec![(0, 0), (100, 0), (100, 100), (0, 100)],
+        };
+        let triangle = Polygon {
+            layer: GdsLayer::new(2, 5),
+            points: vec![(-5, -5), (5, -5), (0, 3)],
+        };
+        let library = Library {
+            name: "PCSEL".into(),
+            database_unit: Length::nanometers(1.0),
+            cells: vec![
+                Cell {
+                    name: "TOP".into(),
+                    polygons: vec![square, triangle],
+                },
+                Cell {
+                    name: "EMPTY".into(),
+                    polygons: Vec::new(),
+                },
+            ],
+        };
+        let mut bytes = Vec::new();
+        library.write(&mut bytes).unwrap();
+        assert_eq!(&bytes[..6], &[0, 6, HEADER, INT2, 0x02, 0x58]);
+        assert_eq!(bytes.len() % 2, 0);
+        let read = Library::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read.name, "PCSEL");
+        assert!((read.database_unit.as_meters() - 1e-9).abs() < 1e-24);
+        assert_eq!(read.cells, library.cells);
+        assert_eq!(read.cell("TOP").unwrap().polygons[1].layer, GdsLayer::new(2, 5));
+
+        let truncated = Library::read(&mut &bytes[..bytes.len() - 3]);
+        assert!(matches!(truncated, Err(LayoutError::Malformed { .. })));
+        assert!(matches!(Library::read(&mut &b"not a layout"[..]), Err(LayoutError::Malformed { .. })));
+    }
+
+    #[test]
+    fn test_references_and_other_elements() {
+        let mut bytes = Vec::new();
+        let mut stream = Stream { out: &mut bytes };
+        stream.int2(HEADER, &[STREAM_VERSION]).unwrap();
+        stream.int2(BGNLIB, &[0; 12]).unwrap();
+        stream.real8(UNITS, &[1e-3, 1e-9]).unwrap();
+        stream.int2(BGNSTR, &[0; 12]).unwrap();
+        stream.ascii(STRNAME, "LABELS").unwrap();
+        stream.record(TEXT, NO_DATA, &[]).unwrap();
+        stream.int2(LAYER, &[63]).unwrap();
+        stream.int4(XY, &[0, 0]).unwrap();
+        stream.record(ENDEL, NO_DATA, &[]).unwrap();
+        stream.record(ENDSTR, NO_DATA, &[]).unwrap();
+        let mut with_reference = stream.out.clone();
+        stream.record(ENDLIB, NO_DATA, &[]).unwrap();
+        let library = Library::read(&mut bytes.as_slice()).unwrap();
+        assert_eq!(library.cells[0].name, "LABELS");
+        assert!(library.cells[0].polygons.is_empty());
+
+        let mut stream = Stream { out: &mut with_reference };
+        stream.int2(BGNSTR, &[0; 12]).unwrap();
+        stream.ascii(STRNAME, "ARRAY").unwrap();
+        stream.record(SREF, NO_DATA, &[]).unwrap();
+        let error = Library::read(&mut with_reference.as_slice()).unwrap_err();
+        assert!(error.to_string().contains("flatten"), "{error}");
+    }
+
+    #[test]
+    fn test_invalid_libraries_are_not_written() {
+        let mut library = Library::new("PCSEL", Length::nanometers(1.0));
+        library.cells.push(Cell {
+            name: "TOP".into(),
+            polygons: vec![Polygon {
+                layer: GdsLayer::new(1, 0),
+                points: vec![(0, 0), (1, 1)],
+            }],
+        });
+        assert!(matches!(library.write(&mut Vec::new()), Err(LayoutError::Options(_))));
+        library.cells[0].polygons.clear();
+        library.name = "réseau".into();
+        assert!(matches!(library.write(&mut Vec::new()), Err(LayoutError::Options(_))));
+    }
+}
@@ -0,0 +1,237 @@
+//! crates/layout/src/import.rs
+//! Supercells read back from fabricated or corrected layouts.
+use core::nalgebra::Matrix2;
+use core::units::Length;
+use core::vectorial::Vector2;
+use phc::base::{AtomInCell, UnitCellBase};
+use phc::crystal_structure::PhotonicCrystal;
+
+use super::gdsii::{Cell, GdsLayer, Library};
+use super::polygon::fit_shape;
+use super::LayoutError;
+
+/// Which holes of a layout make up the supercell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportOptions {
+    /// Cell holding the holes, by default the only cell of the library.
+    pub cell: Option<String>,
+    pub layer: GdsLayer,
+    /// Position in the layout of lattice point (0, 0) of the supercell.
+    pub origin: (Length, Length),
+    /// Number of primitive cells of the supercell along `a1` and `a2`.
+    pub repeats: (usize, usize),
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            cell: None,
+            layer: GdsLayer::new(1, 0),
+            origin: (Length::meters(0.0), Length::meters(0.0)),
+            repeats: (1, 1),
+        }
+    }
+}
+
+/// Reads the holes of one supercell of `design` from `library`.
+///
+/// Each polygon whose centroid lies in the supercell becomes an atom of the shape fitted by
+/// [`fit_shape`]. Materials, heights and sidewall angles are taken from the nearest atom of
+/// `design`. Layouts draw the top surface of a layer of `thickness`, as [`crate::export`]
+/// does, so the shapes of tilted holes are widened back to their mid-height cross-section.
+pub fn import_supercell(
+    library: &Library,
+    design: &PhotonicCrystal,
+    thickness: Length,
+    options: &ImportOptions,
+) -> Result<PhotonicCrystal, LayoutError> {
+    let cell = find_cell(library, options.cell.as_deref())?;
+    let (nx, ny) = options.repeats;
+    if nx == 0 || ny == 0 {
+        return Err(LayoutError::Options(format!("supercell repeats must be positive, found {nx} x {ny}")));
+    }
+    if design.base.atoms.is_empty() {
+        return Err(LayoutError::Options("the design crystal has no atoms to match".into()));
+    }
+    let mut supercell = design.supercell(nx, ny);
+    supercell.base.atoms.clear();
+    let lattice = supercell.lattice.lattice();
+    let (a1, a2) = lattice.in_plane_vectors();
+    let to_fractional = Matrix2::from_columns(&[a1, a2])
+        .try_inverse()
+        .expect("lattice vectors must be linearly independent");
+    let scale = a1.norm();
+    let top = 0.5 * thickness.as_meters() / lattice.a3.norm();
+    let unit = library.database_unit.as_meters();
+    let origin = Vector2::new(options.origin.0.as_meters(), options.origin.1.as_meters());
+
+    let mut atoms = Vec::new();
+    for polygon in cell.polygons.iter().filter(|polygon| polygon.layer == options.layer) {
+        let points: Vec<(f64, f64)> = polygon
+            .points
+            .iter()
+            .map(|&(x, y)| (x as f64 * unit - origin.x, y as f64 * unit - origin.y))
+            .collect();
+        let (shape, (x, y)) = fit_shape(&points);
+        let fractional = to_fractional * Vector2::new(x, y);
+        if !(0.0..1.0).contains(&fractional.x) || !(0.0..1.0).contains(&fractional.y) {
+            continue;
+        }
+        let nearest = nearest_atom(design, (fractional.x * nx as f64, fractional.y * ny as f64));
+        let shape = shape.scaled(1.0 / scale);
+        let dz = (top - nearest.center.2) * lattice.a3.norm() / scale;
+        atoms.push(AtomInCell {
+            shape: if nearest.sidewall_angle == 0.0 { shape } else { shape.dilated(dz * nearest.sidewall_angle.tan()) },
+            center: (fractional.x, fractional.y, nearest.center.2),
+            material: nearest.material.clone(),
+            sidewall_angle: nearest.sidewall_angle,
+        });
+    }
+    if atoms.is_empty() {
+        return Err(LayoutError::NotFound(format!(
+            "no hole of layer {}/{} of cell {} lies in the supercell",
+            options.layer.layer, options.layer.datatype, cell.name
+        )));
+    }
+    Ok(PhotonicCrystal::new(
+        supercell.lattice,
+        UnitCellBase {
+            atoms,
+            background_material: supercell.base.background_material,
+        },
+    ))
+}
+
+fn find_cell<'a>(library: &'a Library, name: Option<&str>) -> Result<&'a Cell, LayoutError> {
+    match name {
+        Some(name) => library.cell(name).ok_or_else(|| LayoutError::NotFound(format!("no cell named {name}"))),
+        None => match library.cells.as_slice() {
+            [cell] => Ok(cell),
+            cells => Err(LayoutError::Options(format!(
+                "the library has {} cells, choose one of them by name",
+                cells.len()
+            ))),
+        },
+    }
+}
+
+/// Atom of `design` nearest to the fractional position `(s, t)` of its primitive lattice.
+fn nearest_atom(design: &PhotonicCrystal, (s, t): (f64, f64)) -> &AtomInCell {
+    let (a1, a2) = design.lattice.lattice().normalized_in_plane_vectors();
+    let distance = |atom: &AtomInCell| {
+        let (ds, dt) = (s - atom.center.0, t - atom.center.1);
+        (a1 * (ds - ds.round()) + a2 * (dt - dt.round())).norm()
+    };
+    design
+        .base
+        .atoms
+        .iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .expect("the design has atoms")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::material::Material;
+    use core::shapes::HoleShape;
+    use phc::lattice::LatticeType;
+
+    use crate::export::{export_layout, DeviceArea, ExportOptions, HoleLayer};
+    use crate::gdsii::Polygon;
+
+    fn design() -> PhotonicCrystal {
+        let lattice = LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: 0.12 }, (0.25, 0.25, 0.0), Material::new_from_eps(1.0));
+        base.add_atom(
+            HoleShape::Ellipse {
+                radius_x: 0.15,
+                radius_y: 0.08,
+                rotation: 0.3,
+            },
+            (0.7, 0.6, 0.1),
+            Material::new_from_eps(2.0),
+        );
+        base.atoms[1].sidewall_angle = 0.05;
+        PhotonicCrystal::new(lattice, base)
+    }
+
+    #[test]
+    fn test_exported_supercell_is_read_back() {
+        let design = design();
+        let thickness = Length::nanometers(118.0);
+        let options = ExportOptions {
+            area: DeviceArea::Cells { nx: 3, ny: 2 },
+            database_unit: Length::nanometers(0.001),
+            circle_vertices: 512,
+            ..ExportOptions::default()
+        };
+        let layer = HoleLayer {
+            crystal: &design,
+            thickness,
+            layer: GdsLayer::new(1, 0),
+        };
+        let library = export_layout(&[layer], &options).unwrap();
+        let import = ImportOptions {
+            repeats: (3, 2),
+            ..ImportOptions::default()
+        };
+        let imported = import_supercell(&library, &design, thickness, &import).unwrap();
+        let expected = design.supercell(3, 2);
+        assert_eq!(imported.lattice, expected.lattice);
+        assert_eq!(imported.base.atoms.len(), expected.base.atoms.len());
+        for (atom, expected) in imported.base.atoms.iter().zip(&expected.base.atoms) {
+            assert!((atom.center.0 - expected.center.0).abs() < 1e-6, "{atom:?}");
+            assert!((atom.center.1 - expected.center.1).abs() < 1e-6, "{atom:?}");
+            assert_eq!((atom.center.2, &atom.material, atom.sidewall_angle), (expected.center.2, &expected.material, expected.sidewall_angle));
+            let ((ax, ay), (ex, ey)) = (atom.shape.half_extents(), expected.shape.half_extents());
+            assert!((ax - ex).abs() < 1e-4 && (ay - ey).abs() < 1e-4, "{:?} != {:?}", atom.shape, expected.shape);
+        }
+    }
+
+    #[test]
+    fn test_origin_and_missing_holes() {
+        let design = design();
+        let mut library = Library::new("LIB", Length::nanometers(1.0));
+        let circle = crate::polygon::shape_vertices(&HoleShape::Circle { radius: 30.0 }, 64, true);
+        let hole = |x: f64, y: f64| Polygon {
+            layer: GdsLayer::new(2, 0),
+            points: circle.iter().map(|&(dx, dy)| ((x + dx).round() as i32, (y + dy).round() as i32)).collect(),
+        };
+        library.cells.push(Cell {
+            name: "TOP".into(),
+            polygons: vec![hole(10_100.0, 5_050.0), hole(9_000.0, 5_000.0)],
+        });
+        let options = ImportOptions {
+            layer: GdsLayer::new(2, 0),
+            origin: (Length::micrometers(10.0), Length::micrometers(5.0)),
+            ..ImportOptions::default()
+        };
+        let imported = import_supercell(&library, &design, Length::nanometers(118.0), &options).unwrap();
+        assert_eq!(imported.base.atoms.len(), 1);
+        let atom = &imported.base.atoms[0];
+        assert_eq!(atom.material, design.base.atoms[0].material);
+        assert!(matches!(atom.shape, HoleShape::Circle { radius } if (radius - 30.0 / 295.0).abs() < 1e-3));
+
+        let missing = ImportOptions {
+            layer: GdsLayer::new(3, 0),
+            ..options.clone()
+        };
+        let error = import_supercell(&library, &design, Length::nanometers(118.0), &missing).unwrap_err();
+        assert!(matches!(error, LayoutError::NotFound(_)));
+        let unnamed = ImportOptions {
+            cell: Some("OTHER".into()),
+            ..options.clone()
+        };
+        assert!(import_supercell(&library, &design, Length::nanometers(118.0), &unnamed).is_err());
+        library.cells.push(Cell {
+            name: "OTHER".into(),
+            polygons: Vec::new(),
+        });
+        assert!(matches!(
+            import_supercell(&library, &design, Length::nanometers(118.0), &options),
+            Err(LayoutError::Options(_))
+        ));
+    }
+}
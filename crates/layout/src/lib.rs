@@ -0,0 +1,57 @@
+//! crates/layout/src/lib.rs
+//!
+//! Mask layouts of photonic crystals for lithography.
+//!
+//! [`export`] tiles crystals over a finite device area and writes their holes as GDSII
+//! polygons, [`import`] reads a hole layer back into a supercell that can be simulated.
+use std::fmt;
+
+pub mod export;
+pub mod gdsii;
+pub mod import;
+pub mod polygon;
+
+pub use export::{export_layout, export_waveguide, DeviceArea, ExportOptions, HoleLayer};
+pub use gdsii::{GdsLayer, Library};
+pub use import::{import_supercell, ImportOptions};
+
+/// Reasons why a layout cannot be written or read.
+#[derive(Debug)]
+pub enum LayoutError {
+    Io(std::io::Error),
+    /// The stream is not valid GDSII.
+    Malformed { offset: usize, reason: String },
+    /// The stream uses features this crate does not read.
+    Unsupported(String),
+    /// The options or the structure cannot be laid out.
+    Options(String),
+    /// The requested cell or holes are not in the layout.
+    NotFound(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Io(error) => write!(f, "{error}"),
+            LayoutError::Malformed { offset, reason } => write!(f, "malformed GDSII at byte {offset}: {reason}"),
+            LayoutError::Unsupported(reason) => write!(f, "unsupported layout: {reason}"),
+            LayoutError::Options(reason) => write!(f, "{reason}"),
+            LayoutError::NotFound(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LayoutError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for LayoutError {
+    fn from(error: std::io::Error) -> Self {
+        LayoutError::Io(error)
+    }
+}
@@ -0,0 +1,167 @@
+//! crates/layout/src/polygon.rs
+//! Conversion between hole shapes and polygons.
+use std::f64::consts::PI;
+
+use core::shapes::HoleShape;
+
+/// Vertices of `shape` centered at the origin, counter-clockwise, in the units of the shape.
+///
+/// Circles and ellipses get `vertices` vertices. With `preserve_area`, they are inscribed in a
+/// slightly larger curve so that the polygon keeps the area of the shape, rather than cutting
+/// its edges inside.
+pub fn shape_vertices(shape: &HoleShape, vertices: usize, preserve_area: bool) -> Vec<(f64, f64)> {
+    let (radius_x, radius_y, rotation) = match *shape {
+        HoleShape::Circle { radius } => (radius, radius, 0.0),
+        HoleShape::Ellipse { radius_x, radius_y, rotation } => (radius_x, radius_y, rotation),
+        HoleShape::Square { .. } | HoleShape::Rectangle { .. } => {
+            let (hx, hy) = shape.half_extents();
+            return vec![(-hx, -hy), (hx, -hy), (hx, hy), (-hx, hy)];
+        }
+    };
+    let n = vertices as f64;
+    // Area of a regular n-gon inscribed in the unit circle is n sin(2π/n) / 2.
+    let scale = if preserve_area { (2.0 * PI / (n * (2.0 * PI / n).sin())).sqrt() } else { 1.0 };
+    let (sin, cos) = f64::sin_cos(rotation);
+    (0..vertices)
+        .map(|k| {
+            let angle = 2.0 * PI * k as f64 / n;
+            let (u, v) = (scale * radius_x * angle.cos(), scale * radius_y * angle.sin());
+            (cos * u - sin * v, sin * u + cos * v)
+        })
+        .collect()
+}
+
+/// Area, centroid and central second moments `(Ixx, Iyy, Ixy)` of a simple polygon, with the
+/// area positive for counter-clockwise vertices.
+pub fn moments(points: &[(f64, f64)]) -> (f64, (f64, f64), (f64, f64, f64)) {
+    let (mut area, mut cx, mut cy, mut xx, mut yy, mut xy) = (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        let cross = x0 * y1 - x1 * y0;
+        area += cross;
+        cx += (x0 + x1) * cross;
+        cy += (y0 + y1) * cross;
+        xx += (x0 * x0 + x0 * x1 + x1 * x1) * cross;
+        yy += (y0 * y0 + y0 * y1 + y1 * y1) * cross;
+        xy += (x0 * y1 + 2.0 * x0 * y0 + 2.0 * x1 * y1 + x1 * y0) * cross;
+    }
+    area /= 2.0;
+    let (cx, cy) = (cx / (6.0 * area), cy / (6.0 * area));
+    // Moments about the origin, shifted to the centroid.
+    let xx = xx / 12.0 - area * cx * cx;
+    let yy = yy / 12.0 - area * cy * cy;
+    let xy = xy / 24.0 - area * cx * cy;
+    (area, (cx, cy), (xx, yy, xy))
+}
+
+/// Relative tolerance under which two lengths of a fitted shape are taken as equal.
+const SAME: f64 = 1e-3;
+
+/// Shape that best describes `points` and its center, in the units of the points.
+///
+/// Axis-aligned rectangles are recognized as such. Other polygons become the ellipse, or the
+/// circle, with the same area and second moments, which is exact for discretized circles and
+/// ellipses drawn with an area-preserving polygon.
+pub fn fit_shape(points: &[(f64, f64)]) -> (HoleShape, (f64, f64)) {
+    let (area, center, (xx, yy, xy)) = moments(points);
+    let (area, xx, yy, xy) = (area.abs(), xx.abs(), yy.abs(), xy * area.signum());
+
+    let (min_x, max_x) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p.0), hi.max(p.0)));
+    let (min_y, max_y) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p.1), hi.max(p.1)));
+    let (width, height) = (max_x - min_x, max_y - min_y);
+    if points.len() == 4 && (area - width * height).abs() <= SAME * area {
+        let shape = if (width - height).abs() <= SAME * width.max(height) {
+            HoleShape::Square { side: 0.5 * (width + height) }
+        } else {
+            HoleShape::Rectangle { width, height }
+        };
+        return (shape, ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0));
+    }
+
+    // An ellipse with semi-axes a and b has principal moments (π a³ b / 4, π a b³ / 4).
+    let mean = (xx + yy) / 2.0;
+    let spread = ((xx - yy) / 2.0).hypot(xy);
+    let (major, minor) = (mean + spread, mean - spread);
+    let shape = if spread <= SAME * mean {
+        HoleShape::Circle {
+            radius: (area / PI).sqrt(),
+        }
+    } else {
+        let ratio = (major / minor).sqrt();
+        let radius_x = (area * ratio / PI).sqrt();
+        // The major axis is the principal direction of [[xx, xy], [xy, yy]] with the largest moment.
+        let rotation = 0.5 * f64::atan2(2.0 * xy, xx - yy);
+        HoleShape::Ellipse {
+            radius_x,
+            radius_y: area / (PI * radius_x),
+            rotation,
+        }
+    };
+    (shape, center)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9 * a.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_vertices_preserve_area() {
+        let circle = HoleShape::Circle { radius: 0.2 };
+        let (area, center, _) = moments(&shape_vertices(&circle, 16, true));
+        assert!(close(area, circle.area()) && close(center.0, 0.0) && close(center.1, 0.0));
+        let (inscribed, _, _) = moments(&shape_vertices(&circle, 16, false));
+        assert!(inscribed < circle.area());
+        let square = shape_vertices(&HoleShape::Square { side: 0.3 }, 64, true);
+        assert_eq!(square.len(), 4);
+        assert!(close(moments(&square).0, 0.09));
+    }
+
+    #[test]
+    fn test_shapes_are_fitted_back() {
+        let shapes = [
+            HoleShape::Circle { radius: 0.16 },
+            HoleShape::Ellipse {
+                radius_x: 0.2,
+                radius_y: 0.1,
+                rotation: 0.4,
+            },
+            HoleShape::Rectangle { width: 0.3, height: 0.1 },
+            HoleShape::Square { side: 0.25 },
+        ];
+        for shape in shapes.clone() {
+            let points: Vec<(f64, f64)> = shape_vertices(&shape, 256, true)
+                .into_iter()
+                .map(|(x, y)| (x + 1.5, y - 0.5))
+                .collect();
+            let (fitted, center) = fit_shape(&points);
+            assert!((center.0 - 1.5).abs() < 1e-12 && (center.1 + 0.5).abs() < 1e-12, "{center:?}");
+            match (&shape, &fitted) {
+                (
+                    HoleShape::Ellipse { radius_x, radius_y, rotation },
+                    HoleShape::Ellipse {
+                        radius_x: fx,
+                        radius_y: fy,
+                        rotation: fr,
+                    },
+                ) => {
+                    assert!((radius_x - fx).abs() < 1e-4 && (radius_y - fy).abs() < 1e-4, "{fitted:?}");
+                    assert!((rotation - fr).abs() < 1e-9, "{fitted:?}");
+                }
+                (HoleShape::Circle { radius }, HoleShape::Circle { radius: r }) => assert!((radius - r).abs() < 1e-12),
+                _ => {
+                    let ((fx, fy), (hx, hy)) = (fitted.half_extents(), shape.half_extents());
+                    assert!((fx - hx).abs() < 1e-12 && (fy - hy).abs() < 1e-12, "{fitted:?}");
+                    assert_eq!(std::mem::discriminant(&fitted), std::mem::discriminant(&shape));
+                }
+            }
+        }
+        // Clockwise polygons give the same shape.
+        let mut clockwise = shape_vertices(&shapes[1], 256, true);
+        clockwise.reverse();
+        assert!(matches!(fit_shape(&clockwise).0, HoleShape::Ellipse { rotation, .. } if (rotation - 0.4).abs() < 1e-9));
+    }
+}
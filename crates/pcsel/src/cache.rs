@@ -0,0 +1,149 @@
+//! crates/pcsel/src/cache.rs
+//! Results of sweep points kept on disk, so that an interrupted sweep resumes where it stopped.
+//!
+//! A point is keyed by the hash of the canonical text of its device, together with the
+//! pipeline version and the names of the results, so that the same device is found again
+//! whichever sweep, order or base file it came from, but not once the solvers or the results
+//! change. Each entry keeps that text to tell hash collisions apart, and is written to a
+//! temporary file first so that an interrupted write leaves no partial entry behind.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::PIPELINE_VERSION;
+
+/// Results of one point, matching `report::sweep_quantities`, or why it failed.
+pub type Outcome = Result<Vec<Option<f64>>, String>;
+
+/// 64-bit FNV-1a hash of `text`. Unlike the hashers of `std` it does not change between Rust
+/// releases, so a cache stays valid across builds.
+pub fn parameter_hash(text: &str) -> u64 {
+    text.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    /// Entries written before versions were kept have version 0.
+    #[serde(default)]
+    version: u32,
+    device: String,
+    quantities: Vec<String>,
+    /// NaN where a result is missing.
+    #[serde(default)]
+    values: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// A directory of results of sweep points.
+pub struct Cache {
+    directory: PathBuf,
+    quantities: Vec<String>,
+}
+
+impl Cache {
+    /// Opens the cache in `directory`, creating it if needed, for results named `quantities`.
+    pub fn open(directory: impl Into<PathBuf>, quantities: Vec<String>) -> io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Cache { directory, quantities })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, device: &str) -> PathBuf {
+        let key = format!("pipeline {PIPELINE_VERSION}\n{}\n{device}", self.quantities.join(","));
+        self.directory.join(format!("{:016x}.toml", parameter_hash(&key)))
+    }
+
+    /// The stored outcome of `device`, the canonical text of a device. Unreadable entries and
+    /// those of other devices, other quantities or other pipeline versions are ignored.
+    pub fn get(&self, device: &str) -> Option<Outcome> {
+        let text = std::fs::read_to_string(self.path(device)).ok()?;
+        let entry: Entry = toml::from_str(&text).ok()?;
+        if entry.version != PIPELINE_VERSION || entry.device != device || entry.quantities != self.quantities {
+            return None;
+        }
+        match entry.error {
+            Some(error) => Some(Err(error)),
+            None if entry.values.len() == self.quantities.len() => {
+                Some(Ok(entry.values.iter().map(|&value| Some(value).filter(|value| !value.is_nan())).collect()))
+            }
+            None => None,
+        }
+    }
+
+    /// Stores the outcome of `device`, replacing any previous entry.
+    pub fn put(&self, device: &str, outcome: &Outcome) -> io::Result<()> {
+        // Distinct names let threads that solve the same device write at once.
+        static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+        let (values, error) = match outcome {
+            Ok(values) => (values.iter().map(|value| value.unwrap_or(f64::NAN)).collect(), None),
+            Err(error) => (Vec::new(), Some(error.clone())),
+        };
+        let entry = Entry {
+            version: PIPELINE_VERSION,
+            device: device.to_string(),
+            quantities: self.quantities.clone(),
+            values,
+            error,
+        };
+        let text = toml::to_string(&entry).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let path = self.path(device);
+        let partial = path.with_extension(format!(
+            "{}.{}.partial",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::write(&partial, text)?;
+        std::fs::rename(&partial, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_is_stable() {
+        assert_eq!(parameter_hash(""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(parameter_hash("a"), 0xAF63_DC4C_8601_EC8C);
+        assert_ne!(parameter_hash("thickness = \"100 nm\""), parameter_hash("thickness = \"101 nm\""));
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let directory = std::env::temp_dir().join(format!("pcsel-cache-{}", std::process::id()));
+        let quantities = vec!["effective_index".to_string(), "pc_confinement".to_string()];
+        let cache = Cache::open(&directory, quantities.clone()).unwrap();
+        assert_eq!(cache.get("device a"), None);
+
+        cache.put("device a", &Ok(vec![Some(f64::INFINITY), None])).unwrap();
+        cache.put("device b", &Err("slab solver failed".into())).unwrap();
+        assert_eq!(cache.get("device a"), Some(Ok(vec![Some(f64::INFINITY), None])));
+        assert_eq!(cache.get("device b"), Some(Err("slab solver failed".into())));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 2);
+
+        // Entries of other quantities, other devices, older pipelines or garbage are misses.
+        let other = Cache::open(&directory, vec!["effective_index".to_string()]).unwrap();
+        assert_ne!(other.path("device a"), cache.path("device a"));
+        std::fs::copy(cache.path("device a"), other.path("device a")).unwrap();
+        assert_eq!(other.get("device a"), None);
+        let text = std::fs::read_to_string(cache.path("device b")).unwrap();
+        std::fs::write(cache.path("device b"), text.replace(&format!("version = {PIPELINE_VERSION}"), "version = 1")).unwrap();
+        assert_eq!(cache.get("device b"), None);
+        std::fs::remove_file(other.path("device a")).unwrap();
+        std::fs::rename(cache.path("device a"), cache.path("device c")).unwrap();
+        assert_eq!(cache.get("device c"), None);
+        std::fs::write(cache.path("device b"), "values = [").unwrap();
+        assert_eq!(cache.get("device b"), None);
+        std::fs::remove_dir_all(directory).ok();
+    }
+}
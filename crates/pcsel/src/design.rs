@@ -0,0 +1,150 @@
+//! crates/pcsel/src/design.rs
+//! Which points of parameter space a sweep visits.
+use toml::Value;
+
+use phc::disorder::SplitMix64;
+
+use crate::overrides::{Axis, OverrideError};
+
+/// How the points of a sweep are chosen from its axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Design {
+    /// Every combination of the values of the axes.
+    Grid,
+    /// `samples` points such that each axis, cut into `samples` equal bins, has one point per
+    /// bin. The bins are paired at random, reproducibly from `seed`.
+    LatinHypercube { samples: usize, seed: u64 },
+}
+
+impl Design {
+    /// Name of the design in result files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Design::Grid => "grid",
+            Design::LatinHypercube { .. } => "latin_hypercube",
+        }
+    }
+}
+
+/// The points of a sweep, laid out along the dimensions of its design.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub design: Design,
+    pub axes: Vec<Axis>,
+    /// Names of the dimensions: the paths of the axes for a grid, `sample` otherwise.
+    pub dimensions: Vec<String>,
+    /// Number of points along each dimension.
+    pub shape: Vec<usize>,
+    /// Values of the axes at each point, in row-major order of `shape`.
+    pub points: Vec<Vec<Value>>,
+}
+
+impl Plan {
+    pub fn new(axes: Vec<Axis>, design: Design) -> Result<Self, OverrideError> {
+        let (dimensions, shape, points) = match design {
+            Design::Grid => {
+                let values = axes.iter().map(Axis::grid).collect::<Result<Vec<_>, _>>()?;
+                let points = values.iter().fold(vec![Vec::new()], |points, values| {
+                    points
+                        .iter()
+                        .flat_map(|point| {
+                            values.iter().map(move |value| {
+                                let mut point = point.clone();
+                                point.push(value.clone());
+                                point
+                            })
+                        })
+                        .collect()
+                });
+                let dimensions = axes.iter().map(|axis| axis.path.clone()).collect();
+                (dimensions, values.iter().map(Vec::len).collect(), points)
+            }
+            Design::LatinHypercube { samples, seed } => {
+                if samples == 0 {
+                    return Err(OverrideError::Syntax("a Latin hypercube needs at least one sample".into()));
+                }
+                let mut rng = SplitMix64::new(seed);
+                let columns: Vec<Vec<Value>> = axes
+                    .iter()
+                    .map(|axis| {
+                        // Fisher-Yates shuffle of the bins, then a uniform point within each.
+                        let mut bins: Vec<usize> = (0..samples).collect();
+                        for i in (1..samples).rev() {
+                            bins.swap(i, (rng.next_u64() % (i as u64 + 1)) as usize);
+                        }
+                        bins.iter()
+                            .map(|&bin| axis.sample((bin as f64 + rng.next_f64()) / samples as f64))
+                            .collect()
+                    })
+                    .collect();
+                let points = (0..samples).map(|i| columns.iter().map(|column| column[i].clone()).collect()).collect();
+                (vec!["sample".to_string()], vec![samples], points)
+            }
+        };
+        Ok(Plan {
+            design,
+            axes,
+            dimensions,
+            shape,
+            points,
+        })
+    }
+
+    /// Index of the dimension along which the values of axis `axis` run.
+    #[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+    pub fn dimension_of(&self, axis: usize) -> usize {
+        match self.design {
+            Design::Grid => axis,
+            Design::LatinHypercube { .. } => 0,
+        }
+    }
+
+    /// Values of axis `axis` along its dimension.
+    #[cfg_attr(not(feature = "hdf5"), allow(dead_code))]
+    pub fn coordinates(&self, axis: usize) -> Vec<Value> {
+        match self.design {
+            Design::Grid => self.axes[axis].grid().expect("grid axes have values"),
+            Design::LatinHypercube { .. } => self.points.iter().map(|point| point[axis].clone()).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn axes(texts: &[&str]) -> Vec<Axis> {
+        texts.iter().map(|text| text.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_grid_covers_every_combination() {
+        let plan = Plan::new(axes(&["a=1:2:2", "b=0.1:0.3:3"]), Design::Grid).unwrap();
+        assert_eq!(plan.shape, [2, 3]);
+        assert_eq!(plan.dimensions, ["a", "b"]);
+        assert_eq!(plan.points.len(), 6);
+        assert_eq!(plan.points[1], [Value::Integer(1), plan.coordinates(1)[1].clone()]);
+        assert_eq!(plan.points[3][0], Value::Integer(2));
+        assert_eq!(plan.dimension_of(1), 1);
+        assert!(Plan::new(axes(&["a=1:2"]), Design::Grid).is_err());
+    }
+
+    #[test]
+    fn test_latin_hypercube_fills_every_bin() {
+        let design = Design::LatinHypercube { samples: 8, seed: 7 };
+        let plan = Plan::new(axes(&["r=0.0:1.0", "t=100nm:200nm", "m=GaAs,InP"]), design).unwrap();
+        assert_eq!((plan.shape.as_slice(), plan.dimensions.as_slice()), (&[8][..], &["sample".to_string()][..]));
+        assert_eq!(plan.dimension_of(2), 0);
+
+        let mut bins: Vec<usize> = plan.points.iter().map(|point| (point[0].as_float().unwrap() * 8.0) as usize).collect();
+        bins.sort_unstable();
+        assert_eq!(bins, (0..8).collect::<Vec<_>>());
+        let materials = plan.points.iter().filter(|point| point[2].as_str() == Some("GaAs")).count();
+        assert_eq!(materials, 4);
+        assert_eq!(plan.coordinates(1).len(), 8);
+
+        assert_eq!(Plan::new(plan.axes.clone(), design).unwrap(), plan);
+        assert_ne!(Plan::new(plan.axes.clone(), Design::LatinHypercube { samples: 8, seed: 8 }).unwrap(), plan);
+        assert!(Plan::new(plan.axes.clone(), Design::LatinHypercube { samples: 0, seed: 0 }).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! crates/pcsel/src/files.rs
+//! HDF5 result files, in the layout of the `io` crate.
+use std::fmt::Write as _;
+use std::path::Path;
+
+use ndarray::{Array1, Array2, Array4, ArrayD, IxDyn};
+use toml::Value;
+
+use core::units::Length;
+use io::schema::SchemaError;
+use io::sweep::{Coordinate, CoordinateValues, Quantity};
+use io::{CwtResult, EpsilonGrid, FourierTable, SimulationFile, SlabResult, SweepDataset, SCHEMA_VERSION};
+use waveguide::device::Device;
+use waveguide::layers::LayerType;
+
+use crate::overrides::display_value;
+use crate::pipeline::Results;
+use crate::report::{sweep_quantities, REPORTED_ORDERS};
+use crate::sweep::Sweep;
+
+/// Writes the stack of `device` and `results` to a new simulation file at `path`.
+pub fn write_results(path: &Path, device: &Device, results: &Results) -> Result<(), SchemaError> {
+    let file = SimulationFile::create(path, &results.waveguide)?;
+    file.write_slab(
+        "fundamental",
+        &SlabResult {
+            wavelength: results.wavelength,
+            mode: results.slab.clone(),
+        },
+    )?;
+    for layer in &results.fourier {
+        let (orders, coefficients) = layer.coefficients.iter().unzip();
+        let table = FourierTable {
+            layer: layer.layer.clone(),
+            orders,
+            coefficients,
+        };
+        file.write_fourier(&layer.layer, &table)?;
+    }
+    if let Ok(solution) = &results.cwt {
+        // The crystal is infinite, so the envelopes are uniform and sampled once at the origin.
+        let modes = &solution.modes;
+        let result = CwtResult {
+            wavelength: results.wavelength,
+            eigenvalues: modes.iter().map(|mode| mode.eigenvalue).collect(),
+            eigenvectors: Array2::from_shape_fn((4, modes.len()), |(wave, mode)| modes[mode].amplitudes[wave]),
+            envelopes: Array4::from_shape_fn((modes.len(), 4, 1, 1), |(mode, wave, _, _)| modes[mode].amplitudes[wave]),
+            x: Array1::zeros(1),
+            y: Array1::zeros(1),
+        };
+        file.write_cwt(&results.fourier[0].layer, &result)?;
+    }
+    if let Some(grid) = &results.grid {
+        let mut epsilon = EpsilonGrid::uniform(grid.epsilon.clone(), grid.size);
+        epsilon.description = format!("{} at {}", device.name, results.wavelength);
+        file.write_grid("epsilon", &epsilon)?;
+    }
+    Ok(())
+}
+
+/// Numbers as they are, lengths in meters, anything else as text.
+fn coordinate_values(values: &[Value]) -> CoordinateValues {
+    let numbers: Option<Vec<f64>> = values
+        .iter()
+        .map(|value| match value {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        })
+        .collect();
+    let lengths = || -> Option<Vec<f64>> {
+        values
+            .iter()
+            .map(|value| Some(value.as_str()?.parse::<Length>().ok()?.as_meters()))
+            .collect()
+    };
+    match (numbers, lengths()) {
+        (Some(values), _) => CoordinateValues::Numbers {
+            values,
+            units: "1".into(),
+        },
+        (None, Some(values)) => CoordinateValues::Numbers {
+            values,
+            units: "m".into(),
+        },
+        (None, None) => CoordinateValues::Labels(values.iter().map(display_value).collect()),
+    }
+}
+
+/// Writes the stack of the base `device` and the results of `sweep` to a new simulation file
+/// at `path`, as the sweep `name`.
+pub fn write_sweep(path: &Path, name: &str, device: &Device, sweep: &Sweep) -> Result<(), SchemaError> {
+    let plan = &sweep.plan;
+    let shape = IxDyn(&plan.shape);
+    let array = |values: Vec<f64>| ArrayD::from_shape_vec(shape.clone(), values).expect("one outcome per point");
+    let dataset = SweepDataset {
+        design: plan.design.name().to_string(),
+        dimensions: plan.dimensions.clone(),
+        coordinates: plan
+            .axes
+            .iter()
+            .enumerate()
+            .map(|(index, axis)| Coordinate {
+                name: axis.path.clone(),
+                dimension: plan.dimension_of(index),
+                values: coordinate_values(&plan.coordinates(index)),
+            })
+            .collect(),
+        quantities: sweep_quantities()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, units))| Quantity {
+                name,
+                units: units.to_string(),
+                values: array(
+                    sweep
+                        .outcomes
+                        .iter()
+                        .map(|outcome| outcome.as_ref().ok().and_then(|values| values[index]).unwrap_or(f64::NAN))
+                        .collect(),
+                ),
+            })
+            .collect(),
+        errors: ArrayD::from_shape_vec(
+            shape.clone(),
+            sweep.outcomes.iter().map(|outcome| outcome.clone().err().unwrap_or_default()).collect(),
+        )
+        .expect("one outcome per point"),
+    };
+    SimulationFile::create(path, &device.waveguide)?.write_sweep(name, &dataset)
+}
+
+fn kind(layer: &LayerType) -> &'static str {
+    match layer {
+        LayerType::Simple { .. } => "simple",
+        LayerType::PhotonicCrystal { .. } => "photonic_crystal",
+        LayerType::Graded { .. } => "graded",
+    }
+}
+
+/// Summary of the simulation file at `path`.
+pub fn inspect(path: &Path) -> Result<String, SchemaError> {
+    let file = SimulationFile::open(path)?;
+    let mut text = String::new();
+    writeln!(text, "file        {} (schema version {SCHEMA_VERSION})", path.display()).unwrap();
+
+    let waveguide = file.waveguide()?;
+    writeln!(
+        text,
+        "structure   {} layers, {} in total",
+        waveguide.layers.len(),
+        waveguide.total_thickness()
+    )
+    .unwrap();
+    for layer in &waveguide.layers {
+        writeln!(text, "            {:<24} {:<16} {}", layer.name(), kind(layer), layer.thickness()).unwrap();
+    }
+
+    for name in file.entries("slab")? {
+        let slab = file.slab(&name)?;
+        writeln!(
+            text,
+            "slab        {name}: {}, n_eff = {:.6}, internal loss = {:.4} 1/cm",
+            slab.wavelength,
+            slab.mode.effective_index,
+            slab.mode.internal_loss().as_per_centimeter()
+        )
+        .unwrap();
+    }
+    for name in file.entries("grids")? {
+        let grid = file.grid(&name)?;
+        let (nx, ny, nz) = grid.epsilon.dim();
+        let range = grid.epsilon.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &eps| {
+            (low.min(eps), high.max(eps))
+        });
+        writeln!(
+            text,
+            "grid        {name}: {nx} x {ny} x {nz}, ε in [{:.4}, {:.4}], {}",
+            range.0, range.1, grid.description
+        )
+        .unwrap();
+    }
+    for name in file.entries("fourier")? {
+        let table = file.fourier(&name)?;
+        writeln!(
+            text,
+            "fourier     {name}: layer '{}', {} coefficients",
+            table.layer,
+            table.coefficients.len()
+        )
+        .unwrap();
+        for order in REPORTED_ORDERS {
+            if let Some(index) = table.orders.iter().position(|&o| o == order) {
+                let xi = table.coefficients[index];
+                writeln!(text, "            ξ({},{}) = {:.6} {:+.6}i", order.0, order.1, xi.re, xi.im).unwrap();
+            }
+        }
+    }
+    for name in file.entries("cwt")? {
+        let result = file.cwt(&name)?;
+        writeln!(text, "cwt         {name}: {}, {} modes", result.wavelength, result.eigenvalues.len()).unwrap();
+        for eigenvalue in &result.eigenvalues {
+            writeln!(text, "            δ + iα/2 = {:.4} {:+.4}i 1/m", eigenvalue.re, eigenvalue.im).unwrap();
+        }
+    }
+    for name in file.entries("sweeps")? {
+        let sweep = file.sweep(&name)?;
+        let shape: Vec<String> = sweep.shape().iter().map(usize::to_string).collect();
+        writeln!(
+            text,
+            "sweep       {name}: {} of {} points ({}), {} failed",
+            sweep.design,
+            sweep.errors.len(),
+            shape.join(" x "),
+            sweep.failures()
+        )
+        .unwrap();
+        for coordinate in &sweep.coordinates {
+            let range = match &coordinate.values {
+                CoordinateValues::Numbers { values, units } => {
+                    let (low, high) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+                        (low.min(value), high.max(value))
+                    });
+                    format!("in [{low}, {high}] {units}")
+                }
+                CoordinateValues::Labels(labels) => labels.join(", "),
+            };
+            writeln!(
+                text,
+                "            {} along {}: {range}",
+                coordinate.name, sweep.dimensions[coordinate.dimension]
+            )
+            .unwrap();
+        }
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design::{Design, Plan};
+    use crate::pipeline::{run, tests::TABLE_I};
+    use crate::sweep::run_sweep;
+
+    #[test]
+    fn test_results_round_trip() {
+        let mut device = Device::from_toml(TABLE_I).unwrap();
+        device.simulation.resolution = Some([4, 4, 8]);
+        let results = run(&device, true).unwrap();
+        let path = std::env::temp_dir().join(format!("pcsel-results-{}.h5", std::process::id()));
+        write_results(&path, &device, &results).unwrap();
+
+        let file = SimulationFile::open(&path).unwrap();
+        assert_eq!(file.slab("fundamental").unwrap().mode, results.slab);
+        assert_eq!(file.fourier("PC").unwrap().coefficients.len(), 441);
+        assert_eq!(file.grid("epsilon").unwrap().epsilon.dim(), (4, 4, 8));
+        let cwt = file.cwt("PC").unwrap();
+        let solution = results.cwt.as_ref().unwrap();
+        assert_eq!(cwt.eigenvalues.len(), 4);
+        assert_eq!(cwt.eigenvalues[0], solution.modes[0].eigenvalue);
+        assert_eq!(cwt.eigenvectors[[2, 1]], solution.modes[1].amplitudes[2]);
+        let summary = inspect(&path).unwrap();
+        assert!(summary.contains("structure   5 layers"), "{summary}");
+        assert!(summary.contains("fourier     PC: layer 'PC', 441 coefficients"), "{summary}");
+        assert!(summary.contains("cwt         PC: 980 nm, 4 modes"), "{summary}");
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_sweep_file() {
+        let device = Device::from_toml(TABLE_I).unwrap();
+        let axes = ["layers.PC.thickness=100 nm:120 nm:3", "layers.PC.crystal.base.atoms.0.shape.radius=0.1,0.6"];
+        let plan = Plan::new(axes.iter().map(|axis| axis.parse().unwrap()).collect(), Design::Grid).unwrap();
+        let sweep = run_sweep(&TABLE_I.parse().unwrap(), plan, None).unwrap();
+        let path = std::env::temp_dir().join(format!("pcsel-sweep-{}.h5", std::process::id()));
+        write_sweep(&path, "radius", &device, &sweep).unwrap();
+
+        let read = SimulationFile::open(&path).unwrap().sweep("radius").unwrap();
+        assert_eq!(read.shape(), [3, 2]);
+        assert_eq!(read.failures(), 3);
+        match &read.coordinates[0].values {
+            CoordinateValues::Numbers { values, units } => {
+                assert_eq!(units, "m");
+                assert!(values.iter().zip([1e-7, 1.1e-7, 1.2e-7]).all(|(value, expected)| (value - expected).abs() < 1e-18));
+            }
+            labels => panic!("expected lengths, found {labels:?}"),
+        }
+        let index = read.quantities.iter().find(|quantity| quantity.name == "effective_index").unwrap();
+        assert!(index.values[[1, 0]] > 3.0 && index.values[[1, 1]].is_nan());
+        let summary = inspect(&path).unwrap();
+        assert!(summary.contains("sweep       radius: grid of 6 points (3 x 2), 3 failed"), "{summary}");
+        std::fs::remove_file(path).ok();
+    }
+}
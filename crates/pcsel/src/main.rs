@@ -0,0 +1,305 @@
+//! crates/pcsel/src/main.rs
+//!
+//! `pcsel`: runs simulations of photonic crystal surface-emitting lasers described by device
+//! files (see `waveguide::device`).
+//!
+//! `run` solves the slab mode of the stack, the Fourier coefficients of its photonic crystal
+//! layers and the coupled-wave modes of the first one (square lattices only), `sweep` does so
+//! over a grid or a Latin hypercube of parameters and `inspect` summarizes a result file.
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use toml::Table;
+
+use waveguide::device::Device;
+
+mod cache;
+mod design;
+mod overrides;
+mod pipeline;
+mod report;
+mod sweep;
+#[cfg(feature = "hdf5")]
+mod files;
+
+use cache::Cache;
+use design::{Design, Plan};
+use overrides::{Assignment, Axis};
+
+#[derive(Parser)]
+#[command(name = "pcsel", version, about = "Simulate PCSELs described by TOML device files")]
+struct Cli {
+    /// Log the progress of the solvers to stderr.
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Solve a device and print a summary of the results.
+    ///
+    /// The coupled-wave modes are only computed when the first photonic crystal layer is a
+    /// square lattice; other lattices get the slab mode and Fourier coefficients only.
+    Run {
+        /// Device file.
+        device: PathBuf,
+        /// Where to write the results: a `.h5` file, or a directory for CSV tables.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Replace a value of the device file, e.g. `simulation.wavelength=985nm`.
+        #[arg(long = "set", value_name = "PATH=VALUE")]
+        set: Vec<Assignment>,
+    },
+    /// Solve a device over a grid or a Latin hypercube of parameter values.
+    Sweep {
+        /// Device file.
+        device: PathBuf,
+        /// Values of a parameter: evenly spaced, e.g. `layers.PC.thickness=100nm:140nm:5`, an
+        /// interval to sample, e.g. `layers.PC.thickness=100nm:140nm`, or a list, e.g.
+        /// `simulation.fourier_order=6,8,10`.
+        #[arg(long = "param", value_name = "PATH=VALUES", required = true)]
+        params: Vec<Axis>,
+        /// Replace a value of the device file for every point.
+        #[arg(long = "set", value_name = "PATH=VALUE")]
+        set: Vec<Assignment>,
+        /// Sample this many points of a Latin hypercube instead of every combination.
+        #[arg(long, value_name = "SAMPLES")]
+        latin_hypercube: Option<usize>,
+        /// Seed of the Latin hypercube.
+        #[arg(long, default_value_t = 0, requires = "latin_hypercube")]
+        seed: u64,
+        /// Number of points solved at once, one per core by default.
+        #[arg(short, long)]
+        jobs: Option<usize>,
+        /// Directory keeping the results of solved points, `OUTPUT` with the extension `.cache`
+        /// by default. Points found there are not solved again.
+        #[arg(long, value_name = "DIRECTORY")]
+        cache: Option<PathBuf>,
+        /// Solve every point and keep nothing.
+        #[arg(long, conflicts_with = "cache")]
+        no_cache: bool,
+        /// A `.h5` file with one array per result over the dimensions of the sweep, or a CSV
+        /// table with one row per point.
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print a summary of a result file written by `run`.
+    Inspect {
+        /// HDF5 result file.
+        file: PathBuf,
+    },
+}
+
+/// Whether `path` names an HDF5 file rather than a directory of CSV tables.
+fn is_hdf5(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("h5" | "hdf5"))
+}
+
+#[cfg(not(feature = "hdf5"))]
+const NO_HDF5: &str = "pcsel was built without HDF5 support, rebuild it with `--features hdf5`";
+
+/// Reads the device file at `path` as a TOML table with `assignments` applied, checking the
+/// file itself first so that its errors carry its own line numbers.
+fn load(path: &Path, assignments: &[Assignment]) -> Result<(String, Table), Box<dyn Error>> {
+    let text = std::fs::read_to_string(path).map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+    Device::from_toml(&text).map_err(|error| format!("{}: {error}", path.display()))?;
+    let mut document: Table = text.parse()?;
+    for assignment in assignments {
+        assignment.apply(&mut document)?;
+    }
+    Ok((text, document))
+}
+
+fn run(device_path: &Path, output: Option<&Path>, assignments: &[Assignment]) -> Result<(), Box<dyn Error>> {
+    let (text, document) = load(device_path, assignments)?;
+    let device = if assignments.is_empty() {
+        Device::from_toml(&text)?
+    } else {
+        Device::from_toml(&toml::to_string(&document)?)
+            .map_err(|error| format!("with the values given by --set: {}", sweep::device_message(error)))?
+    };
+
+    let hdf5 = output.is_some_and(is_hdf5);
+    let results = pipeline::run(&device, hdf5)?;
+    print!("{}", report::summary(&device.name, &results));
+
+    match output {
+        #[cfg(feature = "hdf5")]
+        Some(path) if hdf5 => files::write_results(path, &device, &results)?,
+        #[cfg(not(feature = "hdf5"))]
+        Some(_) if hdf5 => return Err(NO_HDF5.into()),
+        Some(directory) => report::write_csv(directory, &results)?,
+        None => {}
+    }
+    if let Some(path) = output {
+        println!("results written to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Options of the `sweep` command.
+struct SweepOptions<'a> {
+    design: Design,
+    jobs: Option<usize>,
+    cache: Option<&'a Path>,
+    no_cache: bool,
+}
+
+fn sweep(
+    device_path: &Path,
+    axes: &[Axis],
+    assignments: &[Assignment],
+    options: SweepOptions,
+    output: &Path,
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(not(feature = "hdf5"))]
+    if is_hdf5(output) {
+        return Err(NO_HDF5.into());
+    }
+    let (_, document) = load(device_path, assignments)?;
+    let plan = Plan::new(axes.to_vec(), options.design)?;
+    let cache = if options.no_cache {
+        None
+    } else {
+        let directory = options.cache.map_or_else(|| output.with_extension("cache"), Path::to_path_buf);
+        let quantities = report::sweep_quantities().into_iter().map(|(name, _)| name).collect();
+        Some(Cache::open(&directory, quantities).map_err(|error| format!("cannot open the cache {}: {error}", directory.display()))?)
+    };
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(options.jobs.unwrap_or(0)).build()?;
+    let sweep = pool.install(|| sweep::run_sweep(&document, plan, cache.as_ref()))?;
+
+    if is_hdf5(output) {
+        #[cfg(feature = "hdf5")]
+        {
+            let base = Device::from_toml(&toml::to_string(&document)?)
+                .map_err(|error| format!("with the values given by --set: {}", sweep::device_message(error)))?;
+            files::write_sweep(output, "sweep", &base, &sweep)?;
+        }
+    } else {
+        let mut table = std::io::BufWriter::new(std::fs::File::create(output)?);
+        sweep::write_table(&mut table, &sweep)?;
+        std::io::Write::flush(&mut table)?;
+    }
+    println!(
+        "{} of {} points written to {}, {} from the cache, {} failed",
+        sweep.plan.design.name(),
+        sweep.outcomes.len(),
+        output.display(),
+        sweep.cached,
+        sweep.failures()
+    );
+    Ok(())
+}
+
+fn inspect(path: &Path) -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "hdf5")]
+    {
+        print!("{}", files::inspect(path)?);
+        Ok(())
+    }
+    #[cfg(not(feature = "hdf5"))]
+    {
+        let _ = path;
+        Err(NO_HDF5.into())
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let level = if cli.verbose { tracing::Level::DEBUG } else { tracing::Level::WARN };
+    tracing_subscriber::fmt().with_max_level(level).with_writer(std::io::stderr).init();
+
+    let result = match &cli.command {
+        Command::Run { device, output, set } => run(device, output.as_deref(), set),
+        Command::Sweep {
+            device,
+            params,
+            set,
+            latin_hypercube,
+            seed,
+            jobs,
+            cache,
+            no_cache,
+            output,
+        } => {
+            let design = match latin_hypercube {
+                Some(samples) => Design::LatinHypercube {
+                    samples: *samples,
+                    seed: *seed,
+                },
+                None => Design::Grid,
+            };
+            let options = SweepOptions {
+                design,
+                jobs: *jobs,
+                cache: cache.as_deref(),
+                no_cache: *no_cache,
+            };
+            sweep(device, params, set, options, output)
+        }
+        Command::Inspect { file } => inspect(file),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_command_line() {
+        Cli::command().debug_assert();
+        let cli = Cli::try_parse_from([
+            "pcsel",
+            "sweep",
+            "device.toml",
+            "--param",
+            "layers.PC.thickness=100nm:140nm:5",
+            "--set",
+            "simulation.fourier_order=4",
+            "-o",
+            "sweep.csv",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Sweep {
+                params,
+                set,
+                latin_hypercube,
+                ..
+            } => {
+                assert_eq!(params[0].grid().unwrap().len(), 5);
+                assert_eq!(set[0].value, toml::Value::Integer(4));
+                assert_eq!(latin_hypercube, None);
+            }
+            _ => panic!("expected a sweep"),
+        }
+        let lhs = Cli::try_parse_from([
+            "pcsel", "sweep", "device.toml", "--param", "layers.PC.thickness=100nm:140nm", "--latin-hypercube", "20",
+            "--seed", "3", "-j", "2", "--no-cache", "-o", "sweep.h5",
+        ]);
+        assert!(matches!(
+            lhs.unwrap().command,
+            Command::Sweep { latin_hypercube: Some(20), seed: 3, jobs: Some(2), no_cache: true, .. }
+        ));
+        assert!(Cli::try_parse_from(["pcsel", "sweep", "device.toml", "-o", "sweep.csv"]).is_err());
+        let seed_alone = ["pcsel", "sweep", "device.toml", "--param", "r=0:1:2", "--seed", "3", "-o", "sweep.csv"];
+        assert!(Cli::try_parse_from(seed_alone).is_err());
+        let both = ["pcsel", "sweep", "d.toml", "--param", "r=1", "--cache", "c", "--no-cache", "-o", "s.csv"];
+        assert!(Cli::try_parse_from(both).is_err());
+        assert!(Cli::try_parse_from(["pcsel", "run", "device.toml", "--set", "wavelength"]).is_err());
+        assert!(is_hdf5(Path::new("out/result.h5")) && !is_hdf5(Path::new("out")));
+    }
+}
@@ -0,0 +1,342 @@
+//! crates/pcsel/src/overrides.rs
+//! Changes to a device file given on the command line.
+//!
+//! A path names a value of the TOML document with dot-separated keys, e.g.
+//! `simulation.wavelength`. Elements of arrays are selected by index or, for tables with a
+//! `name`, by that name: `layers.PC.thickness` and `layers.2.thickness` are the same value.
+use std::fmt;
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use core::units::Length;
+
+/// Reasons why an override cannot be parsed or applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverrideError {
+    /// The argument does not have the expected form.
+    Syntax(String),
+    /// The path does not lead to a value of the document.
+    Path { path: String, reason: String },
+}
+
+impl fmt::Display for OverrideError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverrideError::Syntax(message) => write!(f, "{message}"),
+            OverrideError::Path { path, reason } => write!(f, "cannot set '{path}': {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for OverrideError {}
+
+/// Parses `text` as a TOML value, falling back to a string so that `985 nm` needs no quotes.
+fn parse_value(text: &str) -> Value {
+    format!("value = {text}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(text.trim().to_string()))
+}
+
+/// Splits `PATH=REST`.
+fn split_assignment(text: &str) -> Result<(String, &str), OverrideError> {
+    match text.split_once('=') {
+        Some((path, rest)) if !path.trim().is_empty() => Ok((path.trim().to_string(), rest)),
+        _ => Err(OverrideError::Syntax(format!("expected PATH=VALUE, found '{text}'"))),
+    }
+}
+
+/// A single value given with `--set PATH=VALUE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub path: String,
+    pub value: Value,
+}
+
+impl FromStr for Assignment {
+    type Err = OverrideError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (path, value) = split_assignment(text)?;
+        Ok(Assignment {
+            path,
+            value: parse_value(value),
+        })
+    }
+}
+
+impl Assignment {
+    pub fn apply(&self, document: &mut Table) -> Result<(), OverrideError> {
+        set(document, &self.path, self.value.clone())
+    }
+}
+
+/// Bound of an interval of values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Integer(i64),
+    Float(f64),
+    Length(Length),
+}
+
+impl Bound {
+    fn parse_pair(start: &str, stop: &str) -> Result<(Bound, Bound), OverrideError> {
+        if let (Ok(start), Ok(stop)) = (start.parse::<i64>(), stop.parse::<i64>()) {
+            return Ok((Bound::Integer(start), Bound::Integer(stop)));
+        }
+        if let (Ok(start), Ok(stop)) = (start.parse::<f64>(), stop.parse::<f64>()) {
+            return Ok((Bound::Float(start), Bound::Float(stop)));
+        }
+        match (start.parse::<Length>(), stop.parse::<Length>()) {
+            (Ok(start), Ok(stop)) => Ok((Bound::Length(start), Bound::Length(stop))),
+            (Err(error), _) | (_, Err(error)) => Err(OverrideError::Syntax(error.to_string())),
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Bound::Integer(value) => value as f64,
+            Bound::Float(value) => value,
+            Bound::Length(length) => length.as_meters(),
+        }
+    }
+}
+
+/// Values of one parameter given with `--param`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AxisValues {
+    /// `START:STOP:COUNT`, evenly spaced, or `START:STOP`, an interval for sampled designs.
+    Range { start: Bound, stop: Bound, count: Option<usize> },
+    /// `A,B,C`, the values themselves.
+    List(Vec<Value>),
+}
+
+/// A parameter swept with `--param PATH=START:STOP:COUNT`, `PATH=START:STOP` or `PATH=A,B,C`.
+///
+/// `START` and `STOP` are both plain numbers or both lengths with a unit. Integer bounds give
+/// integers when every step is whole, e.g. `simulation.fourier_order=6:12:4`. List values are
+/// read like those of `--set`, so materials or other strings can be listed too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub path: String,
+    pub values: AxisValues,
+}
+
+impl FromStr for Axis {
+    type Err = OverrideError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (path, values) = split_assignment(text)?;
+        let parts: Vec<&str> = values.split(':').map(str::trim).collect();
+        let values = match parts[..] {
+            [_] => AxisValues::List(values.split(',').map(parse_value).collect()),
+            [start, stop] => {
+                let (start, stop) = Bound::parse_pair(start, stop)?;
+                AxisValues::Range { start, stop, count: None }
+            }
+            [start, stop, count] => {
+                let count: usize = count
+                    .parse()
+                    .map_err(|_| OverrideError::Syntax(format!("expected a number of values, found '{count}' in '{text}'")))?;
+                if count == 0 {
+                    return Err(OverrideError::Syntax(format!("a range needs at least one value, found '{text}'")));
+                }
+                let (start, stop) = Bound::parse_pair(start, stop)?;
+                AxisValues::Range {
+                    start,
+                    stop,
+                    count: Some(count),
+                }
+            }
+            _ => return Err(OverrideError::Syntax(format!("expected PATH=START:STOP:COUNT or PATH=A,B,C, found '{text}'"))),
+        };
+        Ok(Axis { path, values })
+    }
+}
+
+impl Axis {
+    /// Values of the axis in a grid, which needs every range to have a count.
+    pub fn grid(&self) -> Result<Vec<Value>, OverrideError> {
+        let (start, stop, count) = match &self.values {
+            AxisValues::List(values) => return Ok(values.clone()),
+            AxisValues::Range { count: None, .. } => {
+                return Err(OverrideError::Syntax(format!(
+                    "'{}' needs a number of values, START:STOP:COUNT, unless the points are sampled",
+                    self.path
+                )))
+            }
+            AxisValues::Range {
+                start,
+                stop,
+                count: Some(count),
+            } => (*start, *stop, *count),
+        };
+        let fraction = |i: usize| if count == 1 { 0.0 } else { i as f64 / (count - 1) as f64 };
+        let values = match (start, stop) {
+            (Bound::Integer(start), Bound::Integer(stop)) if (stop - start) % (count as i64 - 1).max(1) == 0 => {
+                let step = (stop - start) / (count as i64 - 1).max(1);
+                (0..count as i64).map(|i| Value::Integer(start + step * i)).collect()
+            }
+            _ => (0..count).map(|i| interpolate(start, stop, fraction(i))).collect(),
+        };
+        Ok(values)
+    }
+
+    /// Value at `fraction` of the axis, in `[0, 1)`. Integer ranges and lists are split into
+    /// equal bins, one per value.
+    pub fn sample(&self, fraction: f64) -> Value {
+        let bin = |count: usize| ((fraction * count as f64) as usize).min(count - 1);
+        match &self.values {
+            AxisValues::List(values) => values[bin(values.len())].clone(),
+            AxisValues::Range {
+                start: Bound::Integer(start),
+                stop: Bound::Integer(stop),
+                ..
+            } => {
+                let (low, high) = (*start.min(stop), *start.max(stop));
+                Value::Integer(low + bin((high - low + 1) as usize) as i64)
+            }
+            AxisValues::Range { start, stop, .. } => interpolate(*start, *stop, fraction),
+        }
+    }
+}
+
+/// Value at `fraction` of the way from `start` to `stop`. Lengths are rounded to the
+/// picometer, so that they read as short numbers of nanometers.
+fn interpolate(start: Bound, stop: Bound, fraction: f64) -> Value {
+    let value = start.as_f64() + (stop.as_f64() - start.as_f64()) * fraction;
+    match start {
+        Bound::Length(_) => {
+            let length = Length::nanometers((value * 1e12).round() / 1e3);
+            Value::try_from(length).expect("lengths serialize to strings")
+        }
+        _ => Value::Float(value),
+    }
+}
+
+/// Text of a value for tables and summaries, without the quotes of strings.
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replaces the value at `path` of `document`. The last key may be new, e.g. an optional setting.
+pub fn set(document: &mut Table, path: &str, value: Value) -> Result<(), OverrideError> {
+    let keys: Vec<&str> = path.split('.').map(str::trim).collect();
+    let mut root = Value::Table(std::mem::take(document));
+    let result = set_in(&mut root, &keys, value);
+    if let Value::Table(table) = root {
+        *document = table;
+    }
+    result.map_err(|reason| OverrideError::Path {
+        path: path.to_string(),
+        reason,
+    })
+}
+
+fn set_in(current: &mut Value, keys: &[&str], value: Value) -> Result<(), String> {
+    let Some((key, rest)) = keys.split_first() else {
+        *current = value;
+        return Ok(());
+    };
+    let kind = current.type_str();
+    let child = match current {
+        Value::Table(table) if rest.is_empty() && !table.contains_key(*key) => {
+            table.insert(key.to_string(), value);
+            return Ok(());
+        }
+        Value::Table(table) => table.get_mut(*key).ok_or_else(|| format!("there is no key '{key}'"))?,
+        Value::Array(array) => select(array, key).ok_or_else(|| format!("there is no element '{key}'"))?,
+        _ => return Err(format!("cannot look up '{key}' in a {kind}")),
+    };
+    set_in(child, rest, value)
+}
+
+/// Element of `array` at the index `key`, or the table whose `name` is `key`.
+fn select<'a>(array: &'a mut [Value], key: &str) -> Option<&'a mut Value> {
+    if let Ok(index) = key.parse::<usize>() {
+        return array.get_mut(index);
+    }
+    array
+        .iter_mut()
+        .find(|element| element.get("name").and_then(Value::as_str) == Some(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::tests::TABLE_I;
+    use waveguide::device::Device;
+
+    #[test]
+    fn test_assignments() {
+        let assignment: Assignment = "simulation.wavelength=985 nm".parse().unwrap();
+        assert_eq!(assignment.value, Value::String("985 nm".into()));
+        let assignment: Assignment = "simulation.fourier_order = 6".parse().unwrap();
+        assert_eq!((assignment.path.as_str(), assignment.value), ("simulation.fourier_order", Value::Integer(6)));
+        assert_eq!("a.b=[1, 2]".parse::<Assignment>().unwrap().value, Value::Array(vec![1.into(), 2.into()]));
+        assert!(matches!("=3".parse::<Assignment>(), Err(OverrideError::Syntax(_))));
+        assert!(matches!("wavelength".parse::<Assignment>(), Err(OverrideError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_axes() {
+        let grid = |text: &str| text.parse::<Axis>().unwrap().grid().unwrap();
+        let values: Vec<String> = grid("layers.PC.thickness=100 nm:140nm:3").iter().map(display_value).collect();
+        assert_eq!(values, ["100 nm", "120 nm", "140 nm"]);
+        assert_eq!(grid("simulation.fourier_order=6:12:4"), [6, 8, 10, 12].map(Value::Integer));
+        let radii = grid("r=0.1:0.2:3");
+        assert_eq!(radii.len(), 3);
+        assert!(matches!(radii[1], Value::Float(r) if (r - 0.15).abs() < 1e-15));
+        assert_eq!(grid("r=0:3:3"), [0.0, 1.5, 3.0].map(Value::Float));
+        assert_eq!(grid("r=2:2:1"), [Value::Integer(2)]);
+        assert_eq!(grid("m=GaAs, InP"), [Value::from("GaAs"), Value::from("InP")]);
+        assert_eq!(grid("r=0.2"), [Value::Float(0.2)]);
+        assert!("r=0.1:0.2:x".parse::<Axis>().is_err());
+        assert!("r=0.1:0.2:0".parse::<Axis>().is_err());
+        assert!("r=0:1:2:3".parse::<Axis>().is_err());
+        assert!("r=1 nm:2 parsecs:3".parse::<Axis>().unwrap_err().to_string().contains("parsecs"));
+
+        let interval: Axis = "r=0.1:0.2".parse().unwrap();
+        assert!(interval.grid().unwrap_err().to_string().contains("START:STOP:COUNT"));
+        assert!(matches!(interval.sample(0.5), Value::Float(r) if (r - 0.15).abs() < 1e-15));
+        let orders: Axis = "o=6:8".parse().unwrap();
+        let sampled: Vec<Value> = [0.0, 0.34, 0.99].iter().map(|&t| orders.sample(t)).collect();
+        assert_eq!(sampled, [6, 7, 8].map(Value::Integer));
+        assert_eq!("m=a,b".parse::<Axis>().unwrap().sample(0.7), Value::from("b"));
+        assert_eq!(display_value(&"t=100nm:200nm".parse::<Axis>().unwrap().sample(0.25)), "125 nm");
+        assert_eq!(display_value(&"t=100nm:200nm".parse::<Axis>().unwrap().sample(0.123456789)), "112.346 nm");
+    }
+
+    #[test]
+    fn test_set_paths() {
+        let mut document: Table = TABLE_I.parse().unwrap();
+        for assignment in [
+            "layers.PC.crystal.base.atoms.0.shape.radius=0.2",
+            "layers.1.thickness=90 nm",
+            "simulation.temperature=320.0",
+        ] {
+            assignment.parse::<Assignment>().unwrap().apply(&mut document).unwrap();
+        }
+        let device = Device::from_toml(&toml::to_string(&document).unwrap()).unwrap();
+        assert_eq!(device.waveguide.layers[1].thickness(), Length::nanometers(90.0));
+        assert_eq!(device.simulation.temperature, Some(320.0));
+        match device.waveguide.get_layer().unwrap() {
+            waveguide::layers::LayerType::PhotonicCrystal { definition, .. } => {
+                assert_eq!(definition.base.atoms[0].shape, core::shapes::HoleShape::Circle { radius: 0.2 });
+            }
+            _ => unreachable!(),
+        }
+
+        let missing = set(&mut document, "layers.QW.thickness", Value::from("5 nm")).unwrap_err();
+        assert_eq!(missing.to_string(), "cannot set 'layers.QW.thickness': there is no element 'QW'");
+        let scalar = set(&mut document, "name.first", Value::from("x")).unwrap_err();
+        assert!(scalar.to_string().contains("in a string"), "{scalar}");
+        assert!(set(&mut document, "simulation.grid.nx", Value::from(3)).is_err());
+        assert_eq!(document["name"].as_str(), Some("table-i"));
+    }
+}
@@ -0,0 +1,259 @@
+//! crates/pcsel/src/pipeline.rs
+//! The chain of solvers run for one device.
+//!
+//! The stack is first evaluated at the device temperature, then its fundamental slab mode is
+//! solved and the permittivity of every photonic crystal layer is expanded in Fourier
+//! coefficients, from which the coupled-wave modes of the first one are found. The
+//! rasterized grid is only built when the device gives a resolution.
+use std::fmt;
+
+use ndarray::Array3;
+
+use core::units::Length;
+use cwt::{CwtError, CwtSolution};
+use fourier::{permittivity_coefficients, FourierCoefficients, FourierError};
+use geom_builder::{rasterize_waveguide_3d, RasterError, RectifiedCell};
+use waveguide::device::Device;
+use waveguide::layers::{LayerType, Waveguide};
+use waveguide::slab::{SlabError, SlabMode};
+use waveguide::temperature::{TemperatureError, TemperatureProfile};
+
+/// Version of the results of [`run`], raised whenever a solver changes them so that sweep
+/// points cached by an older build are solved again.
+pub const PIPELINE_VERSION: u32 = 2;
+
+/// Reasons why a device cannot be solved.
+#[derive(Debug)]
+pub enum PipelineError {
+    Temperature(TemperatureError),
+    Slab(SlabError),
+    Fourier { layer: String, error: FourierError },
+    Raster(RasterError),
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::Temperature(error) => write!(f, "cannot evaluate the stack: {error}"),
+            PipelineError::Slab(error) => write!(f, "slab solver failed: {error}"),
+            PipelineError::Fourier { layer, error } => write!(f, "layer '{layer}': {error}"),
+            PipelineError::Raster(error) => write!(f, "cannot rasterize the stack: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::Temperature(error) => Some(error),
+            PipelineError::Slab(error) => Some(error),
+            PipelineError::Fourier { error, .. } => Some(error),
+            PipelineError::Raster(error) => Some(error),
+        }
+    }
+}
+
+/// Fourier coefficients of one photonic crystal layer.
+#[derive(Debug, Clone)]
+pub struct LayerCoefficients {
+    pub layer: String,
+    pub coefficients: FourierCoefficients,
+}
+
+/// Rasterized permittivity of the stack over one rectified unit cell.
+#[derive(Debug, Clone)]
+pub struct Grid {
+    pub epsilon: Array3<f64>,
+    /// Size of the rasterized box along x, y and z.
+    pub size: (Length, Length, Length),
+}
+
+/// Everything computed for a device.
+#[derive(Debug, Clone)]
+pub struct Results {
+    pub wavelength: Length,
+    /// The stack as evaluated at the device temperature.
+    pub waveguide: Waveguide,
+    pub slab: SlabMode,
+    pub fourier: Vec<LayerCoefficients>,
+    /// Coupled-wave modes of the first photonic crystal layer, or why they were not computed.
+    pub cwt: Result<CwtSolution, CwtError>,
+    /// Rasterized permittivity, when the device gives a resolution and it was asked for.
+    pub grid: Option<Grid>,
+}
+
+impl Results {
+    /// Confinement factor of the first photonic crystal layer.
+    pub fn photonic_crystal_confinement(&self) -> Option<f64> {
+        let index = self.waveguide.layer_index()?;
+        Some(self.slab.layers[index].confinement)
+    }
+}
+
+/// Points per lattice vector at which the unit cell is sampled for Fourier coefficients of `order`.
+pub fn fourier_samples(order: usize) -> usize {
+    (8 * (2 * order + 1)).next_power_of_two()
+}
+
+/// Solves `device`, building its permittivity grid only if `rasterize` is set.
+pub fn run(device: &Device, rasterize: bool) -> Result<Results, PipelineError> {
+    let _span = tracing::info_span!("run", device = %device.name).entered();
+    let settings = &device.simulation;
+    let wavelength = settings.wavelength;
+
+    let waveguide = match settings.temperature {
+        Some(temperature) => device
+            .waveguide
+            .at_temperature(wavelength, &TemperatureProfile::Uniform(temperature), (Length::default(), Length::default()))
+            .map_err(PipelineError::Temperature)?,
+        None => device.waveguide.clone(),
+    };
+
+    let slab = waveguide.slab_mode(wavelength).map_err(PipelineError::Slab)?;
+    tracing::info!(effective_index = slab.effective_index, "slab mode");
+
+    let samples = fourier_samples(settings.fourier_order);
+    let fourier = waveguide
+        .layers
+        .iter()
+        .filter_map(|layer| match layer {
+            LayerType::PhotonicCrystal { name, definition, .. } => Some((name, definition)),
+            _ => None,
+        })
+        .map(|(name, crystal)| {
+            let coefficients = permittivity_coefficients(crystal, wavelength, settings.fourier_order, samples)
+                .map_err(|error| PipelineError::Fourier { layer: name.clone(), error })?;
+            tracing::info!(layer = %name, order = settings.fourier_order, samples, "Fourier coefficients");
+            Ok(LayerCoefficients { layer: name.clone(), coefficients })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cwt = match fourier.first() {
+        Some(layer) => cwt::solve(&waveguide, &slab, &layer.coefficients, wavelength),
+        None => Err(CwtError::NoPhotonicCrystal),
+    };
+    match &cwt {
+        Ok(solution) => tracing::info!(
+            band_edge = %solution.band_edge(solution.lasing_mode()),
+            "coupled-wave modes"
+        ),
+        Err(error) => tracing::warn!(%error, "coupled-wave modes not computed"),
+    }
+
+    let grid = match settings.resolution {
+        Some([nx, ny, nz]) if rasterize => Some(rasterized_grid(&waveguide, (nx, ny, nz)).map_err(PipelineError::Raster)?),
+        _ => None,
+    };
+
+    Ok(Results {
+        wavelength,
+        waveguide,
+        slab,
+        fourier,
+        cwt,
+        grid,
+    })
+}
+
+fn rasterized_grid(waveguide: &Waveguide, resolution: (usize, usize, usize)) -> Result<Grid, RasterError> {
+    let epsilon = rasterize_waveguide_3d(waveguide, resolution)?;
+    let Some(LayerType::PhotonicCrystal { definition, .. }) = waveguide.get_layer() else {
+        return Err(RasterError::NoPhotonicCrystal);
+    };
+    let (width, depth) = RectifiedCell::new(definition.lattice.lattice())?.size();
+    Ok(Grid {
+        epsilon,
+        size: (width, depth, waveguide.total_thickness()),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use waveguide::device::Device;
+
+    pub(crate) const TABLE_I: &str = include_str!("../../waveguide/data/table_i_device.toml");
+
+    #[test]
+    fn test_table_i_pipeline() {
+        let mut device = Device::from_toml(TABLE_I).unwrap();
+        device.simulation.resolution = Some([8, 8, 16]);
+        let results = run(&device, true).unwrap();
+
+        assert!(results.slab.effective_index > 11.0224_f64.sqrt() && results.slab.effective_index < 12.8603_f64.sqrt());
+        let confinement = results.photonic_crystal_confinement().unwrap();
+        assert!(confinement > 0.0 && confinement < 1.0);
+
+        assert_eq!(results.fourier.len(), 1);
+        let pc = &results.fourier[0];
+        assert_eq!(pc.layer, "PC");
+        assert_eq!(pc.coefficients.order(), 10);
+        let fill = std::f64::consts::PI * 0.16 * 0.16;
+        let average = 12.7449 * (1.0 - fill) + fill;
+        assert!((pc.coefficients.get(0, 0).unwrap().re - average).abs() < 1e-2);
+        let grid = results.grid.unwrap();
+        assert_eq!(grid.epsilon.dim(), (8, 8, 16));
+        assert_eq!(grid.size, (Length::nanometers(295.0), Length::nanometers(295.0), Length::nanometers(3265.5)));
+        assert!(run(&device, false).unwrap().grid.is_none());
+
+        // The four band-edge modes of circular holes: two dark ones and a radiating pair.
+        let solution = results.cwt.as_ref().unwrap();
+        assert_eq!(solution.modes.len(), 4);
+        assert!(solution.lasing_mode().radiation_loss().as_per_centimeter().abs() < 1e-6);
+        assert!(solution.modes.iter().any(|mode| mode.radiation_loss().as_per_centimeter() > 1.0));
+        let edge = solution.band_edge(solution.lasing_mode());
+        // Band edges sit near the second-order Bragg wavelength a n_eff.
+        let bragg = Length::nanometers(295.0 * results.slab.effective_index);
+        assert!((edge / bragg - 1.0).abs() < 0.01, "{edge} vs {bragg}");
+    }
+
+    #[test]
+    fn test_cwt_needs_a_square_lattice() {
+        let text = TABLE_I.replace("kind = \"square\"", "kind = \"triangular\"");
+        let results = run(&Device::from_toml(&text).unwrap(), false).unwrap();
+        assert_eq!(results.cwt, Err(CwtError::UnsupportedLattice("PC".into())));
+        assert_eq!(results.fourier.len(), 1);
+    }
+
+    #[test]
+    fn test_temperature_is_applied() {
+        let (dn_dt, reference, temperature) = (2.5e-4, 300.0, 350.0);
+        let mut device = Device::from_toml(TABLE_I).unwrap();
+        for layer in &mut device.waveguide.layers {
+            if let LayerType::Simple { material, .. } = layer {
+                *material = material.clone().with_thermo_optic(dn_dt, reference);
+            }
+        }
+        let cold = run(&device, false).unwrap();
+        device.simulation.temperature = Some(temperature);
+        let hot = run(&device, false).unwrap();
+
+        // TE modes have n_eff² = Σ Γ_j ε_j, so heating the uniform layers by ΔT shifts the
+        // index by Σ Γ_j n_j dn/dT ΔT / n_eff to first order.
+        let wavelength = device.simulation.wavelength;
+        let expected = cold
+            .waveguide
+            .layers
+            .iter()
+            .zip(&cold.slab.layers)
+            .filter_map(|(layer, loss)| match layer {
+                LayerType::Simple { material, .. } => {
+                    Some(loss.confinement * material.epsilon_at(wavelength)[(0, 0)].sqrt() * dn_dt * (temperature - reference))
+                }
+                _ => None,
+            })
+            .sum::<f64>()
+            / cold.slab.effective_index;
+        let shift = hot.slab.effective_index - cold.slab.effective_index;
+        assert!(expected > 0.0 && (shift - expected).abs() < 0.02 * expected, "{shift} vs {expected}");
+        // The photonic crystal layer has no thermal model and keeps its permittivity.
+        let pc = |results: &Results| results.waveguide.layers[2].slab_permittivity(wavelength, 0.5).unwrap();
+        assert_eq!(pc(&hot), pc(&cold));
+        // A hotter, higher-index slab lases at a longer wavelength.
+        let band_edge = |results: &Results| {
+            let solution = results.cwt.as_ref().unwrap();
+            solution.band_edge(solution.lasing_mode())
+        };
+        assert!(band_edge(&hot) > band_edge(&cold));
+    }
+}
@@ -0,0 +1,239 @@
+//! crates/pcsel/src/report.rs
+//! Plain-text summaries and CSV tables of results.
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::pipeline::Results;
+
+/// Fourier orders reported in summaries and sweep tables: the average and the first couplings.
+pub const REPORTED_ORDERS: [(i32, i32); 4] = [(0, 0), (1, 0), (1, 1), (2, 0)];
+
+/// Quotes `field` if it would otherwise break a CSV row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Writes one CSV row.
+pub fn write_row(out: &mut impl Write, fields: &[String]) -> io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    writeln!(out, "{}", fields.join(","))
+}
+
+/// Human-readable summary of `results`.
+pub fn summary(name: &str, results: &Results) -> String {
+    let mut text = String::new();
+    let slab = &results.slab;
+    writeln!(text, "device      {name}").unwrap();
+    writeln!(text, "wavelength  {}", results.wavelength).unwrap();
+    writeln!(
+        text,
+        "slab        n_eff = {:.6}, internal loss = {:.4} 1/cm",
+        slab.effective_index,
+        slab.internal_loss().as_per_centimeter()
+    )
+    .unwrap();
+    for (layer, loss) in results.waveguide.layers.iter().zip(&slab.layers) {
+        writeln!(
+            text,
+            "            {:<24} {:>10}  Γ = {:.4}",
+            layer.name(),
+            layer.thickness().to_string(),
+            loss.confinement
+        )
+        .unwrap();
+    }
+    for layer in &results.fourier {
+        let count = layer.coefficients.iter().count();
+        writeln!(
+            text,
+            "fourier     '{}', order {} ({count} coefficients)",
+            layer.layer,
+            layer.coefficients.order()
+        )
+        .unwrap();
+        for (m, n) in REPORTED_ORDERS {
+            if let Some(xi) = layer.coefficients.get(m, n) {
+                writeln!(text, "            ξ({m},{n}) = {:.6} {:+.6}i", xi.re, xi.im).unwrap();
+            }
+        }
+    }
+    if let Some(grid) = &results.grid {
+        let (nx, ny, nz) = grid.epsilon.dim();
+        let (width, depth, height) = grid.size;
+        writeln!(text, "grid        {nx} x {ny} x {nz} voxels over {width} x {depth} x {height}").unwrap();
+    }
+    match &results.cwt {
+        Ok(solution) => {
+            let lasing = solution.lasing_mode();
+            writeln!(text, "cwt         '{}' (square lattice), {} modes by increasing frequency", results.fourier[0].layer, solution.modes.len()).unwrap();
+            for (index, mode) in solution.modes.iter().enumerate() {
+                writeln!(
+                    text,
+                    "            {}: δ = {:+.2} 1/cm, α = {:.4} 1/cm, band edge {:.3} nm, Q = {:.0}{}",
+                    index + 1,
+                    mode.eigenvalue.re / 100.0,
+                    mode.radiation_loss().as_per_centimeter(),
+                    solution.band_edge(mode).as_nanometers(),
+                    solution.quality_factor(mode),
+                    if std::ptr::eq(mode, lasing) { "  (lasing)" } else { "" }
+                )
+                .unwrap();
+            }
+            writeln!(
+                text,
+                "            threshold gain = {:.4} 1/cm",
+                solution.threshold_gain(lasing).as_per_centimeter()
+            )
+            .unwrap();
+        }
+        Err(error) => writeln!(text, "cwt         not computed: {error}").unwrap(),
+    }
+    text
+}
+
+/// Writes `slab.csv`, one `fourier_<layer>.csv` per photonic crystal layer and, when the
+/// coupled-wave modes were computed, `cwt.csv` into `directory`.
+pub fn write_csv(directory: &Path, results: &Results) -> io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+
+    let mut slab = io::BufWriter::new(std::fs::File::create(directory.join("slab.csv"))?);
+    write_row(&mut slab, &["layer", "thickness_nm", "confinement", "loss_per_cm"].map(String::from))?;
+    for (layer, loss) in results.waveguide.layers.iter().zip(&results.slab.layers) {
+        write_row(
+            &mut slab,
+            &[
+                layer.name().to_string(),
+                layer.thickness().as_nanometers().to_string(),
+                loss.confinement.to_string(),
+                loss.loss.as_per_centimeter().to_string(),
+            ],
+        )?;
+    }
+    slab.flush()?;
+
+    for layer in &results.fourier {
+        let file_name: String = layer
+            .layer
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let mut table = io::BufWriter::new(std::fs::File::create(directory.join(format!("fourier_{file_name}.csv")))?);
+        write_row(&mut table, &["m", "n", "re", "im"].map(String::from))?;
+        for ((m, n), xi) in layer.coefficients.iter() {
+            write_row(&mut table, &[m.to_string(), n.to_string(), xi.re.to_string(), xi.im.to_string()])?;
+        }
+        table.flush()?;
+    }
+
+    if let Ok(solution) = &results.cwt {
+        let mut table = io::BufWriter::new(std::fs::File::create(directory.join("cwt.csv"))?);
+        let header = ["mode", "delta_per_cm", "alpha_per_cm", "band_edge_nm", "threshold_gain_per_cm", "q_factor", "lasing"];
+        write_row(&mut table, &header.map(String::from))?;
+        let lasing = solution.lasing_mode();
+        for (index, mode) in solution.modes.iter().enumerate() {
+            write_row(
+                &mut table,
+                &[
+                    (index + 1).to_string(),
+                    (mode.eigenvalue.re / 100.0).to_string(),
+                    mode.radiation_loss().as_per_centimeter().to_string(),
+                    solution.band_edge(mode).as_nanometers().to_string(),
+                    solution.threshold_gain(mode).as_per_centimeter().to_string(),
+                    solution.quality_factor(mode).to_string(),
+                    std::ptr::eq(mode, lasing).to_string(),
+                ],
+            )?;
+        }
+        table.flush()?;
+    }
+    Ok(())
+}
+
+/// Names and units of the results of [`sweep_values`].
+pub fn sweep_quantities() -> Vec<(String, &'static str)> {
+    let mut quantities = vec![
+        ("effective_index".to_string(), "1"),
+        ("internal_loss_per_cm".to_string(), "1/cm"),
+        ("pc_confinement".to_string(), "1"),
+    ];
+    for (m, n) in REPORTED_ORDERS {
+        quantities.push((format!("xi_{m}_{n}_re"), "1"));
+        quantities.push((format!("xi_{m}_{n}_im"), "1"));
+    }
+    quantities.extend([
+        ("band_edge_nm".to_string(), "nm"),
+        ("radiation_loss_per_cm".to_string(), "1/cm"),
+        ("threshold_gain_per_cm".to_string(), "1/cm"),
+        ("q_factor".to_string(), "1"),
+    ]);
+    quantities
+}
+
+/// Scalar results of one sweep point, matching [`sweep_quantities`]. Fourier results describe
+/// the first photonic crystal layer and are missing beyond its order, coupled-wave results
+/// describe its lasing mode and are missing when they were not computed.
+pub fn sweep_values(results: &Results) -> Vec<Option<f64>> {
+    let mut values = vec![
+        Some(results.slab.effective_index),
+        Some(results.slab.internal_loss().as_per_centimeter()),
+        results.photonic_crystal_confinement(),
+    ];
+    let coefficients = results.fourier.first().map(|layer| &layer.coefficients);
+    for (m, n) in REPORTED_ORDERS {
+        let xi = coefficients.and_then(|coefficients| coefficients.get(m, n));
+        values.extend([xi.map(|xi| xi.re), xi.map(|xi| xi.im)]);
+    }
+    let cwt = results.cwt.as_ref().ok().map(|solution| (solution, solution.lasing_mode()));
+    values.extend([
+        cwt.map(|(solution, mode)| solution.band_edge(mode).as_nanometers()),
+        cwt.map(|(_, mode)| mode.radiation_loss().as_per_centimeter()),
+        cwt.map(|(solution, mode)| solution.threshold_gain(mode).as_per_centimeter()),
+        cwt.map(|(solution, mode)| solution.quality_factor(mode)),
+    ]);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{run, tests::TABLE_I};
+    use waveguide::device::Device;
+
+    #[test]
+    fn test_csv_fields_are_quoted() {
+        let mut out = Vec::new();
+        write_row(&mut out, &["n-clad (AlGaAs)".into(), "a,b".into(), "say \"hi\"".into()]).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "n-clad (AlGaAs),\"a,b\",\"say \"\"hi\"\"\"\n");
+    }
+
+    #[test]
+    fn test_reports() {
+        let results = run(&Device::from_toml(TABLE_I).unwrap(), false).unwrap();
+        let text = summary("table-i", &results);
+        assert!(text.contains("wavelength  980 nm"), "{text}");
+        assert!(text.contains("'PC', order 10 (441 coefficients)"), "{text}");
+        assert!(text.contains("cwt         'PC' (square lattice), 4 modes by increasing frequency"), "{text}");
+        assert!(text.contains("(lasing)"), "{text}");
+        assert_eq!(sweep_values(&results).len(), sweep_quantities().len());
+        assert!(sweep_values(&results).iter().all(Option::is_some));
+
+        let directory = std::env::temp_dir().join(format!("pcsel-report-{}", std::process::id()));
+        write_csv(&directory, &results).unwrap();
+        let slab = std::fs::read_to_string(directory.join("slab.csv")).unwrap();
+        assert_eq!(slab.lines().count(), 6);
+        let pc: Vec<&str> = slab.lines().nth(3).unwrap().split(',').collect();
+        assert_eq!(pc[0], "PC");
+        assert!((pc[1].parse::<f64>().unwrap() - 118.0).abs() < 1e-9, "{slab}");
+        let fourier = std::fs::read_to_string(directory.join("fourier_PC.csv")).unwrap();
+        assert_eq!(fourier.lines().count(), 442);
+        let cwt = std::fs::read_to_string(directory.join("cwt.csv")).unwrap();
+        assert_eq!(cwt.lines().count(), 5);
+        assert_eq!(cwt.lines().filter(|line| line.ends_with(",true")).count(), 1, "{cwt}");
+        std::fs::remove_dir_all(directory).ok();
+    }
+}
@@ -0,0 +1,197 @@
+//! crates/pcsel/src/sweep.rs
+//! Runs a device at every point of a sweep.
+//!
+//! Points are solved in parallel on the current rayon pool. With a [`Cache`], points solved
+//! by an earlier run, even an interrupted one, are read back instead of solved again.
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+use toml::{Table, Value};
+
+use waveguide::device::{Device, DeviceError};
+
+use crate::cache::{Cache, Outcome};
+use crate::design::Plan;
+use crate::overrides::{display_value, set, Axis, OverrideError};
+use crate::pipeline::run;
+use crate::report::{sweep_quantities, sweep_values, write_row};
+
+/// Outcomes of every point of a plan.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub plan: Plan,
+    /// One per point of the plan, in the same order.
+    pub outcomes: Vec<Outcome>,
+    /// Number of points read from the cache.
+    pub cached: usize,
+}
+
+impl Sweep {
+    pub fn failures(&self) -> usize {
+        self.outcomes.iter().filter(|outcome| outcome.is_err()).count()
+    }
+}
+
+/// The message of a device error, without a location in the generated document.
+pub fn device_message(error: DeviceError) -> String {
+    match error {
+        DeviceError::Parse { message, .. } | DeviceError::Invalid { message, .. } => message,
+        other => other.to_string(),
+    }
+}
+
+/// `document` with `values` set at the paths of `axes`.
+fn device_at(document: &Table, axes: &[Axis], values: &[Value]) -> Result<Result<Device, String>, OverrideError> {
+    let mut document = document.clone();
+    for (axis, value) in axes.iter().zip(values) {
+        set(&mut document, &axis.path, value.clone())?;
+    }
+    let text = toml::to_string(&document).expect("tables serialize to TOML");
+    Ok(Device::from_toml(&text).map_err(device_message))
+}
+
+/// Solves one point, unless `cache` has it. Returns its outcome and whether it was cached.
+fn evaluate(document: &Table, axes: &[Axis], values: &[Value], cache: Option<&Cache>) -> Result<(Outcome, bool), OverrideError> {
+    let device = match device_at(document, axes, values)? {
+        Ok(device) => device,
+        Err(error) => return Ok((Err(error), false)),
+    };
+    let text = device.to_toml();
+    if let Some(outcome) = cache.and_then(|cache| cache.get(&text)) {
+        return Ok((outcome, true));
+    }
+    let outcome = run(&device, false)
+        .map(|results| sweep_values(&results))
+        .map_err(|error| error.to_string());
+    if let Some(cache) = cache {
+        if let Err(error) = cache.put(&text, &outcome) {
+            tracing::warn!(%error, directory = %cache.directory().display(), "cannot cache a point");
+        }
+    }
+    Ok((outcome, false))
+}
+
+/// Solves `document` at every point of `plan`.
+///
+/// Points whose device is invalid or fails to solve are kept with the reason. Only paths that
+/// do not exist in `document` stop the sweep.
+pub fn run_sweep(document: &Table, plan: Plan, cache: Option<&Cache>) -> Result<Sweep, OverrideError> {
+    let total = plan.points.len();
+    let finished = AtomicUsize::new(0);
+    let evaluated = plan
+        .points
+        .par_iter()
+        .enumerate()
+        .map(|(index, values)| {
+            let _span = tracing::info_span!("point", index, of = total).entered();
+            let (outcome, cached) = evaluate(document, &plan.axes, values, cache)?;
+            if let Err(error) = &outcome {
+                tracing::warn!(%error, "point failed");
+            }
+            tracing::info!(finished = finished.fetch_add(1, Ordering::Relaxed) + 1, cached, "point done");
+            Ok((outcome, cached))
+        })
+        .collect::<Result<Vec<_>, OverrideError>>()?;
+    let cached = evaluated.iter().filter(|(_, cached)| *cached).count();
+    Ok(Sweep {
+        plan,
+        outcomes: evaluated.into_iter().map(|(outcome, _)| outcome).collect(),
+        cached,
+    })
+}
+
+/// Writes one CSV row per point of `sweep`: the values of the axes, the results and an
+/// `error` column, empty for points that were solved.
+pub fn write_table(out: &mut impl Write, sweep: &Sweep) -> io::Result<()> {
+    let quantities = sweep_quantities();
+    let header: Vec<String> = sweep
+        .plan
+        .axes
+        .iter()
+        .map(|axis| axis.path.clone())
+        .chain(quantities.iter().map(|(name, _)| name.clone()))
+        .chain(["error".to_string()])
+        .collect();
+    write_row(out, &header)?;
+    for (values, outcome) in sweep.plan.points.iter().zip(&sweep.outcomes) {
+        let mut row: Vec<String> = values.iter().map(display_value).collect();
+        match outcome {
+            Ok(results) => {
+                row.extend(results.iter().map(|value| value.map_or(String::new(), |value| value.to_string())));
+                row.push(String::new());
+            }
+            Err(error) => {
+                row.extend(quantities.iter().map(|_| String::new()));
+                row.push(error.clone());
+            }
+        }
+        write_row(out, &row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::design::Design;
+    use crate::pipeline::tests::TABLE_I;
+
+    fn plan(axes: &[&str], design: Design) -> Plan {
+        Plan::new(axes.iter().map(|axis| axis.parse().unwrap()).collect(), design).unwrap()
+    }
+
+    #[test]
+    fn test_sweep_table() {
+        let document: Table = TABLE_I.parse().unwrap();
+        let plan = plan(
+            &["layers.PC.crystal.base.atoms.0.shape.radius=0.1:0.6:2", "layers.PC.thickness=100 nm:120 nm:2"],
+            Design::Grid,
+        );
+        let sweep = run_sweep(&document, plan, None).unwrap();
+        let mut out = Vec::new();
+        write_table(&mut out, &sweep).unwrap();
+        let table = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = table.lines().collect();
+        assert_eq!(rows.len(), 5);
+        assert!(rows[0].starts_with("layers.PC.crystal.base.atoms.0.shape.radius,layers.PC.thickness,effective_index,"));
+        assert!(rows[0].ends_with(",band_edge_nm,radiation_loss_per_cm,threshold_gain_per_cm,q_factor,error"));
+        // Every solved point carries the band edge of its lasing mode.
+        let band_edge = sweep_quantities().iter().position(|(name, _)| name == "band_edge_nm").unwrap();
+        let solved: Vec<_> = sweep.outcomes.iter().filter_map(|outcome| outcome.as_ref().ok()).collect();
+        assert_eq!(solved.len(), 2);
+        assert!(solved.iter().all(|values| values[band_edge].is_some_and(|edge| edge > 900.0)));
+        assert!(rows[1].starts_with("0.1,100 nm,3.") && rows[1].ends_with(','), "{}", rows[1]);
+        // A radius of 0.6 does not fit in the cell.
+        assert_eq!((sweep.failures(), sweep.cached), (2, 0));
+        assert!(rows[4].starts_with("0.6,120 nm,,") && rows[4].contains("periodic image"), "{}", rows[4]);
+
+        let bad = self::plan(&["layers.QW.thickness=1 nm:2 nm:2"], Design::Grid);
+        assert!(matches!(run_sweep(&document, bad, None), Err(OverrideError::Path { .. })));
+    }
+
+    #[test]
+    fn test_sweeps_resume_from_the_cache() {
+        let document: Table = TABLE_I.parse().unwrap();
+        let directory = std::env::temp_dir().join(format!("pcsel-sweep-cache-{}", std::process::id()));
+        let quantities = sweep_quantities().into_iter().map(|(name, _)| name).collect();
+        let cache = Cache::open(&directory, quantities).unwrap();
+        let axes = ["layers.PC.thickness=100 nm:140 nm", "simulation.fourier_order=2,3"];
+        let first = run_sweep(&document, plan(&axes, Design::LatinHypercube { samples: 4, seed: 1 }), Some(&cache)).unwrap();
+        assert_eq!((first.failures(), first.cached), (0, 0));
+
+        // An interrupted run kept only some of its points.
+        let entries: Vec<_> = std::fs::read_dir(&directory).unwrap().map(|entry| entry.unwrap().path()).collect();
+        assert_eq!(entries.len(), 4);
+        std::fs::remove_file(&entries[0]).unwrap();
+        let resumed = run_sweep(&document, plan(&axes, Design::LatinHypercube { samples: 4, seed: 1 }), Some(&cache)).unwrap();
+        assert_eq!(resumed.cached, 3);
+        assert_eq!(resumed.outcomes, first.outcomes);
+
+        // Other points are solved and added.
+        let other = run_sweep(&document, plan(&axes, Design::LatinHypercube { samples: 4, seed: 2 }), Some(&cache)).unwrap();
+        assert_eq!(other.cached, 0);
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 8);
+        std::fs::remove_dir_all(directory).ok();
+    }
+}
@@ -1,21 +1,53 @@
 //! crates/core/src/geometry.rs
 //! Defines the geometry of holes within the unit cell.
 use core::material::{Material, CommonMaterials};
+use core::nalgebra::Matrix2;
 use core::shapes::HoleShape;
+use core::vectorial::Vector2;
+use serde::{Deserialize, Serialize};
+
+use super::lattice::Lattice;
 
 
 
 
 
 /// Represents a single atom (a shape + material) placed within the unit cell.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// When deserialized, the material defaults to air and the sidewall angle to zero.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct AtomInCell {
     /// The shape of the hole.
     pub shape: HoleShape,
     /// Center in fractional coordinates (s, t, z) expressed in units of the lattice vectors.
     pub center: (f64, f64, f64),
     /// The material of this atom.
+    #[serde(default = "air")]
     pub material: Material,
+    /// Tilt of the sidewall from the vertical, in radians.
+    /// Positive values make the hole narrower towards positive z.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sidewall_angle: f64,
+}
+
+fn air() -> Material {
+    CommonMaterials::Air.into()
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+impl AtomInCell {
+    /// Cross-section of the hole at height `dz` above its center, in units of the lattice constant.
+    pub fn shape_at_height(&self, dz: f64) -> HoleShape {
+        if self.sidewall_angle == 0.0 {
+            self.shape.clone()
+        } else {
+            self.shape.dilated(-dz * self.sidewall_angle.tan())
+        }
+    }
 }
 
 impl Default for AtomInCell {
@@ -23,16 +55,35 @@ impl Default for AtomInCell {
         Self {
             shape: HoleShape::Circle { radius: 0.1 },
             center: (0.5, 0.5, 0.0),
-            material: CommonMaterials::Air.into(),
+            material: air(),
+            sidewall_angle: 0.0,
         }
     }
 }
 
 
 
+/// Largest number of buckets along each lattice vector of an [`AtomLookup`].
+const MAX_BUCKETS: usize = 256;
+
+/// Whether a periodic image of `atom` contains the fractional in-plane position `(s, t)`.
+fn covers(atom: &AtomInCell, s: f64, t: f64, (a1, a2): (Vector2, Vector2)) -> bool {
+    let ds = s - atom.center.0;
+    let dt = t - atom.center.1;
+    let (ds, dt) = (ds - ds.round(), dt - dt.round());
+    (-1..=1).any(|m| {
+        (-1..=1).any(|n| {
+            let d = a1 * (ds + m as f64) + a2 * (dt + n as f64);
+            atom.shape.contains(d.x, d.y)
+        })
+    })
+}
+
 /// Defines the complete "base" of the unit cell as a collection of atoms.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct UnitCellBase {
+    #[serde(default)]
     pub atoms: Vec<AtomInCell>,
     pub background_material: Material,
 }
@@ -48,6 +99,7 @@ impl UnitCellBase {
             shape,
             center,
             material,
+            sidewall_angle: 0.0,
         });
     }    
 
@@ -61,13 +113,165 @@ impl UnitCellBase {
         );
         base
     }
+
+    /// Returns the material found at the fractional in-plane position `(s, t)`.
+    ///
+    /// Atoms added later take precedence over earlier ones where they overlap,
+    /// and periodic images of the atoms are taken into account.
+    pub fn material_at(&self, s: f64, t: f64, lattice: &Lattice) -> &Material {
+        self.atom_index_at(s, t, lattice)
+            .map_or(&self.background_material, |index| &self.atoms[index].material)
+    }
+
+    /// Index of the atom covering the fractional in-plane position `(s, t)`, with the
+    /// precedence of [`UnitCellBase::material_at`], or `None` for the background.
+    pub fn atom_index_at(&self, s: f64, t: f64, lattice: &Lattice) -> Option<usize> {
+        let (a1, a2) = lattice.normalized_in_plane_vectors();
+        self.atoms.iter().rposition(|atom| covers(atom, s, t, (a1, a2)))
+    }
+
+    /// Buckets the atoms for repeated queries on the same `lattice`, see [`AtomLookup`].
+    ///
+    /// Atoms are listed in every bucket that comes within `margin` (units of the lattice
+    /// constant) of their bounding box.
+    pub fn lookup<'a>(&'a self, lattice: &'a Lattice, margin: f64) -> AtomLookup<'a> {
+        let buckets = (2 * (self.atoms.len() as f64).sqrt().ceil() as usize).clamp(1, MAX_BUCKETS);
+        let (a1, a2) = lattice.normalized_in_plane_vectors();
+        let to_fractional = Matrix2::from_columns(&[a1, a2]).try_inverse();
+        let mut candidates = vec![Vec::new(); buckets * buckets];
+        // Bucket indices, wrapped into the cell, met by the interval `center ± half`.
+        let range = |center: f64, half: f64| -> Vec<usize> {
+            let (low, high) = (((center - half) * buckets as f64).floor(), ((center + half) * buckets as f64).floor());
+            if high.is_nan() || low.is_nan() || high - low + 1.0 >= buckets as f64 {
+                return (0..buckets).collect();
+            }
+            (low as i64..=high as i64).map(|i| i.rem_euclid(buckets as i64) as usize).collect()
+        };
+        for (index, atom) in self.atoms.iter().enumerate().rev() {
+            let (hx, hy) = atom.shape.half_extents();
+            let (hx, hy) = ((hx + margin) * (1.0 + 1e-9), (hy + margin) * (1.0 + 1e-9));
+            let (hs, ht) = match to_fractional {
+                Some(m) => (m[(0, 0)].abs() * hx + m[(0, 1)].abs() * hy, m[(1, 0)].abs() * hx + m[(1, 1)].abs() * hy),
+                None => (f64::INFINITY, f64::INFINITY),
+            };
+            let rows = range(atom.center.1, ht);
+            for i in range(atom.center.0, hs) {
+                for &j in &rows {
+                    candidates[i * buckets + j].push(index);
+                }
+            }
+        }
+        AtomLookup {
+            base: self,
+            lattice,
+            vectors: (a1, a2),
+            buckets,
+            candidates,
+        }
+    }
+
+    /// Displacement from the center of the nearest periodic image of atom `index` to the
+    /// fractional in-plane position `(s, t)`, in units of the lattice constant.
+    pub fn displacement_from_atom(&self, index: usize, s: f64, t: f64, lattice: &Lattice) -> Vector2 {
+        let (a1, a2) = lattice.normalized_in_plane_vectors();
+        let atom = &self.atoms[index];
+        let ds = s - atom.center.0;
+        let dt = t - atom.center.1;
+        let (ds, dt) = (ds - ds.round(), dt - dt.round());
+        (-1..=1)
+            .flat_map(|m| (-1..=1).map(move |n| a1 * (ds + m as f64) + a2 * (dt + n as f64)))
+            .min_by(|d1, d2| d1.norm_squared().total_cmp(&d2.norm_squared()))
+            .expect("nine periodic images")
+    }
+
+    /// Cross-section of the base at the fractional height `z` along `a3`.
+    ///
+    /// Sidewall tilts are applied to the shapes, which are returned with vertical sidewalls.
+    /// Atoms that taper to nothing are dropped.
+    pub fn slice_at(&self, z: f64, lattice: &Lattice) -> UnitCellBase {
+        let scale = lattice.a3.norm() / lattice.a1.norm();
+        let atoms = self
+            .atoms
+            .iter()
+            .map(|atom| AtomInCell {
+                shape: atom.shape_at_height((z - atom.center.2) * scale),
+                sidewall_angle: 0.0,
+                ..atom.clone()
+            })
+            .filter(|atom| atom.shape.has_positive_dimensions())
+            .collect();
+        UnitCellBase {
+            atoms,
+            background_material: self.background_material.clone(),
+        }
+    }
+}
+
+/// Atoms of a base bucketed on a grid of fractional coordinates.
+///
+/// Answers the queries of [`UnitCellBase`] by testing only the atoms near the query point,
+/// with the same results, which keeps rasterizing large supercells linear in their area.
+#[derive(Debug, Clone)]
+pub struct AtomLookup<'a> {
+    base: &'a UnitCellBase,
+    lattice: &'a Lattice,
+    vectors: (Vector2, Vector2),
+    buckets: usize,
+    /// Atoms reaching into each bucket, in decreasing precedence.
+    candidates: Vec<Vec<usize>>,
+}
+
+impl<'a> AtomLookup<'a> {
+    pub fn base(&self) -> &'a UnitCellBase {
+        self.base
+    }
+
+    /// Indices of the atoms that may lie within the margin of `(s, t)`, in decreasing precedence.
+    pub fn candidates(&self, s: f64, t: f64) -> &[usize] {
+        let bucket = |x: f64| (((x - x.floor()) * self.buckets as f64) as usize).min(self.buckets - 1);
+        &self.candidates[bucket(s) * self.buckets + bucket(t)]
+    }
+
+    /// Same as [`UnitCellBase::atom_index_at`].
+    pub fn atom_index_at(&self, s: f64, t: f64) -> Option<usize> {
+        self.candidates(s, t)
+            .iter()
+            .copied()
+            .find(|&index| covers(&self.base.atoms[index], s, t, self.vectors))
+    }
+
+    /// Same as [`UnitCellBase::material_at`].
+    pub fn material_at(&self, s: f64, t: f64) -> &'a Material {
+        self.atom_index_at(s, t)
+            .map_or(&self.base.background_material, |index| &self.base.atoms[index].material)
+    }
+
+    /// Same as [`UnitCellBase::displacement_from_atom`].
+    pub fn displacement_from_atom(&self, index: usize, s: f64, t: f64) -> Vector2 {
+        self.base.displacement_from_atom(index, s, t, self.lattice)
+    }
 }
+
 #[cfg(test)]
 mod tests {
+    use core::units::Length;
     use core::shapes::HoleShape;
     use super::*;
     use core::material::Material;
 
+    #[test]
+    fn test_base_deserialization_defaults() {
+        let text = "background_material = { epsilon = 12.7449 }\n\n\
+                    [[atoms]]\nshape = { kind = \"circle\", radius = 0.2 }\ncenter = [0.5, 0.5, 0.0]\n";
+        let base: UnitCellBase = toml::from_str(text).unwrap();
+        assert_eq!(base.atoms, vec![AtomInCell { shape: HoleShape::Circle { radius: 0.2 }, ..AtomInCell::default() }]);
+        assert_eq!(base.background_material, Material::new_from_eps(12.7449));
+        let written = toml::to_string(&base).unwrap();
+        assert!(!written.contains("sidewall_angle"), "{written}");
+        assert_eq!(toml::from_str::<UnitCellBase>(&written).unwrap().atoms, base.atoms);
+        assert!(toml::from_str::<UnitCellBase>("background_material = { epsilon = 1.0 }\nfill = 0.2\n").is_err());
+    }
+
     #[test]
     fn test_simple_circle_with_new_lattice_and_material() {
         let material = Material::new_from_eps(1.0);
@@ -75,7 +279,7 @@ mod tests {
         base.add_atom(
             HoleShape::Circle { radius: 0.2 },
             (0.5, 0.5, 0.0),
-            material,
+            material.clone(),
         );
         assert_eq!(base.atoms.len(), 1);
         assert_eq!(base.atoms[0].shape, HoleShape::Circle { radius: 0.2 });
@@ -83,4 +287,75 @@ mod tests {
         assert_eq!(base.atoms[0].material, material);
         
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_material_at_uses_precedence_and_images() {
+        let lattice = crate::lattice::LatticeType::new_square(Length::meters(1.0), Length::meters(1.0));
+        let air = Material::new_from_eps(1.0);
+        let oxide = Material::new_from_eps(2.1);
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: 0.3 }, (0.5, 0.5, 0.0), air.clone());
+        base.add_atom(HoleShape::Square { side: 0.2 }, (0.5, 0.5, 0.0), oxide.clone());
+        base.add_atom(HoleShape::Circle { radius: 0.1 }, (0.0, 0.0, 0.0), air.clone());
+
+        assert_eq!(*base.material_at(0.5, 0.5, lattice.lattice()), oxide);
+        assert_eq!(*base.material_at(0.5, 0.75, lattice.lattice()), air);
+        assert_eq!(*base.material_at(0.97, 0.02, lattice.lattice()), air);
+        assert_eq!(*base.material_at(0.25, 0.9, lattice.lattice()), base.background_material);
+    }
+
+    #[test]
+    fn test_slice_applies_sidewall_tilt() {
+        let lattice = crate::lattice::LatticeType::new_square(Length::meters(1.0), Length::meters(0.5));
+        let mut base = UnitCellBase::from_simple_circle(0.2, Material::new_from_eps(1.0));
+        base.atoms[0].sidewall_angle = 0.2f64.atan();
+
+        // dz = 0.4 * |a3| / |a1| = 0.2 above the center shrinks the radius by 0.04.
+        let top = base.slice_at(0.4, lattice.lattice());
+        assert!((top.atoms[0].shape.area() - HoleShape::Circle { radius: 0.16 }.area()).abs() < 1e-12);
+        assert_eq!(top.atoms[0].sidewall_angle, 0.0);
+        assert_eq!(base.slice_at(0.0, lattice.lattice()).atoms[0].shape, base.atoms[0].shape);
+        assert!(base.slice_at(3.0, lattice.lattice()).atoms.is_empty());
+    }
+
+    #[test]
+    fn test_atom_index_and_displacement() {
+        let lattice = crate::lattice::LatticeType::new_square(Length::meters(1.0), Length::meters(1.0));
+        let mut base = UnitCellBase::from_simple_circle(0.3, Material::new_from_eps(1.0));
+        base.add_atom(HoleShape::Circle { radius: 0.1 }, (0.0, 0.0, 0.0), Material::new_from_eps(2.1));
+        assert_eq!(base.atom_index_at(0.5, 0.6, lattice.lattice()), Some(0));
+        assert_eq!(base.atom_index_at(0.95, 0.05, lattice.lattice()), Some(1));
+        assert_eq!(base.atom_index_at(0.25, 0.9, lattice.lattice()), None);
+
+        let d = base.displacement_from_atom(1, 0.9, 0.05, lattice.lattice());
+        assert!((d.x + 0.1).abs() < 1e-12 && (d.y - 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lookup_matches_linear_scan() {
+        let lattice = crate::lattice::LatticeType::new_triangular(Length::meters(1.0), Length::meters(1.0));
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: 0.2 }, (0.1, 0.9, 0.0), Material::new_from_eps(1.0));
+        base.add_atom(
+            HoleShape::Ellipse {
+                radius_x: 0.3,
+                radius_y: 0.1,
+                rotation: 0.7,
+            },
+            (0.55, 0.45, 0.0),
+            Material::new_from_eps(2.1),
+        );
+        base.add_atom(HoleShape::Square { side: 0.15 }, (0.6, 0.5, 0.0), Material::new_from_eps(3.0));
+        let supercell = crate::crystal_structure::PhotonicCrystal::new(lattice, base).supercell(3, 2);
+        let (base, lattice) = (&supercell.base, supercell.lattice.lattice());
+        let lookup = base.lookup(lattice, 0.0);
+        assert_eq!(lookup.base().atoms.len(), 18);
+        let n = 97;
+        for i in 0..n {
+            for j in 0..n {
+                let (s, t) = (i as f64 / n as f64 - 0.3, j as f64 / n as f64 + 0.2);
+                assert_eq!(lookup.atom_index_at(s, t), base.atom_index_at(s, t, lattice), "{s}, {t}");
+            }
+        }
+    }
+}
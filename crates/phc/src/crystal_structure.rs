@@ -2,29 +2,45 @@
 
 use super::base::UnitCellBase;
 use super::lattice::LatticeType;
+use super::validation::ValidationReport;
+use serde::{Deserialize, Serialize};
 
 /// Defines the 2D periodic geometry (lattice + base).
 /// This struct no longer contains material properties directly.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PhotonicCrystal {
     pub lattice: LatticeType,
     pub base: UnitCellBase,
 }
 
+impl PhotonicCrystal {
+    /// Creates a new photonic crystal structure given lattice and base.
+    pub fn new(lattice: LatticeType, base: UnitCellBase) -> Self {
+        Self { lattice, base }
+    }
+
+    /// Validates the base against the lattice of this crystal.
+    pub fn validate(&self) -> ValidationReport {
+        self.base.validate(self.lattice.lattice())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::base::UnitCellBase;
     use crate::lattice::LatticeType;
     use core::material::Material;
+    use core::units::Length;
 
     #[test]
     fn test_photonic_crystal_struct_is_pure_geometry() {
-        let a = 295e-9;
-        let h: f64 = 0.25*a;
+        let a = Length::nanometers(295.0);
+        let h = 0.25 * a;
         let air = Material::new_from_eps(1.0);
         let lattice = LatticeType::new_square(a, h);
-        let base = UnitCellBase::from_simple_circle(0.16, air);
+        let base = UnitCellBase::from_simple_circle(0.16, air.clone());
         
         let geom = PhotonicCrystal {
             lattice,
@@ -32,16 +48,8 @@ mod tests {
         };
         
         // This struct just holds geometry
-        assert_eq!(geom.lattice.lattice().a1, core::vectorial::Vector3::new(a, 0.0, 0.0));
+        assert_eq!(geom.lattice.lattice().a1, core::vectorial::Vector3::new(a.as_meters(), 0.0, 0.0));
         assert_eq!(geom.base.atoms.len(), 1);
         assert_eq!(geom.base.atoms[0].material, air);
     }
 }
-
-
-impl PhotonicCrystal {
-    /// Creates a new photonic crystal structure given lattice and base.
-    pub fn new(lattice: LatticeType, base: UnitCellBase) -> Self {
-        Self { lattice, base }
-    }    
-}
\ No newline at end of file
@@ -0,0 +1,321 @@
+//! crates/phc/src/disorder.rs
+//! Supercells and seeded fabrication disorder.
+use core::nalgebra::Matrix2;
+use core::shapes::HoleShape;
+use core::vectorial::Vector2;
+
+use super::base::{AtomInCell, UnitCellBase};
+use super::crystal_structure::PhotonicCrystal;
+use super::lattice::{Lattice, LatticeType};
+use super::validation::wrap_unit;
+
+/// Zero-mean random distribution of a perturbation.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Jitter {
+    /// No perturbation.
+    #[default]
+    None,
+    /// Gaussian with standard deviation `sigma`.
+    Normal { sigma: f64 },
+    /// Uniform on `[-half_width, half_width]`.
+    Uniform { half_width: f64 },
+}
+
+impl Jitter {
+    fn sample(&self, rng: &mut SplitMix64) -> f64 {
+        match *self {
+            Jitter::None => 0.0,
+            Jitter::Normal { sigma } => sigma * rng.next_normal(),
+            Jitter::Uniform { half_width } => half_width * (2.0 * rng.next_f64() - 1.0),
+        }
+    }
+}
+
+/// Independent per-hole fabrication variations.
+///
+/// Lengths are in units of the lattice constant of the primitive crystal.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DisorderModel {
+    /// Change of the hole size, applied as a uniform dilation of the shape.
+    pub radius: Jitter,
+    /// Displacement of the hole center, drawn independently along x and y.
+    pub position: Jitter,
+    /// Ellipticity `e`, stretching the shape to semi-axes `(1 + e)` and `(1 - e)` times the original.
+    /// Circles become ellipses with a uniformly random orientation.
+    pub ellipticity: Jitter,
+    /// Change of the sidewall angle, in radians.
+    pub sidewall_angle: Jitter,
+}
+
+/// One perturbed realization of a supercell.
+#[derive(Debug, Clone)]
+pub struct DisorderedSupercell {
+    /// Seed that reproduces this realization with [`DisorderModel::realize`].
+    pub seed: u64,
+    /// Number of primitive cells along `a1` and `a2`.
+    pub repeats: (usize, usize),
+    pub crystal: PhotonicCrystal,
+}
+
+/// A set of independent realizations drawn from the same model.
+#[derive(Debug, Clone)]
+pub struct DisorderEnsemble {
+    /// Seed from which the seeds of all members are derived.
+    pub seed: u64,
+    pub model: DisorderModel,
+    pub members: Vec<DisorderedSupercell>,
+}
+
+impl PhotonicCrystal {
+    /// Replicates the crystal `nx` times along `a1` and `ny` times along `a2`.
+    ///
+    /// Hole dimensions of the result are expressed in units of the supercell `|a1|`.
+    pub fn supercell(&self, nx: usize, ny: usize) -> PhotonicCrystal {
+        self.replicate((nx, ny), |_| {})
+    }
+
+    fn replicate(&self, (nx, ny): (usize, usize), mut perturb: impl FnMut(&mut Perturbation)) -> PhotonicCrystal {
+        assert!(nx > 0 && ny > 0, "supercell repeats must be positive");
+        let primitive = self.lattice.lattice();
+        let (a1, a2) = primitive.normalized_in_plane_vectors();
+        let to_fractional = Matrix2::from_columns(&[a1, a2])
+            .try_inverse()
+            .expect("lattice vectors must be linearly independent");
+
+        let mut atoms = Vec::with_capacity(nx * ny * self.base.atoms.len());
+        for j in 0..ny {
+            for i in 0..nx {
+                for atom in &self.base.atoms {
+                    let mut perturbation = Perturbation {
+                        shape: atom.shape.clone(),
+                        displacement: Vector2::zeros(),
+                        sidewall_angle: atom.sidewall_angle,
+                    };
+                    perturb(&mut perturbation);
+                    let shift = to_fractional * perturbation.displacement;
+                    atoms.push(AtomInCell {
+                        shape: perturbation.shape.scaled(1.0 / nx as f64),
+                        center: (
+                            wrap_unit((i as f64 + atom.center.0 + shift.x) / nx as f64),
+                            wrap_unit((j as f64 + atom.center.1 + shift.y) / ny as f64),
+                            atom.center.2,
+                        ),
+                        material: atom.material.clone(),
+                        sidewall_angle: perturbation.sidewall_angle,
+                    });
+                }
+            }
+        }
+
+        let lattice = Lattice {
+            a1: primitive.a1 * nx as f64,
+            a2: primitive.a2 * ny as f64,
+            a3: primitive.a3,
+        };
+        let lattice = match self.lattice {
+            LatticeType::Square(_) if nx == ny => LatticeType::Square(lattice),
+            LatticeType::Triangular(_) if nx == ny => LatticeType::Triangular(lattice),
+            _ => LatticeType::Oblique(lattice),
+        };
+        PhotonicCrystal::new(
+            lattice,
+            UnitCellBase {
+                atoms,
+                background_material: self.base.background_material.clone(),
+            },
+        )
+    }
+}
+
+impl DisorderModel {
+    /// Builds one perturbed `nx` x `ny` supercell of `crystal`, fully determined by `seed`.
+    pub fn realize(&self, crystal: &PhotonicCrystal, (nx, ny): (usize, usize), seed: u64) -> DisorderedSupercell {
+        let mut rng = SplitMix64::new(seed);
+        let perturbed = crystal.replicate((nx, ny), |p| self.perturb(p, &mut rng));
+        DisorderedSupercell {
+            seed,
+            repeats: (nx, ny),
+            crystal: perturbed,
+        }
+    }
+
+    /// Builds `count` independent realizations, with per-member seeds derived from `seed`.
+    pub fn ensemble(
+        &self,
+        crystal: &PhotonicCrystal,
+        repeats: (usize, usize),
+        count: usize,
+        seed: u64,
+    ) -> DisorderEnsemble {
+        let mut seeds = SplitMix64::new(seed);
+        DisorderEnsemble {
+            seed,
+            model: *self,
+            members: (0..count)
+                .map(|_| self.realize(crystal, repeats, seeds.next_u64()))
+                .collect(),
+        }
+    }
+
+    fn perturb(&self, p: &mut Perturbation, rng: &mut SplitMix64) {
+        let dr = self.radius.sample(rng);
+        if dr != 0.0 {
+            p.shape = p.shape.dilated(dr);
+        }
+        let e = self.ellipticity.sample(rng);
+        if e != 0.0 {
+            p.shape = stretched(&p.shape, e, rng);
+        }
+        p.displacement = Vector2::new(self.position.sample(rng), self.position.sample(rng));
+        p.sidewall_angle += self.sidewall_angle.sample(rng);
+    }
+}
+
+/// Perturbed attributes of one hole, in primitive-cell units.
+struct Perturbation {
+    shape: HoleShape,
+    displacement: Vector2,
+    sidewall_angle: f64,
+}
+
+/// Stretches the shape by `1 + e` along its first axis and `1 - e` along the second.
+fn stretched(shape: &HoleShape, e: f64, rng: &mut SplitMix64) -> HoleShape {
+    match *shape {
+        HoleShape::Circle { radius } => HoleShape::Ellipse {
+            radius_x: radius * (1.0 + e),
+            radius_y: radius * (1.0 - e),
+            rotation: std::f64::consts::PI * rng.next_f64(),
+        },
+        HoleShape::Ellipse { radius_x, radius_y, rotation } => HoleShape::Ellipse {
+            radius_x: radius_x * (1.0 + e),
+            radius_y: radius_y * (1.0 - e),
+            rotation,
+        },
+        HoleShape::Square { side } => HoleShape::Rectangle {
+            width: side * (1.0 + e),
+            height: side * (1.0 - e),
+        },
+        HoleShape::Rectangle { width, height } => HoleShape::Rectangle {
+            width: width * (1.0 + e),
+            height: height * (1.0 - e),
+        },
+    }
+}
+
+/// SplitMix64 generator. Kept in-crate so that a seed reproduces the same
+/// structure regardless of external crate versions.
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample (Box-Muller).
+    fn next_normal(&mut self) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::units::Length;
+    use super::*;
+    use crate::fill_factor::AreaMethod;
+    use core::material::Material;
+
+    fn table_i_crystal() -> PhotonicCrystal {
+        PhotonicCrystal::new(
+            LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0)),
+            UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0)),
+        )
+    }
+
+    #[test]
+    fn test_supercell_preserves_fill_factor() {
+        let pc = table_i_crystal();
+        let supercell = pc.supercell(3, 2);
+        assert_eq!(supercell.base.atoms.len(), 6);
+        assert!(matches!(supercell.lattice, LatticeType::Oblique(_)));
+        assert_eq!(supercell.base.atoms[3].center, (0.5 / 3.0, 0.75, 0.0));
+        assert!((supercell.base.atoms[3].shape.area() - pc.base.atoms[0].shape.area() / 9.0).abs() < 1e-12);
+
+        let primitive = pc.fill_factor(AreaMethod::Analytic).unwrap();
+        let replicated = supercell.fill_factor(AreaMethod::Analytic).unwrap();
+        assert!((primitive - replicated).abs() < 1e-12);
+        assert!(matches!(pc.supercell(4, 4).lattice, LatticeType::Square(_)));
+    }
+
+    #[test]
+    fn test_realizations_are_reproducible() {
+        let pc = table_i_crystal();
+        let model = DisorderModel {
+            radius: Jitter::Normal { sigma: 0.005 },
+            position: Jitter::Uniform { half_width: 0.01 },
+            ellipticity: Jitter::Normal { sigma: 0.02 },
+            sidewall_angle: Jitter::Normal { sigma: 0.01 },
+        };
+        let ensemble = model.ensemble(&pc, (4, 4), 3, 42);
+        assert_eq!(ensemble.members.len(), 3);
+        assert_ne!(ensemble.members[0].seed, ensemble.members[1].seed);
+
+        let member = &ensemble.members[1];
+        let again = model.realize(&pc, (4, 4), member.seed);
+        assert_eq!(again.crystal.base.atoms, member.crystal.base.atoms);
+        assert_ne!(ensemble.members[0].crystal.base.atoms, member.crystal.base.atoms);
+        assert!(member
+            .crystal
+            .base
+            .atoms
+            .iter()
+            .all(|atom| matches!(atom.shape, HoleShape::Ellipse { .. }) && atom.sidewall_angle != 0.0));
+    }
+
+    #[test]
+    fn test_zero_disorder_matches_supercell() {
+        let pc = table_i_crystal();
+        let realization = DisorderModel::default().realize(&pc, (2, 3), 7);
+        assert_eq!(realization.crystal.base.atoms, pc.supercell(2, 3).base.atoms);
+    }
+
+    #[test]
+    fn test_radius_jitter_statistics() {
+        let pc = table_i_crystal();
+        let model = DisorderModel {
+            radius: Jitter::Normal { sigma: 0.01 },
+            ..Default::default()
+        };
+        let realization = model.realize(&pc, (20, 20), 1);
+        let radii: Vec<f64> = realization
+            .crystal
+            .base
+            .atoms
+            .iter()
+            .map(|atom| match atom.shape {
+                HoleShape::Circle { radius } => radius * 20.0,
+                _ => unreachable!(),
+            })
+            .collect();
+        let mean = radii.iter().sum::<f64>() / radii.len() as f64;
+        let std = (radii.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / radii.len() as f64).sqrt();
+        assert!((mean - 0.16).abs() < 2e-3);
+        assert!((std - 0.01).abs() < 2e-3);
+    }
+}
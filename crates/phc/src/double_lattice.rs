@@ -0,0 +1,154 @@
+//! crates/phc/src/double_lattice.rs
+//! Parametric builder for double-lattice unit cells.
+use core::library::MaterialLibrary;
+use core::material::Material;
+use core::shapes::HoleShape;
+use core::units::Length;
+
+use super::base::UnitCellBase;
+use super::validation::wrap_unit;
+
+/// Parameters of a double-lattice unit cell: two holes per cell, displaced by `offset`.
+///
+/// The pair is centered on the cell, i.e. the primary hole sits at `(0.5, 0.5) - offset / 2`
+/// and the secondary one at `(0.5, 0.5) + offset / 2`, both wrapped into [0, 1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoubleLatticeParams {
+    /// Shape of the primary (usually larger) hole.
+    pub primary: HoleShape,
+    /// Shape of the secondary hole.
+    pub secondary: HoleShape,
+    /// Displacement of the secondary hole with respect to the primary one, in fractional coordinates.
+    pub offset: (f64, f64),
+    pub primary_material: Material,
+    pub secondary_material: Material,
+    pub background_material: Material,
+}
+
+impl DoubleLatticeParams {
+    /// Two holes of the same material displaced by a quarter period along both lattice vectors.
+    ///
+    /// The quarter-period shift makes the backward-diffracted waves of the two sub-lattices
+    /// interfere destructively, as in Yoshida et al., Nat. Mater. 18, 121 (2019).
+    pub fn quarter_shift(
+        primary: HoleShape,
+        secondary: HoleShape,
+        hole_material: Material,
+        background_material: Material,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            offset: (0.25, 0.25),
+            primary_material: hole_material.clone(),
+            secondary_material: hole_material,
+            background_material,
+        }
+    }
+
+    /// Double lattice of the 940 nm laser of Yoshida et al., Nat. Mater. 18, 121 (2019): a
+    /// circular and an elliptical air hole in GaAs, the ellipse shifted by a quarter period
+    /// along both lattice vectors. GaAs is the built-in library entry (Adachi, 1985) at 940 nm.
+    ///
+    /// The paper does not tabulate the hole sizes: the radii below keep the filling factors
+    /// near 7 % and 6 % with the holes clear of each other, and are not taken from it.
+    pub fn yoshida_2019() -> Self {
+        let gaas = MaterialLibrary::builtin()
+            .material("GaAs", Length::nanometers(940.0))
+            .expect("GaAs is in the built-in library at 940 nm");
+        Self::quarter_shift(
+            HoleShape::Circle { radius: 0.15 },
+            HoleShape::Ellipse { radius_x: 0.17, radius_y: 0.11, rotation: 0.0 },
+            Material::new_from_eps(1.0),
+            gaas,
+        )
+    }
+
+    /// Fractional in-plane centers of the primary and secondary holes, wrapped into [0, 1).
+    pub fn centers(&self) -> ((f64, f64), (f64, f64)) {
+        let (dx, dy) = self.offset;
+        (
+            (wrap_unit(0.5 - 0.5 * dx), wrap_unit(0.5 - 0.5 * dy)),
+            (wrap_unit(0.5 + 0.5 * dx), wrap_unit(0.5 + 0.5 * dy)),
+        )
+    }
+}
+
+impl UnitCellBase {
+    /// Creates a double-lattice base from the given parameters.
+    pub fn from_double_lattice(params: &DoubleLatticeParams) -> Self {
+        let ((s1, t1), (s2, t2)) = params.centers();
+        let mut base = Self {
+            atoms: Vec::with_capacity(2),
+            background_material: params.background_material.clone(),
+        };
+        base.add_atom(params.primary.clone(), (s1, t1, 0.0), params.primary_material.clone());
+        base.add_atom(params.secondary.clone(), (s2, t2, 0.0), params.secondary_material.clone());
+        base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crystal_structure::PhotonicCrystal;
+    use crate::lattice::LatticeType;
+    use crate::symmetry::PointGroup;
+
+    fn air_holes_in_gaas() -> DoubleLatticeParams {
+        DoubleLatticeParams::quarter_shift(
+            HoleShape::Circle { radius: 0.16 },
+            HoleShape::Circle { radius: 0.10 },
+            Material::new_from_eps(1.0),
+            Material::new_from_eps(12.7449),
+        )
+    }
+
+    #[test]
+    fn test_quarter_shift_double_lattice() {
+        let base = UnitCellBase::from_double_lattice(&air_holes_in_gaas());
+        assert_eq!(base.atoms.len(), 2);
+        assert_eq!(base.atoms[0].center, (0.375, 0.375, 0.0));
+        assert_eq!(base.atoms[1].center, (0.625, 0.625, 0.0));
+        assert_eq!(base.background_material, Material::new_from_eps(12.7449));
+
+        let pc = PhotonicCrystal::new(LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0)), base);
+        let report = pc.validate();
+        assert!(report.is_valid());
+        assert!(report.warnings.is_empty());
+        assert_eq!(pc.symmetry(0.0).point_group, PointGroup::Cs);
+    }
+
+    #[test]
+    fn test_yoshida_2019() {
+        let params = DoubleLatticeParams::yoshida_2019();
+        assert_eq!(params.offset, (0.25, 0.25));
+        let base = UnitCellBase::from_double_lattice(&params);
+        assert_eq!(base.atoms.len(), 2);
+        assert_eq!(base.atoms[0].center, (0.375, 0.375, 0.0));
+        assert_eq!(base.atoms[0].shape, HoleShape::Circle { radius: 0.15 });
+        assert_eq!(base.atoms[1].center, (0.625, 0.625, 0.0));
+        assert_eq!(base.atoms[1].shape, HoleShape::Ellipse { radius_x: 0.17, radius_y: 0.11, rotation: 0.0 });
+        assert!(base.atoms.iter().all(|atom| atom.material == Material::new_from_eps(1.0)));
+        // GaAs has n = 3.552 at 940 nm.
+        let epsilon = base.background_material.epsilon_at(Length::nanometers(940.0))[(0, 0)];
+        assert!((epsilon - 3.552f64.powi(2)).abs() < 1e-2, "{epsilon}");
+
+        // The holes do not touch: the midpoint between them is GaAs.
+        let lattice = LatticeType::new_square(Length::nanometers(277.0), Length::nanometers(100.0));
+        assert_eq!(base.atom_index_at(0.5, 0.5, lattice.lattice()), None);
+        let pc = PhotonicCrystal::new(lattice, base);
+        let report = pc.validate();
+        assert!(report.is_valid());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn test_large_offsets_are_wrapped() {
+        let mut params = air_holes_in_gaas();
+        params.offset = (1.5, -0.5);
+        let base = UnitCellBase::from_double_lattice(&params);
+        assert_eq!(base.atoms[0].center, (0.75, 0.75, 0.0));
+        assert_eq!(base.atoms[1].center, (0.25, 0.25, 0.0));
+    }
+}
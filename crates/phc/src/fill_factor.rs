@@ -0,0 +1,262 @@
+//! crates/phc/src/fill_factor.rs
+//! Area fractions and averaged permittivities of a photonic crystal unit cell.
+use std::fmt;
+
+use core::material::{ComplexDielectricTensor, DielectricTensor, Material};
+use core::units::Length;
+
+use super::crystal_structure::PhotonicCrystal;
+use super::validation::{BaseError, BaseWarning};
+
+/// Strategy used to compute the area covered by the atoms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AreaMethod {
+    /// Exact shape areas. Requires atoms that do not overlap.
+    Analytic,
+    /// Midpoint sampling on a `resolution` x `resolution` grid of fractional coordinates.
+    /// Overlaps are resolved by atom precedence, see [`crate::base::UnitCellBase::material_at`].
+    Raster { resolution: usize },
+}
+
+/// Fraction of the unit cell area occupied by one material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterialFraction {
+    pub material: Material,
+    pub fraction: f64,
+}
+
+/// Reasons why area fractions cannot be computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillFactorError {
+    /// The base did not pass validation.
+    InvalidBase(Vec<BaseError>),
+    /// Analytic areas were requested for atoms that overlap.
+    OverlappingAtoms,
+    /// A raster with zero resolution was requested.
+    ZeroResolution,
+}
+
+impl fmt::Display for FillFactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FillFactorError::InvalidBase(errors) => {
+                write!(f, "invalid unit cell base:")?;
+                for error in errors {
+                    write!(f, " {error};")?;
+                }
+                Ok(())
+            }
+            FillFactorError::OverlappingAtoms => {
+                write!(f, "analytic areas require non-overlapping atoms, use a raster estimate")
+            }
+            FillFactorError::ZeroResolution => write!(f, "raster resolution must be positive"),
+        }
+    }
+}
+
+impl std::error::Error for FillFactorError {}
+
+impl PhotonicCrystal {
+    /// Computes the area fraction of every distinct material in the unit cell.
+    ///
+    /// The background material is always listed first, even if its fraction is zero.
+    pub fn material_fractions(&self, method: AreaMethod) -> Result<Vec<MaterialFraction>, FillFactorError> {
+        let mut fractions = vec![MaterialFraction {
+            material: self.base.background_material.clone(),
+            fraction: 0.0,
+        }];
+        let mut accumulate = |material: &Material, fraction: f64| {
+            match fractions.iter_mut().find(|entry| entry.material == *material) {
+                Some(entry) => entry.fraction += fraction,
+                None => fractions.push(MaterialFraction {
+                    material: material.clone(),
+                    fraction,
+                }),
+            }
+        };
+
+        match method {
+            AreaMethod::Analytic => {
+                let report = self.validate();
+                if !report.is_valid() {
+                    return Err(FillFactorError::InvalidBase(report.errors));
+                }
+                if report.warnings.iter().any(|w| matches!(w, BaseWarning::Overlap { .. })) {
+                    return Err(FillFactorError::OverlappingAtoms);
+                }
+                let lattice = self.lattice.lattice();
+                let cell_area = lattice.unit_cell_area() / lattice.a1.norm_squared();
+                let mut covered = 0.0;
+                for atom in &self.base.atoms {
+                    let fraction = atom.shape.area() / cell_area;
+                    covered += fraction;
+                    accumulate(&atom.material, fraction);
+                }
+                accumulate(&self.base.background_material, 1.0 - covered);
+            }
+            AreaMethod::Raster { resolution } => {
+                if resolution == 0 {
+                    return Err(FillFactorError::ZeroResolution);
+                }
+                let lookup = self.base.lookup(self.lattice.lattice(), 0.0);
+                let weight = 1.0 / (resolution * resolution) as f64;
+                for i in 0..resolution {
+                    let s = (i as f64 + 0.5) / resolution as f64;
+                    for j in 0..resolution {
+                        let t = (j as f64 + 0.5) / resolution as f64;
+                        accumulate(lookup.material_at(s, t), weight);
+                    }
+                }
+            }
+        }
+        Ok(fractions)
+    }
+
+    /// Fraction of the unit cell not occupied by the background material.
+    pub fn fill_factor(&self, method: AreaMethod) -> Result<f64, FillFactorError> {
+        let fractions = self.material_fractions(method)?;
+        Ok(1.0 - fractions[0].fraction)
+    }
+
+    /// Area-weighted average of the dielectric tensor, i.e. the G = 0 Fourier coefficient of epsilon.
+    pub fn average_epsilon(&self, method: AreaMethod) -> Result<DielectricTensor, FillFactorError> {
+        Ok(self
+            .material_fractions(method)?
+            .iter()
+            .map(|entry| entry.material.epsilon_matrix * entry.fraction)
+            .sum())
+    }
+
+    /// Area-weighted average of the complex dielectric tensor, keeping absorption and gain.
+    pub fn average_complex_epsilon(&self, method: AreaMethod) -> Result<ComplexDielectricTensor, FillFactorError> {
+        Ok(self
+            .material_fractions(method)?
+            .iter()
+            .map(|entry| entry.material.complex_epsilon() * core::vectorial::Complex::from(entry.fraction))
+            .sum())
+    }
+
+    /// Like [`Self::average_complex_epsilon`], with dispersive materials evaluated at `wavelength`.
+    pub fn average_complex_epsilon_at(
+        &self,
+        wavelength: Length,
+        method: AreaMethod,
+    ) -> Result<ComplexDielectricTensor, FillFactorError> {
+        Ok(self
+            .material_fractions(method)?
+            .iter()
+            .map(|entry| entry.material.complex_epsilon_at(wavelength) * core::vectorial::Complex::from(entry.fraction))
+            .sum())
+    }
+
+    /// Area-weighted average of the inverse dielectric tensor.
+    ///
+    /// Materials with a singular tensor do not contribute.
+    pub fn average_inverse_epsilon(&self, method: AreaMethod) -> Result<DielectricTensor, FillFactorError> {
+        Ok(self
+            .material_fractions(method)?
+            .iter()
+            .map(|entry| {
+                entry
+                    .material
+                    .epsilon_matrix
+                    .try_inverse()
+                    .unwrap_or_else(DielectricTensor::zeros)
+                    * entry.fraction
+            })
+            .sum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::UnitCellBase;
+    use crate::lattice::LatticeType;
+    use core::shapes::HoleShape;
+    use std::f64::consts::PI;
+
+    fn table_i_crystal(lattice: LatticeType) -> PhotonicCrystal {
+        let air = Material::new_from_eps(1.0);
+        let mut base = UnitCellBase::from_simple_circle(0.16, air);
+        base.background_material = Material::new_from_eps(12.7449);
+        PhotonicCrystal::new(lattice, base)
+    }
+
+    #[test]
+    fn test_analytic_and_raster_agree() {
+        let pc = table_i_crystal(LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(100.0)));
+        let exact = pc.fill_factor(AreaMethod::Analytic).unwrap();
+        assert!((exact - PI * 0.16 * 0.16).abs() < 1e-12);
+
+        let raster = pc.fill_factor(AreaMethod::Raster { resolution: 400 }).unwrap();
+        assert!((raster - exact).abs() < 1e-3);
+
+        let eps = pc.average_epsilon(AreaMethod::Analytic).unwrap();
+        let expected = exact * 1.0 + (1.0 - exact) * 12.7449;
+        assert!((eps[(0, 0)] - expected).abs() < 1e-12);
+        assert!(eps[(0, 1)].abs() < 1e-12);
+
+        let complex = pc.average_complex_epsilon(AreaMethod::Analytic).unwrap();
+        assert!((complex[(0, 0)].re - eps[(0, 0)]).abs() < 1e-12);
+        assert_eq!(complex[(0, 0)].im, 0.0);
+
+        let inv = pc.average_inverse_epsilon(AreaMethod::Analytic).unwrap();
+        let expected = exact / 1.0 + (1.0 - exact) / 12.7449;
+        assert!((inv[(2, 2)] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_lossy_background_is_averaged() {
+        let mut pc = table_i_crystal(LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)));
+        pc.base.background_material = Material::new_from_complex_eps(core::vectorial::Complex::new(12.7449, 0.01));
+        let fill = pc.fill_factor(AreaMethod::Analytic).unwrap();
+        let eps = pc.average_complex_epsilon(AreaMethod::Analytic).unwrap();
+        assert!((eps[(1, 1)].im - (1.0 - fill) * 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dispersive_materials_are_averaged_at_wavelength() {
+        use core::dispersion::Dispersion;
+
+        let mut pc = table_i_crystal(LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)));
+        let cauchy = Dispersion::Cauchy {
+            coefficients: vec![3.4, 0.1],
+        };
+        pc.base.background_material = Material::new_dispersive(cauchy, Length::micrometers(1.0));
+        let fill = pc.fill_factor(AreaMethod::Analytic).unwrap();
+        let at = |um: f64| pc.average_complex_epsilon_at(Length::micrometers(um), AreaMethod::Analytic).unwrap();
+        let n = |um: f64| 3.4 + 0.1 / (um * um);
+        assert!((at(1.0)[(0, 0)].re - pc.average_complex_epsilon(AreaMethod::Analytic).unwrap()[(0, 0)].re).abs() < 1e-12);
+        assert!((at(0.5)[(0, 0)].re - (fill + (1.0 - fill) * n(0.5).powi(2))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_triangular_cell_area() {
+        let pc = table_i_crystal(LatticeType::new_triangular(Length::meters(1.0), Length::meters(1.0)));
+        let exact = pc.fill_factor(AreaMethod::Analytic).unwrap();
+        assert!((exact - PI * 0.16 * 0.16 / (3f64.sqrt() / 2.0)).abs() < 1e-9);
+        let raster = pc.fill_factor(AreaMethod::Raster { resolution: 400 }).unwrap();
+        assert!((raster - exact).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_overlaps_resolved_by_precedence() {
+        let air = Material::new_from_eps(1.0);
+        let oxide = Material::new_from_eps(2.1);
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Square { side: 0.5 }, (0.5, 0.5, 0.0), air);
+        base.add_atom(HoleShape::Square { side: 0.25 }, (0.5, 0.5, 0.0), oxide);
+        let pc = PhotonicCrystal::new(LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)), base);
+
+        assert_eq!(
+            pc.material_fractions(AreaMethod::Analytic),
+            Err(FillFactorError::OverlappingAtoms)
+        );
+        let fractions = pc.material_fractions(AreaMethod::Raster { resolution: 64 }).unwrap();
+        assert_eq!(fractions.len(), 3);
+        assert!((fractions[1].fraction - (0.25 - 0.0625)).abs() < 1e-12);
+        assert!((fractions[2].fraction - 0.0625).abs() < 1e-12);
+        assert!((pc.fill_factor(AreaMethod::Raster { resolution: 64 }).unwrap() - 0.25).abs() < 1e-12);
+    }
+}
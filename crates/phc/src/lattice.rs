@@ -1,6 +1,8 @@
 //! crates/core/src/lattice.rs
 use core::vectorial::{Vector3, Vector2};
 use core::nalgebra;
+use core::units::{Length, Loss};
+use serde::{Deserialize, Serialize};
 // --- Type alias for 3D Lattice Vectors ---
 pub type LatticeBaseVector = Vector3;
 pub type LatticeInPlaneVector = Vector2;
@@ -22,11 +24,40 @@ impl Lattice {
         (self.a1.xy(), self.a2.xy())
     }
 
+    /// Returns the in-plane lattice vectors scaled so that `|a1| = 1`.
+    ///
+    /// Hole dimensions are expressed in these units.
+    pub fn normalized_in_plane_vectors(&self) -> (LatticeInPlaneVector, LatticeInPlaneVector) {
+        let (a1, a2) = self.in_plane_vectors();
+        let a = a1.norm();
+        (a1 / a, a2 / a)
+    }
+
+    /// Lattice constant `a = |a1|`, the length unit of normalized quantities.
+    pub fn lattice_constant(&self) -> Length {
+        Length::meters(self.a1.norm())
+    }
+
+    /// Normalized frequency `a/λ` of the vacuum `wavelength`.
+    pub fn normalized_frequency(&self, wavelength: Length) -> f64 {
+        wavelength.normalized_frequency(self.lattice_constant())
+    }
+
+    /// Vacuum wavelength at the normalized frequency `a/λ`.
+    pub fn wavelength_at(&self, a_over_lambda: f64) -> Length {
+        Length::from_normalized_frequency(a_over_lambda, self.lattice_constant())
+    }
+
+    /// Normalized loss `α a`.
+    pub fn normalized_loss(&self, loss: Loss) -> f64 {
+        loss.normalized(self.lattice_constant())
+    }
+
     /// Calculates the area of the 2D in-plane unit cell.
     pub fn unit_cell_area(&self) -> f64 {
         let (a1_2d, a2_2d) = self.in_plane_vectors();
         let m  = nalgebra::Matrix2::from_columns(&[a1_2d, a2_2d]);
-        m.determinant().abs()*0.5
+        m.determinant().abs()
     }
 
     /// Calculates the unit cell volume.
@@ -34,18 +65,73 @@ impl Lattice {
         let v = self.a1.dot(&self.a2.cross(&self.a3));
         v.abs()
     }
+
+    /// Vertices of the in-plane Wigner-Seitz cell around the lattice point at the origin,
+    /// counter-clockwise.
+    pub fn wigner_seitz_cell(&self) -> Vec<LatticeInPlaneVector> {
+        // Lagrange-Gauss reduction: the nearest neighbours are then among ±b1, ±b2 and ±b1 ± b2.
+        let (mut b1, mut b2) = self.in_plane_vectors();
+        loop {
+            if b2.norm_squared() < b1.norm_squared() {
+                std::mem::swap(&mut b1, &mut b2);
+            }
+            let mu = (b1.dot(&b2) / b1.norm_squared()).round();
+            if mu == 0.0 {
+                break;
+            }
+            b2 -= b1 * mu;
+        }
+        let size = 2.0 * b2.norm();
+        let mut cell = vec![
+            Vector2::new(-size, -size),
+            Vector2::new(size, -size),
+            Vector2::new(size, size),
+            Vector2::new(-size, size),
+        ];
+        for neighbour in [b1, b2, b1 + b2, b1 - b2, -b1, -b2, -b1 - b2, b2 - b1] {
+            // Keep the side of the bisector of the origin and `neighbour` holding the origin.
+            let limit = neighbour.norm_squared() / 2.0;
+            let inside = |p: &Vector2| p.dot(&neighbour) <= limit;
+            let mut clipped = Vec::with_capacity(cell.len() + 1);
+            for (i, p) in cell.iter().enumerate() {
+                let q = &cell[(i + 1) % cell.len()];
+                if inside(p) {
+                    clipped.push(*p);
+                }
+                if inside(p) != inside(q) {
+                    let f = (limit - p.dot(&neighbour)) / (q - p).dot(&neighbour);
+                    clipped.push(p + (q - p) * f);
+                }
+            }
+            cell = clipped;
+        }
+        // Bisectors through a vertex leave repeated points behind.
+        let tolerance = 1e-9 * b1.norm();
+        cell.dedup_by(|p, q| (*p - *q).norm() < tolerance);
+        if cell.len() > 1 && (cell[0] - cell[cell.len() - 1]).norm() < tolerance {
+            cell.pop();
+        }
+        cell
+    }
 }
 
 /// Enum to define specific types of 2D lattices.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Serialized with a `kind` tag. Square and triangular lattices are given by their lattice
+/// constant `a` and out-of-plane period `height`, oblique ones by the vectors `a1`, `a2`, `a3`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "LatticeSpec", into = "LatticeSpec")]
 pub enum LatticeType {
     Square(Lattice),
     Triangular(Lattice),
+    /// Any other lattice, e.g. the rectangular lattice of an N x M supercell.
+    Oblique(Lattice),
 }
 
 impl LatticeType {
-    /// Creates a new square lattice with lattice constant `a`.
-    pub fn new_square(a: f64, h: f64) -> Self {
+    /// Creates a new square lattice with lattice constant `a` and out-of-plane period `h`.
+    pub fn new_square(a: Length, h: Length) -> Self {
+        let (a, h) = (a.as_meters(), h.as_meters());
         LatticeType::Square(Lattice {
             a1: Vector3::new(a, 0.0, 0.0),
             a2: Vector3::new(0.0, a, 0.0),
@@ -53,8 +139,9 @@ impl LatticeType {
         })
     }
 
-    /// Creates a new triangular (hexagonal) lattice with lattice constant `a`.
-    pub fn new_triangular(a: f64, h: f64) -> Self {
+    /// Creates a new triangular (hexagonal) lattice with lattice constant `a` and out-of-plane period `h`.
+    pub fn new_triangular(a: Length, h: Length) -> Self {
+        let (a, h) = (a.as_meters(), h.as_meters());
         LatticeType::Triangular(Lattice {
             a1: Vector3::new(a, 0.0, 0.0),
             a2: Vector3::new(a * 0.5, a * 0.86602540378, 0.0), // (a/2, a*sqrt(3)/2)
@@ -67,6 +154,55 @@ impl LatticeType {
         match self {
             LatticeType::Square(lat) => lat,
             LatticeType::Triangular(lat) => lat,
+            LatticeType::Oblique(lat) => lat,
+        }
+    }
+}
+
+/// Serialized form of a [`LatticeType`].
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum LatticeSpec {
+    Square { a: Length, height: Length },
+    Triangular { a: Length, height: Length },
+    /// Lattice vectors in whatever form the named constructors cannot reproduce exactly.
+    Oblique { a1: [Length; 3], a2: [Length; 3], a3: [Length; 3] },
+}
+
+impl TryFrom<LatticeSpec> for LatticeType {
+    type Error = String;
+
+    fn try_from(spec: LatticeSpec) -> Result<Self, Self::Error> {
+        let lattice = match spec {
+            LatticeSpec::Square { a, height } => LatticeType::new_square(a, height),
+            LatticeSpec::Triangular { a, height } => LatticeType::new_triangular(a, height),
+            LatticeSpec::Oblique { a1, a2, a3 } => {
+                let vector = |v: [Length; 3]| Vector3::new(v[0].as_meters(), v[1].as_meters(), v[2].as_meters());
+                LatticeType::Oblique(Lattice { a1: vector(a1), a2: vector(a2), a3: vector(a3) })
+            }
+        };
+        let lattice_ref = lattice.lattice();
+        if !(lattice_ref.unit_cell_area() > 0.0 && lattice_ref.unit_cell_volume() > 0.0) {
+            return Err("the lattice vectors must be linearly independent".to_string());
+        }
+        Ok(lattice)
+    }
+}
+
+impl From<LatticeType> for LatticeSpec {
+    fn from(lattice: LatticeType) -> Self {
+        let a = lattice.lattice().lattice_constant();
+        let height = Length::meters(lattice.lattice().a3.z);
+        match lattice {
+            LatticeType::Square(_) if lattice == LatticeType::new_square(a, height) => LatticeSpec::Square { a, height },
+            LatticeType::Triangular(_) if lattice == LatticeType::new_triangular(a, height) => {
+                LatticeSpec::Triangular { a, height }
+            }
+            _ => {
+                let lattice = lattice.lattice();
+                let vector = |v: &Vector3| [Length::meters(v.x), Length::meters(v.y), Length::meters(v.z)];
+                LatticeSpec::Oblique { a1: vector(&lattice.a1), a2: vector(&lattice.a2), a3: vector(&lattice.a3) }
+            }
         }
     }
 }
@@ -75,11 +211,40 @@ impl LatticeType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_lattice_type_serialization() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            lattice: LatticeType,
+        }
+        let round_trip = |lattice: LatticeType| {
+            let text = toml::to_string(&Holder { lattice: lattice.clone() }).unwrap();
+            assert_eq!(toml::from_str::<Holder>(&text).unwrap().lattice, lattice, "{text}");
+            text
+        };
+        let (a, h) = (Length::nanometers(295.0), Length::nanometers(100.0));
+        assert_eq!(
+            round_trip(LatticeType::new_square(a, h)),
+            "[lattice]\nkind = \"square\"\na = \"295 nm\"\nheight = \"100 nm\"\n"
+        );
+        assert!(round_trip(LatticeType::new_triangular(a, h)).contains("kind = \"triangular\""));
+        let mut stretched = LatticeType::new_square(a, h).lattice().clone();
+        stretched.a2.y *= 2.0;
+        assert!(round_trip(LatticeType::Oblique(stretched.clone())).contains("kind = \"oblique\""));
+        // A lattice the named constructor cannot reproduce is written out by its vectors.
+        let text = toml::to_string(&Holder { lattice: LatticeType::Square(stretched.clone()) }).unwrap();
+        assert_eq!(toml::from_str::<Holder>(&text).unwrap().lattice, LatticeType::Oblique(stretched));
+
+        let flat = "[lattice]\nkind = \"square\"\na = \"295 nm\"\nheight = \"0 nm\"\n";
+        assert!(toml::from_str::<Holder>(flat).unwrap_err().message().contains("linearly independent"));
+        assert!(toml::from_str::<Holder>("[lattice]\nkind = \"square\"\na = 295e-9\nheight = \"1 um\"\n").is_err());
+    }
+
     #[test]
     fn test_lattice_type_constructors() {
         let a = 1e-6;
         let h = 0.25*a;
-        let square_lat = LatticeType::new_square(a, h);
+        let square_lat = LatticeType::new_square(Length::meters(a), Length::meters(h));
         let lattice = square_lat.lattice();
 
         assert_eq!(lattice.a1, Vector3::new(a, 0.0, 0.0));
@@ -87,7 +252,7 @@ mod tests {
         assert!((lattice.unit_cell_area() - a * a).abs() < 1e-12);
         assert!((lattice.unit_cell_volume() - a * a * h).abs() < 1e-12);
 
-        let tri_lat = LatticeType::new_triangular(a, h);
+        let tri_lat = LatticeType::new_triangular(Length::meters(a), Length::meters(h));
         let lattice = tri_lat.lattice();
         let expected_area = a * (a * 0.86602540378);
 
@@ -97,14 +262,50 @@ mod tests {
         assert!((lattice.unit_cell_volume() - expected_area * h).abs() < 1e-12);
     }
 
+    #[test]
+    fn test_wigner_seitz_cell() {
+        let square = LatticeType::new_square(Length::meters(2.0), Length::meters(1.0));
+        let cell = square.lattice().wigner_seitz_cell();
+        assert_eq!(cell.len(), 4);
+        assert!(cell.iter().all(|p| (p.x.abs() - 1.0).abs() < 1e-12 && (p.y.abs() - 1.0).abs() < 1e-12), "{cell:?}");
+
+        let triangular = LatticeType::new_triangular(Length::meters(1.0), Length::meters(1.0));
+        let lattice = triangular.lattice();
+        let cell = lattice.wigner_seitz_cell();
+        assert_eq!(cell.len(), 6);
+        assert!(cell.iter().all(|p| (p.norm() - 1.0 / 3f64.sqrt()).abs() < 1e-9), "{cell:?}");
+        let area = |cell: &[Vector2]| {
+            (0..cell.len()).map(|i| cell[i].perp(&cell[(i + 1) % cell.len()])).sum::<f64>() / 2.0
+        };
+        assert!((area(&cell) - lattice.unit_cell_area()).abs() < 1e-12);
+
+        // A sheared basis of the same square lattice gives the same cell.
+        let mut sheared = square.lattice().clone();
+        sheared.a2 += sheared.a1 * 3.0;
+        let cell = sheared.wigner_seitz_cell();
+        assert_eq!(cell.len(), 4);
+        assert!((area(&cell) - 4.0).abs() < 1e-12);
+    }
+
     #[test]
     fn test_in_plane_vectors() {
         let a = 100.0;
         let h = 50.0;
-        let square_lat = LatticeType::new_square(a, h);
+        let square_lat = LatticeType::new_square(Length::meters(a), Length::meters(h));
         let lat = square_lat.lattice();
         let (a1_2d, a2_2d) = lat.in_plane_vectors();
         assert_eq!(a1_2d, Vector2::new(a, 0.0));
         assert_eq!(a2_2d, Vector2::new(0.0, a));
     }
+
+    #[test]
+    fn test_normalized_units() {
+        let square_lat = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let lat = square_lat.lattice();
+        assert_eq!(lat.lattice_constant(), Length::nanometers(295.0));
+        let u = lat.normalized_frequency(Length::nanometers(940.0));
+        assert!((u - 295.0 / 940.0).abs() < 1e-12);
+        assert!((lat.wavelength_at(u).as_nanometers() - 940.0).abs() < 1e-9);
+        assert!((lat.normalized_loss(Loss::per_centimeter(10.0)) - 2.95e-4).abs() < 1e-15);
+    }
 }
\ No newline at end of file
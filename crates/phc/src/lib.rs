@@ -1,4 +1,9 @@
 // Declare the modules. Rust will look for `material.rs`, `lattice.rs`, etc.
 pub mod base;
 pub mod lattice;
-pub mod crystal_structure;
\ No newline at end of file
+pub mod crystal_structure;
+pub mod validation;
+pub mod fill_factor;
+pub mod symmetry;
+pub mod double_lattice;
+pub mod disorder;
@@ -0,0 +1,347 @@
+//! crates/phc/src/symmetry.rs
+//! Detection of the point-group symmetry of a photonic crystal unit cell.
+use std::fmt;
+
+use core::material::Material;
+use core::nalgebra::Matrix2;
+use core::vectorial::Vector2;
+
+use super::crystal_structure::PhotonicCrystal;
+use super::lattice::Lattice;
+
+/// Number of samples per lattice direction used to compare the permittivity maps.
+const SAMPLES: usize = 64;
+/// Rotation orders allowed by the crystallographic restriction in 2D.
+const ROTATION_ORDERS: [u32; 4] = [2, 3, 4, 6];
+/// Mirror line orientations (degrees from `a1`) compatible with square and triangular lattices.
+const MIRROR_ANGLES: [f64; 8] = [0.0, 30.0, 45.0, 60.0, 90.0, 120.0, 135.0, 150.0];
+/// Tolerance used to decide whether a rotated lattice vector is a lattice vector.
+const LATTICE_TOLERANCE: f64 = 1e-6;
+
+/// A point symmetry operation acting on the in-plane coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymmetryOperation {
+    /// Rotation by `2π / order`.
+    Rotation { order: u32 },
+    /// Reflection across a line at `angle` degrees from `a1`.
+    Mirror { angle: f64 },
+}
+
+impl SymmetryOperation {
+    /// Matrix of the operation in normalized Cartesian coordinates.
+    pub fn matrix(&self) -> Matrix2<f64> {
+        match *self {
+            SymmetryOperation::Rotation { order } => {
+                let theta = 2.0 * std::f64::consts::PI / order as f64;
+                let (sin, cos) = theta.sin_cos();
+                Matrix2::new(cos, -sin, sin, cos)
+            }
+            SymmetryOperation::Mirror { angle } => {
+                let (sin, cos) = (2.0 * angle.to_radians()).sin_cos();
+                Matrix2::new(cos, sin, sin, -cos)
+            }
+        }
+    }
+}
+
+impl fmt::Display for SymmetryOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymmetryOperation::Rotation { order } => write!(f, "C{order}"),
+            SymmetryOperation::Mirror { angle } => write!(f, "σ({angle}°)"),
+        }
+    }
+}
+
+/// The 2D crystallographic point groups, in Schoenflies notation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointGroup {
+    C1,
+    Cs,
+    C2,
+    C2v,
+    C3,
+    C3v,
+    C4,
+    C4v,
+    C6,
+    C6v,
+}
+
+impl PointGroup {
+    /// Builds the group from its highest rotation order and whether it contains mirrors.
+    pub fn from_generators(rotation_order: u32, has_mirror: bool) -> Self {
+        match (rotation_order, has_mirror) {
+            (6, true) => PointGroup::C6v,
+            (6, false) => PointGroup::C6,
+            (4, true) => PointGroup::C4v,
+            (4, false) => PointGroup::C4,
+            (3, true) => PointGroup::C3v,
+            (3, false) => PointGroup::C3,
+            (2, true) => PointGroup::C2v,
+            (2, false) => PointGroup::C2,
+            (_, true) => PointGroup::Cs,
+            (_, false) => PointGroup::C1,
+        }
+    }
+
+    /// Number of elements of the group.
+    pub fn order(&self) -> usize {
+        match self {
+            PointGroup::C1 => 1,
+            PointGroup::Cs | PointGroup::C2 => 2,
+            PointGroup::C3 => 3,
+            PointGroup::C2v | PointGroup::C4 => 4,
+            PointGroup::C3v | PointGroup::C6 => 6,
+            PointGroup::C4v => 8,
+            PointGroup::C6v => 12,
+        }
+    }
+}
+
+impl fmt::Display for PointGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PointGroup::C1 => "C1",
+            PointGroup::Cs => "Cs",
+            PointGroup::C2 => "C2",
+            PointGroup::C2v => "C2v",
+            PointGroup::C3 => "C3",
+            PointGroup::C3v => "C3v",
+            PointGroup::C4 => "C4",
+            PointGroup::C4v => "C4v",
+            PointGroup::C6 => "C6",
+            PointGroup::C6v => "C6v",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Result of the symmetry analysis of a photonic crystal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymmetryAnalysis {
+    /// The point group of the lattice + base.
+    pub point_group: PointGroup,
+    /// Fractional in-plane coordinates of the symmetry center.
+    pub center: (f64, f64),
+    /// Generating rotations and all mirror lines satisfied about `center`.
+    pub operations: Vec<SymmetryOperation>,
+}
+
+impl SymmetryAnalysis {
+    /// Returns `true` if the structure is invariant under the given operation about `center`.
+    pub fn contains(&self, operation: SymmetryOperation) -> bool {
+        match operation {
+            SymmetryOperation::Rotation { order } => {
+                let highest = self.rotation_order();
+                order == 1 || highest.is_multiple_of(order)
+            }
+            SymmetryOperation::Mirror { angle } => self.operations.iter().any(|op| {
+                matches!(op, SymmetryOperation::Mirror { angle: a } if (a - angle).rem_euclid(180.0) < 1e-9)
+            }),
+        }
+    }
+
+    /// Order of the highest rotation axis.
+    pub fn rotation_order(&self) -> u32 {
+        self.operations
+            .iter()
+            .filter_map(|op| match op {
+                SymmetryOperation::Rotation { order } => Some(*order),
+                SymmetryOperation::Mirror { .. } => None,
+            })
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+impl PhotonicCrystal {
+    /// Determines the point group of the crystal.
+    ///
+    /// The permittivity map is sampled on a grid and compared with its image under every
+    /// candidate operation. `tolerance` is the fraction of samples allowed to differ.
+    /// Candidate centers are the atom centers, the midpoints between atoms and the
+    /// high-symmetry points of the cell; the center yielding the largest group is returned.
+    pub fn symmetry(&self, tolerance: f64) -> SymmetryAnalysis {
+        let lattice = self.lattice.lattice();
+        let (a1, a2) = lattice.normalized_in_plane_vectors();
+        let to_fractional = Matrix2::from_columns(&[a1, a2])
+            .try_inverse()
+            .expect("lattice vectors must be linearly independent");
+
+        let samples: Vec<(Vector2, &Material)> = (0..SAMPLES * SAMPLES)
+            .map(|k| {
+                let s = ((k / SAMPLES) as f64 + 0.5) / SAMPLES as f64;
+                let t = ((k % SAMPLES) as f64 + 0.5) / SAMPLES as f64;
+                (a1 * s + a2 * t, self.base.material_at(s, t, lattice))
+            })
+            .collect();
+        let allowed_mismatches = (tolerance * samples.len() as f64).floor() as usize;
+
+        let is_invariant = |operation: SymmetryOperation, center: Vector2| {
+            if !maps_lattice_onto_itself(lattice, operation) {
+                return false;
+            }
+            let m = operation.matrix();
+            let mut mismatches = 0;
+            for (p, material) in &samples {
+                let q = to_fractional * (center + m * (p - center));
+                if self.base.material_at(q.x, q.y, lattice) != *material {
+                    mismatches += 1;
+                    if mismatches > allowed_mismatches {
+                        return false;
+                    }
+                }
+            }
+            true
+        };
+
+        let mut best: Option<SymmetryAnalysis> = None;
+        for (s, t) in self.candidate_centers() {
+            let center = a1 * s + a2 * t;
+            let mut operations: Vec<SymmetryOperation> = ROTATION_ORDERS
+                .iter()
+                .map(|&order| SymmetryOperation::Rotation { order })
+                .filter(|&op| is_invariant(op, center))
+                .collect();
+            operations.extend(
+                MIRROR_ANGLES
+                    .iter()
+                    .map(|&angle| SymmetryOperation::Mirror { angle })
+                    .filter(|&op| is_invariant(op, center)),
+            );
+
+            let mut analysis = SymmetryAnalysis {
+                point_group: PointGroup::C1,
+                center: (s, t),
+                operations,
+            };
+            let has_mirror = analysis
+                .operations
+                .iter()
+                .any(|op| matches!(op, SymmetryOperation::Mirror { .. }));
+            analysis.point_group = PointGroup::from_generators(analysis.rotation_order(), has_mirror);
+
+            if best
+                .as_ref()
+                .is_none_or(|b| analysis.point_group.order() > b.point_group.order())
+            {
+                best = Some(analysis);
+            }
+        }
+        best.expect("the cell origin is always a candidate center")
+    }
+
+    /// Fractional positions tested as symmetry centers, without duplicates.
+    fn candidate_centers(&self) -> Vec<(f64, f64)> {
+        let atoms = &self.base.atoms;
+        let mut candidates: Vec<(f64, f64)> = atoms.iter().map(|atom| (atom.center.0, atom.center.1)).collect();
+        for (i, first) in atoms.iter().enumerate() {
+            for second in atoms.iter().skip(i + 1) {
+                let s = 0.5 * (first.center.0 + second.center.0);
+                let t = 0.5 * (first.center.1 + second.center.1);
+                candidates.extend([(s, t), (s + 0.5, t), (s, t + 0.5), (s + 0.5, t + 0.5)]);
+            }
+        }
+        candidates.extend([
+            (0.0, 0.0),
+            (0.5, 0.0),
+            (0.0, 0.5),
+            (0.5, 0.5),
+            (1.0 / 3.0, 1.0 / 3.0),
+            (2.0 / 3.0, 2.0 / 3.0),
+        ]);
+
+        let mut unique: Vec<(f64, f64)> = Vec::new();
+        for (s, t) in candidates {
+            let (s, t) = (s.rem_euclid(1.0), t.rem_euclid(1.0));
+            let seen = unique.iter().any(|&(u, v)| {
+                let (ds, dt) = (s - u, t - v);
+                (ds - ds.round()).abs() < 1e-9 && (dt - dt.round()).abs() < 1e-9
+            });
+            if !seen {
+                unique.push((s, t));
+            }
+        }
+        unique
+    }
+}
+
+/// Checks that the operation maps both lattice vectors onto integer combinations of them.
+fn maps_lattice_onto_itself(lattice: &Lattice, operation: SymmetryOperation) -> bool {
+    let (a1, a2) = lattice.normalized_in_plane_vectors();
+    let basis = Matrix2::from_columns(&[a1, a2]);
+    let Some(to_fractional) = basis.try_inverse() else {
+        return false;
+    };
+    let image = to_fractional * operation.matrix() * basis;
+    image.iter().all(|c| (c - c.round()).abs() < LATTICE_TOLERANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::units::Length;
+    use super::*;
+    use crate::base::UnitCellBase;
+    use crate::lattice::LatticeType;
+    use core::shapes::HoleShape;
+
+    fn crystal(lattice: LatticeType, atoms: &[(HoleShape, (f64, f64))]) -> PhotonicCrystal {
+        let mut base = UnitCellBase::new();
+        for (shape, (s, t)) in atoms {
+            base.add_atom(shape.clone(), (*s, *t, 0.0), Material::new_from_eps(1.0));
+        }
+        PhotonicCrystal::new(lattice, base)
+    }
+
+    #[test]
+    fn test_simple_lattices() {
+        let square = crystal(
+            LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(100.0)),
+            &[(HoleShape::Circle { radius: 0.16 }, (0.5, 0.5))],
+        );
+        let analysis = square.symmetry(0.0);
+        assert_eq!(analysis.point_group, PointGroup::C4v);
+        assert_eq!(analysis.center, (0.5, 0.5));
+        assert!(analysis.contains(SymmetryOperation::Rotation { order: 2 }));
+        assert!(analysis.contains(SymmetryOperation::Mirror { angle: 45.0 }));
+        assert!(!analysis.contains(SymmetryOperation::Mirror { angle: 30.0 }));
+
+        let triangular = crystal(
+            LatticeType::new_triangular(Length::meters(1.0), Length::meters(1.0)),
+            &[(HoleShape::Circle { radius: 0.2 }, (0.0, 0.0))],
+        );
+        assert_eq!(triangular.symmetry(0.0).point_group, PointGroup::C6v);
+    }
+
+    #[test]
+    fn test_reduced_symmetries() {
+        let rectangle = crystal(
+            LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)),
+            &[(HoleShape::Rectangle { width: 0.3, height: 0.2 }, (0.5, 0.5))],
+        );
+        assert_eq!(rectangle.symmetry(0.0).point_group, PointGroup::C2v);
+
+        // Two different holes along the diagonal only keep the diagonal mirror.
+        let double = crystal(
+            LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)),
+            &[
+                (HoleShape::Circle { radius: 0.15 }, (0.3, 0.3)),
+                (HoleShape::Circle { radius: 0.1 }, (0.55, 0.55)),
+            ],
+        );
+        let analysis = double.symmetry(0.0);
+        assert_eq!(analysis.point_group, PointGroup::Cs);
+        assert_eq!(analysis.operations, vec![SymmetryOperation::Mirror { angle: 45.0 }]);
+    }
+
+    #[test]
+    fn test_tolerance_absorbs_small_deviations() {
+        let nearly_square = crystal(
+            LatticeType::new_square(Length::meters(1.0), Length::meters(1.0)),
+            &[(HoleShape::Rectangle { width: 0.3, height: 0.36 }, (0.5, 0.5))],
+        );
+        assert_eq!(nearly_square.symmetry(1e-3).point_group, PointGroup::C2v);
+        assert_eq!(nearly_square.symmetry(0.05).point_group, PointGroup::C4v);
+    }
+}
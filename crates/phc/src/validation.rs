@@ -0,0 +1,290 @@
+//! crates/phc/src/validation.rs
+//! Consistency checks for the atoms of a unit cell base.
+use std::fmt;
+
+use core::shapes::HoleShape;
+use core::vectorial::Vector2;
+
+use super::base::UnitCellBase;
+use super::lattice::Lattice;
+
+/// Penetration depth (in units of the lattice constant) below which two shapes are considered touching.
+const OVERLAP_TOLERANCE: f64 = 1e-9;
+
+/// Lattice translations of the eight nearest periodic images.
+const NEIGHBOUR_IMAGES: [(i32, i32); 8] = [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)];
+
+/// A configuration that makes the base physically meaningless.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BaseError {
+    /// The atom has a zero or negative dimension.
+    NonPositiveDimension { atom: usize },
+    /// The atom overlaps its own periodic image, i.e. it does not fit in the cell.
+    WiderThanCell { atom: usize },
+    /// The atoms cover the whole cell or more.
+    FillFactorTooLarge { fill_factor: f64 },
+}
+
+impl fmt::Display for BaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseError::NonPositiveDimension { atom } => {
+                write!(f, "atom {atom} has a non-positive dimension")
+            }
+            BaseError::WiderThanCell { atom } => {
+                write!(f, "atom {atom} overlaps its own periodic image")
+            }
+            BaseError::FillFactorTooLarge { fill_factor } => {
+                write!(f, "fill factor {fill_factor:.4} is not below 1")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BaseError {}
+
+/// A configuration that is suspicious but may be intentional.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BaseWarning {
+    /// Atom `first` overlaps atom `second` translated by `image` lattice vectors.
+    Overlap { first: usize, second: usize, image: (i32, i32) },
+    /// The in-plane center of the atom lies outside [0, 1).
+    CenterOutsideCell { atom: usize },
+}
+
+impl fmt::Display for BaseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseWarning::Overlap { first, second, image } => write!(
+                f,
+                "atom {first} overlaps atom {second} shifted by ({}, {}) lattice vectors",
+                image.0, image.1
+            ),
+            BaseWarning::CenterOutsideCell { atom } => {
+                write!(f, "center of atom {atom} lies outside the unit cell")
+            }
+        }
+    }
+}
+
+/// Outcome of [`UnitCellBase::validate`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    pub errors: Vec<BaseError>,
+    pub warnings: Vec<BaseWarning>,
+}
+
+impl ValidationReport {
+    /// Returns `true` if no errors were found. Warnings are allowed.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Converts the report into the warnings on success, or the errors on failure.
+    pub fn into_result(self) -> Result<Vec<BaseWarning>, Vec<BaseError>> {
+        if self.errors.is_empty() {
+            Ok(self.warnings)
+        } else {
+            Err(self.errors)
+        }
+    }
+}
+
+impl UnitCellBase {
+    /// Checks the atoms against the given lattice.
+    ///
+    /// Shape dimensions are interpreted in units of the lattice constant `|a1|`,
+    /// and overlaps are searched among the nearest periodic images.
+    pub fn validate(&self, lattice: &Lattice) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let (a1, a2) = lattice.normalized_in_plane_vectors();
+
+        for (i, atom) in self.atoms.iter().enumerate() {
+            if !atom.shape.has_positive_dimensions() {
+                report.errors.push(BaseError::NonPositiveDimension { atom: i });
+            }
+            let (s, t, _) = atom.center;
+            if !(0.0..1.0).contains(&s) || !(0.0..1.0).contains(&t) {
+                report.warnings.push(BaseWarning::CenterOutsideCell { atom: i });
+            }
+        }
+        if !report.errors.is_empty() {
+            // Overlap tests are meaningless for degenerate shapes.
+            return report;
+        }
+
+        for (i, atom) in self.atoms.iter().enumerate() {
+            let overlaps_own_image = NEIGHBOUR_IMAGES
+                .iter()
+                .any(|&(m, n)| shapes_overlap(&atom.shape, &atom.shape, a1 * m as f64 + a2 * n as f64));
+            if overlaps_own_image {
+                report.errors.push(BaseError::WiderThanCell { atom: i });
+            }
+        }
+
+        for (i, first) in self.atoms.iter().enumerate() {
+            for (j, second) in self.atoms.iter().enumerate().skip(i + 1) {
+                // Reduce the separation to the nearest image before scanning its neighbours.
+                let ds = second.center.0 - first.center.0;
+                let dt = second.center.1 - first.center.1;
+                let (ns, nt) = (ds.round(), dt.round());
+                for (m, n) in std::iter::once((0, 0)).chain(NEIGHBOUR_IMAGES) {
+                    let offset = a1 * (ds - ns + m as f64) + a2 * (dt - nt + n as f64);
+                    if shapes_overlap(&first.shape, &second.shape, offset) {
+                        report.warnings.push(BaseWarning::Overlap {
+                            first: i,
+                            second: j,
+                            image: (m - ns as i32, n - nt as i32),
+                        });
+                    }
+                }
+            }
+        }
+
+        let cell_area = a1.perp(&a2).abs();
+        let fill_factor = self.atoms.iter().map(|atom| atom.shape.area()).sum::<f64>() / cell_area;
+        if fill_factor >= 1.0 {
+            report.errors.push(BaseError::FillFactorTooLarge { fill_factor });
+        }
+        report
+    }
+
+    /// Wraps the in-plane fractional centers of all atoms into [0, 1).
+    pub fn wrap_centers(&mut self) {
+        for atom in &mut self.atoms {
+            atom.center.0 = wrap_unit(atom.center.0);
+            atom.center.1 = wrap_unit(atom.center.1);
+        }
+    }
+}
+
+/// Wraps `x` into [0, 1), guarding against `rem_euclid` rounding up to exactly 1.
+pub(crate) fn wrap_unit(x: f64) -> f64 {
+    let w = x.rem_euclid(1.0);
+    if w >= 1.0 {
+        0.0
+    } else {
+        w
+    }
+}
+
+/// Tests whether `second`, centered at `offset` from `first`, overlaps `first`.
+fn shapes_overlap(first: &HoleShape, second: &HoleShape, offset: Vector2) -> bool {
+    match (first, second) {
+        (HoleShape::Ellipse { .. }, _) | (_, HoleShape::Ellipse { .. }) => sampled_overlap(first, second, offset),
+        (HoleShape::Circle { radius: r1 }, HoleShape::Circle { radius: r2 }) => {
+            offset.norm() < r1 + r2 - OVERLAP_TOLERANCE
+        }
+        (HoleShape::Circle { radius }, other) => circle_box_overlap(*radius, other.half_extents(), offset),
+        (other, HoleShape::Circle { radius }) => circle_box_overlap(*radius, other.half_extents(), offset),
+        _ => {
+            let (hx1, hy1) = first.half_extents();
+            let (hx2, hy2) = second.half_extents();
+            offset.x.abs() < hx1 + hx2 - OVERLAP_TOLERANCE && offset.y.abs() < hy1 + hy2 - OVERLAP_TOLERANCE
+        }
+    }
+}
+
+/// Overlap test by sampling the intersection of the bounding boxes, for shapes without a closed form.
+fn sampled_overlap(first: &HoleShape, second: &HoleShape, offset: Vector2) -> bool {
+    const SAMPLES: usize = 32;
+    let (hx1, hy1) = first.half_extents();
+    let (hx2, hy2) = second.half_extents();
+    let (x0, x1) = ((-hx1).max(offset.x - hx2), hx1.min(offset.x + hx2));
+    let (y0, y1) = ((-hy1).max(offset.y - hy2), hy1.min(offset.y + hy2));
+    if x1 - x0 <= OVERLAP_TOLERANCE || y1 - y0 <= OVERLAP_TOLERANCE {
+        return false;
+    }
+    (0..SAMPLES * SAMPLES).any(|k| {
+        let x = x0 + (x1 - x0) * ((k / SAMPLES) as f64 + 0.5) / SAMPLES as f64;
+        let y = y0 + (y1 - y0) * ((k % SAMPLES) as f64 + 0.5) / SAMPLES as f64;
+        first.contains(x, y) && second.contains(x - offset.x, y - offset.y)
+    })
+}
+
+/// Overlap between a circle and an axis-aligned box whose centers are `offset` apart.
+fn circle_box_overlap(radius: f64, (hx, hy): (f64, f64), offset: Vector2) -> bool {
+    let dx = (offset.x.abs() - hx).max(0.0);
+    let dy = (offset.y.abs() - hy).max(0.0);
+    (dx * dx + dy * dy).sqrt() < radius - OVERLAP_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::units::Length;
+    use crate::lattice::LatticeType;
+    use core::material::Material;
+
+    fn air() -> Material {
+        Material::new_from_eps(1.0)
+    }
+
+    #[test]
+    fn test_simple_circle_is_valid() {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(100.0));
+        let base = UnitCellBase::from_simple_circle(0.16, air());
+        let report = base.validate(lattice.lattice());
+        assert!(report.is_valid());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_errors_for_degenerate_and_oversized_atoms() {
+        let lattice = LatticeType::new_square(Length::meters(1.0), Length::meters(1.0));
+
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: -0.1 }, (0.5, 0.5, 0.0), air());
+        let report = base.validate(lattice.lattice());
+        assert_eq!(report.errors, vec![BaseError::NonPositiveDimension { atom: 0 }]);
+
+        let base = UnitCellBase::from_simple_circle(0.55, air());
+        let errors = base.validate(lattice.lattice()).into_result().unwrap_err();
+        assert!(errors.contains(&BaseError::WiderThanCell { atom: 0 }));
+
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Square { side: 1.0 }, (0.5, 0.5, 0.0), air());
+        let errors = base.validate(lattice.lattice()).into_result().unwrap_err();
+        assert!(errors.iter().any(|e| matches!(e, BaseError::FillFactorTooLarge { .. })));
+    }
+
+    #[test]
+    fn test_overlap_across_periodic_boundary() {
+        let lattice = LatticeType::new_square(Length::meters(1.0), Length::meters(1.0));
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: 0.1 }, (0.05, 0.5, 0.0), air());
+        base.add_atom(HoleShape::Rectangle { width: 0.2, height: 0.1 }, (0.9, 0.5, 0.0), air());
+
+        let warnings = base.validate(lattice.lattice()).into_result().unwrap();
+        assert_eq!(
+            warnings,
+            vec![BaseWarning::Overlap { first: 0, second: 1, image: (-1, 0) }]
+        );
+    }
+
+    #[test]
+    fn test_triangular_lattice_images() {
+        // On a triangular lattice the nearest image along a2 is one lattice constant away.
+        let lattice = LatticeType::new_triangular(Length::meters(1.0), Length::meters(1.0));
+        let base = UnitCellBase::from_simple_circle(0.49, air());
+        assert!(base.validate(lattice.lattice()).is_valid());
+        let base = UnitCellBase::from_simple_circle(0.51, air());
+        assert!(!base.validate(lattice.lattice()).is_valid());
+    }
+
+    #[test]
+    fn test_wrap_centers() {
+        let mut base = UnitCellBase::new();
+        base.add_atom(HoleShape::Circle { radius: 0.1 }, (1.25, -0.25, 0.3), air());
+        base.add_atom(HoleShape::Circle { radius: 0.1 }, (-1e-17, 0.5, 0.0), air());
+        let lattice = LatticeType::new_square(Length::meters(1.0), Length::meters(1.0));
+        assert!(base
+            .validate(lattice.lattice())
+            .warnings
+            .contains(&BaseWarning::CenterOutsideCell { atom: 0 }));
+
+        base.wrap_centers();
+        assert_eq!(base.atoms[0].center, (0.25, 0.75, 0.3));
+        assert_eq!(base.atoms[1].center, (0.0, 0.5, 0.0));
+    }
+}
@@ -0,0 +1,41 @@
+//! Draws the Table I crystal as SVG and its ε slice and cross-section as PNG.
+//!
+//! Usage: `cargo run -p render --example figures [output directory]`.
+use std::path::PathBuf;
+
+use core::material::Material;
+use core::units::Length;
+use geom_builder::{rasterize_cross_section, rasterize_slice_xy, CutLine};
+use phc::base::UnitCellBase;
+use phc::crystal_structure::PhotonicCrystal;
+use phc::lattice::LatticeType;
+use render::{save_epsilon_png, unit_cell_svg, PngOptions, SvgOptions};
+use waveguide::layers::Waveguide;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let directory = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".into()));
+    std::fs::create_dir_all(&directory)?;
+
+    let gaas = Material::new_from_eps(12.7449);
+    let lattice = LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0));
+    let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+    base.background_material = gaas.clone();
+    let crystal = PhotonicCrystal::new(lattice, base);
+
+    let svg = unit_cell_svg(&crystal, &SvgOptions { periods: (2, 2), ..SvgOptions::default() });
+    std::fs::write(directory.join("unit_cell.svg"), svg)?;
+
+    let lattice = crystal.lattice.lattice().clone();
+    let wg = Waveguide::new_from_table_i(crystal, gaas);
+    let index = wg.layer_index().ok_or("no photonic crystal layer")?;
+    let below: f64 = wg.layers[..index].iter().map(|layer| layer.thickness().as_meters()).sum();
+    let z = Length::meters(below + wg.layers[index].thickness().as_meters() / 2.0);
+    let slice = rasterize_slice_xy(&wg, z, (128, 128))?;
+    save_epsilon_png(directory.join("epsilon_xy.png"), slice.view(), &PngOptions::default())?;
+
+    let cut = CutLine::along_lattice(&lattice, (0.0, 0.5), (1, 0), 4.0);
+    let section = rasterize_cross_section(&wg, &cut, (256, 256))?;
+    save_epsilon_png(directory.join("epsilon_xz.png"), section.view(), &PngOptions::default())?;
+    println!("figures written to {}", directory.display());
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! crates/render/src/colormap.rs
+//! Mapping of scalar values to colors.
+use std::fmt;
+use std::str::FromStr;
+
+/// Perceptually ordered color scales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    /// Dark blue to yellow, the matplotlib default.
+    #[default]
+    Viridis,
+    /// Black through red to pale yellow.
+    Inferno,
+    /// Black to white.
+    Gray,
+}
+
+/// Colors at equally spaced points of the scale, from matplotlib.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [72, 40, 120],
+    [62, 73, 137],
+    [49, 104, 142],
+    [38, 130, 142],
+    [31, 158, 137],
+    [53, 183, 121],
+    [110, 206, 88],
+    [253, 231, 37],
+];
+
+const INFERNO: [[u8; 3]; 9] = [
+    [0, 0, 4],
+    [31, 12, 72],
+    [85, 15, 109],
+    [136, 34, 106],
+    [186, 54, 85],
+    [227, 89, 51],
+    [249, 140, 10],
+    [249, 201, 50],
+    [252, 255, 164],
+];
+
+impl Colormap {
+    /// Color at the fraction `t` of the scale, clamped to `[0, 1]`.
+    pub fn color(&self, t: f64) -> [u8; 3] {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let stops = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Inferno => &INFERNO,
+            Colormap::Gray => return [(255.0 * t).round() as u8; 3],
+        };
+        let position = t * (stops.len() - 1) as f64;
+        let i = (position.floor() as usize).min(stops.len() - 2);
+        let f = position - i as f64;
+        std::array::from_fn(|c| (stops[i][c] as f64 * (1.0 - f) + stops[i + 1][c] as f64 * f).round() as u8)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Colormap::Viridis => "viridis",
+            Colormap::Inferno => "inferno",
+            Colormap::Gray => "gray",
+        }
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        [Colormap::Viridis, Colormap::Inferno, Colormap::Gray]
+            .into_iter()
+            .find(|map| map.name() == text.trim().to_ascii_lowercase())
+            .ok_or_else(|| format!("unknown colormap {text:?}, expected viridis, inferno or gray"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_colormaps() {
+        assert_eq!(Colormap::Viridis.color(0.0), VIRIDIS[0]);
+        assert_eq!(Colormap::Viridis.color(1.0), VIRIDIS[8]);
+        assert_eq!(Colormap::Viridis.color(2.0), VIRIDIS[8]);
+        assert_eq!(Colormap::Inferno.color(0.5), INFERNO[4]);
+        assert_eq!(Colormap::Gray.color(0.5), [128; 3]);
+        // Halfway between two stops.
+        assert_eq!(Colormap::Viridis.color(1.0 / 16.0), [70, 21, 102]);
+        assert_eq!("Inferno".parse::<Colormap>(), Ok(Colormap::Inferno));
+        assert!("jet".parse::<Colormap>().is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! crates/render/src/epsilon.rs
+//! Permittivity maps as PNG images.
+//!
+//! Maps are indexed `[i, j]` with `i` along the horizontal axis and `j` upwards, the layout of
+//! the slices and cross-sections of `geom_builder`.
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use ndarray::ArrayView2;
+
+use super::colormap::Colormap;
+use super::RenderError;
+
+/// How a map is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PngOptions {
+    pub colormap: Colormap,
+    /// Values at the ends of the colormap, by default the extremes of the map.
+    pub range: Option<(f64, f64)>,
+    /// Side of the square of pixels drawn for each sample.
+    pub pixel_size: usize,
+    /// Whether a labelled colorbar is drawn to the right of the map.
+    pub colorbar: bool,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            colormap: Colormap::Viridis,
+            range: None,
+            pixel_size: 2,
+            colorbar: true,
+        }
+    }
+}
+
+/// An RGB image, rows from the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![WHITE; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        self.pixels[y * self.width + x]
+    }
+
+    fn fill(&mut self, (x, y): (usize, usize), (width, height): (usize, usize), color: [u8; 3]) {
+        for row in y..y + height {
+            self.pixels[row * self.width + x..row * self.width + x + width].fill(color);
+        }
+    }
+
+    fn text(&mut self, (x, y): (usize, usize), text: &str) {
+        for (k, c) in text.chars().enumerate() {
+            let rows = glyph(c);
+            for (r, bits) in rows.iter().enumerate() {
+                for b in 0..3 {
+                    if bits & (0b100 >> b) != 0 {
+                        let at = (x + k * GLYPH_ADVANCE + b * FONT_SCALE, y + r * FONT_SCALE);
+                        self.fill(at, (FONT_SCALE, FONT_SCALE), BLACK);
+                    }
+                }
+            }
+        }
+    }
+}
+
+const WHITE: [u8; 3] = [255; 3];
+const BLACK: [u8; 3] = [0; 3];
+const MARGIN: usize = 4;
+const GAP: usize = 10;
+const BAR_WIDTH: usize = 16;
+const MIN_BAR_HEIGHT: usize = 64;
+const TICK: usize = 4;
+const FONT_SCALE: usize = 2;
+const GLYPH_ADVANCE: usize = 4 * FONT_SCALE;
+const GLYPH_HEIGHT: usize = 5 * FONT_SCALE;
+
+/// 3 x 5 bitmaps of the characters of colorbar labels, rows from the top.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        'e' => [0b111, 0b100, 0b111, 0b100, 0b111],
+        _ => [0; 5],
+    }
+}
+
+/// Label of a colorbar tick.
+fn label(value: f64) -> String {
+    if value != 0.0 && !(1e-2..1e4).contains(&value.abs()) {
+        format!("{value:.2e}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+/// Draws `map` with `options`. Samples that are not finite are left white.
+pub fn epsilon_image(map: ArrayView2<f64>, options: &PngOptions) -> Result<Image, RenderError> {
+    let (nx, ny) = map.dim();
+    if nx == 0 || ny == 0 || options.pixel_size == 0 {
+        return Err(RenderError::Options("nothing to draw in an empty map".into()));
+    }
+    let (low, high) = color_range(map, options)?;
+    // A uniform map is drawn in the middle of the scale.
+    let (low, high) = if low == high { (low - 0.5, high + 0.5) } else { (low, high) };
+    let fraction = |value: f64| (value - low) / (high - low);
+
+    let scale = options.pixel_size;
+    let (width, height) = (nx * scale, ny * scale);
+    let origin = if options.colorbar { (MARGIN, MARGIN) } else { (0, 0) };
+    let bar_height = height.max(MIN_BAR_HEIGHT);
+    let labels = [label(high), label((low + high) / 2.0), label(low)];
+    let mut image = if options.colorbar {
+        let label_width = labels.iter().map(String::len).max().unwrap_or(0) * GLYPH_ADVANCE;
+        Image::new(
+            2 * MARGIN + width + GAP + BAR_WIDTH + TICK + 2 + label_width,
+            2 * MARGIN + bar_height,
+        )
+    } else {
+        Image::new(width, height)
+    };
+
+    for ((i, j), &value) in map.indexed_iter() {
+        if value.is_finite() {
+            let at = (origin.0 + i * scale, origin.1 + (ny - 1 - j) * scale);
+            image.fill(at, (scale, scale), options.colormap.color(fraction(value)));
+        }
+    }
+
+    if options.colorbar {
+        let x = MARGIN + width + GAP;
+        for row in 0..bar_height {
+            let t = 1.0 - row as f64 / (bar_height - 1) as f64;
+            image.fill((x, MARGIN + row), (BAR_WIDTH, 1), options.colormap.color(t));
+        }
+        let ticks = [MARGIN, MARGIN + (bar_height - 1) / 2, MARGIN + bar_height - 1];
+        for (tick, text) in ticks.iter().zip(&labels) {
+            image.fill((x + BAR_WIDTH, *tick), (TICK, 1), BLACK);
+            let top = tick.saturating_sub(GLYPH_HEIGHT / 2).clamp(MARGIN, MARGIN + bar_height - GLYPH_HEIGHT);
+            image.text((x + BAR_WIDTH + TICK + 2, top), text);
+        }
+    }
+    Ok(image)
+}
+
+/// Writes `map` as a PNG image, with its color range in a text chunk.
+pub fn write_epsilon_png(out: impl Write, map: ArrayView2<f64>, options: &PngOptions) -> Result<(), RenderError> {
+    let image = epsilon_image(map, options)?;
+    let mut encoder = png::Encoder::new(out, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let (low, high) = color_range(map, options)?;
+    encoder.add_text_chunk("Comment".into(), format!("epsilon from {low} to {high}, colormap {}", options.colormap))?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(image.pixels.as_flattened())?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes `map` to the PNG file `path`.
+pub fn save_epsilon_png(path: impl AsRef<Path>, map: ArrayView2<f64>, options: &PngOptions) -> Result<(), RenderError> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write_epsilon_png(&mut out, map, options)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Values at the ends of the colormap.
+fn color_range(map: ArrayView2<f64>, options: &PngOptions) -> Result<(f64, f64), RenderError> {
+    let (low, high) = match options.range {
+        Some(range) => range,
+        None => map
+            .iter()
+            .filter(|value| value.is_finite())
+            .fold(None, |range: Option<(f64, f64)>, &value| match range {
+                Some((low, high)) => Some((low.min(value), high.max(value))),
+                None => Some((value, value)),
+            })
+            .ok_or_else(|| RenderError::Options("the map has no finite value".into()))?,
+    };
+    if !(low.is_finite() && high.is_finite() && low <= high) {
+        return Err(RenderError::Options(format!("invalid color range {low} to {high}")));
+    }
+    Ok((low, high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::units::Length;
+    use core::material::Material;
+    use ndarray::{array, Array2};
+    use phc::base::UnitCellBase;
+    use phc::crystal_structure::PhotonicCrystal;
+    use phc::lattice::LatticeType;
+    use waveguide::layers::Waveguide;
+
+    #[test]
+    fn test_map_orientation_and_colorbar() {
+        let map = array![[1.0, 2.0, 3.0], [4.0, 5.0, f64::NAN]];
+        let plain = PngOptions {
+            pixel_size: 1,
+            colorbar: false,
+            ..PngOptions::default()
+        };
+        let image = epsilon_image(map.view(), &plain).unwrap();
+        assert_eq!((image.width, image.height), (2, 3));
+        // [0, 0] is the bottom left pixel, [1, 2] the top right one.
+        assert_eq!(image.pixel(0, 2), Colormap::Viridis.color(0.0));
+        assert_eq!(image.pixel(1, 1), Colormap::Viridis.color(1.0));
+        assert_eq!(image.pixel(1, 0), WHITE);
+
+        let image = epsilon_image(map.view(), &PngOptions::default()).unwrap();
+        assert_eq!(image.height, 2 * MARGIN + MIN_BAR_HEIGHT);
+        assert_eq!(image.pixel(MARGIN, MARGIN + 5), Colormap::Viridis.color(0.0));
+        let bar = MARGIN + 4 + GAP;
+        assert_eq!(image.pixel(bar, MARGIN), Colormap::Viridis.color(1.0));
+        assert_eq!(image.pixel(bar, MARGIN + MIN_BAR_HEIGHT - 1), Colormap::Viridis.color(0.0));
+        // Labels "5.00", "3.00" and "1.00" are drawn in black next to the ticks.
+        assert_eq!(image.width, bar + BAR_WIDTH + TICK + 2 + 4 * GLYPH_ADVANCE + MARGIN);
+        assert!(image.pixels.iter().filter(|&&pixel| pixel == BLACK).count() > 3 * TICK);
+
+        let empty = Array2::<f64>::zeros((0, 4));
+        assert!(matches!(epsilon_image(empty.view(), &plain), Err(RenderError::Options(_))));
+        let undefined = array![[f64::NAN]];
+        assert!(epsilon_image(undefined.view(), &plain).is_err());
+        assert!(epsilon_image(map.view(), &PngOptions { range: Some((2.0, 1.0)), ..plain }).is_err());
+    }
+
+    #[test]
+    fn test_png_of_a_rasterized_slice() {
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let crystal = PhotonicCrystal::new(lattice, UnitCellBase::from_simple_circle(0.25, Material::new_from_eps(1.0)));
+        let wg = Waveguide::new_from_table_i(crystal, Material::new_from_eps(12.7449));
+        let index = wg.layer_index().unwrap();
+        let below = wg.layers[..index].iter().map(|layer| layer.thickness().as_meters()).sum::<f64>();
+        let z = below + wg.layers[index].thickness().as_meters() / 2.0;
+        let slice = geom_builder::rasterize_slice_xy(&wg, Length::meters(z), (32, 32)).unwrap();
+
+        let mut bytes = Vec::new();
+        write_epsilon_png(&mut bytes, slice.view(), &PngOptions::default()).unwrap();
+        let mut reader = png::Decoder::new(std::io::Cursor::new(bytes)).read_info().unwrap();
+        let comment = &reader.info().uncompressed_latin1_text[0];
+        let high = slice.fold(f64::NEG_INFINITY, |high, &value| high.max(value));
+        assert_eq!(comment.text, format!("epsilon from 1 to {high}, colormap viridis"));
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+        let image = epsilon_image(slice.view(), &PngOptions::default()).unwrap();
+        assert_eq!((frame.width as usize, frame.height as usize), (image.width, image.height));
+        assert_eq!(&pixels[..frame.buffer_size()], image.pixels.as_flattened());
+        // The hole is in the middle of the cell, the slab at its corners.
+        assert_eq!(image.pixel(MARGIN + 32, MARGIN + 32), Colormap::Viridis.color(0.0));
+        assert_eq!(image.pixel(MARGIN, MARGIN), Colormap::Viridis.color(1.0));
+    }
+}
@@ -0,0 +1,56 @@
+//! crates/render/src/lib.rs
+//!
+//! Headless figures of structures, for reports and CI artifacts.
+//!
+//! [`unit_cell`] draws a photonic crystal as SVG, [`epsilon`] writes permittivity maps, such as
+//! the slices rasterized by `geom_builder`, as PNG images with a colorbar.
+use std::fmt;
+
+pub mod colormap;
+pub mod epsilon;
+pub mod unit_cell;
+
+pub use colormap::Colormap;
+pub use epsilon::{epsilon_image, save_epsilon_png, write_epsilon_png, Image, PngOptions};
+pub use unit_cell::{unit_cell_svg, SvgOptions};
+
+/// Reasons why a figure cannot be drawn or written.
+#[derive(Debug)]
+pub enum RenderError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    /// The data or the options leave nothing to draw.
+    Options(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Io(error) => write!(f, "{error}"),
+            RenderError::Png(error) => write!(f, "cannot encode PNG: {error}"),
+            RenderError::Options(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Io(error) => Some(error),
+            RenderError::Png(error) => Some(error),
+            RenderError::Options(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(error: std::io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for RenderError {
+    fn from(error: png::EncodingError) -> Self {
+        RenderError::Png(error)
+    }
+}
@@ -0,0 +1,290 @@
+//! crates/render/src/unit_cell.rs
+//! Vector drawings of photonic crystal unit cells.
+use std::fmt::Write;
+
+use core::material::Material;
+use core::nalgebra::Matrix2;
+use core::shapes::HoleShape;
+use core::vectorial::Vector2;
+use phc::crystal_structure::PhotonicCrystal;
+
+/// What a drawing of a crystal shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgOptions {
+    /// Number of unit cells outlined along `a1` and `a2`.
+    pub periods: (usize, usize),
+    /// Pixels per lattice constant.
+    pub scale: f64,
+    pub lattice_vectors: bool,
+    pub wigner_seitz: bool,
+    /// Whether the permittivity of each color is listed below the drawing.
+    pub legend: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            periods: (1, 1),
+            scale: 240.0,
+            lattice_vectors: true,
+            wigner_seitz: true,
+            legend: true,
+        }
+    }
+}
+
+/// Fills of the background and of the materials of the atoms, in order of appearance.
+const PALETTE: [&str; 10] = [
+    "#bdbdbd", "#ffffff", "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+const MARGIN: f64 = 0.1;
+const LEGEND_ROW: f64 = 20.0;
+
+/// Draws `crystal` as an SVG document.
+///
+/// The drawing covers `options.periods` unit cells from the lattice point at the origin and the
+/// Wigner-Seitz cell around it, filled with the crystal. Holes are drawn with their shape at
+/// their center height, colored by material.
+pub fn unit_cell_svg(crystal: &PhotonicCrystal, options: &SvgOptions) -> String {
+    let lattice = crystal.lattice.lattice();
+    let (a1, a2) = lattice.normalized_in_plane_vectors();
+    let a = lattice.a1.norm();
+    let wigner_seitz: Vec<Vector2> = lattice.wigner_seitz_cell().iter().map(|p| p / a).collect();
+    let (n1, n2) = (options.periods.0.max(1) as f64, options.periods.1.max(1) as f64);
+    let corners = [Vector2::zeros(), a1 * n1, a1 * n1 + a2 * n2, a2 * n2];
+
+    // Window in lattice units, y upwards.
+    let shown = corners.iter().chain(if options.wigner_seitz { &wigner_seitz[..] } else { &[] });
+    let (mut low, mut high) = (Vector2::repeat(f64::INFINITY), Vector2::repeat(f64::NEG_INFINITY));
+    for p in shown {
+        low = low.inf(p);
+        high = high.sup(p);
+    }
+    let (low, high) = (low.add_scalar(-MARGIN), high.add_scalar(MARGIN));
+    let scale = options.scale;
+    let (width, height) = ((high.x - low.x) * scale, (high.y - low.y) * scale);
+    let at = |p: Vector2| ((p.x - low.x) * scale, (high.y - p.y) * scale);
+
+    let mut materials: Vec<&Material> = vec![&crystal.base.background_material];
+    for atom in &crystal.base.atoms {
+        if !materials.contains(&&atom.material) {
+            materials.push(&atom.material);
+        }
+    }
+    let fill = |material: &Material| PALETTE[materials.iter().position(|m| *m == material).unwrap_or(0) % PALETTE.len()];
+    let legend_height = if options.legend { LEGEND_ROW * materials.len() as f64 + 8.0 } else { 0.0 };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.1}" height="{:.1}" viewBox="0 0 {width:.2} {:.2}" font-family="sans-serif" font-size="12">"#,
+        height + legend_height,
+        height + legend_height
+    );
+    let _ = writeln!(
+        svg,
+        r#"<defs><clipPath id="window"><rect width="{width:.2}" height="{height:.2}"/></clipPath><marker id="arrow" viewBox="0 0 10 10" refX="9" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M0,0 L10,5 L0,10 z"/></marker></defs>"#
+    );
+    let _ = writeln!(svg, r#"<g clip-path="url(#window)">"#);
+    let _ = writeln!(
+        svg,
+        r#"<rect class="background" width="{width:.2}" height="{height:.2}" fill="{}"/>"#,
+        fill(&crystal.base.background_material)
+    );
+
+    // Holes of every cell that reaches into the window.
+    let to_fractional = Matrix2::from_columns(&[a1, a2])
+        .try_inverse()
+        .expect("lattice vectors must be linearly independent");
+    let fractional = [low, Vector2::new(high.x, low.y), high, Vector2::new(low.x, high.y)].map(|p| to_fractional * p);
+    let range = |f: fn(&Vector2) -> f64| {
+        let (lo, hi) = fractional.iter().map(f).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        (lo.floor() as i64 - 1)..=(hi.ceil() as i64 + 1)
+    };
+    for j in range(|p| p.y) {
+        for i in range(|p| p.x) {
+            for atom in &crystal.base.atoms {
+                let center = a1 * (i as f64 + atom.center.0) + a2 * (j as f64 + atom.center.1);
+                let reach = f64::hypot(atom.shape.half_extents().0, atom.shape.half_extents().1);
+                if (center - low).min() < -reach || (high - center).min() < -reach {
+                    continue;
+                }
+                let (cx, cy) = at(center);
+                let color = fill(&atom.material);
+                let _ = match atom.shape {
+                    HoleShape::Circle { radius } => writeln!(
+                        svg,
+                        r#"<circle cx="{cx:.2}" cy="{cy:.2}" r="{:.2}" fill="{color}" stroke="black" stroke-width="0.5"/>"#,
+                        radius * scale
+                    ),
+                    HoleShape::Ellipse { radius_x, radius_y, rotation } => writeln!(
+                        svg,
+                        r#"<ellipse cx="{cx:.2}" cy="{cy:.2}" rx="{:.2}" ry="{:.2}" transform="rotate({:.3} {cx:.2} {cy:.2})" fill="{color}" stroke="black" stroke-width="0.5"/>"#,
+                        radius_x * scale,
+                        radius_y * scale,
+                        -rotation.to_degrees()
+                    ),
+                    HoleShape::Square { .. } | HoleShape::Rectangle { .. } => {
+                        let (hx, hy) = atom.shape.half_extents();
+                        writeln!(
+                            svg,
+                            r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{color}" stroke="black" stroke-width="0.5"/>"#,
+                            cx - hx * scale,
+                            cy - hy * scale,
+                            2.0 * hx * scale,
+                            2.0 * hy * scale
+                        )
+                    }
+                };
+            }
+        }
+    }
+
+    let points = |vertices: &[Vector2]| {
+        vertices.iter().map(|&p| at(p)).map(|(x, y)| format!("{x:.2},{y:.2}")).collect::<Vec<_>>().join(" ")
+    };
+    for j in 0..options.periods.1.max(1) {
+        for i in 0..options.periods.0.max(1) {
+            let origin = a1 * i as f64 + a2 * j as f64;
+            let cell = [origin, origin + a1, origin + a1 + a2, origin + a2];
+            let _ = writeln!(
+                svg,
+                r#"<polygon class="cell" points="{}" fill="none" stroke="black" stroke-width="1"/>"#,
+                points(&cell)
+            );
+        }
+    }
+    if options.wigner_seitz {
+        let _ = writeln!(
+            svg,
+            r##"<polygon class="wigner-seitz" points="{}" fill="none" stroke="#d62728" stroke-width="1.5" stroke-dasharray="6 4"/>"##,
+            points(&wigner_seitz)
+        );
+    }
+    if options.lattice_vectors {
+        let (ox, oy) = at(Vector2::zeros());
+        for (name, vector) in [("1", a1), ("2", a2)] {
+            let (x, y) = at(vector);
+            let (tx, ty) = at(vector * 0.5 + Vector2::new(vector.y, -vector.x) * 0.08);
+            let _ = writeln!(
+                svg,
+                r#"<line class="lattice-vector" x1="{ox:.2}" y1="{oy:.2}" x2="{x:.2}" y2="{y:.2}" stroke="black" stroke-width="2" marker-end="url(#arrow)"/>"#
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{tx:.2}" y="{ty:.2}" text-anchor="middle" font-style="italic">a<tspan baseline-shift="sub" font-size="9">{name}</tspan></text>"#
+            );
+        }
+    }
+    let _ = writeln!(svg, "</g>");
+
+    if options.legend {
+        for (row, material) in materials.iter().enumerate() {
+            let y = height + 4.0 + LEGEND_ROW * row as f64;
+            let name = if row == 0 { "background".to_string() } else { format!("material {row}") };
+            let _ = writeln!(
+                svg,
+                r#"<rect x="8" y="{:.2}" width="14" height="14" fill="{}" stroke="black" stroke-width="0.5"/><text x="28" y="{:.2}">{name}: {}</text>"#,
+                y + 2.0,
+                fill(material),
+                y + 13.0,
+                describe(material)
+            );
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Permittivity of `material` at its reference wavelength, e.g. `ε = 12.25`.
+fn describe(material: &Material) -> String {
+    let number = |value: f64| {
+        let text = format!("{value:.4}");
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    };
+    let diagonal = material.complex_epsilon().diagonal();
+    let complex = |i: usize| {
+        let value = diagonal[i];
+        if value.im == 0.0 {
+            number(value.re)
+        } else if value.im > 0.0 {
+            format!("{} + {}i", number(value.re), number(value.im))
+        } else {
+            format!("{} − {}i", number(value.re), number(-value.im))
+        }
+    };
+    if material.is_isotropic() {
+        format!("ε = {}", complex(0))
+    } else {
+        format!("ε = ({}, {}, {})", complex(0), complex(1), complex(2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::units::Length;
+    use phc::base::UnitCellBase;
+    use phc::lattice::LatticeType;
+
+    fn attribute(svg: &str, element: &str, name: &str) -> String {
+        let start = svg.find(element).unwrap_or_else(|| panic!("no {element} in {svg}"));
+        let rest = &svg[start..];
+        let value = &rest[rest.find(&format!(" {name}=\"")).unwrap() + name.len() + 3..];
+        value[..value.find('"').unwrap()].to_string()
+    }
+
+    #[test]
+    fn test_triangular_cell() {
+        let lattice = LatticeType::new_triangular(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.2, Material::new_from_eps(1.0));
+        base.background_material = Material::new_from_eps(12.25);
+        let crystal = PhotonicCrystal::new(lattice, base);
+        let svg = unit_cell_svg(&crystal, &SvgOptions::default());
+
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<g").count(), svg.matches("</g>").count());
+        // The hexagonal Wigner-Seitz cell reaches 1/√3 below the origin.
+        let wigner_seitz = attribute(&svg, "class=\"wigner-seitz\"", "points");
+        assert_eq!(wigner_seitz.split(' ').count(), 6);
+        let height: f64 = attribute(&svg, "<svg", "height").parse().unwrap();
+        let expected = (3f64.sqrt() / 2.0 + 1.0 / 3f64.sqrt() + 2.0 * MARGIN) * 240.0 + 2.0 * LEGEND_ROW + 8.0;
+        assert!((height - expected).abs() < 0.1, "{height} != {expected}");
+
+        assert_eq!(svg.matches("class=\"cell\"").count(), 1);
+        assert_eq!(svg.matches("class=\"lattice-vector\"").count(), 2);
+        assert!(svg.matches("<circle").count() >= 4);
+        assert!(svg.contains("background: ε = 12.25"), "{svg}");
+        assert!(svg.contains("material 1: ε = 1<"), "{svg}");
+        assert_eq!(attribute(&svg, "<circle", "fill"), PALETTE[1]);
+    }
+
+    #[test]
+    fn test_shapes_and_options() {
+        let lattice = LatticeType::new_square(Length::nanometers(300.0), Length::nanometers(100.0));
+        let mut base = UnitCellBase::new();
+        let ellipse = HoleShape::Ellipse {
+            radius_x: 0.2,
+            radius_y: 0.1,
+            rotation: std::f64::consts::FRAC_PI_6,
+        };
+        base.add_atom(ellipse, (0.3, 0.3, 0.0), Material::new_from_eps(1.0));
+        base.add_atom(HoleShape::Square { side: 0.2 }, (0.7, 0.7, 0.0), Material::new_from_complex_eps(core::vectorial::Complex::new(2.0, 0.5)));
+        let crystal = PhotonicCrystal::new(lattice, base);
+        let options = SvgOptions {
+            periods: (3, 2),
+            scale: 100.0,
+            wigner_seitz: false,
+            legend: false,
+            ..SvgOptions::default()
+        };
+        let svg = unit_cell_svg(&crystal, &options);
+        assert_eq!(svg.matches("class=\"cell\"").count(), 6);
+        assert!(!svg.contains("wigner-seitz") && !svg.contains("material 1"));
+        assert_eq!(attribute(&svg, "<svg", "width"), "320.0");
+        assert!(attribute(&svg, "<ellipse", "transform").starts_with("rotate(-30.000 "));
+        assert_eq!(attribute(&svg, "<rect x=", "width"), "20.00");
+        assert_eq!(attribute(&svg, "<rect x=", "fill"), PALETTE[2]);
+        assert_eq!(describe(&crystal.base.atoms[1].material), "ε = 2 + 0.5i");
+    }
+}
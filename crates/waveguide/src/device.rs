@@ -0,0 +1,456 @@
+//! crates/waveguide/src/device.rs
+//! TOML descriptions of a complete PCSEL: the layer stack and the simulation settings.
+//!
+//! `data/table_i_device.toml` is a commented example describing the format. Loading reports
+//! errors with the line they occur on, and [`Device::to_toml`] writes the canonical form.
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use toml::Spanned;
+
+use core::material::Material;
+use core::thermal::ThermoOptic;
+use core::units::Length;
+use phc::crystal_structure::PhotonicCrystal;
+
+use super::doping::Doping;
+use super::graded::GradedProfile;
+use super::layers::{LayerType, Waveguide};
+
+/// Version of the device format written by [`Device::to_toml`].
+pub const DEVICE_FORMAT_VERSION: u32 = 1;
+
+/// Position in a device file, counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Location of the byte `offset` of `text`.
+    fn of(text: &str, offset: usize) -> Self {
+        let before = &text[..offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Reasons why a device file cannot be loaded or saved.
+#[derive(Debug)]
+pub enum DeviceError {
+    /// The file could not be read or written.
+    Io(std::io::Error),
+    /// The document is not valid TOML or does not match the format.
+    Parse { location: Option<Location>, message: String },
+    /// The document is well formed but describes an impossible device.
+    Invalid { location: Location, message: String },
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::Io(error) => write!(f, "cannot access device file: {error}"),
+            DeviceError::Parse { location: Some(location), message } => write!(f, "{location}: {message}"),
+            DeviceError::Parse { location: None, message } => write!(f, "{message}"),
+            DeviceError::Invalid { location, message } => write!(f, "{location}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DeviceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeviceError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DeviceError {
+    fn from(error: std::io::Error) -> Self {
+        DeviceError::Io(error)
+    }
+}
+
+/// How a device is simulated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Simulation {
+    /// Design wavelength.
+    pub wavelength: Length,
+    /// Largest `|m|`, `|n|` of the Fourier orders kept in the coupled-wave expansion.
+    #[serde(default = "default_fourier_order")]
+    pub fourier_order: usize,
+    /// Voxels `[nx, ny, nz]` of the rasterized unit cell.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<[usize; 3]>,
+    /// Uniform device temperature, in K.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+fn default_fourier_order() -> usize {
+    10
+}
+
+impl Simulation {
+    /// Settings at `wavelength` with the default Fourier order.
+    pub fn at_wavelength(wavelength: Length) -> Self {
+        Simulation {
+            wavelength,
+            fourier_order: default_fourier_order(),
+            resolution: None,
+            temperature: None,
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if !(self.wavelength.as_meters() > 0.0 && self.wavelength.as_meters().is_finite()) {
+            return Err("the wavelength must be positive".into());
+        }
+        if self.fourier_order == 0 {
+            return Err("the Fourier order must be at least 1".into());
+        }
+        if self.resolution.is_some_and(|resolution| resolution.contains(&0)) {
+            return Err("the resolution must be at least one voxel along each axis".into());
+        }
+        if self.temperature.is_some_and(|temperature| !(temperature > 0.0 && temperature.is_finite())) {
+            return Err("the temperature must be positive, in K".into());
+        }
+        Ok(())
+    }
+}
+
+/// A complete PCSEL: its layer stack and how to simulate it.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub name: String,
+    pub description: String,
+    pub waveguide: Waveguide,
+    pub simulation: Simulation,
+}
+
+/// A device file as read, with the positions needed to report errors.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeviceDocument {
+    version: Spanned<u32>,
+    name: String,
+    #[serde(default)]
+    description: String,
+    simulation: Spanned<Simulation>,
+    layers: Spanned<Vec<Spanned<LayerEntry>>>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum LayerKind {
+    Simple,
+    PhotonicCrystal,
+    Graded,
+}
+
+/// A `[[layers]]` table with every field of every kind.
+///
+/// Reading a [`LayerType`] directly goes through the buffering of tagged enums, which loses
+/// the position of errors inside the layer; this flat form keeps them.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerEntry {
+    kind: LayerKind,
+    name: String,
+    thickness: Spanned<Length>,
+    material: Option<Material>,
+    background_material: Option<Spanned<Material>>,
+    crystal: Option<PhotonicCrystal>,
+    profile: Option<GradedProfile>,
+    doping: Option<Doping>,
+    thermo_optic: Option<ThermoOptic>,
+}
+
+impl LayerEntry {
+    /// The layer, or the message and span of the first inconsistency.
+    fn into_layer(self, span: std::ops::Range<usize>) -> Result<LayerType, (String, std::ops::Range<usize>)> {
+        let (kind, allowed): (&str, &[&str]) = match self.kind {
+            LayerKind::Simple => ("simple", &["material", "doping"]),
+            LayerKind::PhotonicCrystal => ("photonic_crystal", &["background_material", "crystal"]),
+            LayerKind::Graded => ("graded", &["profile", "doping", "thermo_optic"]),
+        };
+        let given = [
+            ("material", self.material.is_some()),
+            ("background_material", self.background_material.is_some()),
+            ("crystal", self.crystal.is_some()),
+            ("profile", self.profile.is_some()),
+            ("doping", self.doping.is_some()),
+            ("thermo_optic", self.thermo_optic.is_some()),
+        ];
+        if let Some((field, _)) = given.iter().find(|(field, given)| *given && !allowed.contains(field)) {
+            return Err((format!("`{field}` does not apply to {kind} layers"), span));
+        }
+        let missing = |field: &str| (format!("{kind} layers need `{field}`"), span.clone());
+
+        let thickness_span = self.thickness.span();
+        let thickness = self.thickness.into_inner();
+        if !(thickness.as_meters() > 0.0 && thickness.as_meters().is_finite()) {
+            return Err(("the thickness must be positive".into(), thickness_span));
+        }
+        Ok(match self.kind {
+            LayerKind::Simple => LayerType::Simple {
+                name: self.name,
+                thickness,
+                material: self.material.ok_or_else(|| missing("material"))?,
+                doping: self.doping,
+            },
+            LayerKind::PhotonicCrystal => {
+                let crystal = self.crystal.ok_or_else(|| missing("crystal"))?;
+                let report = crystal.validate();
+                if !report.is_valid() {
+                    let errors: Vec<String> = report.errors.iter().map(ToString::to_string).collect();
+                    return Err((errors.join("; "), span));
+                }
+                let background = self.background_material.ok_or_else(|| missing("background_material"))?;
+                if *background.get_ref() != crystal.base.background_material {
+                    return Err((
+                        "`background_material` differs from `crystal.base.background_material`".into(),
+                        background.span(),
+                    ));
+                }
+                LayerType::PhotonicCrystal {
+                    name: self.name,
+                    thickness,
+                    definition: crystal,
+                    background_material: background.into_inner(),
+                }
+            }
+            LayerKind::Graded => LayerType::Graded {
+                name: self.name,
+                thickness,
+                profile: self.profile.ok_or_else(|| missing("profile"))?,
+                doping: self.doping,
+                thermo_optic: self.thermo_optic,
+            },
+        })
+    }
+}
+
+/// A device file as written, in canonical order.
+#[derive(Serialize)]
+struct CanonicalDocument<'a> {
+    version: u32,
+    name: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    description: &'a str,
+    simulation: &'a Simulation,
+    layers: &'a [LayerType],
+}
+
+impl Device {
+    /// Parses and validates a device description.
+    pub fn from_toml(text: &str) -> Result<Self, DeviceError> {
+        let document: DeviceDocument = toml::from_str(text).map_err(|error| DeviceError::Parse {
+            location: error.span().map(|span| Location::of(text, span.start)),
+            message: error.message().to_string(),
+        })?;
+        let invalid = |span: std::ops::Range<usize>, message: String| DeviceError::Invalid {
+            location: Location::of(text, span.start),
+            message,
+        };
+
+        if *document.version.get_ref() != DEVICE_FORMAT_VERSION {
+            let message = format!(
+                "unsupported version {}, expected {DEVICE_FORMAT_VERSION}",
+                document.version.get_ref()
+            );
+            return Err(invalid(document.version.span(), message));
+        }
+        document
+            .simulation
+            .get_ref()
+            .check()
+            .map_err(|message| invalid(document.simulation.span(), message))?;
+
+        let layers_span = document.layers.span();
+        let mut layers = Vec::new();
+        for entry in document.layers.into_inner() {
+            let span = entry.span();
+            let entry = entry.into_inner();
+            let name = entry.name.clone();
+            let layer = entry
+                .into_layer(span.clone())
+                .map_err(|(message, span)| invalid(span, format!("layer '{name}': {message}")))?;
+            if layers.iter().any(|other: &LayerType| other.name() == layer.name()) {
+                return Err(invalid(span, format!("layer '{}' is defined more than once", layer.name())));
+            }
+            layers.push(layer);
+        }
+        if !layers.iter().any(|layer| matches!(layer, LayerType::PhotonicCrystal { .. })) {
+            return Err(invalid(layers_span, "the stack has no photonic crystal layer".into()));
+        }
+
+        Ok(Device {
+            name: document.name,
+            description: document.description,
+            waveguide: Waveguide { layers },
+            simulation: document.simulation.into_inner(),
+        })
+    }
+
+    /// Reads and validates the device file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DeviceError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// The device in canonical form: fixed key order, defaults written out, comments dropped.
+    pub fn to_toml(&self) -> String {
+        let document = CanonicalDocument {
+            version: DEVICE_FORMAT_VERSION,
+            name: &self.name,
+            description: &self.description,
+            simulation: &self.simulation,
+            layers: &self.waveguide.layers,
+        };
+        toml::to_string(&document).expect("device descriptions are representable in TOML")
+    }
+
+    /// Writes the canonical form of the device to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), DeviceError> {
+        Ok(std::fs::write(path, self.to_toml())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::material::Material;
+
+    const TABLE_I: &str = include_str!("../data/table_i_device.toml");
+
+    #[test]
+    fn test_reference_device_loads() {
+        let device = Device::from_toml(TABLE_I).unwrap();
+        assert_eq!(device.name, "table-i");
+        assert_eq!(device.simulation.wavelength, Length::nanometers(980.0));
+        assert_eq!(device.simulation.resolution, Some([64, 64, 8]));
+        assert_eq!(device.waveguide.layers.len(), 5);
+        assert_eq!(device.waveguide.layer_index(), Some(2));
+        assert!((device.waveguide.total_thickness().as_nanometers() - 3265.5).abs() < 1e-9);
+        match device.waveguide.get_layer().unwrap() {
+            LayerType::PhotonicCrystal { definition, background_material, .. } => {
+                assert_eq!(definition.lattice.lattice().lattice_constant(), Length::nanometers(295.0));
+                assert_eq!(definition.base.atoms[0].material, Material::new_from_eps(1.0));
+                assert_eq!(*background_material, Material::new_from_eps(12.7449));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_canonical_form_round_trips() {
+        // Table I with a doped cladding and a graded cap layer.
+        let text = TABLE_I
+            .replacen(
+                "material = { epsilon = 11.0224 }",
+                "material = { epsilon = 11.0224 }\ndoping = { kind = \"n\", per_cm3 = 1e18 }",
+                1,
+            )
+            .replace(
+                "kind = \"simple\"\nname = \"GaAs\"\nthickness = \"59 nm\"\nmaterial = { epsilon = 12.7449 }",
+                "kind = \"graded\"\nname = \"GaAs\"\nthickness = \"59 nm\"\n\
+                 profile = { kind = \"parabolic\", bottom = 12.7449, top = 11.0224 }\n\
+                 doping = { kind = \"p\", per_cm3 = 5e17 }",
+            );
+        let device = Device::from_toml(&text).unwrap();
+        assert!(matches!(device.waveguide.layers[0], LayerType::Simple { doping: Some(_), .. }));
+        assert!(matches!(device.waveguide.layers[3], LayerType::Graded { doping: Some(_), .. }));
+
+        let canonical = device.to_toml();
+        assert!(canonical.starts_with("version = 1\nname = \"table-i\"\n"), "{canonical}");
+        let reloaded = Device::from_toml(&canonical).unwrap();
+        assert_eq!(reloaded.to_toml(), canonical);
+        assert_eq!(reloaded.simulation, device.simulation);
+        assert_eq!(reloaded.waveguide, device.waveguide);
+    }
+
+    fn error_line(text: &str) -> (usize, String) {
+        match Device::from_toml(text).unwrap_err() {
+            DeviceError::Parse { location: Some(location), message } => (location.line, message),
+            DeviceError::Invalid { location, message } => (location.line, message),
+            error => panic!("no location in {error}"),
+        }
+    }
+
+    #[test]
+    fn test_errors_carry_line_numbers() {
+        let line_of = |needle: &str| TABLE_I.lines().position(|line| line.contains(needle)).unwrap() + 1;
+
+        let text = TABLE_I.replace("thickness = \"59 nm\"", "thickness = \"59 parsecs\"");
+        let (line, message) = error_line(&text);
+        assert_eq!(line, line_of("thickness = \"59 nm\""));
+        assert!(message.contains("parsecs"), "{message}");
+
+        let text = TABLE_I.replace("kind = \"circle\", radius = 0.16", "kind = \"circle\", radius = 0.6");
+        let (line, message) = error_line(&text);
+        assert_eq!(line, line_of("name = \"PC\"") - 2);
+        assert!(message.contains("layer 'PC'") && message.contains("periodic image"), "{message}");
+
+        let text = TABLE_I.replace("thickness = \"88.5 nm\"", "thickness = \"0 nm\"");
+        assert_eq!(error_line(&text).0, line_of("thickness = \"88.5 nm\""));
+
+        let text = TABLE_I.replace("wavelength = \"980 nm\"", "wavelength = \"980 nm\"\nsolver = \"fast\"");
+        assert_eq!(error_line(&text).0, line_of("wavelength = ") + 1);
+
+        let text = TABLE_I.replace("version = 1", "version = 2");
+        assert_eq!(error_line(&text), (line_of("version = 1"), "unsupported version 2, expected 1".into()));
+
+        let text = TABLE_I.replace(
+            "crystal.base.background_material = { epsilon = 12.7449 }",
+            "crystal.base.background_material = { epsilon = 12.25 }",
+        );
+        let (line, message) = error_line(&text);
+        assert_eq!(line, line_of("background_material = { epsilon = 12.7449 }"));
+        assert!(message.contains("layer 'PC'") && message.contains("differs"), "{message}");
+
+        let text = TABLE_I.replace("kind = \"photonic_crystal\"", "kind = \"slab\"");
+        assert_eq!(error_line(&text).0, line_of("kind = \"photonic_crystal\""));
+    }
+
+    #[test]
+    fn test_graded_layers_carry_a_thermo_optic_coefficient() {
+        let graded = "kind = \"graded\"\nname = \"GaAs\"\nthickness = \"59 nm\"\n\
+            profile = { kind = \"linear\", bottom = 12.7449, top = 11.0224 }\n\
+            thermo_optic = { dn_dt = 2.5e-4, reference_temperature = 300.0 }";
+        let text = TABLE_I.replace("kind = \"simple\"\nname = \"GaAs\"\nthickness = \"59 nm\"\nmaterial = { epsilon = 12.7449 }", graded);
+        let device = Device::from_toml(&text).unwrap();
+        match &device.waveguide.layers[3] {
+            LayerType::Graded { thermo_optic, .. } => assert_eq!(thermo_optic.unwrap().dn_dt, 2.5e-4),
+            _ => unreachable!(),
+        }
+        let reloaded = Device::from_toml(&device.to_toml()).unwrap();
+        assert!(matches!(&reloaded.waveguide.layers[3], LayerType::Graded { thermo_optic: Some(_), .. }));
+
+        let text = TABLE_I.replace(
+            "material = { epsilon = 12.8603 }",
+            "material = { epsilon = 12.8603 }\nthermo_optic = { dn_dt = 2.5e-4, reference_temperature = 300.0 }",
+        );
+        assert!(error_line(&text).1.contains("`thermo_optic` does not apply to simple layers"));
+    }
+
+    #[test]
+    fn test_stack_needs_a_photonic_crystal() {
+        let mut device = Device::from_toml(TABLE_I).unwrap();
+        device.waveguide.layers.remove(2);
+        let error = Device::from_toml(&device.to_toml()).unwrap_err();
+        assert!(error.to_string().contains("no photonic crystal layer"), "{error}");
+    }
+}
@@ -0,0 +1,147 @@
+//! crates/waveguide/src/doping.rs
+//! Doping of the layers and the free-carrier absorption it causes.
+//!
+//! Absorption follows `α = σ(λ) N` with cross sections typical of GaAs/AlGaAs at 980 nm,
+//! scaled with the Drude `λ²` law. The change of the real index is neglected.
+use core::units::{Length, Loss};
+use core::vectorial::Complex;
+use serde::{Deserialize, Serialize};
+
+/// Wavelength at which the free-carrier cross sections are given.
+const REFERENCE_WAVELENGTH: f64 = 0.98e-6;
+/// Free-electron absorption cross section at the reference wavelength, in m².
+const ELECTRON_CROSS_SECTION: f64 = 3e-22;
+/// Free-hole absorption cross section at the reference wavelength, in m².
+const HOLE_CROSS_SECTION: f64 = 7e-22;
+
+/// Type of the majority carriers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DopingType {
+    N,
+    P,
+}
+
+/// Doping of a layer.
+///
+/// Serialized as `{ kind = "n", per_cm3 = 1e18 }`, or with `per_m3` for the concentration in 1/m³.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "DopingSpec", into = "DopingSpec")]
+pub struct Doping {
+    pub kind: DopingType,
+    /// Free carrier concentration, in 1/m³.
+    pub concentration: f64,
+}
+
+impl Doping {
+    /// n-type doping with `concentration` electrons per cm³.
+    pub fn n_per_cm3(concentration: f64) -> Self {
+        Self {
+            kind: DopingType::N,
+            concentration: concentration * 1e6,
+        }
+    }
+
+    /// p-type doping with `concentration` holes per cm³.
+    pub fn p_per_cm3(concentration: f64) -> Self {
+        Self {
+            kind: DopingType::P,
+            concentration: concentration * 1e6,
+        }
+    }
+
+    /// Free-carrier absorption coefficient at the vacuum `wavelength`.
+    pub fn free_carrier_absorption(&self, wavelength: Length) -> Loss {
+        let cross_section = match self.kind {
+            DopingType::N => ELECTRON_CROSS_SECTION,
+            DopingType::P => HOLE_CROSS_SECTION,
+        };
+        let scale = (wavelength.as_meters() / REFERENCE_WAVELENGTH).powi(2);
+        Loss::per_meter(cross_section * scale * self.concentration)
+    }
+
+    /// Adds the free-carrier absorption at `wavelength` to the permittivity `eps`.
+    ///
+    /// An absorption `α` is an extinction coefficient `κ = αλ/4π`, i.e. `Im ε += 2nκ`.
+    pub fn absorbing_permittivity(&self, eps: Complex, wavelength: Length) -> Complex {
+        let kappa = self.free_carrier_absorption(wavelength).as_per_meter() * wavelength.as_meters()
+            / (4.0 * std::f64::consts::PI);
+        eps + Complex::new(0.0, 2.0 * eps.sqrt().re * kappa)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DopingSpec {
+    kind: DopingType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    per_cm3: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    per_m3: Option<f64>,
+}
+
+impl TryFrom<DopingSpec> for Doping {
+    type Error = String;
+
+    fn try_from(spec: DopingSpec) -> Result<Self, Self::Error> {
+        let concentration = match (spec.per_cm3, spec.per_m3) {
+            (Some(per_cm3), None) => per_cm3 * 1e6,
+            (None, Some(per_m3)) => per_m3,
+            _ => return Err("give the concentration as exactly one of `per_cm3` or `per_m3`".to_string()),
+        };
+        if !(concentration >= 0.0 && concentration.is_finite()) {
+            return Err(format!("invalid doping concentration {concentration} 1/m³"));
+        }
+        Ok(Doping { kind: spec.kind, concentration })
+    }
+}
+
+impl From<Doping> for DopingSpec {
+    fn from(doping: Doping) -> Self {
+        let per_cm3 = doping.concentration / 1e6;
+        let exact = per_cm3 * 1e6 == doping.concentration;
+        DopingSpec {
+            kind: doping.kind,
+            per_cm3: exact.then_some(per_cm3),
+            per_m3: (!exact).then_some(doping.concentration),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doping_serialization() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            doping: Doping,
+        }
+        let text = toml::to_string(&Holder { doping: Doping::p_per_cm3(1e18) }).unwrap();
+        assert!(text.contains("kind = \"p\"\nper_cm3 = "), "{text}");
+        assert_eq!(toml::from_str::<Holder>(&text).unwrap().doping, Doping::p_per_cm3(1e18));
+        let per_m3: Holder = toml::from_str("doping = { kind = \"n\", per_m3 = 2e24 }").unwrap();
+        assert_eq!(per_m3.doping, Doping::n_per_cm3(2e18));
+        assert!(toml::from_str::<Holder>("doping = { kind = \"n\" }").is_err());
+        assert!(toml::from_str::<Holder>("doping = { kind = \"n\", per_cm3 = 1e18, per_m3 = 1e24 }").is_err());
+        assert!(toml::from_str::<Holder>("doping = { kind = \"x\", per_cm3 = 1e18 }").is_err());
+    }
+
+    #[test]
+    fn test_free_carrier_absorption() {
+        let lambda = Length::nanometers(980.0);
+        let n = Doping::n_per_cm3(1e18);
+        let p = Doping::p_per_cm3(1e18);
+        assert!((n.free_carrier_absorption(lambda).as_per_centimeter() - 3.0).abs() < 1e-9);
+        assert!((p.free_carrier_absorption(lambda).as_per_centimeter() - 7.0).abs() < 1e-9);
+        let long = p.free_carrier_absorption(Length::nanometers(1960.0));
+        assert!((long.as_per_centimeter() - 28.0).abs() < 1e-9);
+
+        // The extinction added to the permittivity reproduces the absorption coefficient.
+        let eps = n.absorbing_permittivity(Complex::new(11.0224, 0.0), lambda);
+        let kappa = eps.sqrt().im;
+        let alpha = 4.0 * std::f64::consts::PI * kappa / lambda.as_meters();
+        assert!((alpha / 100.0 - 3.0).abs() < 1e-6);
+    }
+}
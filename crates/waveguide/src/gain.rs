@@ -0,0 +1,425 @@
+//! crates/waveguide/src/gain.rs
+//! Material gain of the active layer and conversion of threshold gain into current.
+//!
+//! Two models are available: a logarithmic gain–current law with fitted parameters,
+//! and a parabolic-band quantum-well model with a single e1–hh1 subband pair, TE
+//! polarization and Lorentzian broadening. Current densities are in A/m² and carrier
+//! densities in 1/m³. [`Waveguide::threshold_point`] takes the confinement factor of the
+//! active layer from the slab mode of the stack.
+use std::f64::consts::PI;
+use std::fmt;
+
+use core::units::{Length, Loss, SPEED_OF_LIGHT};
+
+use super::layers::Waveguide;
+use super::slab::SlabMode;
+
+const ELEMENTARY_CHARGE: f64 = 1.602_176_634e-19;
+const HBAR: f64 = 1.054_571_817e-34;
+const ELECTRON_MASS: f64 = 9.109_383_7e-31;
+const VACUUM_PERMITTIVITY: f64 = 8.854_187_812_8e-12;
+const BOLTZMANN: f64 = 1.380_649e-23;
+/// Photon energy times wavelength, in eV·m.
+const HC_EV_M: f64 = 1.239_841_984e-6;
+
+/// Reasons why a threshold cannot be reached.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GainError {
+    /// The confinement factor is not in (0, 1].
+    InvalidConfinement(f64),
+    /// The required material gain exceeds what the model can provide.
+    Unreachable { required: Loss, maximum: Loss },
+    /// The waveguide has no layer of the given name.
+    UnknownLayer(String),
+}
+
+impl fmt::Display for GainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GainError::InvalidConfinement(gamma) => write!(f, "confinement factor {gamma} is not in (0, 1]"),
+            GainError::Unreachable { required, maximum } => write!(
+                f,
+                "required material gain {required} exceeds the maximum gain {maximum}"
+            ),
+            GainError::UnknownLayer(name) => write!(f, "the waveguide has no layer named '{name}'"),
+        }
+    }
+}
+
+impl std::error::Error for GainError {}
+
+/// Logarithmic gain–current law `g = g0 ln(J / (N_w J_tr))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogarithmicGain {
+    /// Gain coefficient `g0`.
+    pub g0: Loss,
+    /// Transparency current density per well, in A/m².
+    pub transparency_current_density: f64,
+    /// Number of wells `N_w`.
+    pub wells: usize,
+}
+
+impl LogarithmicGain {
+    /// Material gain at the current density `j` (A/m²).
+    pub fn gain(&self, j: f64) -> Loss {
+        self.g0 * (j / (self.wells as f64 * self.transparency_current_density)).ln()
+    }
+
+    /// Current density (A/m²) providing the material gain `g`.
+    pub fn current_density(&self, g: Loss) -> f64 {
+        self.wells as f64 * self.transparency_current_density * (g / self.g0).exp()
+    }
+}
+
+/// Parameters of one quantum well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantumWell {
+    pub well_width: Length,
+    /// Energy of the e1–hh1 transition edge, in eV.
+    pub transition_energy: f64,
+    /// In-plane effective masses, in units of the free electron mass.
+    pub electron_mass: f64,
+    pub hole_mass: f64,
+    /// Kane energy `E_p`, in eV.
+    pub kane_energy: f64,
+    pub refractive_index: f64,
+    /// Half width at half maximum of the Lorentzian broadening, in eV.
+    pub linewidth: f64,
+    /// Temperature, in K.
+    pub temperature: f64,
+}
+
+impl QuantumWell {
+    /// A compressively strained 8 nm InGaAs/GaAs well emitting near 940 nm at 300 K.
+    pub fn in_ga_as_940nm() -> Self {
+        Self {
+            well_width: Length::nanometers(8.0),
+            transition_energy: 1.30,
+            electron_mass: 0.06,
+            hole_mass: 0.11,
+            kane_energy: 25.0,
+            refractive_index: 3.55,
+            linewidth: 6.6e-3,
+            temperature: 300.0,
+        }
+    }
+
+    fn thermal_energy(&self) -> f64 {
+        BOLTZMANN * self.temperature / ELEMENTARY_CHARGE
+    }
+
+    /// Quasi-Fermi level (eV) above the subband edge for `density` carriers of mass `mass`.
+    fn fermi_level(&self, density: f64, mass: f64) -> f64 {
+        let kt = self.thermal_energy();
+        let sheet = density * self.well_width.as_meters();
+        let states = mass * ELECTRON_MASS * kt * ELEMENTARY_CHARGE / (PI * HBAR * HBAR);
+        kt * (sheet / states).exp_m1().ln()
+    }
+
+    /// Material gain at the photon energy `energy` (eV) for the carrier density `density` (1/m³).
+    /// Negative values are absorption.
+    pub fn gain_at_energy(&self, energy: f64, density: f64) -> Loss {
+        let kt = self.thermal_energy();
+        let fc = self.fermi_level(density, self.electron_mass);
+        let fv = self.fermi_level(density, self.hole_mass);
+        let reduced_mass = self.electron_mass * self.hole_mass / (self.electron_mass + self.hole_mass);
+
+        let omega = energy * ELEMENTARY_CHARGE / HBAR;
+        let prefactor = PI * ELEMENTARY_CHARGE * ELEMENTARY_CHARGE
+            / (self.refractive_index * SPEED_OF_LIGHT * VACUUM_PERMITTIVITY * ELECTRON_MASS * ELECTRON_MASS * omega);
+        // TE coupling to heavy holes: |M|² = m0 E_p / 4.
+        let momentum = ELECTRON_MASS * self.kane_energy * ELEMENTARY_CHARGE / 4.0;
+        let density_of_states =
+            reduced_mass * ELECTRON_MASS / (PI * HBAR * HBAR * self.well_width.as_meters());
+
+        // Integrate the inversion over transition energies, weighted by the Lorentzian line shape.
+        let span = 40.0 * kt + 20.0 * self.linewidth;
+        let steps = 800;
+        let de = span / steps as f64;
+        let inversion: f64 = (0..steps)
+            .map(|i| {
+                let excess = (i as f64 + 0.5) * de;
+                let ec = excess * reduced_mass / self.electron_mass;
+                let ev = excess * reduced_mass / self.hole_mass;
+                let occupation = 1.0 / (1.0 + ((ec - fc) / kt).exp()) + 1.0 / (1.0 + ((ev - fv) / kt).exp()) - 1.0;
+                let detuning = self.transition_energy + excess - energy;
+                let lorentzian = self.linewidth / PI / (detuning * detuning + self.linewidth * self.linewidth);
+                occupation * lorentzian * de
+            })
+            .sum();
+        Loss::per_meter(prefactor * momentum * density_of_states * inversion)
+    }
+
+    /// Material gain at the vacuum `wavelength`.
+    pub fn gain(&self, wavelength: Length, density: f64) -> Loss {
+        self.gain_at_energy(HC_EV_M / wavelength.as_meters(), density)
+    }
+
+    /// Wavelength and value of the gain maximum at `density`.
+    pub fn gain_peak(&self, density: f64) -> (Length, Loss) {
+        let start = self.transition_energy - 5.0 * self.linewidth;
+        let (energy, gain) = (0..=600)
+            .map(|i| {
+                let energy = start + i as f64 * 0.5e-3;
+                (energy, self.gain_at_energy(energy, density))
+            })
+            .fold((start, Loss::per_meter(f64::NEG_INFINITY)), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        (Length::meters(HC_EV_M / energy), gain)
+    }
+}
+
+/// Carrier recombination rate `A N + B N² + C N³`, in SI units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recombination {
+    /// Shockley–Read–Hall coefficient, in 1/s.
+    pub a: f64,
+    /// Radiative coefficient, in m³/s.
+    pub b: f64,
+    /// Auger coefficient, in m⁶/s.
+    pub c: f64,
+}
+
+impl Default for Recombination {
+    /// Typical values for InGaAs/GaAs wells.
+    fn default() -> Self {
+        Self {
+            a: 1e8,
+            b: 1e-16,
+            c: 3.5e-42,
+        }
+    }
+}
+
+/// Quantum wells of the active layer, all at the same carrier density.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultiQuantumWell {
+    pub well: QuantumWell,
+    pub wells: usize,
+    pub recombination: Recombination,
+    /// Fraction of the injected current recombining in the wells.
+    pub injection_efficiency: f64,
+}
+
+impl MultiQuantumWell {
+    /// Current density (A/m²) sustaining the carrier density `density` in every well.
+    pub fn current_density(&self, density: f64) -> f64 {
+        let Recombination { a, b, c } = self.recombination;
+        let rate = a * density + b * density.powi(2) + c * density.powi(3);
+        ELEMENTARY_CHARGE * self.wells as f64 * self.well.well_width.as_meters() * rate / self.injection_efficiency
+    }
+
+    /// Carrier density giving the material gain `g` at `wavelength`, found by bisection.
+    pub fn density_for_gain(&self, g: Loss, wavelength: Length) -> Result<f64, GainError> {
+        const MAX_DENSITY: f64 = 1e26;
+        let maximum = self.well.gain(wavelength, MAX_DENSITY);
+        if maximum < g {
+            return Err(GainError::Unreachable { required: g, maximum });
+        }
+        let (mut low, mut high) = (1e18f64, MAX_DENSITY);
+        for _ in 0..100 {
+            let mid = (low * high).sqrt();
+            if self.well.gain(wavelength, mid) < g {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(high)
+    }
+}
+
+/// Material gain model of the active layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GainModel {
+    Logarithmic(LogarithmicGain),
+    QuantumWell(MultiQuantumWell),
+}
+
+/// Operating point of the laser at threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdPoint {
+    /// Material gain `(α_th + α_i) / Γ` required at threshold.
+    pub material_gain: Loss,
+    /// Threshold current density, in A/m².
+    pub current_density: f64,
+    /// Carrier density at threshold, if the model resolves it.
+    pub carrier_density: Option<f64>,
+    /// Lasing wavelength minus the wavelength of the gain peak, if the model resolves it.
+    pub detuning: Option<Length>,
+}
+
+impl GainModel {
+    /// Converts the modal threshold gain `threshold` (e.g. from CWT) into a threshold point.
+    ///
+    /// `confinement` is the confinement factor of all wells together, and `wavelength` is
+    /// the lasing wavelength.
+    pub fn threshold(
+        &self,
+        threshold: Loss,
+        internal_loss: Loss,
+        confinement: f64,
+        wavelength: Length,
+    ) -> Result<ThresholdPoint, GainError> {
+        if !(confinement > 0.0 && confinement <= 1.0) {
+            return Err(GainError::InvalidConfinement(confinement));
+        }
+        let material_gain = (threshold + internal_loss) / confinement;
+        match self {
+            GainModel::Logarithmic(model) => Ok(ThresholdPoint {
+                material_gain,
+                current_density: model.current_density(material_gain),
+                carrier_density: None,
+                detuning: None,
+            }),
+            GainModel::QuantumWell(mqw) => {
+                let density = mqw.density_for_gain(material_gain, wavelength)?;
+                let (peak, _) = mqw.well.gain_peak(density);
+                Ok(ThresholdPoint {
+                    material_gain,
+                    current_density: mqw.current_density(density),
+                    carrier_density: Some(density),
+                    detuning: Some(wavelength - peak),
+                })
+            }
+        }
+    }
+}
+
+impl Waveguide {
+    /// Converts the modal threshold gain `threshold` into a threshold point for `model`
+    /// pumping the layer named `active`, with the confinement factor and internal loss of
+    /// `mode`, the slab mode of this waveguide at `wavelength`.
+    ///
+    /// Quantum-well models only overlap their wells, so the confinement factor of the layer is
+    /// scaled by the fraction of its thickness filled by the wells.
+    pub fn threshold_point(
+        &self,
+        active: &str,
+        model: &GainModel,
+        mode: &SlabMode,
+        threshold: Loss,
+        wavelength: Length,
+    ) -> Result<ThresholdPoint, GainError> {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer.name() == active)
+            .ok_or_else(|| GainError::UnknownLayer(active.to_string()))?;
+        let thickness = self.layers[index].thickness();
+        let fill = match model {
+            GainModel::Logarithmic(_) => 1.0,
+            GainModel::QuantumWell(mqw) => (mqw.well.well_width * mqw.wells as f64 / thickness).min(1.0),
+        };
+        model.threshold(threshold, mode.internal_loss(), mode.layers[index].confinement * fill, wavelength)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mqw() -> MultiQuantumWell {
+        MultiQuantumWell {
+            well: QuantumWell::in_ga_as_940nm(),
+            wells: 3,
+            recombination: Recombination::default(),
+            injection_efficiency: 0.8,
+        }
+    }
+
+    #[test]
+    fn test_logarithmic_law() {
+        let model = LogarithmicGain {
+            g0: Loss::per_centimeter(1500.0),
+            transparency_current_density: 50e4,
+            wells: 2,
+        };
+        assert!(model.gain(100e4).as_per_meter().abs() < 1e-9);
+        let g = Loss::per_centimeter(1500.0);
+        assert!((model.current_density(g) - 100e4 * std::f64::consts::E).abs() < 1e-6);
+        assert!((model.gain(model.current_density(g)) - g).as_per_meter().abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_quantum_well_spectrum() {
+        let well = QuantumWell::in_ga_as_940nm();
+        // Unpumped wells absorb above the edge and are transparent well below it.
+        assert!(well.gain_at_energy(1.35, 1e20).as_per_centimeter() < -1000.0);
+        assert!(well.gain_at_energy(1.20, 3e24).as_per_centimeter().abs() < 100.0);
+
+        let (low_peak, low_gain) = well.gain_peak(2.5e24);
+        let (high_peak, high_gain) = well.gain_peak(4e24);
+        assert!(high_gain > low_gain && low_gain.as_per_centimeter() > 0.0);
+        assert!(high_gain.as_per_centimeter() > 1000.0 && high_gain.as_per_centimeter() < 1e4);
+        // Band filling shifts the peak to shorter wavelengths.
+        assert!(high_peak < low_peak);
+        assert!((high_peak.as_nanometers() - 940.0).abs() < 20.0);
+    }
+
+    #[test]
+    fn test_threshold_from_modal_gain() {
+        let model = GainModel::QuantumWell(mqw());
+        let lambda = Length::nanometers(940.0);
+        let point = model
+            .threshold(Loss::per_centimeter(5.0), Loss::per_centimeter(3.0), 0.03, lambda)
+            .unwrap();
+        assert!((point.material_gain.as_per_centimeter() - 8.0 / 0.03).abs() < 1e-9);
+        let density = point.carrier_density.unwrap();
+        assert!((mqw().well.gain(lambda, density) - point.material_gain).as_per_centimeter().abs() < 1e-3);
+        // Threshold current densities of PCSELs are of order 0.1-1 kA/cm².
+        let ka_per_cm2 = point.current_density * 1e-7;
+        assert!(ka_per_cm2 > 0.05 && ka_per_cm2 < 2.0);
+        assert!(point.detuning.unwrap().as_nanometers().abs() < 30.0);
+
+        assert_eq!(
+            model.threshold(Loss::per_centimeter(5.0), Loss::default(), 0.0, lambda),
+            Err(GainError::InvalidConfinement(0.0))
+        );
+        assert!(matches!(
+            model.threshold(Loss::per_centimeter(1000.0), Loss::default(), 0.03, lambda),
+            Err(GainError::Unreachable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_threshold_uses_the_confinement_of_the_active_layer() {
+        use crate::layers::LayerType;
+        use core::material::Material;
+        use phc::base::UnitCellBase;
+        use phc::crystal_structure::PhotonicCrystal;
+        use phc::lattice::LatticeType;
+
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+        base.background_material = Material::new_from_eps(12.7449);
+        let wg = Waveguide::new_from_table_i(PhotonicCrystal::new(lattice, base), Material::new_from_eps(12.7449));
+        let lambda = Length::nanometers(940.0);
+        let mode = wg.slab_mode(lambda).unwrap();
+        let active = wg.layers.iter().position(|layer| layer.name() == "Active").unwrap();
+        assert!(matches!(wg.layers[active], LayerType::Simple { .. }));
+
+        let model = GainModel::QuantumWell(mqw());
+        let threshold = Loss::per_centimeter(5.0);
+        let point = wg.threshold_point("Active", &model, &mode, threshold, lambda).unwrap();
+        let wells = mode.layers[active].confinement * 24.0 / 88.5;
+        assert_eq!(point, model.threshold(threshold, mode.internal_loss(), wells, lambda).unwrap());
+
+        let logarithmic = GainModel::Logarithmic(LogarithmicGain {
+            g0: Loss::per_centimeter(1500.0),
+            transparency_current_density: 50e4,
+            wells: 3,
+        });
+        let point = wg.threshold_point("Active", &logarithmic, &mode, threshold, lambda).unwrap();
+        assert!((point.material_gain * mode.layers[active].confinement - threshold).as_per_meter().abs() < 1e-9);
+        assert_eq!(
+            wg.threshold_point("QW", &model, &mode, threshold, lambda),
+            Err(GainError::UnknownLayer("QW".into()))
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! crates/waveguide/src/graded.rs
+//! Continuous permittivity profiles ε(z) of graded layers.
+//!
+//! Positions are fractions `u = z / d` of the layer thickness, measured from the bottom.
+use std::fmt;
+
+use core::thermal::ThermoOptic;
+use serde::{Deserialize, Serialize};
+
+/// Reasons why a tabulated profile is rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileError {
+    /// The table has no rows.
+    Empty,
+    /// The position and permittivity columns have different lengths.
+    MismatchedLengths,
+    /// The positions are not strictly increasing.
+    NotIncreasing,
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Empty => write!(f, "tabulated profile has no data"),
+            ProfileError::MismatchedLengths => write!(f, "position and permittivity columns must have the same length"),
+            ProfileError::NotIncreasing => write!(f, "tabulated positions must be strictly increasing"),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// Permittivity as a function of the position inside a graded layer.
+///
+/// Serialized with a `kind` tag, e.g. `{ kind = "linear", bottom = 11.0, top = 12.7 }`.
+/// Tabulated profiles are checked as by [`GradedProfile::tabulated`] when deserialized.
+/// Analytic profiles keep the shift `dn` of their refractive index, so that `ε` becomes
+/// `(√ε + dn)²`; it is zero, and omitted, unless the profile was shifted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", try_from = "ProfileSpec")]
+pub enum GradedProfile {
+    /// `ε(u) = bottom + (top - bottom) u`.
+    Linear {
+        bottom: f64,
+        top: f64,
+        #[serde(skip_serializing_if = "is_zero")]
+        dn: f64,
+    },
+    /// `ε(u) = bottom + (top - bottom) u²`, flat at the bottom of the layer.
+    Parabolic {
+        bottom: f64,
+        top: f64,
+        #[serde(skip_serializing_if = "is_zero")]
+        dn: f64,
+    },
+    /// `ε(u) = top + (bottom - top) (1 - u)²`, flat at the top of the layer.
+    ParabolicTop {
+        bottom: f64,
+        top: f64,
+        #[serde(skip_serializing_if = "is_zero")]
+        dn: f64,
+    },
+    /// Samples at the fractional `positions`, interpolated linearly and clamped outside.
+    Tabulated { positions: Vec<f64>, epsilon: Vec<f64> },
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+/// Adds `dn` to the refractive index `√eps`, leaving non-positive permittivities alone.
+fn shift(eps: f64, dn: f64) -> f64 {
+    if eps > 0.0 && dn != 0.0 {
+        (eps.sqrt() + dn).powi(2)
+    } else {
+        eps
+    }
+}
+
+impl GradedProfile {
+    /// Creates a tabulated profile after checking the table.
+    pub fn tabulated(positions: Vec<f64>, epsilon: Vec<f64>) -> Result<Self, ProfileError> {
+        if positions.is_empty() {
+            return Err(ProfileError::Empty);
+        }
+        if positions.len() != epsilon.len() {
+            return Err(ProfileError::MismatchedLengths);
+        }
+        if positions.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(ProfileError::NotIncreasing);
+        }
+        Ok(GradedProfile::Tabulated { positions, epsilon })
+    }
+
+    /// Permittivity at the fractional position `u`.
+    pub fn epsilon_at(&self, u: f64) -> f64 {
+        match self {
+            GradedProfile::Linear { bottom, top, dn } => shift(bottom + (top - bottom) * u, *dn),
+            GradedProfile::Parabolic { bottom, top, dn } => shift(bottom + (top - bottom) * u * u, *dn),
+            GradedProfile::ParabolicTop { bottom, top, dn } => shift(top + (bottom - top) * (1.0 - u).powi(2), *dn),
+            GradedProfile::Tabulated { positions, epsilon } => {
+                let i = positions.partition_point(|&p| p <= u);
+                if i == 0 {
+                    epsilon[0]
+                } else if i == positions.len() {
+                    epsilon[i - 1]
+                } else {
+                    let t = (u - positions[i - 1]) / (positions[i] - positions[i - 1]);
+                    epsilon[i - 1] * (1.0 - t) + epsilon[i] * t
+                }
+            }
+        }
+    }
+
+    /// Adds `dn` to the refractive index `√ε` of the profile, or of every sample of a table.
+    pub fn shifted_index(&self, dn: f64) -> Self {
+        match self {
+            GradedProfile::Linear { bottom, top, dn: old } => GradedProfile::Linear {
+                bottom: *bottom,
+                top: *top,
+                dn: old + dn,
+            },
+            GradedProfile::Parabolic { bottom, top, dn: old } => GradedProfile::Parabolic {
+                bottom: *bottom,
+                top: *top,
+                dn: old + dn,
+            },
+            GradedProfile::ParabolicTop { bottom, top, dn: old } => GradedProfile::ParabolicTop {
+                bottom: *bottom,
+                top: *top,
+                dn: old + dn,
+            },
+            GradedProfile::Tabulated { positions, epsilon } => GradedProfile::Tabulated {
+                positions: positions.clone(),
+                epsilon: epsilon.iter().map(|&eps| shift(eps, dn)).collect(),
+            },
+        }
+    }
+
+    /// The profile at `temperature` (K), for a layer whose profile follows `thermo_optic`.
+    ///
+    /// Returns the shifted profile and the thermo-optic model referenced to `temperature`.
+    pub fn at_temperature(&self, thermo_optic: ThermoOptic, temperature: f64) -> (Self, ThermoOptic) {
+        let dn = thermo_optic.dn_dt * (temperature - thermo_optic.reference_temperature);
+        let profile = if dn == 0.0 { self.clone() } else { self.shifted_index(dn) };
+        (
+            profile,
+            ThermoOptic {
+                reference_temperature: temperature,
+                ..thermo_optic
+            },
+        )
+    }
+
+    /// Reverses the profile, e.g. to grade back from the core to the opposite cladding.
+    pub fn flipped(&self) -> Self {
+        match *self {
+            GradedProfile::Linear { bottom, top, dn } => GradedProfile::Linear { bottom: top, top: bottom, dn },
+            GradedProfile::Parabolic { bottom, top, dn } => GradedProfile::ParabolicTop { bottom: top, top: bottom, dn },
+            GradedProfile::ParabolicTop { bottom, top, dn } => GradedProfile::Parabolic { bottom: top, top: bottom, dn },
+            GradedProfile::Tabulated { ref positions, ref epsilon } => GradedProfile::Tabulated {
+                positions: positions.iter().rev().map(|p| 1.0 - p).collect(),
+                epsilon: epsilon.iter().rev().cloned().collect(),
+            },
+        }
+    }
+}
+
+/// Unchecked form of a [`GradedProfile`] as read from a file.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum ProfileSpec {
+    Linear {
+        bottom: f64,
+        top: f64,
+        #[serde(default)]
+        dn: f64,
+    },
+    Parabolic {
+        bottom: f64,
+        top: f64,
+        #[serde(default)]
+        dn: f64,
+    },
+    ParabolicTop {
+        bottom: f64,
+        top: f64,
+        #[serde(default)]
+        dn: f64,
+    },
+    Tabulated { positions: Vec<f64>, epsilon: Vec<f64> },
+}
+
+impl TryFrom<ProfileSpec> for GradedProfile {
+    type Error = ProfileError;
+
+    fn try_from(spec: ProfileSpec) -> Result<Self, Self::Error> {
+        Ok(match spec {
+            ProfileSpec::Linear { bottom, top, dn } => GradedProfile::Linear { bottom, top, dn },
+            ProfileSpec::Parabolic { bottom, top, dn } => GradedProfile::Parabolic { bottom, top, dn },
+            ProfileSpec::ParabolicTop { bottom, top, dn } => GradedProfile::ParabolicTop { bottom, top, dn },
+            ProfileSpec::Tabulated { positions, epsilon } => GradedProfile::tabulated(positions, epsilon)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_serialization() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Holder {
+            profile: GradedProfile,
+        }
+        let table = GradedProfile::tabulated(vec![0.0, 1.0], vec![11.0, 12.5]).unwrap();
+        let text = toml::to_string(&Holder { profile: table.clone() }).unwrap();
+        assert!(text.contains("kind = \"tabulated\""), "{text}");
+        assert_eq!(toml::from_str::<Holder>(&text).unwrap().profile, table);
+        let linear: Holder = toml::from_str("profile = { kind = \"linear\", bottom = 11.0, top = 13.0 }").unwrap();
+        assert_eq!(linear.profile, GradedProfile::Linear { bottom: 11.0, top: 13.0, dn: 0.0 });
+        assert!(!toml::to_string(&linear).unwrap().contains("dn"));
+        // A shifted profile keeps its shift.
+        let shifted = Holder { profile: GradedProfile::Parabolic { bottom: 11.0, top: 13.0, dn: 0.01 }.flipped() };
+        let text = toml::to_string(&shifted).unwrap();
+        assert!(text.contains("kind = \"parabolic_top\"") && text.contains("dn = 0.01"), "{text}");
+        assert_eq!(toml::from_str::<Holder>(&text).unwrap(), shifted);
+        let unsorted = "profile = { kind = \"tabulated\", positions = [0.5, 0.5], epsilon = [1.0, 2.0] }";
+        let error = toml::from_str::<Holder>(unsorted).unwrap_err();
+        assert!(error.message().contains("strictly increasing"), "{error}");
+    }
+
+    #[test]
+    fn test_profiles() {
+        let linear = GradedProfile::Linear { bottom: 11.0, top: 13.0, dn: 0.0 };
+        assert_eq!(linear.epsilon_at(0.25), 11.5);
+        let parabolic = GradedProfile::Parabolic { bottom: 11.0, top: 13.0, dn: 0.0 };
+        assert_eq!(parabolic.epsilon_at(0.5), 11.5);
+        assert_eq!(parabolic.epsilon_at(1.0), 13.0);
+
+        let table = GradedProfile::tabulated(vec![0.0, 0.5, 1.0], vec![11.0, 12.0, 12.5]).unwrap();
+        assert_eq!(table.epsilon_at(-1.0), 11.0);
+        assert_eq!(table.epsilon_at(0.75), 12.25);
+        assert_eq!(table.epsilon_at(2.0), 12.5);
+        assert_eq!(GradedProfile::tabulated(vec![], vec![]), Err(ProfileError::Empty));
+        assert_eq!(GradedProfile::tabulated(vec![0.0], vec![1.0, 2.0]), Err(ProfileError::MismatchedLengths));
+        assert_eq!(GradedProfile::tabulated(vec![0.5, 0.5], vec![1.0, 2.0]), Err(ProfileError::NotIncreasing));
+
+        let shifted = table.shifted_index(0.01);
+        assert!((shifted.epsilon_at(0.5) - (12f64.sqrt() + 0.01).powi(2)).abs() < 1e-12);
+        assert_eq!(linear.shifted_index(0.0), linear);
+        // Analytic profiles stay analytic and shifts add up.
+        let hot = parabolic.shifted_index(0.01).shifted_index(0.02);
+        assert_eq!(hot, GradedProfile::Parabolic { bottom: 11.0, top: 13.0, dn: 0.03 });
+        for u in [0.0, 0.3, 0.7, 1.0] {
+            let expected = (parabolic.epsilon_at(u).sqrt() + 0.03).powi(2);
+            assert!((hot.epsilon_at(u) - expected).abs() < 1e-12);
+        }
+
+        for profile in [linear, parabolic, hot, table] {
+            let flipped = profile.flipped();
+            assert_eq!(flipped.flipped(), profile);
+            for u in [0.0, 0.3, 0.5, 0.7, 1.0] {
+                assert!((flipped.epsilon_at(u) - profile.epsilon_at(1.0 - u)).abs() < 1e-12);
+            }
+        }
+    }
+}
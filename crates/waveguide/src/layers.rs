@@ -0,0 +1,240 @@
+//! crates/waveguide/src/layers.rs
+//! Layers and the multilayer waveguide stack.
+use core::material::Material;
+use core::thermal::ThermoOptic;
+use core::units::Length;
+use phc::crystal_structure::PhotonicCrystal;
+use serde::{Deserialize, Serialize};
+
+use super::doping::Doping;
+use super::graded::GradedProfile;
+
+/// Represents a single layer in the waveguide stack.
+///
+/// Serialized with a `kind` tag of `simple`, `photonic_crystal` or `graded`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+#[allow(clippy::large_enum_variant)]
+pub enum LayerType {
+    Simple {
+        name: String,
+        thickness: Length,
+        material: Material,
+        /// Doping of the layer, causing free-carrier absorption.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        doping: Option<Doping>,
+    },
+    PhotonicCrystal {
+        name: String,
+        thickness: Length,
+        /// The geometric definition of the PC.
+        #[serde(rename = "crystal")]
+        definition: PhotonicCrystal,
+        /// The material of the "background" slab.
+        background_material: Material,
+    },
+    /// A layer with a continuous permittivity profile, e.g. a graded SCH region.
+    Graded {
+        name: String,
+        thickness: Length,
+        profile: GradedProfile,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        doping: Option<Doping>,
+        /// Temperature dependence of every ε sample; the profile holds at its reference temperature.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thermo_optic: Option<ThermoOptic>,
+    },
+}
+
+impl LayerType {
+    /// Helper method to get the thickness of any layer type.
+    pub fn thickness(&self) -> Length {
+        match self {
+            LayerType::Simple { thickness, .. } => *thickness,
+            LayerType::PhotonicCrystal { thickness, .. } => *thickness,
+            LayerType::Graded { thickness, .. } => *thickness,
+        }
+    }
+
+    /// Helper method to get the name of any layer type.
+    pub fn name(&self) -> &str {
+        match self {
+            LayerType::Simple { name, .. } => name,
+            LayerType::PhotonicCrystal { name, .. } => name,
+            LayerType::Graded { name, .. } => name,
+        }
+    }
+
+    /// Doping of the layer, if any. Photonic crystal layers are undoped.
+    pub fn doping(&self) -> Option<Doping> {
+        match self {
+            LayerType::Simple { doping, .. } | LayerType::Graded { doping, .. } => *doping,
+            LayerType::PhotonicCrystal { .. } => None,
+        }
+    }
+
+    /// Visits every material of the layer, including the holes of a photonic crystal.
+    /// Graded layers have no materials and are returned unchanged, see
+    /// [`GradedProfile::at_temperature`] for their temperature dependence.
+    pub fn try_map_materials<E>(&self, mut f: impl FnMut(&Material) -> Result<Material, E>) -> Result<Self, E> {
+        Ok(match self {
+            LayerType::Simple {
+                name,
+                thickness,
+                material,
+                doping,
+            } => LayerType::Simple {
+                name: name.clone(),
+                thickness: *thickness,
+                material: f(material)?,
+                doping: *doping,
+            },
+            LayerType::PhotonicCrystal {
+                name,
+                thickness,
+                definition,
+                background_material,
+            } => {
+                let mut definition = definition.clone();
+                definition.base.background_material = f(&definition.base.background_material)?;
+                for atom in &mut definition.base.atoms {
+                    atom.material = f(&atom.material)?;
+                }
+                LayerType::PhotonicCrystal {
+                    name: name.clone(),
+                    thickness: *thickness,
+                    definition,
+                    background_material: f(background_material)?,
+                }
+            }
+            LayerType::Graded { .. } => self.clone(),
+        })
+    }
+}
+
+/// Represents the complete multilayer waveguide structure, listed from bottom to top.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waveguide {
+    pub layers: Vec<LayerType>,
+}
+
+impl Waveguide {
+    /// Creates a new waveguide based on Table I.
+    pub fn new_from_table_i(definition: PhotonicCrystal, background_material: Material) -> Self {
+        Self {
+            layers: vec![
+                LayerType::Simple {
+                    name: "n-clad (AlGaAs)".into(),
+                    thickness: Length::micrometers(1.5),
+                    material: Material::new_from_eps(11.0224),
+                    doping: None,
+                },
+                LayerType::Simple {
+                    name: "Active".into(),
+                    thickness: Length::nanometers(88.5),
+                    material: Material::new_from_eps(12.8603),
+                    doping: None,
+                },
+                LayerType::PhotonicCrystal {
+                    name: "PC".into(),
+                    thickness: Length::nanometers(118.0),
+                    definition,
+                    background_material,
+                },
+                LayerType::Simple {
+                    name: "GaAs".into(),
+                    thickness: Length::nanometers(59.0),
+                    material: Material::new_from_eps(12.7449),
+                    doping: None,
+                },
+                LayerType::Simple {
+                    name: "p-clad (AlGaAs)".into(),
+                    thickness: Length::micrometers(1.5),
+                    material: Material::new_from_eps(11.0224),
+                    doping: None,
+                },
+            ],
+        }
+    }
+
+    /// Total thickness of the stack.
+    pub fn total_thickness(&self) -> Length {
+        self.layers
+            .iter()
+            .fold(Length::default(), |total, layer| total + layer.thickness())
+    }
+
+    /// Finds the index of the first PhotonicCrystal layer.
+    pub fn layer_index(&self) -> Option<usize> {
+        self.layers
+            .iter()
+            .position(|layer| matches!(layer, LayerType::PhotonicCrystal { .. }))
+    }
+
+    /// Gets a reference to the first PhotonicCrystal *layer*.
+    pub fn get_layer(&self) -> Option<&LayerType> {
+        self.layers
+            .iter()
+            .find(|layer| matches!(layer, LayerType::PhotonicCrystal { .. }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phc::base::UnitCellBase;
+    use phc::lattice::LatticeType;
+
+    // Helper to create a default PC for testing
+    fn create_test_pc() -> (PhotonicCrystal, Material) {
+        let air = Material::new_from_eps(1.0);
+        let gaas = Material::new_from_eps(12.7449);
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let base = UnitCellBase::from_simple_circle(0.16, air);
+        (PhotonicCrystal::new(lattice, base), gaas)
+    }
+
+    #[test]
+    fn test_waveguide_creation_with_material_pc() {
+        let (geom, gaas) = create_test_pc();
+        let wg = Waveguide::new_from_table_i(geom.clone(), gaas.clone());
+
+        assert_eq!(wg.layers.len(), 5);
+        assert_eq!(wg.layers[2].name(), "PC");
+        assert!((wg.total_thickness().as_nanometers() - 3265.5).abs() < 1e-9);
+
+        match &wg.layers[2] {
+            LayerType::PhotonicCrystal {
+                definition,
+                background_material,
+                ..
+            } => {
+                assert_eq!(definition.lattice.lattice().a1, geom.lattice.lattice().a1);
+                assert_eq!(definition.base.atoms.len(), 1);
+                assert_eq!(*background_material, gaas);
+            }
+            _ => panic!("Layer 2 was not a PhotonicCrystal type"),
+        }
+
+        match &wg.layers[0] {
+            LayerType::Simple { material, .. } => assert_eq!(material.epsilon_matrix[(0, 0)], 11.0224),
+            _ => panic!("Layer 0 was not a Simple type"),
+        }
+    }
+
+    #[test]
+    fn test_layer_index_and_getter() {
+        let (geom, gaas) = create_test_pc();
+        let wg = Waveguide::new_from_table_i(geom, gaas);
+
+        assert_eq!(wg.layer_index(), Some(2));
+        match wg.get_layer().unwrap() {
+            LayerType::PhotonicCrystal { definition, thickness, .. } => {
+                assert_eq!(definition.lattice.lattice().lattice_constant(), Length::nanometers(295.0));
+                assert_eq!(*thickness, Length::nanometers(118.0));
+            }
+            _ => panic!("expected the PC layer"),
+        }
+    }
+}
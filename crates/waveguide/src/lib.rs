@@ -0,0 +1,10 @@
+//! crates/waveguide/src/lib.rs
+//!
+//! Vertical layer stacks of photonic crystal surface-emitting lasers.
+pub mod layers;
+pub mod temperature;
+pub mod gain;
+pub mod doping;
+pub mod slab;
+pub mod graded;
+pub mod device;
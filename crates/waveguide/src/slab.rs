@@ -0,0 +1,392 @@
+//! crates/waveguide/src/slab.rs
+//! Fundamental TE mode of the layer stack, solved with transfer matrices.
+//!
+//! The bottom and top layers extend as half-spaces below and above the stack. Photonic
+//! crystal layers are replaced by their area-averaged permittivity and graded layers are
+//! cut into uniform sublayers sampled at their midpoints (a graded outer layer extends with
+//! its outermost sublayer). The mode is found for
+//! the real part of the permittivity; absorption enters to first order, giving the
+//! modal internal loss `α_i = Σ_j (k0 / n_eff) Γ_j Im ε_j`.
+use std::fmt;
+
+use core::units::{Length, Loss};
+use core::vectorial::Complex;
+use phc::fill_factor::{AreaMethod, FillFactorError};
+
+use super::doping::Doping;
+use super::layers::{LayerType, Waveguide};
+
+/// Raster resolution used when analytic fill factors are unavailable.
+const FALLBACK_RESOLUTION: usize = 256;
+/// Samples of the effective index scanned for sign changes of the dispersion function.
+const SCAN_POINTS: usize = 2000;
+/// Field samples per uniform section for the overlap integrals.
+const SAMPLES_PER_LAYER: usize = 200;
+/// Sublayer thickness used by [`Waveguide::slab_mode`] for graded layers.
+pub const DEFAULT_GRADED_STEP: Length = Length::meters(1e-9);
+
+/// Reasons why the slab mode cannot be computed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlabError {
+    /// The stack has fewer than three layers.
+    TooFewLayers(usize),
+    /// No layer has a permittivity above both half-spaces.
+    NoGuidedMode,
+    /// The average permittivity of a photonic crystal layer is not available.
+    FillFactor { layer: String, error: FillFactorError },
+}
+
+impl fmt::Display for SlabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlabError::TooFewLayers(count) => write!(f, "a slab needs at least 3 layers, found {count}"),
+            SlabError::NoGuidedMode => write!(f, "the stack supports no guided TE mode"),
+            SlabError::FillFactor { layer, error } => write!(f, "layer '{layer}': {error}"),
+        }
+    }
+}
+
+impl std::error::Error for SlabError {}
+
+/// Contribution of one layer to the modal internal loss.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerLoss {
+    pub name: String,
+    /// Fraction of the modal power `∫|E|²` inside the layer.
+    pub confinement: f64,
+    pub loss: Loss,
+}
+
+/// Fundamental TE mode of a waveguide.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlabMode {
+    pub effective_index: f64,
+    /// Confinement factor and internal loss of every layer, bottom to top.
+    pub layers: Vec<LayerLoss>,
+}
+
+impl SlabMode {
+    /// Modal internal loss `α_i`, summed over the layers.
+    pub fn internal_loss(&self) -> Loss {
+        self.layers
+            .iter()
+            .fold(Loss::default(), |total, layer| total + layer.loss)
+    }
+}
+
+impl LayerType {
+    /// Complex in-plane permittivity `ε_xx` seen by a TE slab mode at `wavelength` and at the
+    /// fractional height `u` inside the layer, including free-carrier absorption from the doping.
+    pub fn slab_permittivity(&self, wavelength: Length, u: f64) -> Result<Complex, SlabError> {
+        let with_doping = |eps: Complex, doping: &Option<Doping>| match doping {
+            Some(doping) => doping.absorbing_permittivity(eps, wavelength),
+            None => eps,
+        };
+        match self {
+            LayerType::Simple { material, doping, .. } => Ok(with_doping(
+                material.complex_epsilon_at(wavelength)[(0, 0)],
+                doping,
+            )),
+            LayerType::Graded { profile, doping, .. } => {
+                Ok(with_doping(Complex::new(profile.epsilon_at(u), 0.0), doping))
+            }
+            LayerType::PhotonicCrystal { name, definition, .. } => definition
+                .average_complex_epsilon_at(wavelength, AreaMethod::Analytic)
+                .or_else(|_| definition.average_complex_epsilon_at(wavelength, AreaMethod::Raster {
+                    resolution: FALLBACK_RESOLUTION,
+                }))
+                .map(|eps| eps[(0, 0)])
+                .map_err(|error| SlabError::FillFactor {
+                    layer: name.clone(),
+                    error,
+                }),
+        }
+    }
+}
+
+/// Transfer matrix of `E, dE/dz` across a layer of permittivity `eps` and thickness `d`.
+fn propagate(eps: f64, k0: f64, beta: f64, d: f64, (e, de): (f64, f64)) -> (f64, f64) {
+    let q2 = k0 * k0 * eps - beta * beta;
+    if q2 >= 0.0 {
+        let q = q2.sqrt();
+        let (sin, cos) = (q * d).sin_cos();
+        let sinc = if q * d == 0.0 { d } else { sin / q };
+        (e * cos + de * sinc, -e * q * sin + de * cos)
+    } else {
+        let g = (-q2).sqrt();
+        let (sinh, cosh) = ((g * d).sinh(), (g * d).cosh());
+        (e * cosh + de * sinh / g, e * g * sinh + de * cosh)
+    }
+}
+
+/// Decay constant of the field in a half-space.
+fn decay(eps: f64, k0: f64, beta: f64) -> f64 {
+    (beta * beta - k0 * k0 * eps).max(0.0).sqrt()
+}
+
+/// Field `(E, dE/dz)` at the top of every layer, starting from a decaying bottom half-space.
+fn interfaces(eps: &[f64], thickness: &[f64], k0: f64, beta: f64) -> Vec<(f64, f64)> {
+    let mut state = (1.0, decay(eps[0], k0, beta));
+    let mut fields = vec![state];
+    for (eps, d) in eps.iter().zip(thickness).skip(1).take(eps.len() - 2) {
+        state = propagate(*eps, k0, beta, *d, state);
+        fields.push(state);
+    }
+    fields
+}
+
+/// Mismatch of the field at the top half-space, zero for a guided mode.
+fn dispersion(eps: &[f64], thickness: &[f64], k0: f64, beta: f64) -> f64 {
+    let (e, de) = *interfaces(eps, thickness, k0, beta).last().unwrap();
+    let scale = e.abs() + de.abs() / k0;
+    (de + decay(eps[eps.len() - 1], k0, beta) * e) / (k0 * scale)
+}
+
+impl Waveguide {
+    /// Solves the fundamental TE mode at the vacuum `wavelength` and splits the modal
+    /// internal loss between the layers, cutting graded layers into [`DEFAULT_GRADED_STEP`] sublayers.
+    pub fn slab_mode(&self, wavelength: Length) -> Result<SlabMode, SlabError> {
+        self.slab_mode_with_step(wavelength, DEFAULT_GRADED_STEP)
+    }
+
+    /// Same as [`Waveguide::slab_mode`], with graded layers cut into sublayers no thicker than `step`.
+    pub fn slab_mode_with_step(&self, wavelength: Length, step: Length) -> Result<SlabMode, SlabError> {
+        if self.layers.len() < 3 {
+            return Err(SlabError::TooFewLayers(self.layers.len()));
+        }
+        // Uniform sections as (owning layer, permittivity, thickness).
+        let mut sections = Vec::new();
+        for (index, layer) in self.layers.iter().enumerate() {
+            let slices = match layer {
+                LayerType::Graded { thickness, .. } => ((*thickness / step).ceil() as usize).max(1),
+                _ => 1,
+            };
+            let d = layer.thickness().as_meters() / slices as f64;
+            for i in 0..slices {
+                let eps = layer.slab_permittivity(wavelength, (i as f64 + 0.5) / slices as f64)?;
+                sections.push((index, eps, d));
+            }
+        }
+        let count = sections.len();
+        let eps: Vec<f64> = sections.iter().map(|section| section.1.re).collect();
+        let thickness: Vec<f64> = sections.iter().map(|section| section.2).collect();
+        let k0 = 2.0 * std::f64::consts::PI / wavelength.as_meters();
+
+        let n_low = eps[0].max(eps[count - 1]).sqrt();
+        let n_high = eps.iter().cloned().fold(f64::MIN, f64::max).sqrt();
+        if n_high <= n_low {
+            return Err(SlabError::NoGuidedMode);
+        }
+
+        // Scan downwards from the highest index; the first root is the fundamental mode.
+        let f = |n: f64| dispersion(&eps, &thickness, k0, k0 * n);
+        let dn = (n_high - n_low) / SCAN_POINTS as f64;
+        let mut upper = n_high;
+        let mut bracket = None;
+        for i in 1..SCAN_POINTS {
+            let lower = n_high - i as f64 * dn;
+            if f(upper).signum() != f(lower).signum() {
+                bracket = Some((lower, upper));
+                break;
+            }
+            upper = lower;
+        }
+        let (mut low, mut high) = bracket.ok_or(SlabError::NoGuidedMode)?;
+        let f_low = f(low).signum();
+        for _ in 0..100 {
+            let mid = 0.5 * (low + high);
+            if f(mid).signum() == f_low {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        let n_eff = 0.5 * (low + high);
+        let beta = k0 * n_eff;
+
+        // Power in every layer: analytic exponential tails for the half-spaces, midpoint
+        // sampling inside the stack.
+        let fields = interfaces(&eps, &thickness, k0, beta);
+        let mut power = vec![0.0; count];
+        power[0] = 1.0 / (2.0 * decay(eps[0], k0, beta));
+        let (e_top, _) = fields[count - 2];
+        power[count - 1] = e_top * e_top / (2.0 * decay(eps[count - 1], k0, beta));
+        for j in 1..count - 1 {
+            let dz = thickness[j] / SAMPLES_PER_LAYER as f64;
+            power[j] = (0..SAMPLES_PER_LAYER)
+                .map(|i| {
+                    let (e, _) = propagate(eps[j], k0, beta, (i as f64 + 0.5) * dz, fields[j - 1]);
+                    e * e * dz
+                })
+                .sum();
+        }
+        let total: f64 = power.iter().sum();
+
+        let mut layers: Vec<LayerLoss> = self
+            .layers
+            .iter()
+            .map(|layer| LayerLoss {
+                name: layer.name().to_string(),
+                confinement: 0.0,
+                loss: Loss::default(),
+            })
+            .collect();
+        for ((index, eps, _), power) in sections.iter().zip(power) {
+            let confinement = power / total;
+            layers[*index].confinement += confinement;
+            layers[*index].loss = layers[*index].loss + Loss::per_meter(k0 / n_eff * confinement * eps.im);
+        }
+        Ok(SlabMode {
+            effective_index: n_eff,
+            layers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doping::Doping;
+    use core::material::Material;
+
+    fn layer(name: &str, eps: f64, nm: f64, doping: Option<Doping>) -> LayerType {
+        LayerType::Simple {
+            name: name.into(),
+            thickness: Length::nanometers(nm),
+            material: Material::new_from_eps(eps),
+            doping,
+        }
+    }
+
+    #[test]
+    fn test_symmetric_slab_matches_analytic_mode() {
+        // Symmetric slab: tan(q d/2) = γ/q for the fundamental TE mode.
+        let lambda = Length::micrometers(1.0);
+        let wg = Waveguide {
+            layers: vec![layer("clad", 2.25, 1000.0, None), layer("core", 12.25, 200.0, None), layer("clad", 2.25, 1000.0, None)],
+        };
+        let mode = wg.slab_mode(lambda).unwrap();
+        let k0 = 2.0 * std::f64::consts::PI / lambda.as_meters();
+        let q = k0 * (12.25 - mode.effective_index.powi(2)).sqrt();
+        let g = k0 * (mode.effective_index.powi(2) - 2.25).sqrt();
+        assert!(((q * 100e-9).tan() - g / q).abs() < 1e-6);
+        assert!(mode.effective_index > 1.5 && mode.effective_index < 3.5);
+
+        let total: f64 = mode.layers.iter().map(|layer| layer.confinement).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+        assert!((mode.layers[0].confinement - mode.layers[2].confinement).abs() < 1e-6);
+        assert_eq!(mode.internal_loss(), Loss::default());
+    }
+
+    #[test]
+    fn test_internal_loss_split_per_layer() {
+        let lambda = Length::nanometers(940.0);
+        let wg = Waveguide {
+            layers: vec![
+                layer("n-clad", 11.0224, 1500.0, Some(Doping::n_per_cm3(1e18))),
+                layer("core", 12.8603, 300.0, None),
+                layer("p-clad", 11.0224, 1500.0, Some(Doping::p_per_cm3(1e18))),
+            ],
+        };
+        let mode = wg.slab_mode(lambda).unwrap();
+        assert_eq!(mode.layers[1].loss, Loss::default());
+        let n_loss = mode.layers[0].loss.as_per_centimeter();
+        let p_loss = mode.layers[2].loss.as_per_centimeter();
+        // Each cladding loses about Γ_j α_j, the p side more than the n side.
+        let expected = |doping: Doping, layer: &LayerLoss| {
+            doping.free_carrier_absorption(lambda).as_per_centimeter() * layer.confinement
+        };
+        assert!((n_loss / expected(Doping::n_per_cm3(1e18), &mode.layers[0]) - 1.0).abs() < 0.05);
+        assert!((p_loss / expected(Doping::p_per_cm3(1e18), &mode.layers[2]) - 1.0).abs() < 0.05);
+        assert!(p_loss > n_loss);
+        assert!((mode.internal_loss().as_per_centimeter() - n_loss - p_loss).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_graded_layers_are_discretized() {
+        use crate::graded::GradedProfile;
+
+        let lambda = Length::nanometers(940.0);
+        let graded = |name: &str, profile: GradedProfile| LayerType::Graded {
+            name: name.into(),
+            thickness: Length::nanometers(150.0),
+            profile,
+            doping: None,
+            thermo_optic: None,
+        };
+        let sch = |profile: GradedProfile| Waveguide {
+            layers: vec![
+                layer("n-clad", 11.0224, 1500.0, None),
+                graded("lower SCH", profile.clone()),
+                layer("core", 12.8603, 100.0, None),
+                graded("upper SCH", profile.flipped()),
+                layer("p-clad", 11.0224, 1500.0, None),
+            ],
+        };
+
+        // A flat profile reproduces a uniform layer.
+        let flat = sch(GradedProfile::Linear { bottom: 12.0, top: 12.0, dn: 0.0 }).slab_mode(lambda).unwrap();
+        let uniform = Waveguide {
+            layers: vec![
+                layer("n-clad", 11.0224, 1500.0, None),
+                layer("lower SCH", 12.0, 150.0, None),
+                layer("core", 12.8603, 100.0, None),
+                layer("upper SCH", 12.0, 150.0, None),
+                layer("p-clad", 11.0224, 1500.0, None),
+            ],
+        }
+        .slab_mode(lambda)
+        .unwrap();
+        assert!((flat.effective_index - uniform.effective_index).abs() < 1e-9);
+        assert!((flat.layers[2].confinement - uniform.layers[2].confinement).abs() < 1e-6);
+
+        // The result converges as the step shrinks.
+        let linear = sch(GradedProfile::Linear { bottom: 11.0224, top: 12.8603, dn: 0.0 });
+        let coarse = linear.slab_mode_with_step(lambda, Length::nanometers(50.0)).unwrap();
+        let fine = linear.slab_mode_with_step(lambda, Length::nanometers(0.5)).unwrap();
+        let default = linear.slab_mode(lambda).unwrap();
+        assert_eq!(fine.layers.len(), 5);
+        assert!((default.effective_index - fine.effective_index).abs() < 1e-5);
+        assert!((coarse.effective_index - fine.effective_index).abs() < 1e-2);
+        assert!(fine.effective_index > 11.0224f64.sqrt() && fine.effective_index < 12.8603f64.sqrt());
+        let total: f64 = fine.layers.iter().map(|layer| layer.confinement).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_table_i_stack() {
+        use phc::base::UnitCellBase;
+        use phc::crystal_structure::PhotonicCrystal;
+        use phc::lattice::LatticeType;
+
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+        base.background_material = Material::new_from_eps(12.7449);
+        let wg = Waveguide::new_from_table_i(PhotonicCrystal::new(lattice, base), Material::new_from_eps(12.7449));
+        let mode = wg.slab_mode(Length::nanometers(940.0)).unwrap();
+        assert!(mode.effective_index > 11.0224f64.sqrt() && mode.effective_index < 12.8603f64.sqrt());
+        assert!(mode.layers[1].confinement > 0.1 && mode.layers[1].confinement < 0.5);
+
+        // Dispersive materials of the crystal are averaged at the requested wavelength.
+        let mut dispersive = wg.clone();
+        if let LayerType::PhotonicCrystal { definition, .. } = &mut dispersive.layers[2] {
+            definition.base.background_material = Material::new_dispersive(
+                core::dispersion::Dispersion::Cauchy {
+                    coefficients: vec![3.3, 0.2],
+                },
+                Length::nanometers(940.0),
+            );
+        }
+        let fill = std::f64::consts::PI * 0.16 * 0.16;
+        for lambda in [Length::nanometers(940.0), Length::nanometers(1300.0)] {
+            let n = 3.3 + 0.2 / lambda.as_micrometers().powi(2);
+            let eps = dispersive.layers[2].slab_permittivity(lambda, 0.5).unwrap();
+            assert!((eps.re - (fill + (1.0 - fill) * n * n)).abs() < 1e-12, "{lambda}");
+        }
+
+        let too_thin = Waveguide {
+            layers: wg.layers[..2].to_vec(),
+        };
+        assert_eq!(too_thin.slab_mode(Length::nanometers(940.0)), Err(SlabError::TooFewLayers(2)));
+    }
+}
@@ -0,0 +1,326 @@
+//! crates/waveguide/src/temperature.rs
+//! Evaluation of a waveguide under a temperature profile.
+//!
+//! Materials respond to temperature through their thermal model (see `core::thermal`);
+//! materials without one keep their dielectric tensor. Graded layers follow the thermo-optic
+//! coefficient they carry, if any.
+use std::fmt;
+
+use core::alloys::AlloyError;
+use core::units::Length;
+
+use super::layers::{LayerType, Waveguide};
+
+/// Reasons why a waveguide cannot be evaluated under a temperature profile.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemperatureError {
+    /// A per-layer profile does not have one temperature per layer.
+    LayerCountMismatch { expected: usize, found: usize },
+    /// A temperature map has no samples or a number of values different from `nx * ny`.
+    InvalidMap { shape: (usize, usize), values: usize },
+    /// A material of the named layer cannot be evaluated at the requested temperature.
+    Material { layer: String, error: AlloyError },
+}
+
+impl fmt::Display for TemperatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemperatureError::LayerCountMismatch { expected, found } => {
+                write!(f, "expected {expected} layer temperatures, found {found}")
+            }
+            TemperatureError::InvalidMap { shape, values } => {
+                write!(f, "temperature map of shape {}x{} cannot hold {values} values", shape.0, shape.1)
+            }
+            TemperatureError::Material { layer, error } => write!(f, "layer '{layer}': {error}"),
+        }
+    }
+}
+
+impl std::error::Error for TemperatureError {}
+
+/// Temperatures (K) sampled on a regular in-plane grid.
+///
+/// Samples sit on the nodes of an `nx` x `ny` grid spanning `origin` to `origin + size`.
+/// Values in between are interpolated bilinearly and values outside are clamped to the edge.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemperatureMap {
+    origin: (Length, Length),
+    size: (Length, Length),
+    shape: (usize, usize),
+    values: Vec<f64>,
+}
+
+impl TemperatureMap {
+    /// Creates a map from `values` stored row by row, with x varying fastest.
+    pub fn new(
+        origin: (Length, Length),
+        size: (Length, Length),
+        (nx, ny): (usize, usize),
+        values: Vec<f64>,
+    ) -> Result<Self, TemperatureError> {
+        if nx == 0 || ny == 0 || values.len() != nx * ny {
+            return Err(TemperatureError::InvalidMap {
+                shape: (nx, ny),
+                values: values.len(),
+            });
+        }
+        Ok(Self {
+            origin,
+            size,
+            shape: (nx, ny),
+            values,
+        })
+    }
+
+    /// Temperature at the in-plane position `(x, y)`.
+    pub fn at(&self, x: Length, y: Length) -> f64 {
+        let (nx, ny) = self.shape;
+        let grid = |p: Length, origin: Length, size: Length, n: usize| {
+            if n == 1 {
+                return (0, 0, 0.0);
+            }
+            let u = ((p - origin) / size).clamp(0.0, 1.0) * (n - 1) as f64;
+            let i = (u.floor() as usize).min(n - 2);
+            (i, i + 1, u - i as f64)
+        };
+        let (i0, i1, fx) = grid(x, self.origin.0, self.size.0, nx);
+        let (j0, j1, fy) = grid(y, self.origin.1, self.size.1, ny);
+        let value = |i: usize, j: usize| self.values[j * nx + i];
+        let bottom = value(i0, j0) * (1.0 - fx) + value(i1, j0) * fx;
+        let top = value(i0, j1) * (1.0 - fx) + value(i1, j1) * fx;
+        bottom * (1.0 - fy) + top * fy
+    }
+}
+
+/// Temperature distribution over a waveguide.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemperatureProfile {
+    /// The same temperature everywhere.
+    Uniform(f64),
+    /// One temperature per layer, bottom to top.
+    PerLayer(Vec<f64>),
+    /// An in-plane map shared by all layers, e.g. from a thermal simulation of the chip.
+    InPlane(TemperatureMap),
+}
+
+impl TemperatureProfile {
+    /// Temperature of `layer` at the in-plane `position`.
+    pub fn layer_temperature(&self, layer: usize, position: (Length, Length)) -> f64 {
+        match self {
+            TemperatureProfile::Uniform(temperature) => *temperature,
+            TemperatureProfile::PerLayer(temperatures) => temperatures[layer],
+            TemperatureProfile::InPlane(map) => map.at(position.0, position.1),
+        }
+    }
+}
+
+impl Waveguide {
+    /// Returns the stack evaluated at the vacuum `wavelength` under `profile`, as seen
+    /// at the in-plane `position` (only used by in-plane maps).
+    pub fn at_temperature(
+        &self,
+        wavelength: Length,
+        profile: &TemperatureProfile,
+        position: (Length, Length),
+    ) -> Result<Self, TemperatureError> {
+        if let TemperatureProfile::PerLayer(temperatures) = profile {
+            if temperatures.len() != self.layers.len() {
+                return Err(TemperatureError::LayerCountMismatch {
+                    expected: self.layers.len(),
+                    found: temperatures.len(),
+                });
+            }
+        }
+        let layers = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                let temperature = profile.layer_temperature(i, position);
+                if let LayerType::Graded {
+                    name,
+                    thickness,
+                    profile,
+                    doping,
+                    thermo_optic: Some(thermo_optic),
+                } = layer
+                {
+                    let (profile, thermo_optic) = profile.at_temperature(*thermo_optic, temperature);
+                    return Ok(LayerType::Graded {
+                        name: name.clone(),
+                        thickness: *thickness,
+                        profile,
+                        doping: *doping,
+                        thermo_optic: Some(thermo_optic),
+                    });
+                }
+                layer.try_map_materials(|material| {
+                    material
+                        .at_temperature(wavelength, temperature)
+                        .map_err(|error| TemperatureError::Material {
+                            layer: layer.name().to_string(),
+                            error,
+                        })
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { layers })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::material::Material;
+
+    fn alloy_stack() -> Waveguide {
+        let lambda = Length::nanometers(940.0);
+        let layer = |name: &str, x: f64, nm: f64| LayerType::Simple {
+            name: name.into(),
+            thickness: Length::nanometers(nm),
+            material: Material::al_ga_as(x, lambda, 300.0).unwrap(),
+            doping: None,
+        };
+        Waveguide {
+            layers: vec![
+                layer("n-clad", 0.4, 1500.0),
+                layer("core", 0.0, 200.0),
+                LayerType::Simple {
+                    name: "oxide".into(),
+                    thickness: Length::nanometers(100.0),
+                    material: Material::new_from_eps(2.1),
+                    doping: None,
+                },
+            ],
+        }
+    }
+
+    fn eps(wg: &Waveguide, layer: usize) -> f64 {
+        match &wg.layers[layer] {
+            LayerType::Simple { material, .. } => material.epsilon_matrix[(0, 0)],
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_uniform_and_per_layer_profiles() {
+        let wg = alloy_stack();
+        let lambda = Length::nanometers(940.0);
+        let origin = (Length::default(), Length::default());
+
+        let hot = wg.at_temperature(lambda, &TemperatureProfile::Uniform(330.0), origin).unwrap();
+        assert!(eps(&hot, 0) > eps(&wg, 0) && eps(&hot, 1) > eps(&wg, 1));
+        assert_eq!(eps(&hot, 2), 2.1);
+
+        let profile = TemperatureProfile::PerLayer(vec![300.0, 330.0, 330.0]);
+        let heated_core = wg.at_temperature(lambda, &profile, origin).unwrap();
+        assert!((eps(&heated_core, 0) - eps(&wg, 0)).abs() < 1e-12);
+        assert_eq!(eps(&heated_core, 1), eps(&hot, 1));
+
+        assert_eq!(
+            wg.at_temperature(lambda, &TemperatureProfile::PerLayer(vec![300.0]), origin)
+                .unwrap_err(),
+            TemperatureError::LayerCountMismatch { expected: 3, found: 1 }
+        );
+        assert!(matches!(
+            wg.at_temperature(lambda, &TemperatureProfile::Uniform(450.0), origin),
+            Err(TemperatureError::Material { layer, .. }) if layer == "n-clad"
+        ));
+    }
+
+    #[test]
+    fn test_in_plane_map() {
+        let um = Length::micrometers;
+        let map = TemperatureMap::new((um(-50.0), um(-50.0)), (um(100.0), um(100.0)), (2, 2), vec![300.0, 340.0, 320.0, 360.0])
+            .unwrap();
+        assert_eq!(map.at(um(-50.0), um(-50.0)), 300.0);
+        assert!((map.at(um(0.0), um(0.0)) - 330.0).abs() < 1e-12);
+        assert_eq!(map.at(um(500.0), um(-500.0)), 340.0);
+        assert!(TemperatureMap::new((um(0.0), um(0.0)), (um(1.0), um(1.0)), (2, 2), vec![300.0]).is_err());
+
+        let wg = alloy_stack();
+        let profile = TemperatureProfile::InPlane(map);
+        let center = wg
+            .at_temperature(Length::nanometers(940.0), &profile, (um(0.0), um(0.0)))
+            .unwrap();
+        let uniform = wg
+            .at_temperature(Length::nanometers(940.0), &TemperatureProfile::Uniform(330.0), (um(0.0), um(0.0)))
+            .unwrap();
+        assert!((eps(&center, 1) - eps(&uniform, 1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_graded_layers_follow_their_thermo_optic_coefficient() {
+        use crate::graded::GradedProfile;
+        use core::thermal::ThermoOptic;
+
+        let graded = |thermo_optic: Option<ThermoOptic>| LayerType::Graded {
+            name: "SCH".into(),
+            thickness: Length::nanometers(150.0),
+            profile: GradedProfile::Linear { bottom: 11.0, top: 12.8, dn: 0.0 },
+            doping: None,
+            thermo_optic,
+        };
+        let thermo_optic = ThermoOptic {
+            dn_dt: 2.5e-4,
+            reference_temperature: 300.0,
+        };
+        let wg = Waveguide {
+            layers: vec![graded(Some(thermo_optic)), graded(None)],
+        };
+        let origin = (Length::default(), Length::default());
+        let hot = wg
+            .at_temperature(Length::nanometers(940.0), &TemperatureProfile::Uniform(340.0), origin)
+            .unwrap();
+        let LayerType::Graded { profile, thermo_optic, .. } = &hot.layers[0] else {
+            unreachable!()
+        };
+        for (u, eps) in [(0.0, 11.0), (0.5, 11.9), (1.0, 12.8)] {
+            let expected = (f64::sqrt(eps) + 2.5e-4 * 40.0).powi(2);
+            assert!((profile.epsilon_at(u) - expected).abs() < 1e-4, "{u}");
+        }
+        assert_eq!(thermo_optic.unwrap().reference_temperature, 340.0);
+        // Evaluating again at the reference temperature restores the profile.
+        let back = hot
+            .at_temperature(Length::nanometers(940.0), &TemperatureProfile::Uniform(300.0), origin)
+            .unwrap();
+        let LayerType::Graded { profile, .. } = &back.layers[0] else { unreachable!() };
+        assert!((profile.epsilon_at(0.5) - 11.9).abs() < 1e-4);
+        // Without a coefficient the profile does not depend on temperature.
+        let LayerType::Graded { profile, .. } = &hot.layers[1] else { unreachable!() };
+        assert_eq!(profile, &GradedProfile::Linear { bottom: 11.0, top: 12.8, dn: 0.0 });
+    }
+
+    #[test]
+    fn test_photonic_crystal_layer_materials_follow_temperature() {
+        use phc::base::UnitCellBase;
+        use phc::crystal_structure::PhotonicCrystal;
+        use phc::lattice::LatticeType;
+
+        let gaas = Material::al_ga_as(0.0, Length::nanometers(940.0), 300.0).unwrap();
+        let lattice = LatticeType::new_square(Length::nanometers(295.0), Length::nanometers(118.0));
+        let mut base = UnitCellBase::from_simple_circle(0.16, Material::new_from_eps(1.0));
+        base.background_material = gaas.clone();
+        let wg = Waveguide::new_from_table_i(PhotonicCrystal::new(lattice, base), gaas.clone());
+
+        let hot = wg
+            .at_temperature(
+                Length::nanometers(940.0),
+                &TemperatureProfile::Uniform(350.0),
+                (Length::default(), Length::default()),
+            )
+            .unwrap();
+        match &hot.layers[2] {
+            LayerType::PhotonicCrystal {
+                definition,
+                background_material,
+                ..
+            } => {
+                assert!(background_material.epsilon_matrix[(0, 0)] > gaas.epsilon_matrix[(0, 0)]);
+                assert_eq!(definition.base.background_material, *background_material);
+                assert_eq!(definition.base.atoms[0].material, Material::new_from_eps(1.0));
+            }
+            _ => panic!("expected the PC layer"),
+        }
+    }
+}